@@ -3,11 +3,19 @@ use std::{
     ops::{Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive},
 };
 
+use smallvec::SmallVec;
+
 use crate::{
     disasm::OpcodePrinter,
     traits::{Address, InsnRead, InsnWrite, Reloc, RelocCode},
 };
 
+/// Inline capacity for [`CleverInstruction`]'s operand list: enough for
+/// every instruction this crate encodes today (at most two register/
+/// immediate operands plus an index), so assembling a file doesn't heap
+/// allocate once per instruction the way a bare `Vec` would.
+pub type CleverOperands = SmallVec<[CleverOperand; 3]>;
+
 #[derive(Debug)]
 pub struct CleverExtensionFromStrError;
 
@@ -624,6 +632,29 @@ macro_rules! clever_instructions{
                 }
             }
         }
+
+        impl crate::isa::InsnDescriptor for CleverOpcode {
+            type Operands = CleverOperandKind;
+
+            fn mnemonic(&self) -> &'static str {
+                self.name()
+            }
+
+            fn operand_kinds(&self) -> Self::Operands {
+                self.operands()
+            }
+
+            fn required_extension(&self) -> Option<&'static str> {
+                match self.extension() {
+                    CleverExtension::Main => None,
+                    ext => Some(ext.extension_name()),
+                }
+            }
+
+            fn encoding(&self) -> u64 {
+                self.opcode() as u64
+            }
+        }
     }
 }
 
@@ -1490,26 +1521,26 @@ impl core::fmt::Display for CleverOperand {
 pub struct CleverInstruction {
     prefix: Option<CleverOpcode>,
     opcode: CleverOpcode,
-    operands: Vec<CleverOperand>,
+    operands: CleverOperands,
 }
 
 impl CleverInstruction {
-    pub const fn new(opcode: CleverOpcode, operands: Vec<CleverOperand>) -> Self {
+    pub fn new(opcode: CleverOpcode, operands: impl Into<CleverOperands>) -> Self {
         Self {
             prefix: None,
             opcode,
-            operands,
+            operands: operands.into(),
         }
     }
-    pub const fn new_prefixed(
+    pub fn new_prefixed(
         prefix: CleverOpcode,
         opcode: CleverOpcode,
-        operands: Vec<CleverOperand>,
+        operands: impl Into<CleverOperands>,
     ) -> Self {
         Self {
             prefix: Some(prefix),
             opcode,
-            operands,
+            operands: operands.into(),
         }
     }
 
@@ -1963,6 +1994,10 @@ impl Default for CleverPrinter {
 }
 
 impl OpcodePrinter for CleverPrinter {
+    fn min_insn_size(&self) -> usize {
+        2
+    }
+
     fn print_opcode(
         &self,
         f: &mut core::fmt::Formatter,
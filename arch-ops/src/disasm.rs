@@ -10,4 +10,42 @@ pub trait OpcodePrinter {
     fn handle_option(&mut self, _key: &str, _value: &str) -> bool {
         false
     }
+
+    /// The smallest unit [`Self::print_opcode`] ever consumes in one call,
+    /// in bytes -- what [`Self::resync`]'s default implementation skips
+    /// over after a decode failure. Most ISAs decode byte-at-a-time;
+    /// override this for one with a coarser minimum opcode granularity
+    /// (e.g. Clever, whose opcodes are always 2-byte-aligned).
+    fn min_insn_size(&self) -> usize {
+        1
+    }
+
+    /// Recovers from [`Self::print_opcode`] failing to decode the bytes at
+    /// `pos` in `bytes` (an invalid opcode or other malformed encoding,
+    /// not an I/O error reading `bytes` itself, which by construction
+    /// can't happen here since the caller already has it all in memory):
+    /// writes a `.byte` pseudo-op covering [`Self::min_insn_size`] bytes
+    /// to `f` and returns how many bytes the caller should advance `pos`
+    /// by before calling [`Self::print_opcode`] again.
+    ///
+    /// This lets a disassembly loop treat an undecodable instruction the
+    /// way an assembler treats a literal byte, and keep going instead of
+    /// aborting the rest of the section -- the data-in-text regions (jump
+    /// tables, padding) that a linear disassembler runs into are exactly
+    /// what this is for.
+    fn resync(
+        &self,
+        f: &mut core::fmt::Formatter,
+        bytes: &[u8],
+        pos: usize,
+    ) -> std::io::Result<usize> {
+        let len = self.min_insn_size().max(1).min(bytes.len() - pos);
+        write!(f, ".byte ").unwrap();
+        let mut sep = "";
+        for b in &bytes[pos..pos + len] {
+            write!(f, "{}0x{:02x}", sep, b).unwrap();
+            sep = ", ";
+        }
+        Ok(len)
+    }
 }
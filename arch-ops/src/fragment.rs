@@ -0,0 +1,304 @@
+//! Fragment-based instruction emission: an [`InsnWrite`] implementor
+//! that accumulates labeled chunks of encoded bytes instead of writing
+//! straight into a flat stream, so a caller can reorder, duplicate, or
+//! insert between them before [`layout_fragments`] flattens everything
+//! into final bytes.
+//!
+//! This is the same problem GNU `as`'s "frag" list solves: a `.align`
+//! that needs to land after a macro expansion of unknown length, or a
+//! literal pool that has to be placed after the function that
+//! references it, can't be laid out while the encoder is still running
+//! one instruction at a time -- the encoder just needs somewhere to put
+//! bytes that doesn't commit to a final address yet.
+//!
+//! No encoder in this crate writes through this today; each still
+//! targets a flat [`InsnWrite`] (a `Vec<u8>`, or [`binfmt::fmt::Section`]).
+//! This is the data model an assembler frontend would sit on top of.
+
+use std::collections::HashMap;
+use std::io;
+
+use crate::traits::{Address, InsnWrite, Reloc, RelocCode};
+
+/// The content of one [`Fragment`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FragmentKind {
+    /// Encoded bytes with a fixed size, known as soon as they're
+    /// written -- an instruction, a literal, padding of a known count.
+    Fixed { content: Vec<u8>, relocs: Vec<Reloc> },
+    /// Padding up to the next multiple of `align` (in bytes), repeating
+    /// `fill`. Its size isn't known until [`layout_fragments`] knows
+    /// this fragment's starting offset, which is exactly why it can't
+    /// just be a run of [`FragmentKind::Fixed`] zero bytes written in
+    /// place -- the run of bytes before it might still grow or move.
+    Align { align: usize, fill: u8 },
+}
+
+/// One chunk of a [`FragmentWriter`]'s output. `label`, if present,
+/// names this fragment's starting offset for reordering (move
+/// everything from this label onward somewhere else) or for symbol
+/// resolution once [`layout_fragments`] has run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fragment {
+    pub label: Option<String>,
+    pub kind: FragmentKind,
+}
+
+impl Fragment {
+    fn new_fixed(label: Option<String>) -> Self {
+        Self {
+            label,
+            kind: FragmentKind::Fixed {
+                content: Vec::new(),
+                relocs: Vec::new(),
+            },
+        }
+    }
+}
+
+/// An [`InsnWrite`] that emits into a growable list of [`Fragment`]s
+/// rather than a flat byte stream.
+///
+/// Call [`Self::align`] where a `.align` directive belongs and
+/// [`Self::start_fragment`] anywhere a later pass might want to name a
+/// point to reorder around; everything written in between lands in the
+/// [`FragmentKind::Fixed`] fragment that's currently open. [`Self::offset`]
+/// (and therefore [`InsnWrite::write_addr`]'s non-relocated address
+/// writes) only reflects this writer's current fragment order -- it is
+/// *not* a final address, and becomes wrong the moment a caller
+/// reorders fragments with [`std::mem::swap`] or similar before calling
+/// [`layout_fragments`]. Anything that needs to survive reordering must
+/// go through a [`Reloc`] against a label, not a raw computed offset.
+#[derive(Clone, Debug, Default)]
+pub struct FragmentWriter {
+    fragments: Vec<Fragment>,
+    running_offset: usize,
+}
+
+impl FragmentWriter {
+    pub fn new() -> Self {
+        Self {
+            fragments: vec![Fragment::new_fixed(None)],
+            running_offset: 0,
+        }
+    }
+
+    /// Closes the currently-open fixed fragment and opens a new one,
+    /// naming it `label` if given. A later pass can find this point
+    /// again by label to reorder the fragment stream around it.
+    pub fn start_fragment(&mut self, label: Option<String>) {
+        self.fragments.push(Fragment::new_fixed(label));
+    }
+
+    /// Inserts an [`FragmentKind::Align`] fragment, then opens a fresh
+    /// fixed fragment after it so writing can continue.
+    pub fn align(&mut self, align: usize, fill: u8) {
+        self.fragments.push(Fragment {
+            label: None,
+            kind: FragmentKind::Align { align, fill },
+        });
+        self.fragments.push(Fragment::new_fixed(None));
+    }
+
+    /// Consumes the writer, returning its fragments in emission order.
+    pub fn into_fragments(self) -> Vec<Fragment> {
+        self.fragments
+    }
+
+    fn current_fixed(&mut self) -> (&mut Vec<u8>, &mut Vec<Reloc>) {
+        if matches!(self.fragments.last(), Some(Fragment { kind: FragmentKind::Align { .. }, .. }) | None) {
+            self.fragments.push(Fragment::new_fixed(None));
+        }
+        match &mut self
+            .fragments
+            .last_mut()
+            .expect("a fixed fragment was just pushed if none was open")
+            .kind
+        {
+            FragmentKind::Fixed { content, relocs } => (content, relocs),
+            FragmentKind::Align { .. } => unreachable!("just ensured the open fragment is Fixed"),
+        }
+    }
+}
+
+impl io::Write for FragmentWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (content, _) = self.current_fixed();
+        content.extend_from_slice(buf);
+        self.running_offset += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl InsnWrite for FragmentWriter {
+    fn write_addr(&mut self, size: usize, addr: Address, rel: bool) -> io::Result<()> {
+        let width = size / 8;
+        match (addr, rel) {
+            (Address::Abs(val), false) => {
+                let bytes = val.to_le_bytes();
+                io::Write::write_all(self, &bytes[..width])
+            }
+            (Address::Disp(disp), true) => {
+                let bytes = disp.to_le_bytes();
+                io::Write::write_all(self, &bytes[..width])
+            }
+            (Address::Symbol { name, disp }, rel) => {
+                let code = if rel {
+                    RelocCode::Rel { addr_width: size }
+                } else {
+                    RelocCode::Abs { addr_width: size }
+                };
+                let (content, relocs) = self.current_fixed();
+                let offset = content.len() as u64;
+                content.extend(std::iter::repeat(0).take(width));
+                relocs.push(Reloc {
+                    code,
+                    symbol: name,
+                    addend: Some(if rel { disp - (width as i64) } else { disp }),
+                    offset,
+                });
+                self.running_offset += width;
+                Ok(())
+            }
+            (Address::PltSym { name }, rel) => {
+                let code = if rel {
+                    RelocCode::RelPlt { addr_width: size }
+                } else {
+                    RelocCode::Plt { addr_width: size }
+                };
+                let (content, relocs) = self.current_fixed();
+                let offset = content.len() as u64;
+                content.extend(std::iter::repeat(0).take(width));
+                relocs.push(Reloc {
+                    code,
+                    symbol: name,
+                    addend: Some(-(width as i64)),
+                    offset,
+                });
+                self.running_offset += width;
+                Ok(())
+            }
+            (Address::Abs(_), true) | (Address::Disp(_), false) => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "unsupported address/relocation combination"))
+            }
+        }
+    }
+
+    fn write_reloc(&mut self, mut reloc: Reloc) -> io::Result<()> {
+        let (content, relocs) = self.current_fixed();
+        reloc.offset += content.len() as u64;
+        relocs.push(reloc);
+        Ok(())
+    }
+
+    fn offset(&self) -> usize {
+        self.running_offset
+    }
+}
+
+/// Flattens `fragments` into final bytes, starting layout at
+/// `start_offset`. Every [`FragmentKind::Align`] is resolved against its
+/// actual position in `fragments`' order -- call this only after any
+/// reordering/duplication is done. Returns the encoded bytes, every
+/// relocation rebased to its final offset into those bytes, and a map
+/// from each fragment's label to the offset it landed at.
+pub fn layout_fragments(fragments: &[Fragment], start_offset: usize) -> (Vec<u8>, Vec<Reloc>, HashMap<String, usize>) {
+    let mut bytes = Vec::new();
+    let mut relocs = Vec::new();
+    let mut labels = HashMap::new();
+
+    for fragment in fragments {
+        if let Some(label) = &fragment.label {
+            labels.insert(label.clone(), start_offset + bytes.len());
+        }
+
+        match &fragment.kind {
+            FragmentKind::Fixed { content, relocs: frag_relocs } => {
+                let base = bytes.len() as u64;
+                bytes.extend_from_slice(content);
+                relocs.extend(frag_relocs.iter().cloned().map(|mut reloc| {
+                    reloc.offset += base;
+                    reloc
+                }));
+            }
+            FragmentKind::Align { align, fill } => {
+                let pos = start_offset + bytes.len();
+                let padding = pos.next_multiple_of(*align) - pos;
+                bytes.extend(std::iter::repeat(*fill).take(padding));
+            }
+        }
+    }
+
+    (bytes, relocs, labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_land_in_the_open_fragment() {
+        let mut writer = FragmentWriter::new();
+        io::Write::write_all(&mut writer, &[1, 2, 3]).unwrap();
+        writer.start_fragment(Some("l2".to_string()));
+        io::Write::write_all(&mut writer, &[4, 5]).unwrap();
+
+        let (bytes, _, labels) = layout_fragments(&writer.into_fragments(), 0);
+        assert_eq!(bytes, vec![1, 2, 3, 4, 5]);
+        assert_eq!(labels["l2"], 3);
+    }
+
+    #[test]
+    fn align_fragment_pads_to_boundary() {
+        let mut writer = FragmentWriter::new();
+        io::Write::write_all(&mut writer, &[1, 2, 3]).unwrap();
+        writer.align(4, 0);
+        writer.start_fragment(Some("aligned".to_string()));
+        io::Write::write_all(&mut writer, &[9]).unwrap();
+
+        let (bytes, _, labels) = layout_fragments(&writer.into_fragments(), 0);
+        assert_eq!(bytes, vec![1, 2, 3, 0, 9]);
+        assert_eq!(labels["aligned"], 4);
+    }
+
+    #[test]
+    fn reordering_fragments_before_layout_moves_their_bytes() {
+        let mut writer = FragmentWriter::new();
+        io::Write::write_all(&mut writer, &[1, 1]).unwrap();
+        writer.start_fragment(Some("b".to_string()));
+        io::Write::write_all(&mut writer, &[2, 2]).unwrap();
+
+        let mut fragments = writer.into_fragments();
+        fragments.swap(0, 1);
+
+        let (bytes, _, labels) = layout_fragments(&fragments, 0);
+        assert_eq!(bytes, vec![2, 2, 1, 1]);
+        assert_eq!(labels["b"], 0);
+    }
+
+    #[test]
+    fn symbol_address_produces_a_relocation_at_the_right_offset() {
+        let mut writer = FragmentWriter::new();
+        io::Write::write_all(&mut writer, &[0xAA]).unwrap();
+        writer
+            .write_addr(
+                32,
+                Address::Symbol {
+                    name: "target".to_string(),
+                    disp: 0,
+                },
+                false,
+            )
+            .unwrap();
+
+        let (bytes, relocs, _) = layout_fragments(&writer.into_fragments(), 0);
+        assert_eq!(bytes.len(), 5);
+        assert_eq!(relocs.len(), 1);
+        assert_eq!(relocs[0].offset, 1);
+        assert_eq!(relocs[0].symbol, "target");
+    }
+}
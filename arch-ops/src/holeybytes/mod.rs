@@ -1,6 +1,7 @@
 use std::str::FromStr;
 
 pub mod codec;
+pub mod stream;
 
 with_builtin_macros::with_builtin! {
     let $spec = include_from_root!("src/holeybytes/instructions.in") in {
@@ -34,7 +35,7 @@ macro_rules! opcodes {
             }
 
             impl Opcode {
-                pub fn ops_type(self) -> OpsType {
+                pub const fn ops_type(self) -> OpsType {
                     match self {
                         $(Self::[<$mnemonic:camel>] => OpsType::[<Ops $ty>]),*
                     }
@@ -73,6 +74,35 @@ macro_rules! opcodes {
 
 invoke_with_def!(opcodes);
 
+impl Opcode {
+    /// Total encoded length of an instruction with this opcode, in
+    /// bytes, including the opcode byte itself.
+    #[inline]
+    pub const fn encoded_len(self) -> usize {
+        1 + self.ops_type().encoded_len()
+    }
+
+    /// Whether this opcode ends a basic block: the relative and absolute
+    /// jumps, their linking (call) variants, and the conditional
+    /// branches. `ECA`/`EBP` trap into the environment but execution
+    /// falls through afterward, so they aren't block terminators.
+    pub const fn is_branch(self) -> bool {
+        matches!(
+            self,
+            Self::Jmp
+                | Self::Jmp16
+                | Self::Jal
+                | Self::Jala
+                | Self::Jeq
+                | Self::Jne
+                | Self::Jltu
+                | Self::Jgtu
+                | Self::Jlts
+                | Self::Jgts
+        )
+    }
+}
+
 /// Define newtype for operand type (or something else?)
 macro_rules! operands {
     ($($name:ident $inner:tt),* $(,)?) => {
@@ -187,6 +217,40 @@ define_operands! {
     = OpsN    (                                          ),
 }
 
+impl OpsType {
+    /// Size, in bytes, of this operand encoding as written by
+    /// [`codec::HbEncoder`] -- not including the leading opcode byte.
+    ///
+    /// This mirrors [`codec::HbEncoder::write_instruction`]'s layout
+    /// rather than re-deriving it, so a stream walker (see
+    /// [`super::stream`]) can skip an instruction by its opcode alone,
+    /// without decoding its operands.
+    pub const fn encoded_len(self) -> usize {
+        match self {
+            Self::OpsRR => 2,
+            Self::OpsRRR => 3,
+            Self::OpsRRRR => 4,
+            Self::OpsRRB => 3,
+            Self::OpsRRH => 4,
+            Self::OpsRRW => 6,
+            Self::OpsRB => 2,
+            Self::OpsRH => 3,
+            Self::OpsRW => 5,
+            Self::OpsRD => 9,
+            Self::OpsRRD => 10,
+            Self::OpsRRA => 10,
+            Self::OpsRRAH => 12,
+            Self::OpsRROH => 8,
+            Self::OpsRRPH => 6,
+            Self::OpsRRO => 6,
+            Self::OpsRRP => 4,
+            Self::OpsO => 4,
+            Self::OpsP => 2,
+            Self::OpsN => 0,
+        }
+    }
+}
+
 /// Verify if operands defined in spec do exist
 macro_rules! verify_ops {
     ($($_o:expr, $mnemonic:ident, $ty:ident, $_d:literal;)*) => {
@@ -251,4 +315,11 @@ impl Instruction {
     pub fn into_pair(self) -> (Opcode, Operands) {
         (self.opcode, self.operands)
     }
+
+    /// Total encoded length of this instruction, in bytes, as written by
+    /// [`codec::HbEncoder::write_instruction`].
+    #[inline]
+    pub const fn encoded_len(&self) -> usize {
+        self.opcode.encoded_len()
+    }
 }
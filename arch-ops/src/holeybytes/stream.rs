@@ -0,0 +1,81 @@
+//! Scanning over encoded holeybytes streams without decoding operands.
+//!
+//! [`Opcode::encoded_len`] is enough to walk a stream of already-encoded
+//! instructions one opcode byte at a time, and [`Opcode::is_branch`]
+//! tells us where a basic block ends -- together that's enough to
+//! relocate or patch a chunk of bytecode without a full
+//! decode-modify-encode cycle.
+
+use super::Opcode;
+
+/// Byte offset and [`Opcode`] of one instruction found while scanning a
+/// stream with [`instructions`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstructionOffset {
+    pub offset: usize,
+    pub opcode: Opcode,
+}
+
+/// A contiguous run of bytes, `start..end`, containing no branch except
+/// possibly as its final instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A byte in `code` did not correspond to any known [`Opcode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidOpcode {
+    pub offset: usize,
+    pub byte: u8,
+}
+
+impl core::fmt::Display for InvalidOpcode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid opcode {:#04x} at offset {}", self.byte, self.offset)
+    }
+}
+
+impl std::error::Error for InvalidOpcode {}
+
+/// Walks `code`, an encoded holeybytes instruction stream, yielding the
+/// offset and opcode of each instruction in turn by reading only the
+/// opcode byte of each instruction -- never its operands.
+pub fn instructions(code: &[u8]) -> impl Iterator<Item = Result<InstructionOffset, InvalidOpcode>> + '_ {
+    let mut pos = 0usize;
+    core::iter::from_fn(move || {
+        let byte = *code.get(pos)?;
+        let offset = pos;
+        let opcode = match Opcode::try_from(byte) {
+            Ok(opcode) => opcode,
+            Err(()) => return Some(Err(InvalidOpcode { offset, byte })),
+        };
+        pos += opcode.encoded_len();
+        Some(Ok(InstructionOffset { offset, opcode }))
+    })
+}
+
+/// Computes the basic-block boundaries of `code`: a block starts at
+/// offset `0` and immediately after every instruction for which
+/// [`Opcode::is_branch`] is true, so a block's last instruction is
+/// either a branch or the final instruction of the stream.
+pub fn basic_blocks(code: &[u8]) -> Result<Vec<BasicBlock>, InvalidOpcode> {
+    let mut blocks = Vec::new();
+    let mut start = 0usize;
+
+    for item in instructions(code) {
+        let InstructionOffset { offset, opcode } = item?;
+        let end = offset + opcode.encoded_len();
+        if opcode.is_branch() {
+            blocks.push(BasicBlock { start, end });
+            start = end;
+        }
+    }
+
+    if start < code.len() {
+        blocks.push(BasicBlock { start, end: code.len() });
+    }
+
+    Ok(blocks)
+}
@@ -0,0 +1,61 @@
+//! A common trait architecture-specific opcode enums (`X86Opcode`,
+//! `CleverOpcode`, ...) can implement so a driver that doesn't care which
+//! architecture it's looking at -- a documentation generator, the
+//! assembler's mnemonic lookup, a fuzzer -- can walk an instruction set's
+//! mnemonics, operand shapes, and required extensions generically.
+//! [`InsnTable::opcodes`] is the `X86_OPCODES`-style array promoted to a
+//! trait method, for architectures whose opcode enum has no per-variant
+//! payload and so can list every one of its variants as a single
+//! `'static` slice; [`InsnDescriptor`] alone (with no [`InsnTable`]) is
+//! what an architecture like Clever's, whose opcode enum packs
+//! per-instruction bit-field data into some variants, can still offer --
+//! one opcode value's metadata at a time, not a complete static
+//! enumeration of them.
+
+use std::fmt;
+
+/// One instruction form's static metadata: what an assembler's mnemonic
+/// lookup, a documentation generator, or a fuzzer driving an architecture
+/// generically needs, without inventing a lossy "kind of operand" enum
+/// that would have to flatten every architecture's real operand
+/// representation down to it.
+pub trait InsnDescriptor {
+    /// Each architecture's own operand-kind representation -- e.g.
+    /// `&'static [X86OperandType]` or `CleverOperandKind` -- returned
+    /// as-is rather than through a shared-but-lossy operand-kind enum.
+    type Operands: fmt::Debug + Copy;
+
+    /// The instruction's assembler mnemonic, as the target's assembly
+    /// syntax spells it.
+    fn mnemonic(&self) -> &'static str;
+
+    /// What operands this form takes, in the architecture's own
+    /// operand-kind representation.
+    fn operand_kinds(&self) -> Self::Operands;
+
+    /// The ISA extension this form requires, if the architecture has
+    /// extensions at all; `None` on one that doesn't, or for a form every
+    /// implementation of the architecture must support.
+    fn required_extension(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// This form's identity within the architecture's raw encoding space
+    /// -- not necessarily a complete encoded instruction (narrower than
+    /// that on an architecture that packs operand-selecting bits into the
+    /// same word, like Clever's h-bits), but enough to place every form
+    /// uniquely.
+    fn encoding(&self) -> u64;
+}
+
+/// An architecture whose [`InsnDescriptor`] implementor has no per-form
+/// payload, so every one of its opcode enum's variants can be listed as
+/// a single `'static` array -- what `X86_OPCODES` already was, exposed
+/// as a trait method a generic caller can reach through.
+pub trait InsnTable {
+    type Opcode: InsnDescriptor + 'static;
+
+    /// Every instruction form this architecture defines, in declaration
+    /// order.
+    fn opcodes() -> &'static [Self::Opcode];
+}
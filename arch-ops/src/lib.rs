@@ -5,6 +5,10 @@ pub mod traits;
 
 pub mod disasm;
 
+pub mod fragment;
+
+pub mod isa;
+
 #[cfg(feature = "w65")]
 pub mod w65;
 
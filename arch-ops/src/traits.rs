@@ -130,6 +130,11 @@ pub enum RelocCode {
     Plt { addr_width: usize },
     RelPlt { addr_width: usize },
     DynSymEntry { width: usize },
+    /// A TLS symbol's offset from its module's thread-local storage block
+    /// (the `@dtprel` family of relocations), as written by `.dtprelword`/
+    /// `.dtpreldword` -- resolved at link time the same way `Abs` is, just
+    /// relative to the TLS block's base rather than absolute.
+    DtpRel { addr_width: usize },
     W65Direct,
     W65RelaxJsl,
     W65RelaxJml,
@@ -149,3 +154,68 @@ pub struct Reloc {
     pub addend: Option<i64>,
     pub offset: u64,
 }
+
+/// Wraps an [`InsnRead`], invoking a callback with the stream offset and
+/// decoded [`Address`] every time the inner decoder reads an address or
+/// relocation. Used by disassemblers that want to annotate instructions with
+/// symbol/relocation information as they decode, without requiring each
+/// architecture's decoder to know about the annotation consumer.
+pub struct AnnotatingReader<R, F> {
+    inner: R,
+    pos: usize,
+    on_addr: F,
+}
+
+impl<R, F: FnMut(usize, &Address)> AnnotatingReader<R, F> {
+    pub fn new(inner: R, on_addr: F) -> Self {
+        Self {
+            inner,
+            pos: 0,
+            on_addr,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// The number of bytes read from the stream so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+impl<R: Read, F> Read for AnnotatingReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<R: InsnRead, F: FnMut(usize, &Address)> InsnRead for AnnotatingReader<R, F> {
+    fn read_addr(&mut self, size: usize, rel: bool) -> std::io::Result<Address> {
+        let at = self.pos;
+        let addr = self.inner.read_addr(size, rel)?;
+        self.pos += size / 8;
+        (self.on_addr)(at, &addr);
+        Ok(addr)
+    }
+
+    fn read_reloc(
+        &mut self,
+        size: usize,
+        rel: bool,
+        offset: Option<isize>,
+    ) -> std::io::Result<Option<Address>> {
+        let at = self.pos;
+        let addr = self.inner.read_reloc(size, rel, offset)?;
+        if offset.is_none() {
+            self.pos += size / 8;
+        }
+        if let Some(addr) = &addr {
+            (self.on_addr)(at, addr);
+        }
+        Ok(addr)
+    }
+}
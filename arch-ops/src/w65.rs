@@ -1194,3 +1194,98 @@ impl<W: InsnWrite> W65Encoder<W> {
         }
     }
 }
+
+/// The interrupt vectors reachable from native mode, occupying
+/// `$FFE4`-`$FFEF`. `$FFEC`-`$FFED` has no vector and is always written as
+/// zero by [`write_vector_table`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct W65NativeVectors {
+    pub cop: Option<String>,
+    pub brk: Option<String>,
+    pub abort: Option<String>,
+    pub nmi: Option<String>,
+    pub irq: Option<String>,
+}
+
+/// The interrupt vectors reachable from emulation mode -- the mode every
+/// 65C816 is reset into -- occupying `$FFF4`-`$FFFF`. `$FFF6`-`$FFF7` has no
+/// vector and is always written as zero by [`write_vector_table`].
+/// Emulation mode has no separate BRK vector: IRQ and BRK share `irq_brk`,
+/// the same as on a plain 6502.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct W65EmulationVectors {
+    pub cop: Option<String>,
+    pub abort: Option<String>,
+    pub nmi: Option<String>,
+    pub reset: Option<String>,
+    pub irq_brk: Option<String>,
+}
+
+/// The full `$FFE4`-`$FFFF` vector table of a 65C816 image. [`write_vector_table`]
+/// lays this out as 14 little-endian words in the order the hardware reads
+/// them; it is the caller's job to place the resulting bytes at `$FFE4`
+/// (typically via a linker script section), since nothing in `arch-ops`
+/// knows about final image layout.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct W65VectorTable {
+    pub native: W65NativeVectors,
+    pub emulation: W65EmulationVectors,
+}
+
+fn write_vector_word<W: InsnWrite>(w: &mut W, sym: Option<&str>) -> std::io::Result<()> {
+    match sym {
+        Some(name) => w.write_addr(
+            16,
+            Address::Symbol {
+                name: name.to_string(),
+                disp: 0,
+            },
+            false,
+        ),
+        None => w.write_all(&[0, 0]),
+    }
+}
+
+/// Writes the 28-byte, 14-word `$FFE4`-`$FFFF` vector table described by
+/// `table`. Each populated vector is written as a 16-bit absolute relocation
+/// against the named handler symbol; unpopulated vectors, including the two
+/// reserved gaps at `$FFEC` and `$FFF6`, are written as zero.
+pub fn write_vector_table<W: InsnWrite>(w: &mut W, table: &W65VectorTable) -> std::io::Result<()> {
+    write_vector_word(w, table.native.cop.as_deref())?;
+    write_vector_word(w, table.native.brk.as_deref())?;
+    write_vector_word(w, table.native.abort.as_deref())?;
+    write_vector_word(w, table.native.nmi.as_deref())?;
+    write_vector_word(w, None)?;
+    write_vector_word(w, table.native.irq.as_deref())?;
+    write_vector_word(w, None)?;
+    write_vector_word(w, None)?;
+    write_vector_word(w, table.emulation.cop.as_deref())?;
+    write_vector_word(w, None)?;
+    write_vector_word(w, table.emulation.abort.as_deref())?;
+    write_vector_word(w, table.emulation.nmi.as_deref())?;
+    write_vector_word(w, table.emulation.reset.as_deref())?;
+    write_vector_word(w, table.emulation.irq_brk.as_deref())
+}
+
+/// Emits the canonical 65C816 reset stub: disable interrupts, leave
+/// emulation mode for native mode, then set up the stack pointer at
+/// `stack_top`. This is the sequence every native-mode 65C816 program needs
+/// to run before anything else; it encodes `ldx`/`txs` using `enc`'s current
+/// mode, so a caller targeting an encoder whose index registers are not
+/// already 16-bit should `rep`/widen them first. It does not touch the `M`
+/// width flag or the `D`/`B` registers -- callers that need a 16-bit
+/// accumulator or a particular direct page/data bank should follow this
+/// with their own setup.
+pub fn write_reset_stub<W: InsnWrite>(
+    enc: &mut W65Encoder<W>,
+    stack_top: u16,
+) -> std::io::Result<()> {
+    enc.write_insn(W65Instruction::new(W65Opcode::Sei, W65Operand::Implied))?;
+    enc.write_insn(W65Instruction::new(W65Opcode::Clc, W65Operand::Implied))?;
+    enc.write_insn(W65Instruction::new(W65Opcode::Xce, W65Operand::Implied))?;
+    enc.write_insn(W65Instruction::new(
+        W65Opcode::Ldx,
+        W65Operand::Immediate(stack_top),
+    ))?;
+    enc.write_insn(W65Instruction::new(W65Opcode::Txs, W65Operand::Implied))
+}
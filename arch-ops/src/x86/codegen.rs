@@ -5,8 +5,16 @@ use std::{
     mem::MaybeUninit,
 };
 
+use smallvec::SmallVec;
+
 use super::{X86Mode, X86Register, X86RegisterClass};
 
+/// Inline capacity for [`X86Instruction`]'s operand list: enough for every
+/// instruction this crate encodes today without falling back to a heap
+/// allocation (the same rationale `x86::insn::X86Instruction`'s own
+/// operand list follows, for the unrelated operand type it encodes).
+type X86Operands = SmallVec<[X86Operand; 3]>;
+
 macro_rules! options{
     {
         $(#[$meta:meta])*
@@ -639,7 +647,7 @@ impl core::fmt::Display for X86Prefix {
 pub struct X86Instruction {
     prefix: Option<X86Prefix>,
     opc: X86CodegenOpcode,
-    oprs: Vec<X86Operand>,
+    oprs: X86Operands,
     mode_override: Option<X86Mode>,
 }
 
@@ -650,7 +658,7 @@ macro_rules! nop_instructions{
         paste::paste!{
             #[allow(non_upper_case_globals)]
             impl X86Instruction{
-                $(pub const [<$mnemonic:camel>]: Self = Self::new(X86CodegenOpcode::[<$mnemonic:camel>],vec![]);)*
+                $(pub const [<$mnemonic:camel>]: Self = Self::new_zop(X86CodegenOpcode::[<$mnemonic:camel>]);)*
             }
         }
     }
@@ -661,14 +669,28 @@ nop_instructions![
 ];
 
 impl X86Instruction {
-    pub const fn new(opc: X86CodegenOpcode, oprs: Vec<X86Operand>) -> Self {
+    pub fn new(opc: X86CodegenOpcode, oprs: impl Into<X86Operands>) -> Self {
+        Self {
+            prefix: None,
+            opc,
+            oprs: oprs.into(),
+            mode_override: None,
+        }
+    }
+
+    /// Built by the [`nop_instructions`] macro for the fixed set of
+    /// zero-operand instructions above, as a `const` item -- [`Self::new`]
+    /// can't be `const` once its operand list goes through [`Into`], so
+    /// this calls [`SmallVec::new_const`] directly instead.
+    const fn new_zop(opc: X86CodegenOpcode) -> Self {
         Self {
             prefix: None,
             opc,
-            oprs,
+            oprs: SmallVec::new_const(),
             mode_override: None,
         }
     }
+
     pub const fn with_prefix(mut self, prefix: X86Prefix) -> Self {
         self.prefix = Some(prefix);
         self
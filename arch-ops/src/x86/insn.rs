@@ -1,9 +1,17 @@
 use std::io::{Read, Write};
 
+use smallvec::SmallVec;
+
 use crate::traits::{Address, InsnRead, InsnWrite, Reloc};
 
 use super::{X86Register, X86RegisterClass};
 
+/// Inline capacity for [`X86Instruction`]'s operand list: enough for every
+/// instruction this crate encodes today (at most a destination, a source,
+/// and an immediate/shift count), so assembling a file doesn't heap
+/// allocate once per instruction the way a bare `Vec` would.
+type X86Operands = SmallVec<[X86Operand; 3]>;
+
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Prefix {
     Lock,
@@ -177,6 +185,19 @@ macro_rules! define_x86_instructions {
                 }
             }
 
+            /// Every feature at least one of which must be enabled for
+            /// this opcode to be valid -- disjunctive, like
+            /// [`X86Register::required_feature`]. Empty for an opcode
+            /// every implementation of its [`valid_in_mode`](Self::valid_in_mode)
+            /// modes must support.
+            pub fn required_features(&self) -> &'static [super::features::X86Feature] {
+                #[allow(unreachable_code)]
+                match self{
+                    $(Self:: $enum =>{ $($(return &[$(super::features::X86Feature::$feature),*];)?)? &[]}),*
+                    Self::__NoMoreOpcodes => unreachable!(),
+                }
+            }
+
         }
 
         impl std::fmt::Display for X86Opcode {
@@ -189,6 +210,34 @@ macro_rules! define_x86_instructions {
         }
 
         pub const X86_OPCODES: [X86Opcode; X86Opcode::__NoMoreOpcodes as usize] = [ $(X86Opcode::$enum,)* ];
+
+        impl crate::isa::InsnDescriptor for X86Opcode {
+            type Operands = &'static [X86OperandType];
+
+            fn mnemonic(&self) -> &'static str {
+                self.mnemonic()
+            }
+
+            fn operand_kinds(&self) -> Self::Operands {
+                self.operands()
+            }
+
+            fn required_extension(&self) -> Option<&'static str> {
+                self.required_features().first().map(|f| f.feature_name())
+            }
+
+            fn encoding(&self) -> u64 {
+                self.opcode()
+            }
+        }
+
+        impl crate::isa::InsnTable for X86Opcode {
+            type Opcode = X86Opcode;
+
+            fn opcodes() -> &'static [X86Opcode] {
+                &X86_OPCODES
+            }
+        }
     }
 }
 
@@ -679,27 +728,39 @@ pub enum X86Operand {
 #[derive(Clone, Debug)]
 pub struct X86Instruction {
     opc: X86Opcode,
-    operands: Vec<X86Operand>,
+    operands: X86Operands,
     mode_override: Option<X86Mode>,
 }
 
 impl X86Instruction {
-    pub const fn new(opc: X86Opcode, operands: Vec<X86Operand>) -> Self {
+    pub fn new(opc: X86Opcode, operands: impl Into<X86Operands>) -> Self {
         Self {
             opc,
-            operands,
+            operands: operands.into(),
             mode_override: None,
         }
     }
 
-    pub const fn new_in_mode(opc: X86Opcode, operands: Vec<X86Operand>, mode: X86Mode) -> Self {
+    pub fn new_in_mode(opc: X86Opcode, operands: impl Into<X86Operands>, mode: X86Mode) -> Self {
         Self {
             opc,
-            operands,
+            operands: operands.into(),
             mode_override: Some(mode),
         }
     }
 
+    /// Built by the [`zop_insns`] macro for the fixed set of zero-operand
+    /// instructions below, as a `const` item -- [`Self::new`] can't be
+    /// `const` once its operand list goes through [`Into`], so this calls
+    /// [`SmallVec::new_const`] directly instead.
+    const fn new_zop(opc: X86Opcode) -> Self {
+        Self {
+            opc,
+            operands: SmallVec::new_const(),
+            mode_override: None,
+        }
+    }
+
     pub const fn opcode(&self) -> X86Opcode {
         self.opc
     }
@@ -717,7 +778,7 @@ macro_rules! zop_insns{
     [$($name:ident),* $(,)?] => {
         #[allow(non_upper_case_globals)]
         impl X86Instruction{
-            $(pub const $name: X86Instruction = X86Instruction::new(X86Opcode:: $name, Vec::new());)*
+            $(pub const $name: X86Instruction = X86Instruction::new_zop(X86Opcode:: $name);)*
         }
     }
 }
@@ -774,6 +835,64 @@ impl<R> X86Decoder<R> {
     }
 }
 
+/// Mode-change markers for decoding a stream whose bitness switches
+/// mid-stream -- boot code transitioning real -> protected -> long mode,
+/// or an `objdump -M <mode>` override applied to part of a section.
+/// Markers are kept sorted by offset; [`ModeMarkers::mode_at`] finds the
+/// mode that applies at a given offset (the most recent marker at or
+/// before it, or a caller-supplied default if none apply yet).
+///
+/// Nothing drives this automatically: [`X86Decoder::read_insn`] isn't
+/// implemented yet, so there's no decode loop to call it from. A caller
+/// that walks a stream while tracking its own offset should call
+/// [`X86Decoder::set_mode`] with [`ModeMarkers::mode_at`]'s result before
+/// decoding each instruction.
+#[derive(Clone, Debug, Default)]
+pub struct ModeMarkers {
+    markers: Vec<(u64, X86Mode)>,
+}
+
+impl ModeMarkers {
+    pub fn new() -> Self {
+        Self {
+            markers: Vec::new(),
+        }
+    }
+
+    /// Adds a marker switching to `mode` at `offset`. Markers may be
+    /// added in any order; a second call with the same `offset` replaces
+    /// the earlier one.
+    pub fn add(&mut self, offset: u64, mode: X86Mode) {
+        self.markers.retain(|&(o, _)| o != offset);
+        let pos = self.markers.partition_point(|&(o, _)| o < offset);
+        self.markers.insert(pos, (offset, mode));
+    }
+
+    /// The mode that applies at `offset`: the mode of the most recent
+    /// marker at or before it, or `default` if none apply yet.
+    pub fn mode_at(&self, offset: u64, default: X86Mode) -> X86Mode {
+        let idx = self.markers.partition_point(|&(o, _)| o <= offset);
+        if idx == 0 {
+            default
+        } else {
+            self.markers[idx - 1].1
+        }
+    }
+}
+
+impl X86Mode {
+    /// Parses the mode names accepted by `objdump`'s `-M i8086`/`-M
+    /// i386`/`-M x86-64` per-range disassembly overrides.
+    pub fn from_objdump_name(name: &str) -> Option<X86Mode> {
+        match name {
+            "i8086" | "i086" => Some(X86Mode::Real),
+            "i386" => Some(X86Mode::Protected),
+            "x86-64" | "x86_64" => Some(X86Mode::Long),
+            _ => None,
+        }
+    }
+}
+
 impl<R: Read> Read for X86Decoder<R> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.reader.read(buf)
@@ -1991,10 +2110,47 @@ mod test {
 
     use crate::x86::X86Register;
 
-    use super::{ModRM, X86Encoder, X86Instruction, X86Mode, X86Opcode, X86Operand};
+    use super::{ModeMarkers, ModRM, X86Encoder, X86Instruction, X86Mode, X86Opcode, X86Operand};
 
     use crate::test::TestWriter;
 
+    #[test]
+    fn test_mode_markers_default_before_first() {
+        let markers = ModeMarkers::new();
+        assert_eq!(markers.mode_at(0, X86Mode::Real), X86Mode::Real);
+    }
+
+    #[test]
+    fn test_mode_markers_switches_at_boundary() {
+        let mut markers = ModeMarkers::new();
+        markers.add(0x7c00, X86Mode::Real);
+        markers.add(0x7e00, X86Mode::Protected);
+        markers.add(0x8000, X86Mode::Long);
+
+        assert_eq!(markers.mode_at(0x7c00, X86Mode::Long), X86Mode::Real);
+        assert_eq!(markers.mode_at(0x7dff, X86Mode::Long), X86Mode::Real);
+        assert_eq!(markers.mode_at(0x7e00, X86Mode::Long), X86Mode::Protected);
+        assert_eq!(markers.mode_at(0x7fff, X86Mode::Long), X86Mode::Protected);
+        assert_eq!(markers.mode_at(0x8000, X86Mode::Real), X86Mode::Long);
+    }
+
+    #[test]
+    fn test_mode_markers_replaces_duplicate_offset() {
+        let mut markers = ModeMarkers::new();
+        markers.add(0x100, X86Mode::Real);
+        markers.add(0x100, X86Mode::Protected);
+
+        assert_eq!(markers.mode_at(0x100, X86Mode::Long), X86Mode::Protected);
+    }
+
+    #[test]
+    fn test_mode_from_objdump_name() {
+        assert_eq!(X86Mode::from_objdump_name("i8086"), Some(X86Mode::Real));
+        assert_eq!(X86Mode::from_objdump_name("i386"), Some(X86Mode::Protected));
+        assert_eq!(X86Mode::from_objdump_name("x86-64"), Some(X86Mode::Long));
+        assert_eq!(X86Mode::from_objdump_name("bogus"), None);
+    }
+
     #[test]
     fn test_encoder_simple() {
         let mut enc = X86Encoder::new(TestWriter { inner: Vec::new() }, X86Mode::Protected);
@@ -3,10 +3,15 @@ use std::{
     io::{Seek, SeekFrom},
 };
 
+#[global_allocator]
+static ALLOC: binfmt::profile::CountingAllocator<std::alloc::System> =
+    binfmt::profile::CountingAllocator(std::alloc::System);
+
 fn main() -> std::io::Result<()> {
     let mut args = std::env::args();
     args.next().unwrap();
     let fname = args.next().unwrap();
+    let profile = args.any(|arg| arg == "--profile");
 
     let mut file = File::open(&fname)?;
 
@@ -30,5 +35,14 @@ fn main() -> std::io::Result<()> {
     }
     println!("]");
 
+    if profile {
+        for p in binfmt::profile::take_samples() {
+            println!(
+                "Profile: format={} duration={:?} bytes_materialized={} allocations={}",
+                p.format, p.duration, p.bytes_materialized, p.allocations
+            );
+        }
+    }
+
     Ok(())
 }
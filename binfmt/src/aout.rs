@@ -1 +1,553 @@
+//! The classic Unix `a.out` object/executable format (`OMAGIC`/`NMAGIC`/
+//! `ZMAGIC`), as used by early Unix and a number of retro toolchains that
+//! still target it.
 
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    fmt::{BinaryFile, Binfmt, CallbackError, FileType, Section, SectionType},
+    howto::{HowTo, HowToError, Reloc, RelocCode, RelocOutput},
+    sym::{Symbol, SymbolKind, SymbolType},
+    traits::ReadSeek,
+};
+
+pub const OMAGIC: u32 = 0o407;
+pub const NMAGIC: u32 = 0o410;
+pub const ZMAGIC: u32 = 0o413;
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AoutMagic {
+    OMagic,
+    NMagic,
+    ZMagic,
+}
+
+impl AoutMagic {
+    fn from_word(word: u32) -> Option<Self> {
+        match word {
+            OMAGIC => Some(Self::OMagic),
+            NMAGIC => Some(Self::NMagic),
+            ZMAGIC => Some(Self::ZMagic),
+            _ => None,
+        }
+    }
+
+    fn to_word(self) -> u32 {
+        match self {
+            Self::OMagic => OMAGIC,
+            Self::NMagic => NMAGIC,
+            Self::ZMagic => ZMAGIC,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct AoutHeader {
+    pub a_magic: u32,
+    pub a_text: u32,
+    pub a_data: u32,
+    pub a_bss: u32,
+    pub a_syms: u32,
+    pub a_entry: u32,
+    pub a_trsize: u32,
+    pub a_drsize: u32,
+}
+
+static_assertions::const_assert_eq!(core::mem::size_of::<AoutHeader>(), 32);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct AoutNlist {
+    pub n_strx: u32,
+    pub n_type: u8,
+    pub n_other: u8,
+    pub n_desc: i16,
+    pub n_value: u32,
+}
+
+static_assertions::const_assert_eq!(core::mem::size_of::<AoutNlist>(), 12);
+
+pub const N_UNDF: u8 = 0x00;
+pub const N_ABS: u8 = 0x02;
+pub const N_TEXT: u8 = 0x04;
+pub const N_DATA: u8 = 0x06;
+pub const N_BSS: u8 = 0x08;
+pub const N_EXT: u8 = 0x01;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct AoutReloc {
+    pub r_address: u32,
+    /// Packed `r_symbolnum:24 | r_pcrel:1 | r_length:2 | r_extern:1 | _pad:4`
+    pub r_info: u32,
+}
+
+static_assertions::const_assert_eq!(core::mem::size_of::<AoutReloc>(), 8);
+
+impl AoutReloc {
+    pub fn symbolnum(&self) -> u32 {
+        self.r_info & 0x00FF_FFFF
+    }
+
+    pub fn pcrel(&self) -> bool {
+        (self.r_info & (1 << 24)) != 0
+    }
+
+    pub fn length(&self) -> u32 {
+        (self.r_info >> 25) & 0x3
+    }
+
+    pub fn is_extern(&self) -> bool {
+        (self.r_info & (1 << 27)) != 0
+    }
+
+    pub fn new(symbolnum: u32, pcrel: bool, length: u32, is_extern: bool) -> Self {
+        let mut info = symbolnum & 0x00FF_FFFF;
+        if pcrel {
+            info |= 1 << 24;
+        }
+        info |= (length & 0x3) << 25;
+        if is_extern {
+            info |= 1 << 27;
+        }
+        Self {
+            r_address: 0,
+            r_info: info,
+        }
+    }
+}
+
+#[non_exhaustive]
+pub enum AoutHowTo {
+    Dir32,
+    Pcrel32,
+    Dir16,
+    Pcrel16,
+    Dir8,
+}
+
+impl AoutHowTo {
+    fn length_code(&self) -> u32 {
+        match self {
+            AoutHowTo::Dir8 => 0,
+            AoutHowTo::Dir16 | AoutHowTo::Pcrel16 => 1,
+            AoutHowTo::Dir32 | AoutHowTo::Pcrel32 => 2,
+        }
+    }
+}
+
+impl HowTo for AoutHowTo {
+    fn from_relnum<'a>(num: u32) -> Option<&'a Self>
+    where
+        Self: Sized + 'a,
+    {
+        match num {
+            0 => Some(&AoutHowTo::Dir32),
+            1 => Some(&AoutHowTo::Pcrel32),
+            2 => Some(&AoutHowTo::Dir16),
+            3 => Some(&AoutHowTo::Pcrel16),
+            4 => Some(&AoutHowTo::Dir8),
+            _ => None,
+        }
+    }
+
+    fn from_reloc_code<'a>(code: RelocCode) -> Option<&'a Self>
+    where
+        Self: Sized + 'a,
+    {
+        match code {
+            RelocCode::Abs { addr_width: 32 } => Some(&AoutHowTo::Dir32),
+            RelocCode::Rel { addr_width: 32 } => Some(&AoutHowTo::Pcrel32),
+            RelocCode::Abs { addr_width: 16 } => Some(&AoutHowTo::Dir16),
+            RelocCode::Rel { addr_width: 16 } => Some(&AoutHowTo::Pcrel16),
+            RelocCode::Abs { addr_width: 8 } => Some(&AoutHowTo::Dir8),
+            _ => None,
+        }
+    }
+
+    fn reloc_num(&self) -> u32 {
+        match self {
+            AoutHowTo::Dir32 => 0,
+            AoutHowTo::Pcrel32 => 1,
+            AoutHowTo::Dir16 => 2,
+            AoutHowTo::Pcrel16 => 3,
+            AoutHowTo::Dir8 => 4,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            AoutHowTo::Dir32 => "DIR32",
+            AoutHowTo::Pcrel32 => "PCREL32",
+            AoutHowTo::Dir16 => "DIR16",
+            AoutHowTo::Pcrel16 => "PCREL16",
+            AoutHowTo::Dir8 => "DIR8",
+        }
+    }
+
+    fn reloc_size(&self) -> usize {
+        match self {
+            AoutHowTo::Dir32 | AoutHowTo::Pcrel32 => 4,
+            AoutHowTo::Dir16 | AoutHowTo::Pcrel16 => 2,
+            AoutHowTo::Dir8 => 1,
+        }
+    }
+
+    fn pcrel(&self) -> bool {
+        matches!(self, AoutHowTo::Pcrel32 | AoutHowTo::Pcrel16)
+    }
+
+    fn is_relax(&self) -> bool {
+        false
+    }
+
+    fn relax_size(&self, _addr: u128, _at_addr: u128) -> Option<usize> {
+        None
+    }
+
+    fn apply<'a>(
+        &self,
+        addr: u128,
+        at_addr: u128,
+        region: &'a mut [u8],
+    ) -> Result<&'a mut [u8], HowToError> {
+        let val = if self.pcrel() {
+            (addr as i128) - (at_addr as i128)
+        } else {
+            addr as i128
+        };
+        match self {
+            AoutHowTo::Dir32 | AoutHowTo::Pcrel32 => {
+                region.copy_from_slice(&(val as i32).to_le_bytes())
+            }
+            AoutHowTo::Dir16 | AoutHowTo::Pcrel16 => {
+                region.copy_from_slice(&(val as i16).to_le_bytes())
+            }
+            AoutHowTo::Dir8 => region.copy_from_slice(&(val as i8).to_le_bytes()),
+        }
+        Ok(region)
+    }
+
+    fn valid_in(&self, _output_ty: RelocOutput, _sym_vis: &Symbol) -> bool {
+        true
+    }
+}
+
+fn section_name_for_ntype(n_type: u8) -> &'static str {
+    match n_type & !N_EXT {
+        N_TEXT => ".text",
+        N_DATA => ".data",
+        N_BSS => ".bss",
+        _ => ".text",
+    }
+}
+
+fn ntype_for_section(name: &str) -> u8 {
+    match name {
+        ".text" => N_TEXT,
+        ".data" => N_DATA,
+        ".bss" => N_BSS,
+        _ => N_ABS,
+    }
+}
+
+pub struct Aout {
+    magic: AoutMagic,
+}
+
+pub fn create_format() -> Aout {
+    Aout {
+        magic: AoutMagic::ZMagic,
+    }
+}
+
+impl Binfmt for Aout {
+    fn relnum_to_howto(&self, relnum: u32) -> Option<&dyn HowTo> {
+        AoutHowTo::from_relnum(relnum).map(|x| x as &dyn HowTo)
+    }
+
+    fn code_to_howto(&self, code: RelocCode) -> Option<&dyn HowTo> {
+        AoutHowTo::from_reloc_code(code).map(|x| x as &dyn HowTo)
+    }
+
+    fn name(&self) -> &'static str {
+        "aout"
+    }
+
+    fn create_file(&self, ty: FileType) -> BinaryFile {
+        BinaryFile::create(self, Box::new(()), ty)
+    }
+
+    fn ident_file(&self, file: &mut (dyn std::io::Read + '_)) -> std::io::Result<bool> {
+        let mut buf = [0u8; 4];
+        if file.read_exact(&mut buf).is_err() {
+            return Ok(false);
+        }
+        Ok(AoutMagic::from_word(u32::from_le_bytes(buf)).is_some())
+    }
+
+    fn read_file(&self, file: &mut (dyn ReadSeek + '_)) -> crate::error::Result<Option<BinaryFile>> {
+        let mut header = AoutHeader::zeroed();
+        file.read_exact(bytemuck::bytes_of_mut(&mut header))?;
+
+        if AoutMagic::from_word(header.a_magic).is_none() {
+            return Ok(None);
+        }
+
+        let ty = if header.a_syms == 0 && header.a_trsize == 0 && header.a_drsize == 0 {
+            FileType::Exec
+        } else {
+            FileType::Relocatable
+        };
+        let mut bfile = BinaryFile::create(self, Box::new(()), ty);
+
+        let mut text = vec![0u8; header.a_text as usize];
+        file.read_exact(&mut text)?;
+        let mut data = vec![0u8; header.a_data as usize];
+        file.read_exact(&mut data)?;
+
+        let mut text_relocs_raw =
+            vec![AoutReloc::zeroed(); header.a_trsize as usize / size_of::<AoutReloc>()];
+        file.read_exact(bytemuck::cast_slice_mut(&mut text_relocs_raw))?;
+        let mut data_relocs_raw =
+            vec![AoutReloc::zeroed(); header.a_drsize as usize / size_of::<AoutReloc>()];
+        file.read_exact(bytemuck::cast_slice_mut(&mut data_relocs_raw))?;
+
+        let mut nlists =
+            vec![AoutNlist::zeroed(); header.a_syms as usize / size_of::<AoutNlist>()];
+        file.read_exact(bytemuck::cast_slice_mut(&mut nlists))?;
+
+        let mut strtab_size_buf = [0u8; 4];
+        file.read_exact(&mut strtab_size_buf)?;
+        let strtab_size = u32::from_le_bytes(strtab_size_buf);
+        let mut strtab = vec![0u8; strtab_size.max(4) as usize];
+        if strtab_size > 4 {
+            file.read_exact(&mut strtab[4..])?;
+        }
+
+        let read_str = |strx: u32| -> String {
+            let bytes = &strtab[(strx as usize).min(strtab.len())..];
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            String::from_utf8_lossy(&bytes[..end]).into_owned()
+        };
+
+        let text_secno = bfile
+            .add_section(Section {
+                name: ".text".to_string(),
+                align: 4,
+                ty: SectionType::ProgBits,
+                content: text,
+                ..Default::default()
+            })
+            .ok();
+        let data_secno = bfile
+            .add_section(Section {
+                name: ".data".to_string(),
+                align: 4,
+                ty: SectionType::ProgBits,
+                content: data,
+                ..Default::default()
+            })
+            .ok();
+        bfile
+            .add_section(Section {
+                name: ".bss".to_string(),
+                align: 4,
+                ty: SectionType::NoBits,
+                tail_size: header.a_bss as usize,
+                ..Default::default()
+            })
+            .ok();
+
+        for nlist in &nlists {
+            if nlist.n_strx == 0 {
+                continue;
+            }
+            let name = read_str(nlist.n_strx);
+            let kind = if (nlist.n_type & N_EXT) != 0 {
+                SymbolKind::Global
+            } else {
+                SymbolKind::Local
+            };
+            let secno = match nlist.n_type & !N_EXT {
+                N_TEXT => text_secno,
+                N_DATA => data_secno,
+                N_UNDF => None,
+                _ => Some(0),
+            };
+            let sym = match secno {
+                Some(secno) => {
+                    Symbol::new(name, secno, nlist.n_value as u128, SymbolType::Null, kind)
+                }
+                None => Symbol::new_undef(name, SymbolType::Null, kind),
+            };
+            bfile.insert_symbol(sym).ok();
+        }
+
+        for relocs_raw in [&text_relocs_raw, &data_relocs_raw] {
+            for raw in relocs_raw {
+                let howto = AoutHowTo::from_relnum(match (raw.pcrel(), raw.length()) {
+                    (false, 2) => 0,
+                    (true, 2) => 1,
+                    (false, 1) => 2,
+                    (true, 1) => 3,
+                    _ => 4,
+                })
+                .unwrap();
+                let symbol = if raw.is_extern() {
+                    nlists
+                        .iter()
+                        .filter(|n| (n.n_type & N_EXT) != 0)
+                        .nth(raw.symbolnum() as usize)
+                        .map(|n| read_str(n.n_strx))
+                        .unwrap_or_default()
+                } else {
+                    section_name_for_ntype(raw.symbolnum() as u8).to_string()
+                };
+                let code = match howto {
+                    AoutHowTo::Dir32 => RelocCode::Abs { addr_width: 32 },
+                    AoutHowTo::Pcrel32 => RelocCode::Rel { addr_width: 32 },
+                    AoutHowTo::Dir16 => RelocCode::Abs { addr_width: 16 },
+                    AoutHowTo::Pcrel16 => RelocCode::Rel { addr_width: 16 },
+                    AoutHowTo::Dir8 => RelocCode::Abs { addr_width: 8 },
+                };
+                bfile
+                    .create_reloc(Reloc {
+                        code,
+                        symbol,
+                        addend: None,
+                        offset: raw.r_address as u64,
+                    })
+                    .ok();
+            }
+        }
+
+        Ok(Some(bfile))
+    }
+
+    fn write_file(
+        &self,
+        file: &mut (dyn std::io::Write + '_),
+        bfile: &BinaryFile,
+    ) -> crate::error::Result<()> {
+        let text = bfile
+            .sections()
+            .find(|s| s.name == ".text")
+            .map(|s| s.content.clone())
+            .unwrap_or_default();
+        let data = bfile
+            .sections()
+            .find(|s| s.name == ".data")
+            .map(|s| s.content.clone())
+            .unwrap_or_default();
+        let bss = bfile
+            .sections()
+            .find(|s| s.name == ".bss")
+            .map(|s| s.tail_size)
+            .unwrap_or(0);
+
+        let is_obj = matches!(bfile.file_type(), FileType::Relocatable);
+
+        let mut strtab = vec![0u8; 4];
+        let mut nlists = Vec::new();
+        let mut global_order = Vec::new();
+
+        for sym in bfile.symbols() {
+            let strx = strtab.len() as u32;
+            strtab.extend_from_slice(sym.name().as_bytes());
+            strtab.push(0);
+
+            let mut n_type = match sym.section().and_then(|secno| bfile.get_section(secno)) {
+                Some(sect) => ntype_for_section(&sect.name),
+                None => N_UNDF,
+            };
+            if sym.kind() == SymbolKind::Global || sym.kind() == SymbolKind::Weak {
+                n_type |= N_EXT;
+                global_order.push(sym.name().to_string());
+            }
+
+            nlists.push(AoutNlist {
+                n_strx: strx,
+                n_type,
+                n_other: 0,
+                n_desc: 0,
+                n_value: sym.value().unwrap_or(0) as u32,
+            });
+        }
+
+        let mut text_relocs = Vec::new();
+        let mut data_relocs = Vec::new();
+        for (sect_name, out) in [(".text", &mut text_relocs), (".data", &mut data_relocs)] {
+            let Some(sect) = bfile.sections().find(|s| s.name == sect_name) else {
+                continue;
+            };
+            for reloc in &sect.relocs {
+                let howto = self
+                    .code_to_howto(reloc.code)
+                    .and_then(|h| AoutHowTo::from_relnum(h.reloc_num()));
+                let (pcrel, length) = howto.map_or((false, 2), |h| (h.pcrel(), h.length_code()));
+                let symbolnum = global_order.iter().position(|n| n == &reloc.symbol);
+                let mut raw = AoutReloc::new(
+                    symbolnum.unwrap_or(0) as u32,
+                    pcrel,
+                    length,
+                    symbolnum.is_some(),
+                );
+                raw.r_address = reloc.offset as u32;
+                out.push(raw);
+            }
+        }
+
+        let header = AoutHeader {
+            a_magic: self.magic.to_word(),
+            a_text: text.len() as u32,
+            a_data: data.len() as u32,
+            a_bss: bss as u32,
+            a_syms: if is_obj {
+                (nlists.len() * size_of::<AoutNlist>()) as u32
+            } else {
+                0
+            },
+            a_entry: 0,
+            a_trsize: if is_obj {
+                (text_relocs.len() * size_of::<AoutReloc>()) as u32
+            } else {
+                0
+            },
+            a_drsize: if is_obj {
+                (data_relocs.len() * size_of::<AoutReloc>()) as u32
+            } else {
+                0
+            },
+        };
+
+        file.write_all(bytemuck::bytes_of(&header))?;
+        file.write_all(&text)?;
+        file.write_all(&data)?;
+        if is_obj {
+            file.write_all(bytemuck::cast_slice(&text_relocs))?;
+            file.write_all(bytemuck::cast_slice(&data_relocs))?;
+            file.write_all(bytemuck::cast_slice(&nlists))?;
+            file.write_all(&(strtab.len() as u32).to_le_bytes())?;
+            file.write_all(&strtab[4..])?;
+        }
+
+        Ok(())
+    }
+
+    fn has_sections(&self) -> bool {
+        true
+    }
+
+    fn create_symbol(&self, _sym: &mut Symbol) -> Result<(), CallbackError> {
+        Ok(())
+    }
+
+    fn create_reloc(&self, _reloc: &mut Reloc) -> Result<(), CallbackError> {
+        Ok(())
+    }
+}
@@ -2,8 +2,9 @@ use std::{
     error::Error,
     ffi::{CString, OsStr, OsString},
     fmt::Display,
-    io::{Cursor, ErrorKind, Read, Write},
+    io::{Cursor, ErrorKind, Read, Seek, SeekFrom, Write},
     mem::size_of,
+    path::Path,
     slice,
     time::SystemTime,
 };
@@ -167,6 +168,94 @@ impl ArchiveMember {
         }
     }
 
+    fn field_str(field: &[u8]) -> &str {
+        std::str::from_utf8(field).unwrap_or_default().trim()
+    }
+
+    /// The member's modification date, as a Unix timestamp, or `None` if the
+    /// field has never been set.
+    pub fn date(&self) -> Option<u64> {
+        Self::field_str(&self.header.ar_date).parse().ok()
+    }
+
+    pub fn uid(&self) -> Option<u32> {
+        Self::field_str(&self.header.ar_uid).parse().ok()
+    }
+
+    pub fn gid(&self) -> Option<u32> {
+        Self::field_str(&self.header.ar_gid).parse().ok()
+    }
+
+    /// The member's Unix file mode, parsed as octal the way `ar_mode` is
+    /// always written.
+    pub fn mode(&self) -> Option<u32> {
+        u32::from_str_radix(Self::field_str(&self.header.ar_mode), 8).ok()
+    }
+
+    pub fn set_mode(&mut self, mode: u32) -> Result<(), ArchiveMetaOutOfRange<u32>> {
+        if mode > 0o7777777 {
+            Err(ArchiveMetaOutOfRange(mode))
+        } else {
+            write!((&mut self.header.ar_mode) as &mut [_], "{:<8o}", mode).unwrap();
+            Ok(())
+        }
+    }
+
+    /// Extracts this member's contents to `path`, optionally restoring its
+    /// stored `mtime`/mode (GNU ar's `-o`/`O` and default-mode behaviour).
+    pub fn extract_to(&self, path: &Path, preserve_date: bool) -> std::io::Result<()> {
+        std::fs::write(path, &self.bytes)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if let Some(mode) = self.mode() {
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+            }
+        }
+
+        if preserve_date {
+            if let Some(date) = self.date() {
+                let mtime = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(date);
+                let file = std::fs::File::open(path)?;
+                file.set_modified(mtime)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds a member from a file on disk, using its name, mtime, uid, gid,
+    /// and mode (on unix) as the member metadata, the way GNU `ar r`/`ar q`
+    /// populate a new archive member.
+    pub fn from_path(path: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let meta = std::fs::metadata(path)?;
+
+        let mut member = Self::new();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        member.set_name(&name);
+
+        if let Ok(modified) = meta.modified() {
+            let _ = member.set_date(modified);
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let _ = member.set_uid(meta.uid());
+            let _ = member.set_gid(meta.gid());
+            let _ = member.set_mode(meta.mode());
+        }
+
+        std::io::Write::write_all(&mut member, &bytes)?;
+
+        Ok(member)
+    }
+
     pub fn set_name(&mut self, st: &str) {
         if st.len() > 15 {
             self.long_name = Some(OsString::from(st));
@@ -441,6 +530,34 @@ impl Archive {
         self.members.last_mut().unwrap()
     }
 
+    /// Appends a new member built from the file at `path`, preserving its
+    /// on-disk metadata (mtime/uid/gid/mode), the way `ar r <archive> <path>`
+    /// does.
+    pub fn add_member_from_path(&mut self, path: &Path) -> std::io::Result<&mut ArchiveMember> {
+        let member = ArchiveMember::from_path(path)?;
+        self.members.push(member);
+        Ok(self.members.last_mut().unwrap())
+    }
+
+    /// Extracts the named member to `path`. `preserve_date` corresponds to
+    /// GNU ar's `-o`/original-date extraction flag; without it, the
+    /// extracted file is given the current time like a freshly-created file.
+    pub fn extract_member_to(
+        &self,
+        name: &OsStr,
+        path: &Path,
+        preserve_date: bool,
+    ) -> std::io::Result<()> {
+        let member = self
+            .members
+            .iter()
+            .find(|m| m.get_name() == name)
+            .ok_or_else(|| {
+                std::io::Error::new(ErrorKind::NotFound, "No such member in archive")
+            })?;
+        member.extract_to(path, preserve_date)
+    }
+
     pub fn members(&self) -> &[ArchiveMember] {
         &self.members
     }
@@ -456,5 +573,166 @@ impl Default for Archive {
     }
 }
 
+/// The special member name GNU `ar` gives its 64-bit symbol index,
+/// used once any member's header would start past the 4 GiB a classic
+/// `/`-named index's 32-bit big-endian offsets can address -- the same
+/// point `nm`/`ranlib` switch formats at.
+pub const SYM64_NAME: &str = "/SYM64/";
+
+/// One `name -> defining member` entry from a GNU archive symbol index
+/// (the `/` or `/SYM64/` special member): the symbol's name and the
+/// byte offset, from the start of the archive (its `ARMAG` included),
+/// of the header of the member that defines it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolIndexEntry {
+    pub name: String,
+    pub member_offset: u64,
+}
+
+/// Whether a symbol index needs the 64-bit GNU extension: true once any
+/// entry's offset doesn't fit in a big-endian `u32`, the classic `/`
+/// index's offset width.
+pub fn needs_sym64(entries: &[SymbolIndexEntry]) -> bool {
+    entries.iter().any(|e| e.member_offset > u32::MAX as u64)
+}
+
+/// Parses a symbol index member's contents -- the bytes of the special
+/// `/` or `/SYM64/` member -- given its offset width (4 for `/`, 8 for
+/// `/SYM64/`): a big-endian entry count of that same width, that many
+/// big-endian offsets, then that many NUL-terminated symbol names in the
+/// same order as the offsets.
+pub fn parse_symbol_index(bytes: &[u8], offset_width: usize) -> std::io::Result<Vec<SymbolIndexEntry>> {
+    fn read_be(bytes: &[u8], width: usize) -> std::io::Result<(u64, &[u8])> {
+        if bytes.len() < width {
+            return Err(std::io::Error::new(ErrorKind::InvalidData, "truncated symbol index"));
+        }
+        let (field, rest) = (&bytes[..width], &bytes[width..]);
+        let mut buf = [0u8; 8];
+        buf[8 - width..].copy_from_slice(field);
+        Ok((u64::from_be_bytes(buf), rest))
+    }
+
+    let (count, mut rest) = read_be(bytes, offset_width)?;
+    let count = usize::try_from(count)
+        .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+
+    let mut offsets = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (offset, tail) = read_be(rest, offset_width)?;
+        offsets.push(offset);
+        rest = tail;
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    for offset in offsets {
+        let nul = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidData, "unterminated symbol name"))?;
+        let name = std::str::from_utf8(&rest[..nul])
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?
+            .to_string();
+        rest = &rest[nul + 1..];
+        entries.push(SymbolIndexEntry {
+            name,
+            member_offset: offset,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Builds a symbol index member's contents from `entries`, choosing
+/// 8-byte big-endian offsets (and the `/SYM64/` name) if
+/// [`needs_sym64`] says any of them need it, or 4-byte offsets (and the
+/// classic `/` name) otherwise. Returns the member name to give the
+/// built bytes alongside the bytes themselves.
+pub fn build_symbol_index(entries: &[SymbolIndexEntry]) -> (&'static str, Vec<u8>) {
+    let width = if needs_sym64(entries) { 8 } else { 4 };
+    let name = if width == 8 { SYM64_NAME } else { "/" };
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(entries.len() as u64).to_be_bytes()[8 - width..]);
+    for entry in entries {
+        bytes.extend_from_slice(&entry.member_offset.to_be_bytes()[8 - width..]);
+    }
+    for entry in entries {
+        bytes.extend_from_slice(entry.name.as_bytes());
+        bytes.push(0);
+    }
+
+    (name, bytes)
+}
+
+/// One archive member's header, read without loading its content into
+/// memory -- what [`iter_member_headers`] yields.
+#[derive(Copy, Clone, Debug)]
+pub struct StreamedMemberHeader {
+    pub header: ArchiveHeader,
+    pub size: u64,
+    /// Byte offset, from the start of the archive, of this member's
+    /// content (immediately following its header).
+    pub content_offset: u64,
+}
+
+/// Walks an archive's member headers by seeking past each member's
+/// content rather than reading it, so an archive far larger than
+/// available memory -- or a caller that only needs a handful of its
+/// members -- never needs the whole thing resident at once. [`read_member_content`] reads a given member's bytes on
+/// demand once its header is known.
+pub fn iter_member_headers<R: Read + Seek>(mut r: R) -> std::io::Result<Vec<StreamedMemberHeader>> {
+    let mut mag = [0u8; 8];
+    r.read_exact(&mut mag)?;
+    if mag != ARMAG {
+        return Err(std::io::Error::new(ErrorKind::InvalidData, "Invalid Archive"));
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        let mut raw = [0u8; size_of::<ArchiveHeader>()];
+        match r.read_exact(&mut raw) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        // SAFETY: see `ArchiveMember::read` -- same header type, same
+        // fixed-size all-bytes layout.
+        let header: ArchiveHeader = unsafe { core::mem::transmute(raw) };
+        if header.ar_fmag != FMAG {
+            return Err(std::io::Error::new(ErrorKind::InvalidData, "Invalid Archive Header"));
+        }
+
+        let size = std::str::from_utf8(&header.ar_size)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))
+            .and_then(|s| {
+                s.trim()
+                    .parse::<u64>()
+                    .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))
+            })?;
+
+        let content_offset = r.stream_position()?;
+        let padded_size = size + (size % 2);
+        r.seek(SeekFrom::Current(padded_size as i64))?;
+
+        headers.push(StreamedMemberHeader {
+            header,
+            size,
+            content_offset,
+        });
+    }
+
+    Ok(headers)
+}
+
+/// Reads one streamed member's content on demand, seeking to where
+/// [`iter_member_headers`] found it rather than assuming the stream is
+/// still positioned there.
+pub fn read_member_content<R: Read + Seek>(mut r: R, member: &StreamedMemberHeader) -> std::io::Result<Vec<u8>> {
+    r.seek(SeekFrom::Start(member.content_offset))?;
+    let mut bytes = vec![0u8; member.size as usize];
+    r.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
 #[cfg(test)]
 pub mod tests;
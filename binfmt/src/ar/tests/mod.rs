@@ -1,6 +1,7 @@
 use std::ffi::OsStr;
+use std::io::Cursor;
 
-use super::Archive;
+use super::{build_symbol_index, iter_member_headers, needs_sym64, parse_symbol_index, read_member_content, Archive, SymbolIndexEntry, SYM64_NAME};
 
 #[test]
 #[ignore] // broken - fix later
@@ -12,3 +13,58 @@ pub fn archive() {
     let m1 = &members[0];
     assert_eq!(m1.get_name(), OsStr::new("empty_rel.o"));
 }
+
+#[test]
+pub fn small_symbol_index_uses_32_bit_offsets() {
+    let entries = vec![
+        SymbolIndexEntry {
+            name: "foo".to_string(),
+            member_offset: 8,
+        },
+        SymbolIndexEntry {
+            name: "bar".to_string(),
+            member_offset: 200,
+        },
+    ];
+
+    let (name, bytes) = build_symbol_index(&entries);
+    assert_eq!(name, "/");
+
+    let parsed = parse_symbol_index(&bytes, 4).unwrap();
+    assert_eq!(parsed, entries);
+}
+
+#[test]
+pub fn oversized_offset_upgrades_to_sym64() {
+    let entries = vec![SymbolIndexEntry {
+        name: "big".to_string(),
+        member_offset: (u32::MAX as u64) + 1,
+    }];
+    assert!(needs_sym64(&entries));
+
+    let (name, bytes) = build_symbol_index(&entries);
+    assert_eq!(name, SYM64_NAME);
+
+    let parsed = parse_symbol_index(&bytes, 8).unwrap();
+    assert_eq!(parsed, entries);
+}
+
+#[test]
+pub fn streamed_headers_find_every_member_without_reading_content() {
+    let mut archive = Archive::new();
+    archive.new_member().set_name("a.o");
+    std::io::Write::write_all(&mut archive.members_mut()[0], b"hello").unwrap();
+    archive.new_member().set_name("b.o");
+    std::io::Write::write_all(&mut archive.members_mut()[1], b"a bit longer body").unwrap();
+
+    let mut bytes = Vec::new();
+    archive.write(&mut bytes).unwrap();
+
+    let headers = iter_member_headers(Cursor::new(&bytes)).unwrap();
+    assert_eq!(headers.len(), 2);
+    assert_eq!(headers[0].size, 5);
+    assert_eq!(headers[1].size, 17);
+
+    let content = read_member_content(Cursor::new(&bytes), &headers[1]).unwrap();
+    assert_eq!(content, b"a bit longer body");
+}
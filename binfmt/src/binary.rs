@@ -9,6 +9,35 @@ pub fn create_format() -> Binary {
     Binary
 }
 
+/// Format-specific metadata for a raw `binary`-format [`BinaryFile`]. Plain
+/// binary dumps have no header to carry a load address or entry point, so
+/// this is threaded through [`BinaryFile::data`] instead, to be set by a
+/// caller (such as `lcld`) before the file is written, or inspected after it
+/// is read.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BinaryMetadata {
+    pub load_addr: u64,
+    pub entry: Option<u64>,
+}
+
+/// Retrieves the [`BinaryMetadata`] of a `binary`-format file, or the
+/// default (zero load address, no entry point) if none has been set.
+pub fn metadata(bfile: &BinaryFile) -> BinaryMetadata {
+    bfile
+        .data()
+        .downcast_ref::<BinaryMetadata>()
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Sets the [`BinaryMetadata`] of a `binary`-format file created via
+/// [`Binary::create_file`] or returned from [`Binary::read_file`].
+pub fn set_metadata(bfile: &mut BinaryFile, metadata: BinaryMetadata) {
+    if let Some(slot) = bfile.data_mut().downcast_mut::<BinaryMetadata>() {
+        *slot = metadata;
+    }
+}
+
 impl Binfmt for Binary {
     fn relnum_to_howto(&self, _relnum: u32) -> Option<&dyn crate::howto::HowTo> {
         None
@@ -23,16 +52,17 @@ impl Binfmt for Binary {
     }
 
     fn create_file(&self, ty: FileType) -> crate::fmt::BinaryFile {
-        BinaryFile::create(self, Box::new(()), ty)
+        BinaryFile::create(self, Box::new(BinaryMetadata::default()), ty)
     }
 
     fn read_file(
         &self,
         file: &mut (dyn ReadSeek + '_),
-    ) -> std::io::Result<Option<crate::fmt::BinaryFile>> {
+    ) -> crate::error::Result<Option<crate::fmt::BinaryFile>> {
         let mut vec = Vec::new();
         file.read_to_end(&mut vec)?;
-        let mut file = BinaryFile::create(self, Box::new(()), FileType::Exec);
+        let mut file =
+            BinaryFile::create(self, Box::new(BinaryMetadata::default()), FileType::Exec);
         let _ = file.add_section(Section {
             align: 1,
             content: vec,
@@ -49,7 +79,7 @@ impl Binfmt for Binary {
         &self,
         file: &mut (dyn std::io::Write + '_),
         bfile: &crate::fmt::BinaryFile,
-    ) -> std::io::Result<()> {
+    ) -> crate::error::Result<()> {
         for s in bfile.sections() {
             file.write_all(&s.content)?;
         }
@@ -60,6 +90,14 @@ impl Binfmt for Binary {
         true
     }
 
+    fn supports_relocs(&self) -> bool {
+        false
+    }
+
+    fn supports_debug(&self) -> bool {
+        false
+    }
+
     fn create_section(
         &self,
         _section: &mut crate::fmt::Section,
@@ -1,5 +1,8 @@
 use bytemuck::{Pod, Zeroable};
 
+#[cfg(feature = "x86")]
+pub mod i386;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Zeroable, Pod)]
 pub struct CoffHeader {
@@ -0,0 +1,295 @@
+use crate::howto::{HowTo, HowToError, RelocCode};
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum CoffI386HowTo {
+    Absolute,
+    Dir16,
+    Rel16,
+    Dir32,
+    Dir32Nb,
+    Seg12,
+    Section,
+    Secrel,
+    Token,
+    Secrel7,
+    Rel32,
+}
+
+static HOWTO: [Option<CoffI386HowTo>; 0x15] = [
+    Some(CoffI386HowTo::Absolute),
+    Some(CoffI386HowTo::Dir16),
+    Some(CoffI386HowTo::Rel16),
+    None,
+    None,
+    None,
+    Some(CoffI386HowTo::Dir32),
+    Some(CoffI386HowTo::Dir32Nb),
+    None,
+    Some(CoffI386HowTo::Seg12),
+    Some(CoffI386HowTo::Section),
+    Some(CoffI386HowTo::Secrel),
+    Some(CoffI386HowTo::Token),
+    Some(CoffI386HowTo::Secrel7),
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    Some(CoffI386HowTo::Rel32),
+];
+
+impl HowTo for CoffI386HowTo {
+    fn from_relnum<'a>(num: u32) -> Option<&'a Self>
+    where
+        Self: Sized + 'a,
+    {
+        HOWTO.get(num as usize).and_then(Option::as_ref)
+    }
+
+    fn from_reloc_code<'a>(code: RelocCode) -> Option<&'a Self>
+    where
+        Self: Sized + 'a,
+    {
+        match code {
+            RelocCode::None => Self::from_relnum(0),
+            RelocCode::Abs { addr_width: 16 } => Self::from_relnum(1),
+            RelocCode::Abs { addr_width: 32 } => Self::from_relnum(6),
+            RelocCode::Rel { addr_width: 16 } => Self::from_relnum(2),
+            RelocCode::Rel { addr_width: 32 } => Self::from_relnum(0x14),
+            _ => None,
+        }
+    }
+
+    fn reloc_num(&self) -> u32 {
+        match self {
+            Self::Absolute => 0,
+            Self::Dir16 => 1,
+            Self::Rel16 => 2,
+            Self::Dir32 => 6,
+            Self::Dir32Nb => 7,
+            Self::Seg12 => 9,
+            Self::Section => 0xA,
+            Self::Secrel => 0xB,
+            Self::Token => 0xC,
+            Self::Secrel7 => 0xD,
+            Self::Rel32 => 0x14,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Absolute => "IMAGE_REL_I386_ABSOLUTE",
+            Self::Dir16 => "IMAGE_REL_I386_DIR16",
+            Self::Rel16 => "IMAGE_REL_I386_REL16",
+            Self::Dir32 => "IMAGE_REL_I386_DIR32",
+            Self::Dir32Nb => "IMAGE_REL_I386_DIR32NB",
+            Self::Seg12 => "IMAGE_REL_I386_SEG12",
+            Self::Section => "IMAGE_REL_I386_SECTION",
+            Self::Secrel => "IMAGE_REL_I386_SECREL",
+            Self::Token => "IMAGE_REL_I386_TOKEN",
+            Self::Secrel7 => "IMAGE_REL_I386_SECREL7",
+            Self::Rel32 => "IMAGE_REL_I386_REL32",
+        }
+    }
+
+    fn reloc_size(&self) -> usize {
+        match self {
+            Self::Absolute => 0,
+            Self::Dir16 | Self::Rel16 | Self::Section | Self::Secrel7 => 2,
+            Self::Dir32 | Self::Dir32Nb | Self::Secrel | Self::Token | Self::Rel32 => 4,
+            Self::Seg12 => 2,
+        }
+    }
+
+    fn pcrel(&self) -> bool {
+        matches!(self, Self::Rel16 | Self::Rel32)
+    }
+
+    fn is_relax(&self) -> bool {
+        false
+    }
+
+    fn relax_size(&self, _addr: u128, _at_addr: u128) -> Option<usize> {
+        None
+    }
+
+    fn apply<'a>(
+        &self,
+        addr: u128,
+        at_addr: u128,
+        region: &'a mut [u8],
+    ) -> Result<&'a mut [u8], HowToError> {
+        match self {
+            Self::Absolute => Ok(region),
+            Self::Dir32 => {
+                if addr > u32::MAX as u128 {
+                    Err(HowToError::UnsignedOverflow)
+                } else {
+                    region.copy_from_slice(&(addr as u32).to_le_bytes());
+                    Ok(region)
+                }
+            }
+            Self::Rel32 => {
+                let val = (at_addr as i128) - (addr as i128);
+                if !(i32::MIN as i128..=i32::MAX as i128).contains(&val) {
+                    Err(HowToError::SignedOverflow)
+                } else {
+                    region.copy_from_slice(&(val as i32).to_le_bytes());
+                    Ok(region)
+                }
+            }
+            // These all need information (the image base, a section index,
+            // a COFF symbol-table index) that isn't available to a `HowTo`
+            // in isolation -- they depend on whatever writes the final PE
+            // image, which doesn't exist in this crate yet.
+            Self::Dir16
+            | Self::Rel16
+            | Self::Dir32Nb
+            | Self::Seg12
+            | Self::Section
+            | Self::Secrel
+            | Self::Token
+            | Self::Secrel7 => todo!(),
+        }
+    }
+
+    fn valid_in(&self, _output_ty: crate::howto::RelocOutput, _sym_vis: &crate::sym::Symbol) -> bool {
+        todo!()
+    }
+}
+
+/// Bit 0 of the `@feat.00` absolute symbol's value: set by the compiler
+/// when the object contains no structured exception handlers that would be
+/// unsafe for `link /SAFESEH` to trust (every handler it registers is
+/// listed in `.sxdata`). `link.exe` refuses `/SAFESEH` unless every linked
+/// i386 object both defines `@feat.00` and has this bit set.
+pub const FEAT00_SAFESEH: u32 = 0x1;
+
+/// The symbol name MSVC-compatible i386 COFF producers emit to record
+/// `/SAFESEH`-relevant (and other `/feature`-style) compile-time facts
+/// about an object file. It is always an absolute symbol (`secno`
+/// `IMAGE_SYM_ABSOLUTE`), never a reference to actual code or data.
+pub const FEAT00_SYMBOL_NAME: &str = "@feat.00";
+
+/// The value an i386 object's `@feat.00` symbol should carry.
+/// `safeseh_compatible` is [`FEAT00_SAFESEH`]; other `@feat.00` bits (e.g.
+/// control-flow-guard compatibility) aren't modeled here.
+pub fn feat00_value(safeseh_compatible: bool) -> u32 {
+    if safeseh_compatible {
+        FEAT00_SAFESEH
+    } else {
+        0
+    }
+}
+
+/// Why [`enforce_safeseh`] rejected a set of input objects for `/SAFESEH`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SafeSehError {
+    /// The object at this index has no `@feat.00` symbol at all.
+    MissingFeat00 { object_index: usize },
+    /// The object at this index has `@feat.00`, but without
+    /// [`FEAT00_SAFESEH`] set.
+    NotSafeSehCompatible { object_index: usize },
+}
+
+/// Checks every input object's `@feat.00` value (`None` if the object
+/// defines no such symbol) against the rule `link /SAFESEH` enforces: the
+/// whole image can only get a `/SAFESEH`-blessed load config entry if
+/// every single linked object opted in. Doesn't build the final
+/// `.sxdata`/load-config image content -- that's [`SafeSehTable`]'s job,
+/// once a real PE writer exists to place it.
+pub fn enforce_safeseh(feat00_values: impl IntoIterator<Item = Option<u32>>) -> Result<(), SafeSehError> {
+    for (object_index, feat00) in feat00_values.into_iter().enumerate() {
+        match feat00 {
+            None => return Err(SafeSehError::MissingFeat00 { object_index }),
+            Some(value) if value & FEAT00_SAFESEH == 0 => {
+                return Err(SafeSehError::NotSafeSehCompatible { object_index })
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// The `.sxdata` section's contents: the COFF symbol-table index of every
+/// function in the object that is a valid SEH exception handler. The
+/// linker concatenates every input object's table (translating each index
+/// into the merged output symbol table) into the image's final
+/// `SEHandlerTable`/`SEHandlerCount` load-config entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SafeSehTable {
+    handler_symbol_indices: Vec<u32>,
+}
+
+impl SafeSehTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_handler(&mut self, symtab_index: u32) {
+        self.handler_symbol_indices.push(symtab_index);
+    }
+
+    pub fn handlers(&self) -> &[u32] {
+        &self.handler_symbol_indices
+    }
+
+    /// Serializes this table into `.sxdata`'s raw, little-endian, 4-byte-
+    /// per-entry on-disk form.
+    pub fn to_section_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.handler_symbol_indices.len() * 4);
+        for index in &self.handler_symbol_indices {
+            out.extend_from_slice(&index.to_le_bytes());
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feat00_value_sets_only_the_safeseh_bit() {
+        assert_eq!(feat00_value(true), FEAT00_SAFESEH);
+        assert_eq!(feat00_value(false), 0);
+    }
+
+    #[test]
+    fn enforce_safeseh_accepts_when_every_object_opts_in() {
+        assert_eq!(
+            enforce_safeseh([Some(FEAT00_SAFESEH), Some(FEAT00_SAFESEH)]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn enforce_safeseh_rejects_an_object_missing_feat00() {
+        assert_eq!(
+            enforce_safeseh([Some(FEAT00_SAFESEH), None]),
+            Err(SafeSehError::MissingFeat00 { object_index: 1 })
+        );
+    }
+
+    #[test]
+    fn enforce_safeseh_rejects_an_object_without_the_safeseh_bit() {
+        assert_eq!(
+            enforce_safeseh([Some(0)]),
+            Err(SafeSehError::NotSafeSehCompatible { object_index: 0 })
+        );
+    }
+
+    #[test]
+    fn safe_seh_table_serializes_indices_little_endian() {
+        let mut table = SafeSehTable::new();
+        table.push_handler(1);
+        table.push_handler(0x0100_0000);
+
+        assert_eq!(table.handlers(), &[1, 0x0100_0000]);
+        assert_eq!(
+            table.to_section_bytes(),
+            vec![1, 0, 0, 0, 0, 0, 0, 1]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! An [`InsnRead`] adapter over a [`Section`]'s bytes and relocation
+//! table, the reading-side counterpart to [`Section`]'s own
+//! [`InsnWrite`] impl: where that turns a symbolic [`Address`] into raw
+//! bytes plus a [`Reloc`] when assembling, [`SectionReader`] turns them
+//! back into an [`Address`] when disassembling, so every arch decoder
+//! that already goes through [`InsnRead::read_addr`]/[`InsnRead::read_reloc`]
+//! gets symbolized operands against a relocatable object for free,
+//! without needing to know anything about [`Reloc`] or [`HowTo`] itself.
+//!
+//! A relocation's declared [`HowTo::reloc_size`] is authoritative over
+//! whatever width the decoder itself asked for -- a decoder's nominal
+//! operand size is a property of the *encoding*, but the bytes actually
+//! covered belong to whichever [`HowTo`] `fmt` resolves the relocation's
+//! [`RelocCode`] to, and those can disagree (e.g. a relaxed encoding
+//! [`crate::howto::HowTo::relax_shrink`] already shrank). Neither
+//! [`HowTo::pcrel`] nor the decoder's own `rel` argument is consulted when
+//! a relocation covers the read: a relocation's addend already bakes in
+//! whatever adjustment its site needs (see [`Section::write_addr`]'s
+//! `Address::Symbol` arms), so the symbolic [`Address`] this returns is
+//! the relocation's symbol and addend as recorded, whichever way the
+//! decoder thought it was reading.
+
+use std::io::{self, Read};
+
+use arch_ops::traits::{Address, InsnRead};
+
+use crate::fmt::{Binfmt, Section};
+use crate::howto::Reloc;
+
+/// Reads a [`Section`]'s `content` byte-for-byte like any other
+/// [`Read`]er, except that [`InsnRead::read_addr`]/[`InsnRead::read_reloc`]
+/// consult the section's [`Reloc`] table first: a read that lands on a
+/// relocated field returns that relocation's symbol instead of decoding
+/// the placeholder bytes [`Section::write_addr`] left there.
+pub struct SectionReader<'a> {
+    section: &'a Section,
+    fmt: &'a dyn Binfmt,
+    pos: usize,
+}
+
+impl<'a> SectionReader<'a> {
+    pub fn new(section: &'a Section, fmt: &'a dyn Binfmt) -> Self {
+        Self { section, fmt, pos: 0 }
+    }
+
+    /// The number of bytes read from [`Section::content`] so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves the cursor to `pos` (clamped to the section's length)
+    /// without reading anything -- for a caller that decoded an
+    /// instruction and now knows, independently of how many bytes the
+    /// decoder actually consumed, exactly where the next one starts (e.g.
+    /// [`arch_ops::disasm::OpcodePrinter::resync`]'s return value after a
+    /// failed decode, which the decoder itself can't have applied since
+    /// it never sees the caller's running offset).
+    pub fn seek_to(&mut self, pos: usize) {
+        self.pos = pos.min(self.section.content.len());
+    }
+
+    /// The relocation (if any) whose [`Reloc::offset`] is exactly
+    /// `rel_offset` bytes from the cursor.
+    fn reloc_at(&self, rel_offset: isize) -> Option<&'a Reloc> {
+        let target = self.pos.checked_add_signed(rel_offset)? as u64;
+        self.section.relocs.iter().find(|r| r.offset == target)
+    }
+
+    /// How many bytes a read covering `reloc` should consume: its
+    /// [`HowTo`][crate::howto::HowTo]'s declared [`reloc_size`][crate::howto::HowTo::reloc_size]
+    /// if `fmt` has one for its code, else `fallback` (the decoder's own
+    /// requested size, for a format/code pair nothing recognizes).
+    fn reloc_len(&self, reloc: &Reloc, fallback: usize) -> usize {
+        self.fmt
+            .code_to_howto(reloc.code)
+            .map(|howto| howto.reloc_size())
+            .unwrap_or(fallback)
+    }
+}
+
+impl Read for SectionReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = &self.section.content[self.pos.min(self.section.content.len())..];
+        let n = remaining.len().min(buf.len());
+        buf[..n].copy_from_slice(&remaining[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl InsnRead for SectionReader<'_> {
+    fn read_addr(&mut self, size: usize, rel: bool) -> io::Result<Address> {
+        let start = self.pos;
+        if let Some(addr) = self.read_reloc(size, rel, None)? {
+            return Ok(addr);
+        }
+
+        // `read_reloc` already advanced the cursor past this field (there
+        // was no relocation to consult for its width, so it used `size`
+        // itself) -- decode the bytes it passed over as a literal value
+        // rather than reading (and so advancing past) a second field.
+        let byte_len = size / 8;
+        let bytes = &self.section.content[start..start + byte_len];
+        let mut val: u128 = 0;
+        for (i, b) in bytes.iter().enumerate() {
+            val |= (*b as u128) << (i * 8);
+        }
+
+        if rel {
+            let shift = 128 - size;
+            Ok(Address::Disp((((val << shift) as i128) >> shift) as i64))
+        } else {
+            Ok(Address::Abs(val))
+        }
+    }
+
+    fn read_reloc(
+        &mut self,
+        size: usize,
+        _rel: bool,
+        offset: Option<isize>,
+    ) -> io::Result<Option<Address>> {
+        let byte_len = (size / 8) as isize;
+        if let Some(offset) = offset {
+            if offset.unsigned_abs() > byte_len.unsigned_abs() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "read_reloc offset out of range",
+                ));
+            }
+        }
+
+        let rel_offset = offset.unwrap_or(0);
+        let reloc = self.reloc_at(rel_offset);
+
+        let addr = reloc.map(|reloc| Address::Symbol {
+            name: reloc.symbol.clone(),
+            disp: reloc.addend.unwrap_or(0),
+        });
+
+        if offset.is_none() {
+            let advance = reloc
+                .map(|reloc| self.reloc_len(reloc, size / 8))
+                .unwrap_or(size / 8);
+            let remaining = self.section.content.len().saturating_sub(self.pos);
+            self.pos += advance.min(remaining);
+        }
+
+        Ok(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fmt::{FileType, SectionType};
+    use crate::howto::{HowToError, RelocCode};
+    use crate::sym::Symbol;
+
+    struct TestHowTo;
+
+    impl crate::howto::HowTo for TestHowTo {
+        fn from_relnum<'a>(_: u32) -> Option<&'a Self> {
+            Some(&TestHowTo)
+        }
+        fn from_reloc_code<'a>(_: RelocCode) -> Option<&'a Self> {
+            Some(&TestHowTo)
+        }
+        fn reloc_num(&self) -> u32 {
+            0
+        }
+        fn name(&self) -> &'static str {
+            "test"
+        }
+        fn reloc_size(&self) -> usize {
+            4
+        }
+        fn pcrel(&self) -> bool {
+            false
+        }
+        fn is_relax(&self) -> bool {
+            false
+        }
+        fn relax_size(&self, _: u128, _: u128) -> Option<usize> {
+            None
+        }
+        fn apply<'a>(
+            &self,
+            _: u128,
+            _: u128,
+            region: &'a mut [u8],
+        ) -> Result<&'a mut [u8], HowToError> {
+            Ok(region)
+        }
+        fn valid_in(&self, _: crate::howto::RelocOutput, _: &Symbol) -> bool {
+            true
+        }
+    }
+
+    struct TestBinfmt;
+
+    impl Binfmt for TestBinfmt {
+        fn relnum_to_howto(&self, _: u32) -> Option<&dyn crate::howto::HowTo> {
+            Some(&TestHowTo)
+        }
+        fn code_to_howto(&self, _: RelocCode) -> Option<&dyn crate::howto::HowTo> {
+            Some(&TestHowTo)
+        }
+        fn name(&self) -> &'static str {
+            "test"
+        }
+        fn create_file(&self, _: FileType) -> crate::fmt::BinaryFile {
+            todo!()
+        }
+        fn ident_file(&self, _: &mut (dyn io::Read + '_)) -> io::Result<bool> {
+            Ok(false)
+        }
+        fn read_file(
+            &self,
+            _: &mut (dyn crate::traits::ReadSeek + '_),
+        ) -> crate::error::Result<Option<crate::fmt::BinaryFile>> {
+            todo!()
+        }
+        fn write_file(
+            &self,
+            _: &mut (dyn io::Write + '_),
+            _: &crate::fmt::BinaryFile,
+        ) -> crate::error::Result<()> {
+            todo!()
+        }
+        fn has_sections(&self) -> bool {
+            true
+        }
+    }
+
+    fn section(content: &[u8], relocs: Vec<Reloc>) -> Section {
+        Section {
+            name: ".text".to_string(),
+            align: 1,
+            ty: SectionType::ProgBits,
+            content: content.to_vec(),
+            tail_size: 0,
+            relocs,
+            info: 0,
+            link: 0,
+            flags: None,
+            __private: (),
+        }
+    }
+
+    #[test]
+    fn relocated_field_returns_the_symbol_instead_of_the_placeholder_bytes() {
+        let sec = section(
+            &[0, 0, 0, 0],
+            vec![Reloc {
+                code: RelocCode::Abs { addr_width: 32 },
+                symbol: "foo".to_string(),
+                addend: Some(4),
+                offset: 0,
+            }],
+        );
+        let fmt = TestBinfmt;
+        let mut reader = SectionReader::new(&sec, &fmt);
+
+        let addr = reader.read_addr(32, false).unwrap();
+        assert_eq!(
+            addr,
+            Address::Symbol {
+                name: "foo".to_string(),
+                disp: 4
+            }
+        );
+        assert_eq!(reader.position(), 4);
+    }
+
+    #[test]
+    fn unrelocated_field_decodes_the_raw_bytes() {
+        let sec = section(&[0x2a, 0, 0, 0], vec![]);
+        let fmt = TestBinfmt;
+        let mut reader = SectionReader::new(&sec, &fmt);
+
+        let addr = reader.read_addr(32, false).unwrap();
+        assert_eq!(addr, Address::Abs(0x2a));
+    }
+
+    #[test]
+    fn negative_displacement_sign_extends() {
+        let sec = section(&[0xff, 0xff, 0xff, 0xff], vec![]);
+        let fmt = TestBinfmt;
+        let mut reader = SectionReader::new(&sec, &fmt);
+
+        let addr = reader.read_addr(32, true).unwrap();
+        assert_eq!(addr, Address::Disp(-1));
+    }
+
+    #[test]
+    fn reloc_size_from_howto_advances_the_cursor_not_the_decoders_guess() {
+        let sec = section(
+            &[0, 0, 0, 0, 0xAB],
+            vec![Reloc {
+                code: RelocCode::Abs { addr_width: 64 },
+                symbol: "foo".to_string(),
+                addend: None,
+                offset: 0,
+            }],
+        );
+        let fmt = TestBinfmt;
+        let mut reader = SectionReader::new(&sec, &fmt);
+
+        // The decoder asks for a 64-bit field, but TestHowTo::reloc_size
+        // says the relocation only covers 4 bytes.
+        reader.read_addr(64, false).unwrap();
+        assert_eq!(reader.position(), 4);
+    }
+
+    #[test]
+    fn out_of_range_offset_is_rejected() {
+        let sec = section(&[0, 0, 0, 0], vec![]);
+        let fmt = TestBinfmt;
+        let mut reader = SectionReader::new(&sec, &fmt);
+
+        assert!(reader.read_reloc(32, false, Some(5)).is_err());
+    }
+}
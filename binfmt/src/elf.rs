@@ -16,7 +16,7 @@ use crate::howto::HowTo;
 use crate::howto::RelocOutput;
 use crate::sym::{self, SymbolKind, SymbolType};
 use crate::traits::private::Sealed;
-use crate::traits::{Numeric, ReadSeek};
+use crate::traits::{Endianness, Numeric, ReadSeek};
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 #[non_exhaustive]
@@ -129,7 +129,7 @@ pub trait ElfProgramHeader: Sealed {
     fn flags(&self) -> ElfWord<Self::Class>;
 }
 
-pub trait ElfClass: Sealed + Sized + Copy + core::fmt::Debug + 'static {
+pub trait ElfClass: Sealed + Sized + Copy + core::fmt::Debug + Send + Sync + 'static {
     type Byte: Numeric;
     const EI_CLASS: consts::EiClass;
     type Half: Numeric;
@@ -288,6 +288,196 @@ impl<Class: ElfClass + ElfRelocationExtractHelpers> ElfRelocation for ElfRela<Cl
     }
 }
 
+/// One `Elf32_Dyn`/`Elf64_Dyn` entry out of a `.dynamic` section: a
+/// tag/value pair, where what `d_val` means (an integer, or an address
+/// into the loaded image) depends on `d_tag` (one of the `consts::DT_*`
+/// constants).
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ElfDyn<Class: ElfClass> {
+    d_tag: ElfOffset<Class>,
+    d_val: ElfAddr<Class>,
+}
+
+unsafe impl<Class: ElfClass> Zeroable for ElfDyn<Class> {}
+unsafe impl<Class: ElfClass> Pod for ElfDyn<Class> {}
+
+impl<Class: ElfClass> ElfDyn<Class> {
+    pub fn new(tag: u64, val: u64) -> Self {
+        Self {
+            d_tag: Numeric::from_usize(tag as usize),
+            d_val: Numeric::from_usize(val as usize),
+        }
+    }
+
+    pub fn tag(&self) -> u64 {
+        self.d_tag.as_u64()
+    }
+
+    pub fn val(&self) -> u64 {
+        self.d_val.as_u64()
+    }
+}
+
+/// One `Elf32_Verdef`/`Elf64_Verdef` entry out of a `.gnu.version_d`
+/// section: one version this object *defines*, e.g. the `VERS_1.1` a
+/// `foo@@VERS_1.1` symbol resolves to. Unlike [`ElfSymbol`]/[`ElfDyn`],
+/// this layout is identical on 32- and 64-bit ELF -- every field is a
+/// `Half`/`Word`, never an `Addr` -- so it isn't generic over
+/// [`ElfClass`]. `vd_aux` and `vd_next` are byte offsets, relative to
+/// this entry, to its first [`ElfVerdaux`] and the next `ElfVerdef` in
+/// the section respectively (`0` ends the chain).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct ElfVerdef {
+    pub vd_version: u16,
+    pub vd_flags: u16,
+    pub vd_ndx: u16,
+    pub vd_cnt: u16,
+    pub vd_hash: u32,
+    pub vd_aux: u32,
+    pub vd_next: u32,
+}
+
+/// One name chained off an [`ElfVerdef`]: the version's own name, plus
+/// (if `vd_cnt` is more than `1`) the names of the versions it inherits
+/// from. `vda_name` is a `.dynstr` offset; `vda_next` is a byte offset
+/// to the next `ElfVerdaux` in the chain (`0` ends it).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct ElfVerdaux {
+    pub vda_name: u32,
+    pub vda_next: u32,
+}
+
+/// One `Elf32_Verneed`/`Elf64_Verneed` entry out of a `.gnu.version_r`
+/// section: the versions this object *requires* from one `DT_NEEDED`
+/// library. `vn_file` is a `.dynstr` offset naming that library;
+/// `vn_aux`/`vn_next` are byte offsets, same convention as
+/// [`ElfVerdef`]'s.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct ElfVerneed {
+    pub vn_version: u16,
+    pub vn_cnt: u16,
+    pub vn_file: u32,
+    pub vn_aux: u32,
+    pub vn_next: u32,
+}
+
+/// One required version chained off an [`ElfVerneed`]: `vna_name` is
+/// the `.dynstr` offset of the version's name, `vna_other` is the
+/// `.gnu.version` index this requirement is assigned, and `vna_next`
+/// is a byte offset to the next `ElfVernaux` in the chain (`0` ends
+/// it).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct ElfVernaux {
+    pub vna_hash: u32,
+    pub vna_flags: u16,
+    pub vna_other: u16,
+    pub vna_name: u32,
+    pub vna_next: u32,
+}
+
+/// Builds a `.dynamic` section's raw content from `entries`, appending the
+/// `DT_NULL` terminator [`dynamic_entries`] looks for.
+pub fn write_dynamic_entries<Class: ElfClass>(entries: impl IntoIterator<Item = (u64, u64)>) -> Vec<u8> {
+    let mut out: Vec<ElfDyn<Class>> = entries
+        .into_iter()
+        .map(|(tag, val)| ElfDyn::new(tag, val))
+        .collect();
+    out.push(ElfDyn::new(consts::DT_NULL, 0));
+    bytemuck::cast_slice(&out).to_vec()
+}
+
+/// Interprets a `.dynamic` section's raw content as a sequence of
+/// tag/value pairs sized for `Class`, stopping at (and including) the
+/// first `DT_NULL` terminator if one is present -- same as `ld.so` does --
+/// or at the end of `content` if not.
+pub fn dynamic_entries<Class: ElfClass>(content: &[u8]) -> std::io::Result<Vec<ElfDyn<Class>>> {
+    let entries: &[ElfDyn<Class>] = bytemuck::try_cast_slice(content)
+        .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, format!("{e:?}")))?;
+
+    let len = entries
+        .iter()
+        .position(|e| e.tag() == consts::DT_NULL)
+        .map_or(entries.len(), |p| p + 1);
+
+    Ok(entries[..len].to_vec())
+}
+
+/// Reads the name of every `DT_NEEDED` entry in `entries`, resolving each
+/// one's `d_val` as an offset into `dynstr` (the section named by the
+/// `.dynamic` section's `sh_link`).
+pub fn needed_libraries<Class: ElfClass>(
+    entries: &[ElfDyn<Class>],
+    dynstr: &[u8],
+) -> std::io::Result<Vec<String>> {
+    entries
+        .iter()
+        .filter(|e| e.tag() == consts::DT_NEEDED)
+        .map(|e| {
+            let off = e.val() as usize;
+            let bytes = dynstr.get(off..).ok_or_else(|| {
+                std::io::Error::new(ErrorKind::InvalidData, "DT_NEEDED offset out of range")
+            })?;
+            from_null_term_str(bytes)
+        })
+        .collect()
+}
+
+/// The SysV `.hash` section's bucket hash, as specified by the System V
+/// ABI (and implemented by `elf_hash` in every ELF `ld.so`): folds `name`
+/// into a 32-bit value used to pick a hash bucket over the dynamic symbol
+/// table.
+pub fn elf_hash(name: &str) -> u32 {
+    let mut h: u32 = 0;
+    for b in name.bytes() {
+        h = h.wrapping_shl(4).wrapping_add(b as u32);
+        let g = h & 0xf000_0000;
+        if g != 0 {
+            h ^= g >> 24;
+        }
+        h &= !g;
+    }
+    h
+}
+
+/// Builds a SysV `.hash` section (`nbucket`, `nchain`, `bucket[]`,
+/// `chain[]`, per the System V ABI) over a dynamic symbol table whose
+/// names (in symbol-table order, including the mandatory `STN_UNDEF`
+/// entry at index 0) are `names`.
+///
+/// `nbucket` is chosen as the symbol count (at least 1), matching the
+/// simple "one bucket per symbol" strategy `binutils` falls back to; a
+/// linker that wants the GNU `.gnu.hash` bloom-filter format instead will
+/// need its own section builder, which isn't implemented here.
+pub fn write_sysv_hash_section(names: &[&str]) -> Vec<u8> {
+    let nchain = names.len().max(1);
+    let nbucket = nchain;
+
+    let mut bucket = vec![0u32; nbucket];
+    let mut chain = vec![0u32; nchain];
+
+    for (i, name) in names.iter().enumerate().skip(1) {
+        let b = (elf_hash(name) as usize) % nbucket;
+        chain[i] = bucket[b];
+        bucket[b] = i as u32;
+    }
+
+    let mut out = Vec::with_capacity((2 + nbucket + nchain) * 4);
+    out.extend_from_slice(&(nbucket as u32).to_ne_bytes());
+    out.extend_from_slice(&(nchain as u32).to_ne_bytes());
+    for b in &bucket {
+        out.extend_from_slice(&b.to_ne_bytes());
+    }
+    for c in &chain {
+        out.extend_from_slice(&c.to_ne_bytes());
+    }
+    out
+}
+
 impl Sealed for Elf64Sym {}
 impl ElfSymbol for Elf64Sym {
     type Class = Elf64;
@@ -742,6 +932,9 @@ pub mod consts {
             PT_NOTE = 4,
             PT_SHLIB = 5,
             PT_PHDR = 6,
+            PT_GNU_EH_FRAME = 0x6474e550,
+            PT_GNU_STACK = 0x6474e551,
+            PT_GNU_RELRO = 0x6474e552,
         }
     }
 
@@ -761,9 +954,94 @@ pub mod consts {
             SHT_REL = 9,
             SHT_SHLIB = 10,
             SHT_DYNSYM = 11,
+            SHT_INIT_ARRAY = 14,
+            SHT_FINI_ARRAY = 15,
+            SHT_PREINIT_ARRAY = 16,
             SHT_GROUP = 17,
         }
     }
+
+    /// `sh_flags` bit marking a section as one `ld --gc-sections` must
+    /// never discard even if nothing references it -- set by
+    /// `__attribute__((retain))` (GCC/Clang) or `.section ...,"R"`. Not
+    /// one of the bits [`super::SectionFlag`] names directly; represent
+    /// it as `SectionFlag::FormatSpecific(SHF_GNU_RETAIN as u32)`.
+    pub const SHF_GNU_RETAIN: u64 = 0x0020_0000;
+
+    /// `sh_flags` bit marking a section as safe to tail-merge: every
+    /// NUL-terminated string in it (with [`SHF_STRINGS`]) or every
+    /// fixed-size element in it (without, given `sh_entsize`) may appear
+    /// more than once across input objects, and a linker using `-O` may
+    /// deduplicate them, same as `lcld`'s string-merging pass does for
+    /// the string case. Represent it as
+    /// `SectionFlag::FormatSpecific(SHF_MERGE as u32)`.
+    pub const SHF_MERGE: u64 = 0x0000_0010;
+
+    /// `sh_flags` bit marking a [`SHF_MERGE`] section's contents as
+    /// NUL-terminated strings rather than fixed-size `sh_entsize`
+    /// records. Represent it as
+    /// `SectionFlag::FormatSpecific(SHF_STRINGS as u32)`.
+    pub const SHF_STRINGS: u64 = 0x0000_0020;
+
+    /// `d_tag` values for `.dynamic` section entries ([`super::ElfDyn`]).
+    /// Only the handful this crate's dynamic-linking analysis actually
+    /// reads are named here; the rest of the real set (`DT_RPATH`,
+    /// `DT_INIT`, the versioning tags, ...) can be added as something
+    /// needs them.
+    pub const DT_NULL: u64 = 0;
+    pub const DT_NEEDED: u64 = 1;
+    pub const DT_STRTAB: u64 = 5;
+    pub const DT_SYMTAB: u64 = 6;
+    pub const DT_STRSZ: u64 = 10;
+    pub const DT_SONAME: u64 = 14;
+    pub const DT_FLAGS: u64 = 30;
+    pub const DT_FLAGS_1: u64 = 0x6fff_fffb;
+
+    /// `DT_FLAGS` bit: the dynamic linker must resolve all of this
+    /// object's dynamic symbols at load time (`-z now`) rather than
+    /// lazily through the PLT on first call.
+    pub const DF_BIND_NOW: u64 = 0x0000_0008;
+
+    /// `DT_FLAGS_1` bit: the object was linked `-pie` and the dynamic
+    /// linker should treat it like an executable (run its entry point)
+    /// rather than a plain shared object, despite having `ET_DYN` type.
+    pub const DF_1_PIE: u64 = 0x0800_0000;
+
+    /// `DT_FLAGS_1` bit, the modern counterpart to [`DF_BIND_NOW`]: newer
+    /// dynamic linkers prefer this over `DT_FLAGS`/`DF_BIND_NOW`, so `-z
+    /// now` sets both.
+    pub const DF_1_NOW: u64 = 0x0000_0001;
+
+    /// `.dynamic` tags pointing at the symbol-versioning sections: the
+    /// versions this object defines ([`super::ElfVerdef`]), the versions
+    /// it needs from its `DT_NEEDED` libraries ([`super::ElfVerneed`]),
+    /// and the per-symbol version index table running parallel to
+    /// `.dynsym`.
+    pub const DT_VERSYM: u64 = 0x6fff_fff0;
+    pub const DT_VERDEF: u64 = 0x6fff_fffc;
+    pub const DT_VERDEFNUM: u64 = 0x6fff_fffd;
+    pub const DT_VERNEED: u64 = 0x6fff_fffe;
+    pub const DT_VERNEEDNUM: u64 = 0x6fff_ffff;
+
+    /// `sh_type` for `.gnu.version_d`/`.gnu.version_r`/`.gnu.version`.
+    pub const SHT_GNU_VERDEF: u64 = 0x6fff_fffd;
+    pub const SHT_GNU_VERNEED: u64 = 0x6fff_fffe;
+    pub const SHT_GNU_VERSYM: u64 = 0x6fff_ffff;
+
+    /// `vd_flags`/`vna_flags` bit marking a version as the file's base
+    /// version (the one unversioned symbols implicitly belong to),
+    /// rather than one introduced later in the library's history.
+    pub const VER_FLG_BASE: u16 = 0x1;
+    /// `vna_flags` bit marking a requirement as weak: the referencing
+    /// object still loads if the needed library lacks this version.
+    pub const VER_FLG_WEAK: u16 = 0x2;
+
+    /// Reserved `.gnu.version` indices: `0` for symbols local to this
+    /// object (not versioned at all) and `1` for the file's base,
+    /// unnamed version. Every named [`super::ElfVerdef`] gets an index
+    /// starting at `2`.
+    pub const VER_NDX_LOCAL: u16 = 0;
+    pub const VER_NDX_GLOBAL: u16 = 1;
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -788,6 +1066,47 @@ pub struct ElfHeader<E: ElfClass> {
 unsafe impl<E: ElfClass> Zeroable for ElfHeader<E> {}
 unsafe impl<E: ElfClass + 'static> Pod for ElfHeader<E> {}
 
+impl<E: ElfClass> ElfHeader<E> {
+    /// Byte-swaps every multi-byte field but `e_ident` (whose bytes --
+    /// the magic number, `ei_class`, `ei_data` itself, etc. -- are
+    /// single-byte values with no order to have) between `endian` and
+    /// the host's native order; when `endian` is native every field is
+    /// left as it is. Called on read with the order `e_ident.ei_data`
+    /// declares, and again on write with the target's declared order,
+    /// so [`ElfFormat`] can otherwise build and inspect headers
+    /// using ordinary host-native arithmetic throughout.
+    fn swap_endian(&mut self, endian: Endianness) {
+        self.e_type = endian.convert(self.e_type);
+        self.e_machine = endian.convert(self.e_machine);
+        self.e_version = endian.convert(self.e_version);
+        self.e_entry = endian.convert(self.e_entry);
+        self.e_phoff = endian.convert(self.e_phoff);
+        self.e_shoff = endian.convert(self.e_shoff);
+        self.e_flags = endian.convert(self.e_flags);
+        self.e_ehsize = endian.convert(self.e_ehsize);
+        self.e_phentsize = endian.convert(self.e_phentsize);
+        self.e_phnum = endian.convert(self.e_phnum);
+        self.e_shentsize = endian.convert(self.e_shentsize);
+        self.e_shnum = endian.convert(self.e_shnum);
+        self.e_shstrndx = endian.convert(self.e_shstrndx);
+    }
+}
+
+/// The [`Endianness`] ELF's `EI_DATA` declares, or [`Endianness::native`]
+/// for `ELFDATANONE`/an unrecognized value -- there's nothing sensible to
+/// swap against otherwise, and a format that actually cares already
+/// rejected the file by the time this matters (see
+/// [`ElfFormat::read_file`]'s `ei_data` check).
+fn ei_data_to_endianness(data: consts::EiData) -> Endianness {
+    if data == consts::ELFDATA2MSB {
+        Endianness::Big
+    } else if data == consts::ELFDATA2LSB {
+        Endianness::Little
+    } else {
+        Endianness::native()
+    }
+}
+
 pub trait SectionHeader {}
 
 #[derive(Copy, Clone, Debug, Zeroable, Pod)]
@@ -1009,15 +1328,54 @@ fn elf_type_to_file_type(ty: consts::ElfType) -> FileType {
     }
 }
 
+/// The inverse of the `sh_flags` encoding the ELF writer below builds:
+/// turns the bits GC, ICF, and (once it's wired in) string-merging care
+/// about -- `SHF_WRITE`/`SHF_ALLOC`/`SHF_EXECINSTR` and anything else,
+/// carried through opaquely as [`SectionFlag::FormatSpecific`] one bit at
+/// a time, since that variant requires a single power-of-two bit -- into
+/// the [`SectionFlags`] a reader of an ELF file gets back.
+fn sh_flags_to_section_flags(sh_flags: u64) -> crate::fmt::SectionFlags {
+    let mut flags = crate::fmt::SectionFlags::default();
+
+    if sh_flags & 0x1 != 0 {
+        flags = flags | SectionFlag::Writable;
+    }
+    if sh_flags & 0x2 != 0 {
+        flags = flags | SectionFlag::Alloc;
+    }
+    if sh_flags & 0x4 != 0 {
+        flags = flags | SectionFlag::Executable;
+    }
+
+    let mut rest = sh_flags & !0x7;
+    while rest != 0 {
+        let bit = rest & rest.wrapping_neg();
+        flags = flags | SectionFlag::FormatSpecific(bit as u32);
+        rest &= !bit;
+    }
+
+    flags
+}
+
 fn elf_shtype_to_file_type(ty: consts::SectionType) -> SectionType {
     match ty {
         consts::SHT_PROGBITS => SectionType::ProgBits,
-        consts::SHT_SYMTAB => SectionType::SymbolTable,
+        // `.dynsym` and `.symtab` share a layout and are read the exact
+        // same way; `SectionType` doesn't distinguish them (so writing
+        // one back out always produces a plain `SHT_SYMTAB`), but that's
+        // an existing limitation of the enum, not something introduced
+        // here -- the alternative is silently never reading dynamic
+        // symbol tables at all, which is worse for every reader of a
+        // dynamically-linked object.
+        consts::SHT_SYMTAB | consts::SHT_DYNSYM => SectionType::SymbolTable,
         consts::SHT_STRTAB => SectionType::StringTable,
         consts::SHT_REL => SectionType::RelocationTable,
         consts::SHT_RELA => SectionType::RelocationAddendTable,
         consts::SHT_DYNAMIC => SectionType::Dynamic,
         consts::SHT_NOBITS => SectionType::NoBits,
+        consts::SHT_INIT_ARRAY => SectionType::InitArray,
+        consts::SHT_FINI_ARRAY => SectionType::FiniArray,
+        consts::SHT_PREINIT_ARRAY => SectionType::PreinitArray,
         consts::SectionType(ty) => SectionType::FormatSpecific(ty),
     }
 }
@@ -1085,7 +1443,7 @@ impl<Class: ElfClass + 'static, Howto: HowTo + 'static> Binfmt for ElfFormat<Cla
     fn read_file(
         &self,
         file: &mut (dyn ReadSeek + '_),
-    ) -> std::io::Result<Option<crate::fmt::BinaryFile>> {
+    ) -> crate::error::Result<Option<crate::fmt::BinaryFile>> {
         let mut header = ElfHeader::<Class>::zeroed();
         file.read_exact(bytemuck::bytes_of_mut(&mut header.e_ident))?;
 
@@ -1102,13 +1460,13 @@ impl<Class: ElfClass + 'static, Howto: HowTo + 'static> Binfmt for ElfFormat<Cla
         }
 
         file.read_exact(&mut bytemuck::bytes_of_mut(&mut header)[16..])?;
+        header.swap_endian(ei_data_to_endianness(self.data));
 
         if header.e_phentsize != Numeric::from_usize(size_of::<Class::ProgramHeader>())
             && header.e_phnum != Numeric::zero()
         {
-            return Err(std::io::Error::new(
-                ErrorKind::InvalidData,
-                "Invalid Program Header Entry Size",
+            return Err(crate::error::BinfmtError::InvalidFormat(
+                "Invalid Program Header Entry Size".to_string(),
             ));
         }
         let mut phdrs = vec![Class::ProgramHeader::zeroed(); header.e_phnum.as_usize()];
@@ -1125,9 +1483,8 @@ impl<Class: ElfClass + 'static, Howto: HowTo + 'static> Binfmt for ElfFormat<Cla
         if header.e_shentsize != Numeric::from_usize(size_of::<ElfSectionHeader<Class>>())
             && header.e_shnum != Numeric::zero()
         {
-            return Err(std::io::Error::new(
-                ErrorKind::InvalidData,
-                "Invalid Section Header Entry Size",
+            return Err(crate::error::BinfmtError::InvalidFormat(
+                "Invalid Section Header Entry Size".to_string(),
             ));
         }
 
@@ -1157,6 +1514,7 @@ impl<Class: ElfClass + 'static, Howto: HowTo + 'static> Binfmt for ElfFormat<Cla
             let mut sect = Section {
                 align: Numeric::as_usize(shdr.sh_addralign),
                 ty: elf_shtype_to_file_type(shdr.sh_type),
+                flags: Some(sh_flags_to_section_flags(Numeric::as_u64(shdr.sh_flags))),
                 ..Section::default()
             };
 
@@ -1179,9 +1537,8 @@ impl<Class: ElfClass + 'static, Howto: HowTo + 'static> Binfmt for ElfFormat<Cla
             match sect.ty {
                 SectionType::SymbolTable => {
                     if shdr.sh_entsize.as_usize() != size_of::<Class::Symbol>() {
-                        return Err(std::io::Error::new(
-                            ErrorKind::InvalidData,
-                            "Invalid Symbol Header Entry Size",
+                        return Err(crate::error::BinfmtError::InvalidFormat(
+                            "Invalid Symbol Header Entry Size".to_string(),
                         ));
                     }
                 }
@@ -1257,7 +1614,24 @@ impl<Class: ElfClass + 'static, Howto: HowTo + 'static> Binfmt for ElfFormat<Cla
         &self,
         file: &mut (dyn std::io::Write + '_),
         bfile: &crate::fmt::BinaryFile,
-    ) -> std::io::Result<()> {
+    ) -> crate::error::Result<()> {
+        let phdrs = &bfile
+            .data()
+            .downcast_ref::<ElfFileData<Class>>()
+            .unwrap()
+            .phdrs;
+        let mut layout_errors = crate::validate::validate_sections(bfile);
+        layout_errors.extend(crate::validate::validate_program_headers(phdrs));
+        if !layout_errors.is_empty() {
+            return Err(crate::error::BinfmtError::InvalidFormat(
+                layout_errors
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ));
+        }
+
         let mut shstrtab = (Vec::new(), HashMap::new());
         fn strtab_cmp(haystack: &[u8], needle: &str) -> bool {
             let len = needle.len();
@@ -1353,6 +1727,9 @@ impl<Class: ElfClass + 'static, Howto: HowTo + 'static> Binfmt for ElfFormat<Cla
                     SectionType::SymbolTable => consts::SHT_SYMTAB,
                     SectionType::StringTable => consts::SHT_STRTAB,
                     SectionType::Dynamic => consts::SHT_DYNAMIC,
+                    SectionType::InitArray => consts::SHT_INIT_ARRAY,
+                    SectionType::FiniArray => consts::SHT_FINI_ARRAY,
+                    SectionType::PreinitArray => consts::SHT_PREINIT_ARRAY,
                     SectionType::ProcedureLinkageTable => todo!(),
                     SectionType::GlobalOffsetTable => todo!(),
                     SectionType::FormatSpecific(_) => todo!(),
@@ -1554,6 +1931,13 @@ impl<Class: ElfClass + 'static, Howto: HowTo + 'static> Binfmt for ElfFormat<Cla
         header.e_shnum = Class::Half::from_usize(shdrs.len());
         header.e_shoff = Class::Offset::from_usize(offset);
         header.e_shstrndx = Class::Half::from_usize(shdrs.len() - 1);
+        // Only the file header is byte-order-corrected so far; the
+        // section headers, symbol table, and relocation tables below are
+        // still written in host-native order regardless of `self.data`,
+        // so `elf32-genericbe`/`elf64-genericbe` only round-trip a
+        // header-only file today. Extending `swap_endian`-style
+        // conversion to those tables is follow-up work.
+        header.swap_endian(ei_data_to_endianness(self.data));
         file.write_all(bytemuck::bytes_of(&header))?;
         for section in bfile.sections() {
             if section.ty == SectionType::NoBits || section.content.is_empty() {
@@ -1586,6 +1970,39 @@ impl<Class: ElfClass + 'static, Howto: HowTo + 'static> Binfmt for ElfFormat<Cla
         true
     }
 
+    fn segment_security_audit(&self, bfile: &crate::fmt::BinaryFile) -> crate::fmt::SegmentAudit {
+        const PF_X: u32 = 1;
+        const PF_W: u32 = 2;
+
+        let mut audit = crate::fmt::SegmentAudit::default();
+
+        let Some(data) = bfile.data().downcast_ref::<ElfFileData<Class>>() else {
+            return audit;
+        };
+
+        for (idx, phdr) in data.phdrs.iter().enumerate() {
+            let flags = phdr.flags().as_usize() as u32;
+            match phdr.pt_type() {
+                consts::PT_LOAD if (flags & PF_W) != 0 && (flags & PF_X) != 0 => {
+                    audit.rwx_segments.push((
+                        idx,
+                        crate::fmt::SegmentPermissions {
+                            read: (flags & 4) != 0,
+                            write: true,
+                            execute: true,
+                        },
+                    ));
+                }
+                consts::PT_GNU_STACK if (flags & PF_X) != 0 => {
+                    audit.executable_stack = true;
+                }
+                _ => {}
+            }
+        }
+
+        audit
+    }
+
     fn ident_file(&self, file: &mut (dyn std::io::Read + '_)) -> std::io::Result<bool> {
         let mut header = ElfHeader::<Class>::zeroed();
         file.read_exact(bytemuck::bytes_of_mut(&mut header.e_ident))?;
@@ -1672,3 +2089,38 @@ impl HowTo for ElfHowToUnknown {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::fmt::{Binfmt, FileType};
+
+    /// `elf32-genericbe`'s header should serialize big-endian regardless
+    /// of the host's own byte order, and read back out to the same
+    /// values it was written with -- the same round trip
+    /// `elf32-genericle` already got for free by happening to match most
+    /// hosts' native order.
+    #[test]
+    fn elf32_genericbe_header_round_trips_big_endian() {
+        let fmt = crate::elf32::genericbe::create_format();
+        let bfile = fmt.create_file(FileType::Relocatable);
+
+        let mut buf = Vec::new();
+        fmt.write_file(&mut buf, &bfile).unwrap();
+
+        // `e_type` sits right after the 16-byte `e_ident`; `ET_REL` is
+        // `1`, which is `[0x00, 0x01]` big-endian and `[0x01, 0x00]`
+        // little-endian -- so this only passes if the header was
+        // actually swapped for a target whose order differs from the
+        // host's, not just on a big-endian host running the test.
+        assert_eq!(&buf[16..18], &[0x00, 0x01]);
+
+        let read_back = fmt
+            .read_file(&mut Cursor::new(buf))
+            .unwrap()
+            .expect("elf32-genericbe should recognize its own output");
+
+        assert_eq!(*read_back.file_type(), FileType::Relocatable);
+    }
+}
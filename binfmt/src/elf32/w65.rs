@@ -259,7 +259,44 @@ impl HowTo for Elf32W65HowTo {
             Elf32W65HowTo::RelaxBrl => unimplemented!(),
             Elf32W65HowTo::RelaxDirect => unimplemented!(),
             Elf32W65HowTo::RelaxAbs => unimplemented!(),
-            Elf32W65HowTo::RelaxJmp => unimplemented!(),
+            // The un-shrunk form: a full `JMP abs` (opcode + 16-bit address,
+            // same bank as the instruction). `region` covers the opcode
+            // too, unlike the non-`Relax*` codes above, since shrinking (see
+            // `relax_shrink` below) needs to rewrite it.
+            Elf32W65HowTo::RelaxJmp => {
+                let bytes = addr.to_le_bytes();
+                region[0] = 0x4C;
+                region[1..3].copy_from_slice(&bytes[..2]);
+                Ok(region)
+            }
+        }
+    }
+
+    /// Shrinks a `JMP abs` (`RelaxJmp`) into a `BRA rel8` once its target
+    /// is within a short branch's reach of the byte following it. None of
+    /// the other five `Relax*` codes are implemented yet -- `RelaxJsl`/
+    /// `RelaxJml` (long call/jump shrinking to same-bank `JSR`/`JMP`) and
+    /// `RelaxAbs`/`RelaxDirect` (absolute addressing shrinking to the
+    /// zero-page `Direct` mode) would need the current bank/direct-page
+    /// registers, which nothing threads through this interface yet, and
+    /// `RelaxBrl`'s already-relative `BRL` shrinking further to `BRA` is a
+    /// straightforward follow-on of this one but out of scope here.
+    fn relax_shrink(
+        &self,
+        region: &mut [u8],
+        _is_local: bool,
+        addr: u128,
+        at_addr: u128,
+    ) -> Option<(crate::howto::RelocCode, usize)> {
+        match self {
+            Elf32W65HowTo::RelaxJmp => {
+                let disp = (addr as i128) - (at_addr as i128 + 2);
+                let x = i8::try_from(disp).ok()?;
+                region[0] = 0x80; // BRA rel8
+                region[1] = x as u8;
+                Some((crate::howto::RelocCode::None, 2))
+            }
+            _ => None,
         }
     }
 
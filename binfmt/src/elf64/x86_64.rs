@@ -1,3 +1,4 @@
+use crate::elf::{DynBuilder, Elf64, ElfFormat, PltType};
 use crate::howto::{HowTo, HowToError, RelocCode};
 
 use super::consts;
@@ -30,12 +31,14 @@ pub enum Elf64X86_64HowTo {
     Pc64,
     GotOff64,
     GotPc32,
+    GotPcRelX,
+    RexGotPcRelX,
 }
 
 mod howtos {
     use super::Elf64X86_64HowTo::{self, *};
 
-    pub static RELOCS: [Option<Elf64X86_64HowTo>; 27] = [
+    pub static RELOCS: [Option<Elf64X86_64HowTo>; 43] = [
         Some(Elf64X86_64HowTo::None),
         Some(Abs64),
         Some(Pc32),
@@ -63,6 +66,22 @@ mod howtos {
         Some(Pc64),
         Some(GotOff64),
         Some(GotPc32),
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Option::None,
+        Some(GotPcRelX),
+        Some(RexGotPcRelX),
     ];
 }
 
@@ -129,6 +148,8 @@ impl HowTo for Elf64X86_64HowTo {
             Elf64X86_64HowTo::Pc64 => todo!(),
             Elf64X86_64HowTo::GotOff64 => 24,
             Elf64X86_64HowTo::GotPc32 => 25,
+            Elf64X86_64HowTo::GotPcRelX => 41,
+            Elf64X86_64HowTo::RexGotPcRelX => 42,
         }
     }
 
@@ -168,6 +189,60 @@ impl HowTo for Elf64X86_64HowTo {
     ) -> bool {
         todo!()
     }
+
+    fn relax(&self, region: &mut [u8], is_local: bool) -> Option<RelocCode> {
+        if !is_local {
+            return None;
+        }
+        match self {
+            // `mov sym@GOTPCREL(%rip), %reg` (opcode 0x8B) relaxes to
+            // `lea sym(%rip), %reg` (opcode 0x8D): same operand encoding,
+            // just the opcode byte immediately before the displacement
+            // changes, and the relocation becomes a plain `rip`-relative
+            // one instead of a GOT-relative one.
+            Elf64X86_64HowTo::GotPcRelX | Elf64X86_64HowTo::RexGotPcRelX => {
+                match region.first_mut() {
+                    Some(opcode @ 0x8B) => {
+                        *opcode = 0x8D;
+                        Some(RelocCode::Rel { addr_width: 32 })
+                    }
+                    _ => None,
+                }
+            }
+            // `call`/`jmp sym@PLT` (E8/E9 rel32) need no byte rewrite to
+            // become a direct call/jump once `sym` binds locally -- only
+            // the relocation kind changes, so the linker resolves straight
+            // to the target instead of routing through the PLT stub.
+            Elf64X86_64HowTo::Plt32 => Some(RelocCode::Rel { addr_width: 32 }),
+            _ => None,
+        }
+    }
+}
+
+impl DynBuilder for ElfFormat<Elf64, Elf64X86_64HowTo> {
+    type HowTo = Elf64X86_64HowTo;
+
+    fn supports_dyn(&self) -> bool {
+        true
+    }
+
+    fn supports_plt_type(&self, ty: PltType) -> Result<(), PltType> {
+        match ty {
+            PltType::Lazy => Ok(()),
+            // x86_64 PLT stubs bounce through `.plt.sec`/the resolver on
+            // first call; there's no non-lazy stub shape implemented here,
+            // so fall back to the one we do support.
+            PltType::NonLazy => Err(PltType::Lazy),
+        }
+    }
+
+    fn jump_slot_howto(&self) -> Self::HowTo {
+        Elf64X86_64HowTo::JumpSlot
+    }
+
+    fn global_data_howto(&self) -> Self::HowTo {
+        Elf64X86_64HowTo::GlobDat
+    }
 }
 
 pub fn create_format() -> super::Elf64Format<Elf64X86_64HowTo> {
@@ -0,0 +1,59 @@
+//! A structured error type for [`Binfmt::read_file`]/[`Binfmt::write_file`]
+//! failures, distinguishing an I/O failure (the underlying stream erroring)
+//! from the file simply not being well-formed for the format that is
+//! attempting to read or write it.
+//!
+//! [`Binfmt::read_file`]: crate::fmt::Binfmt::read_file
+//! [`Binfmt::write_file`]: crate::fmt::Binfmt::write_file
+
+use std::{fmt, io};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BinfmtError {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// The file's header, magic number, or internal structure did not match
+    /// what the format expects.
+    InvalidFormat(String),
+    /// The file is well-formed, but uses a feature this implementation does
+    /// not (yet) support.
+    Unsupported(String),
+}
+
+impl fmt::Display for BinfmtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinfmtError::Io(e) => e.fmt(f),
+            BinfmtError::InvalidFormat(msg) => write!(f, "invalid format: {}", msg),
+            BinfmtError::Unsupported(msg) => write!(f, "unsupported: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BinfmtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BinfmtError::Io(e) => Some(e),
+            BinfmtError::InvalidFormat(_) | BinfmtError::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for BinfmtError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<BinfmtError> for io::Error {
+    fn from(e: BinfmtError) -> Self {
+        match e {
+            BinfmtError::Io(e) => e,
+            BinfmtError::InvalidFormat(msg) => io::Error::new(io::ErrorKind::InvalidData, msg),
+            BinfmtError::Unsupported(msg) => io::Error::new(io::ErrorKind::Unsupported, msg),
+        }
+    }
+}
+
+pub type Result<T> = core::result::Result<T, BinfmtError>;
@@ -1,12 +1,12 @@
 use std::{
     any::Any,
-    collections::{hash_map::Values, HashMap},
     io::{self, Read, Write},
     ops::BitOr,
     slice::{Iter, IterMut},
 };
 
 use crate::{
+    error::Result as BinfmtResult,
     howto::{HowTo, Reloc, RelocCode},
     sym::{Symbol, SymbolKind, SymbolType},
     traits::ReadSeek,
@@ -23,7 +23,13 @@ pub enum CallbackError {
     NotAccepted,
 }
 
-pub trait Binfmt {
+/// `Send + Sync` because every implementor is a stateless format
+/// descriptor that lives for `'static` in the registry `define_formats!`
+/// builds (itself already stored as `Box<dyn Binfmt + Sync + Send>`), and
+/// callers elsewhere hold on to `&'static dyn Binfmt` across threads (e.g.
+/// to identify several input files concurrently), so the bound needs to
+/// be on the trait itself rather than re-asserted at each call site.
+pub trait Binfmt: Send + Sync {
     fn relnum_to_howto(&self, relnum: u32) -> Option<&dyn HowTo>;
     fn code_to_howto(&self, code: RelocCode) -> Option<&dyn HowTo>;
 
@@ -33,8 +39,8 @@ pub trait Binfmt {
     fn file_priority(&self) -> i32 {
         0
     }
-    fn read_file(&self, file: &mut (dyn ReadSeek + '_)) -> io::Result<Option<BinaryFile>>;
-    fn write_file(&self, file: &mut (dyn Write + '_), bfile: &BinaryFile) -> io::Result<()>;
+    fn read_file(&self, file: &mut (dyn ReadSeek + '_)) -> BinfmtResult<Option<BinaryFile>>;
+    fn write_file(&self, file: &mut (dyn Write + '_), bfile: &BinaryFile) -> BinfmtResult<()>;
 
     fn has_sections(&self) -> bool;
 
@@ -60,6 +66,150 @@ pub trait Binfmt {
     fn has_groups(&self) -> bool {
         false
     }
+
+    /// Whether [`write_file`](Self::write_file) can produce output at all,
+    /// for drivers that want to reject a write-mode operation up front
+    /// (e.g. an unknown `-oformat`) instead of only failing once a write is
+    /// actually attempted. Every format currently in this crate can write,
+    /// so this defaults to `true`; a future read-only format (disassembly
+    /// of a vendor format this crate can't reproduce, say) would override
+    /// it.
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    /// Whether this format can represent relocations produced by a
+    /// relocatable (`-r`) link, i.e. whether [`relnum_to_howto`] or
+    /// [`code_to_howto`] ever return `Some` for it. Formats with no
+    /// relocation model at all (fixed-layout ROM/raw formats) override
+    /// this to `false`, so a driver can reject `-r` against them with a
+    /// precise diagnostic instead of silently dropping the relocations
+    /// during [`write_file`](Self::write_file).
+    ///
+    /// [`relnum_to_howto`]: Self::relnum_to_howto
+    /// [`code_to_howto`]: Self::code_to_howto
+    fn supports_relocs(&self) -> bool {
+        true
+    }
+
+    /// Whether this format preserves arbitrary named sections (and their
+    /// symbols) well enough for debug info to round-trip through it.
+    /// Formats that only recognize a fixed, known set of section names on
+    /// write (so an unrecognized `.debug_*` section would simply be
+    /// dropped) override this to `false`.
+    fn supports_debug(&self) -> bool {
+        true
+    }
+
+    /// Reports the writable+executable (`RWX`) load segments, and whether the
+    /// stack is marked executable, for formats that expose a segment/program
+    /// header table (currently only ELF). Formats without segments (or that
+    /// have not implemented this audit) report no findings.
+    fn segment_security_audit(&self, _bfile: &BinaryFile) -> SegmentAudit {
+        SegmentAudit::default()
+    }
+}
+
+/// The permission bits of a loadable segment, independent of file format.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SegmentPermissions {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl SegmentPermissions {
+    pub fn is_rwx(&self) -> bool {
+        self.write && self.execute
+    }
+}
+
+/// Result of [`Binfmt::segment_security_audit`]: the indices (within the
+/// format's segment/program header table) of segments with both write and
+/// execute permission, plus whether the stack is executable.
+///
+/// `lcld` uses this to print the standard "has a LOAD segment with RWX
+/// permissions" warning, and `readelf --segments` uses it to annotate its
+/// segment listing.
+#[derive(Clone, Debug, Default)]
+pub struct SegmentAudit {
+    pub rwx_segments: Vec<(usize, SegmentPermissions)>,
+    pub executable_stack: bool,
+}
+
+impl SegmentAudit {
+    pub fn has_warnings(&self) -> bool {
+        !self.rwx_segments.is_empty() || self.executable_stack
+    }
+}
+
+/// A content-defined checksum of a section's bytes: the same content always
+/// hashes to the same value, regardless of which format read it or where it
+/// landed in the output. Used by map-file output to let a reader spot a
+/// section that changed between two otherwise-identical links, and by
+/// `--verify`-style comparisons between two builds of the same input.
+///
+/// Computed with FNV-1a rather than [`std::hash::DefaultHasher`], since the
+/// latter's seed is randomized per-process and would make the checksum
+/// useless for comparing across runs.
+pub fn section_checksum(section: &Section) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    let mut hash = FNV_OFFSET_BASIS;
+    for &byte in &section.content {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Computes [`section_checksum`] for every section in `bfile`, in section
+/// order, for inclusion in a linker map or object-verification report.
+pub fn section_checksums(bfile: &BinaryFile) -> Vec<(String, u64)> {
+    bfile
+        .sections()
+        .map(|sect| (sect.name.clone(), section_checksum(sect)))
+        .collect()
+}
+
+/// How [`extract_section`] should represent a section's trailing
+/// [`Section::tail_size`] zero-fill, the bytes a `.bss`-style section
+/// reserves in memory without storing in [`Section::content`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExtractMode {
+    /// Only the bytes actually stored in `content` -- plain `objcopy -O
+    /// binary` behavior, which never materializes `.bss` padding.
+    Raw,
+    /// `content` followed by `tail_size` zero bytes, giving back the
+    /// section's full in-memory size as one literal blob -- what firmware
+    /// packaging that flashes a section verbatim, instead of memset-ing its
+    /// own `.bss` at startup, wants.
+    Padded,
+}
+
+/// Extracts `name`'s bytes out of `bfile` as a standalone buffer, the same
+/// job `objcopy -O binary -j <name>` does for one section at a time.
+///
+/// Returns `None` if no section named `name` exists. If more than one does
+/// (unusual outside of an unlinked object file, where duplicate names are
+/// legal), the first one in section order is returned.
+///
+/// There's no segment-level equivalent here: the program header table ELF's
+/// `read_file` keeps (see [`Binfmt::segment_security_audit`]) records a
+/// segment's virtual address, file offset, and size, but `read_file` never
+/// retains the file's raw bytes once sections have been parsed out of them,
+/// so there's no buffer left to slice a segment's bytes out of by the time a
+/// caller holds a [`BinaryFile`]. Extracting a segment as a blob would need
+/// `read_file` to keep the raw file around (or every [`Section`] to record
+/// the file offset it was read from), neither of which happens today.
+pub fn extract_section(bfile: &BinaryFile, name: &str, mode: ExtractMode) -> Option<Vec<u8>> {
+    let section = bfile.sections().find(|sect| sect.name == name)?;
+    let mut out = section.content.clone();
+    if mode == ExtractMode::Padded {
+        out.resize(out.len() + section.tail_size, 0);
+    }
+    Some(out)
 }
 
 impl core::fmt::Debug for dyn Binfmt {
@@ -93,7 +243,7 @@ pub enum FileType {
 
 pub struct BinaryFile<'a> {
     sections: Option<Vec<Section>>,
-    symbols: Option<HashMap<String, Symbol>>,
+    symbols: Option<Vec<Symbol>>,
     relocs: Option<Vec<Reloc>>,
     groups: Option<Vec<SectionGroup>>,
     fmt: &'a dyn Binfmt,
@@ -193,59 +343,79 @@ impl<'a> BinaryFile<'a> {
         &mut self,
         syms: I,
     ) -> Result<(), CallbackError> {
-        if self.symbols.is_none() {
-            self.symbols = Some(HashMap::new());
-        }
-
-        let symtab = self.symbols.as_mut().unwrap();
+        let symtab = self.symbols.get_or_insert_with(Vec::new);
 
         for mut sym in syms {
             self.fmt.create_symbol(&mut sym)?;
-            symtab.insert(sym.name().to_string(), sym);
+            match symtab.iter_mut().find(|x| x.name() == sym.name()) {
+                Some(slot) => *slot = sym,
+                None => symtab.push(sym),
+            }
         }
 
         Ok(())
     }
 
     pub fn get_or_create_symbol(&mut self, name: &str) -> Result<&mut Symbol, CallbackError> {
-        if self.symbols.is_none() {
-            self.symbols = Some(HashMap::new());
-        }
+        let symtab = self.symbols.get_or_insert_with(Vec::new);
 
-        let symtab = self.symbols.as_mut().unwrap();
-        // SAFETY: Hecking NLL not being powerful enough
-        if let Some(x) = unsafe { &mut *(symtab as *mut HashMap<String, Symbol>) }.get_mut(name) {
-            return Ok(x);
-        }
-        {
-            let mut sym = Symbol::new_undef(name.to_string(), SymbolType::Null, SymbolKind::Local);
-            self.fmt.create_symbol(&mut sym)?;
-            symtab.insert(name.to_string(), sym);
-            Ok(symtab.get_mut(name).unwrap())
+        if let Some(pos) = symtab.iter().position(|x| x.name() == name) {
+            return Ok(&mut symtab[pos]);
         }
+
+        let mut sym = Symbol::new_undef(name.to_string(), SymbolType::Null, SymbolKind::Local);
+        self.fmt.create_symbol(&mut sym)?;
+        symtab.push(sym);
+        Ok(symtab.last_mut().unwrap())
     }
 
     pub fn insert_symbol(&mut self, mut sym: Symbol) -> Result<(), Symbol> {
-        if self.symbols.is_none() {
-            self.symbols = Some(HashMap::new());
-        }
+        let symtab = self.symbols.get_or_insert_with(Vec::new);
 
-        let symbols = self.symbols.as_mut().unwrap();
         if self.fmt.create_symbol(&mut sym).is_err() {
-            Err(sym)
-        } else {
-            let name = sym.name().to_string();
-            symbols.insert(name, sym);
-            Ok(())
+            return Err(sym);
         }
+
+        match symtab.iter_mut().find(|x| x.name() == sym.name()) {
+            Some(slot) => *slot = sym,
+            None => symtab.push(sym),
+        }
+        Ok(())
     }
 
+    /// Iterates over every symbol in file order, borrowing directly from
+    /// the symbol table rather than cloning names or values -- callers
+    /// that only need a symbol's `&str` name (e.g. `objdump`'s listing)
+    /// never allocate for it.
     pub fn symbols(&self) -> Symbols {
-        Symbols(self.symbols.as_ref().map(|x| x.values()))
+        Symbols(self.symbols.as_ref().map(|x| x.iter()))
+    }
+
+    /// Every symbol this file exports, normalized the same way regardless
+    /// of which format-specific mechanism it came from -- ELF's dynamic
+    /// symbol table, a PE export directory entry, a Mach-O export trie
+    /// entry -- since each format's `read_file` already folds its own
+    /// mechanism into the same [`Symbol`]/[`SymbolKind`] representation
+    /// [`symbols`](Self::symbols) exposes, rather than keeping a separate
+    /// per-format export list. A symbol is exported if it's both defined
+    /// (has a section) and has global or weak binding. `o65`'s own writer
+    /// uses the same defined-symbol test for its export table, but takes
+    /// only global bindings.
+    ///
+    /// This can't yet distinguish ELF's `STV_DEFAULT` from `STV_HIDDEN`/
+    /// `STV_PROTECTED` visibility, since [`Symbol`] has no visibility field
+    /// of its own (only [`SymbolKind`]'s binding) -- see the `.hidden`
+    /// handling note in `lcas`'s symbol emission. A global symbol the
+    /// source format marked hidden is still reported here as exported.
+    pub fn exports(&self) -> impl Iterator<Item = &Symbol> + '_ {
+        self.symbols()
+            .filter(|s| matches!(s.kind(), SymbolKind::Global | SymbolKind::Weak) && s.section().is_some())
     }
 
     pub fn remove_symbol(&mut self, name: &str) -> Option<Symbol> {
-        self.symbols.as_mut().and_then(|x| x.remove(name))
+        let symtab = self.symbols.as_mut()?;
+        let pos = symtab.iter().position(|x| x.name() == name)?;
+        Some(symtab.remove(pos))
     }
 
     pub fn create_reloc(&mut self, mut reloc: Reloc) -> Result<(), Reloc> {
@@ -308,7 +478,7 @@ impl<'a> Iterator for SectionsMut<'a> {
     }
 }
 
-pub struct Symbols<'a>(Option<Values<'a, String, Symbol>>);
+pub struct Symbols<'a>(Option<Iter<'a, Symbol>>);
 
 impl<'a> Iterator for Symbols<'a> {
     type Item = &'a Symbol;
@@ -349,6 +519,9 @@ pub enum SectionType {
     RelocationTable,
     RelocationAddendTable,
     Note,
+    PreinitArray,
+    InitArray,
+    FiniArray,
     FormatSpecific(u32),
 }
 
@@ -600,10 +773,9 @@ mod tests {
         fn read_file(
             &self,
             _: &mut (dyn ReadSeek + '_),
-        ) -> std::io::Result<Option<super::BinaryFile>> {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::Unsupported,
-                "Can't Read/write Test Binfmts",
+        ) -> crate::error::Result<Option<super::BinaryFile>> {
+            Err(crate::error::BinfmtError::Unsupported(
+                "Can't Read/write Test Binfmts".to_string(),
             ))
         }
 
@@ -611,10 +783,9 @@ mod tests {
             &self,
             _: &mut (dyn std::io::Write + '_),
             _: &super::BinaryFile,
-        ) -> std::io::Result<()> {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::Unsupported,
-                "Can't Read/write Test Binfmts",
+        ) -> crate::error::Result<()> {
+            Err(crate::error::BinfmtError::Unsupported(
+                "Can't Read/write Test Binfmts".to_string(),
             ))
         }
 
@@ -631,4 +802,30 @@ mod tests {
         let fmt = TestBinfmt.create_file(FileType::Exec);
         fmt.data().downcast_ref::<()>();
     }
+
+    #[test]
+    fn extract_section_raw_and_padded() {
+        let mut bfile = TestBinfmt.create_file(FileType::Exec);
+        bfile
+            .add_section(super::Section {
+                name: ".text".to_string(),
+                content: vec![1, 2, 3],
+                tail_size: 2,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            super::extract_section(&bfile, ".text", super::ExtractMode::Raw),
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(
+            super::extract_section(&bfile, ".text", super::ExtractMode::Padded),
+            Some(vec![1, 2, 3, 0, 0])
+        );
+        assert_eq!(
+            super::extract_section(&bfile, ".missing", super::ExtractMode::Raw),
+            None
+        );
+    }
 }
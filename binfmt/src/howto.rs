@@ -35,6 +35,47 @@ pub trait HowTo {
         region: &'a mut [u8],
     ) -> Result<&'a mut [u8], HowToError>;
     fn valid_in(&self, output_ty: RelocOutput, sym_vis: &Symbol) -> bool;
+
+    /// Attempts to relax this relocation into a cheaper form once the
+    /// linker knows the target binds locally (e.g. a GOT-relative `mov`
+    /// into a `rip`-relative `lea`, or a PLT call into a direct one).
+    ///
+    /// `region` covers the same bytes as [`Self::apply`] would be given,
+    /// plus however many bytes immediately before it the relaxed encoding
+    /// needs to rewrite (how many, if any, is `HowTo`-specific; callers
+    /// that can't spare bytes ahead of the relocated field should just
+    /// skip relaxation). Implementations that take this path rewrite
+    /// `region` in place and return the [`RelocCode`] the caller should
+    /// re-resolve the relocation as; the default is to never relax.
+    fn relax(&self, _region: &mut [u8], _is_local: bool) -> Option<RelocCode> {
+        None
+    }
+
+    /// Like [`Self::relax`], but for a relaxation that shrinks the
+    /// encoding rather than just swapping it for an equal-size one -- a
+    /// long, fixed-width branch/call shrinking to a short relative form
+    /// once `addr`/`at_addr` (the same resolved target and relocation
+    /// site address [`Self::apply`] would be given) turn out to be close
+    /// enough, the kind of relaxation w65 and clever's variable-width
+    /// branch encodings want.
+    ///
+    /// `region` covers the same bytes [`Self::apply`] would be given (its
+    /// length is [`Self::reloc_size`]'s original, un-shrunk value); an
+    /// implementation that can shrink writes the new, shorter encoding
+    /// into `region`'s leading bytes and returns the relocation code to
+    /// re-resolve it as together with how many of `region`'s bytes that
+    /// encoding actually used. `lcld`'s `relax::relax_sections` pass
+    /// deletes the rest and shifts every later relocation in the same
+    /// section to match. Defaults to never shrinking.
+    fn relax_shrink(
+        &self,
+        _region: &mut [u8],
+        _is_local: bool,
+        _addr: u128,
+        _at_addr: u128,
+    ) -> Option<(RelocCode, usize)> {
+        None
+    }
 }
 
 pub use arch_ops::traits::{Reloc, RelocCode};
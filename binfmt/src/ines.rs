@@ -0,0 +1,185 @@
+//! The iNES/NES 2.0 ROM container format used by NES/Famicom emulators.
+//!
+//! A cartridge image is an `NES\x1A` header followed by a PRG-ROM bank
+//! (stored as section `.prg`) and, if present, a CHR-ROM bank (`.chr`).
+//! There is no symbol table or relocations; `write_file` lays the sections
+//! back out in header order.
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    fmt::{BinaryFile, Binfmt, CallbackError, FileType, Section, SectionType},
+    howto::{HowTo, RelocCode},
+    sym::Symbol,
+    traits::ReadSeek,
+};
+
+pub const MAGIC: [u8; 4] = [b'N', b'E', b'S', 0x1A];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct InesHeader {
+    pub magic: [u8; 4],
+    pub prg_rom_16k: u8,
+    pub chr_rom_8k: u8,
+    pub flags6: u8,
+    pub flags7: u8,
+    pub flags8: u8,
+    pub flags9: u8,
+    pub flags10: u8,
+    pub padding: [u8; 5],
+}
+
+static_assertions::const_assert_eq!(core::mem::size_of::<InesHeader>(), 16);
+
+impl InesHeader {
+    /// NES 2.0 is identified by bits 2-3 of byte 7 being `0b10`.
+    pub fn is_nes20(&self) -> bool {
+        self.flags7 & 0x0C == 0x08
+    }
+
+    pub fn mapper(&self) -> u16 {
+        let lo = ((self.flags6 >> 4) | (self.flags7 & 0xF0)) as u16;
+        if self.is_nes20() {
+            lo | (((self.flags8 & 0x0F) as u16) << 8)
+        } else {
+            lo
+        }
+    }
+
+    pub fn has_trainer(&self) -> bool {
+        self.flags6 & 0x04 != 0
+    }
+}
+
+pub struct Ines;
+
+pub fn create_format() -> Ines {
+    Ines
+}
+
+impl Binfmt for Ines {
+    fn relnum_to_howto(&self, _relnum: u32) -> Option<&dyn HowTo> {
+        None
+    }
+
+    fn code_to_howto(&self, _code: RelocCode) -> Option<&dyn HowTo> {
+        None
+    }
+
+    fn name(&self) -> &'static str {
+        "ines"
+    }
+
+    fn create_file(&self, ty: FileType) -> BinaryFile {
+        BinaryFile::create(self, Box::new(InesHeader::zeroed()), ty)
+    }
+
+    fn ident_file(&self, file: &mut (dyn std::io::Read + '_)) -> std::io::Result<bool> {
+        let mut magic = [0u8; 4];
+        if file.read_exact(&mut magic).is_err() {
+            return Ok(false);
+        }
+        Ok(magic == MAGIC)
+    }
+
+    fn read_file(&self, file: &mut (dyn ReadSeek + '_)) -> crate::error::Result<Option<BinaryFile>> {
+        let mut hdr = InesHeader::zeroed();
+        file.read_exact(bytemuck::bytes_of_mut(&mut hdr))?;
+        if hdr.magic != MAGIC {
+            return Ok(None);
+        }
+
+        let mut bfile = BinaryFile::create(self, Box::new(hdr), FileType::Exec);
+
+        if hdr.has_trainer() {
+            let mut trainer = vec![0u8; 512];
+            file.read_exact(&mut trainer)?;
+            bfile
+                .add_section(Section {
+                    name: ".trainer".to_string(),
+                    align: 1,
+                    ty: SectionType::ProgBits,
+                    content: trainer,
+                    ..Default::default()
+                })
+                .ok();
+        }
+
+        let mut prg = vec![0u8; hdr.prg_rom_16k as usize * 16 * 1024];
+        file.read_exact(&mut prg)?;
+        bfile
+            .add_section(Section {
+                name: ".prg".to_string(),
+                align: 1,
+                ty: SectionType::ProgBits,
+                content: prg,
+                ..Default::default()
+            })
+            .ok();
+
+        if hdr.chr_rom_8k > 0 {
+            let mut chr = vec![0u8; hdr.chr_rom_8k as usize * 8 * 1024];
+            file.read_exact(&mut chr)?;
+            bfile
+                .add_section(Section {
+                    name: ".chr".to_string(),
+                    align: 1,
+                    ty: SectionType::ProgBits,
+                    content: chr,
+                    ..Default::default()
+                })
+                .ok();
+        }
+
+        Ok(Some(bfile))
+    }
+
+    fn write_file(
+        &self,
+        file: &mut (dyn std::io::Write + '_),
+        bfile: &BinaryFile,
+    ) -> crate::error::Result<()> {
+        let hdr = bfile
+            .data()
+            .downcast_ref::<InesHeader>()
+            .copied()
+            .unwrap_or(InesHeader::zeroed());
+
+        let mut hdr = hdr;
+        hdr.magic = MAGIC;
+
+        for sect in bfile.sections() {
+            match &*sect.name {
+                ".prg" => hdr.prg_rom_16k = (sect.content.len() / (16 * 1024)) as u8,
+                ".chr" => hdr.chr_rom_8k = (sect.content.len() / (8 * 1024)) as u8,
+                _ => {}
+            }
+        }
+
+        file.write_all(bytemuck::bytes_of(&hdr))?;
+        for name in [".trainer", ".prg", ".chr"] {
+            if let Some(sect) = bfile.sections().find(|s| s.name == name) {
+                file.write_all(&sect.content)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn has_sections(&self) -> bool {
+        true
+    }
+
+    fn supports_relocs(&self) -> bool {
+        false
+    }
+
+    fn supports_debug(&self) -> bool {
+        false
+    }
+
+    fn create_symbol(&self, _sym: &mut Symbol) -> Result<(), CallbackError> {
+        Err(CallbackError::NotAccepted)
+    }
+}
@@ -9,13 +9,31 @@ pub mod traits;
 
 pub mod debug;
 
+pub mod error;
 pub mod fmt;
 pub mod howto;
 pub mod sym;
 
+pub mod disasm;
+
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
+#[cfg(feature = "profile")]
+pub mod profile;
+
 #[cfg(feature = "elf")]
 pub mod elf;
 
+#[cfg(feature = "elf")]
+pub mod stats;
+
+#[cfg(feature = "elf")]
+pub mod validate;
+
+#[cfg(feature = "elf")]
+pub mod debuglink;
+
 #[cfg(feature = "elf32")]
 pub mod elf32;
 
@@ -46,6 +64,15 @@ pub mod xo65;
 #[cfg(feature = "o65")]
 pub mod o65;
 
+#[cfg(feature = "xcoff")]
+pub mod xcoff;
+
+#[cfg(feature = "ines")]
+pub mod ines;
+
+#[cfg(feature = "snes")]
+pub mod snes;
+
 pub mod binary;
 
 extern crate lazy_static;
@@ -116,6 +143,18 @@ define_formats![
     elf64-genericle,
     #[cfg(feature = "elf64")]
     elf64-genericbe,
+    #[cfg(all(feature = "o65", feature = "w65"))]
+    o65,
+    #[cfg(all(feature = "xo65", feature = "w65"))]
+    xo65,
+    #[cfg(feature = "aout")]
+    aout,
+    #[cfg(feature = "xcoff")]
+    xcoff,
+    #[cfg(feature = "ines")]
+    ines,
+    #[cfg(feature = "snes")]
+    snes,
     binary
 ];
 
@@ -138,6 +177,8 @@ pub fn def_vec_for(targ: &Target) -> &'static (dyn crate::fmt::Binfmt + Sync + S
         match (targ){
             w65-*-elf => &*BINARY_FORMATS_BY_NAME["elf32-w65"],
             w65-*-snes-elf => &*BINARY_FORMATS_BY_NAME["elf32-w65"],
+            w65-*-none => &*BINARY_FORMATS_BY_NAME["xo65"],
+            w65-*-nes => &*BINARY_FORMATS_BY_NAME["o65"],
             x86_64-*-elf => &*BINARY_FORMATS_BY_NAME["elf64-x86_64"],
             x86_64-*-*-elf => &*BINARY_FORMATS_BY_NAME["elf64-x86_64"],
             x86_64-*-*-gnu => &*BINARY_FORMATS_BY_NAME["elf64-x86_64"],
@@ -156,24 +197,51 @@ pub fn def_vec_for(targ: &Target) -> &'static (dyn crate::fmt::Binfmt + Sync + S
     }
 }
 
-pub fn identify_file<R: Read + Seek>(mut read: R) -> std::io::Result<Option<&'static dyn Binfmt>> {
+/// One format that accepted a file in [`identify_file_scored`], along with
+/// the confidence ([`Binfmt::file_priority`]) it reported. Higher confidence
+/// wins; among equal-confidence matches, the format registered earlier (see
+/// [`formats`]) wins, matching the historical first-match behaviour of
+/// [`identify_file`].
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct IdentMatch {
+    pub fmt: &'static dyn Binfmt,
+    pub confidence: i32,
+}
+
+/// Runs every registered format's [`Binfmt::ident_file`] against `read`,
+/// collecting every format that accepts it with its reported confidence,
+/// most confident first. Unlike [`identify_file`], this does not stop at the
+/// first match, so a reader can fall back to the next candidate if the most
+/// confident one turns out to fail to parse.
+pub fn identify_file_scored<R: Read + Seek>(mut read: R) -> std::io::Result<Vec<IdentMatch>> {
     let begin = read.stream_position()?;
+    let mut matches = Vec::new();
+
     for fmt in crate::formats() {
         if fmt == format_by_name("binary").unwrap() {
-            break;
+            continue;
         }
-        #[allow(clippy::branches_sharing_code)]
-        // As much as I'd love to follow your suggestion clippy, I'd rather have the correct behaviour at runtime
-        // So shut it
-        if let Ok(true) = fmt.ident_file(&mut read) {
-            read.seek(std::io::SeekFrom::Start(begin))?;
-
-            return Ok(Some(fmt));
-        } else {
-            read.seek(std::io::SeekFrom::Start(begin))?;
+        let accepted = matches!(fmt.ident_file(&mut read), Ok(true));
+        read.seek(std::io::SeekFrom::Start(begin))?;
+        if accepted {
+            matches.push(IdentMatch {
+                fmt,
+                confidence: fmt.file_priority(),
+            });
         }
     }
 
+    matches.sort_by_key(|m| core::cmp::Reverse(m.confidence));
+    Ok(matches)
+}
+
+pub fn identify_file<R: Read + Seek>(mut read: R) -> std::io::Result<Option<&'static dyn Binfmt>> {
+    let begin = read.stream_position()?;
+    if let Some(m) = identify_file_scored(&mut read)?.into_iter().next() {
+        read.seek(std::io::SeekFrom::Start(begin))?;
+        return Ok(Some(m.fmt));
+    }
+    read.seek(std::io::SeekFrom::Start(begin))?;
     Ok(None)
 }
 
@@ -185,7 +253,17 @@ pub fn open_file<R: Read + Seek>(mut read: R) -> std::io::Result<BinaryFile<'sta
         // So shut it
         if let Ok(true) = fmt.ident_file(&mut read) {
             read.seek(std::io::SeekFrom::Start(begin))?;
+            #[cfg(feature = "profile")]
+            let (profile_start, profile_allocs_before) =
+                (std::time::Instant::now(), crate::profile::allocation_count());
             let file = fmt.read_file(&mut read)?.unwrap();
+            #[cfg(feature = "profile")]
+            crate::profile::record(crate::profile::ParseProfile {
+                format: fmt.name(),
+                duration: profile_start.elapsed(),
+                bytes_materialized: file.sections().map(|s| s.content.len()).sum(),
+                allocations: crate::profile::allocation_count() - profile_allocs_before,
+            });
             return Ok(file);
         } else {
             read.seek(std::io::SeekFrom::Start(begin))?;
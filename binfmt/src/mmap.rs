@@ -0,0 +1,46 @@
+//! Memory-mapped file access, for opening large objects without copying the
+//! whole file into a heap buffer up front.
+//!
+//! [`crate::open_file`] and [`crate::fmt::Binfmt::read_file`] already
+//! accept any `Read + Seek`, so a mapped file can be fed into either
+//! directly via a [`Cursor`] over the mapping -- [`MappedObject`] just owns
+//! the mapping so callers don't have to manage the underlying [`File`]
+//! themselves, and lets the OS page cache supply the bytes (and reclaim
+//! them under memory pressure) instead of holding them in an explicitly
+//! allocated `Vec`.
+//!
+//! Note that [`crate::fmt::Binfmt::read_file`] implementations still copy section
+//! contents out of the source into owned [`Section`](crate::fmt::Section)
+//! buffers as they parse; true zero-copy section borrowing would need
+//! `Section::content` to become `Cow<'a, [u8]>`, which this doesn't
+//! attempt.
+
+use std::{fs::File, io, io::Cursor, path::Path};
+
+use memmap2::Mmap;
+
+pub struct MappedObject {
+    mmap: Mmap,
+}
+
+impl MappedObject {
+    /// Maps `path` into memory for reading. Inherits
+    /// [`memmap2::Mmap::map`]'s requirement that the file not be truncated
+    /// (by another process or handle) while the mapping is held.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self { mmap })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    /// A fresh `Read + Seek` view over the mapping, for passing to
+    /// [`crate::open_file`] or
+    /// [`crate::fmt::Binfmt::read_file`]/[`crate::fmt::Binfmt::ident_file`].
+    pub fn cursor(&self) -> Cursor<&[u8]> {
+        Cursor::new(self.as_bytes())
+    }
+}
@@ -1,7 +1,19 @@
-#![allow(dead_code)] // fixme later
+//! The `o65` relocatable object format used by the cc65 toolchain for 6502
+//! family CPUs, and its "extended" `xo65` sibling (see [`crate::xo65`]) which
+//! widens every address-sized field from 16 to 32 bits for the banked/24-bit
+//! addressing modes of the 65816.
+
+use std::{collections::HashMap, mem::size_of};
 
 use bytemuck::{Pod, Zeroable};
 
+use crate::{
+    fmt::{BinaryFile, Binfmt, CallbackError, FileType, Section, SectionType},
+    howto::{HowTo, HowToError, RelocCode, RelocOutput},
+    sym::{Symbol, SymbolKind, SymbolType},
+    traits::ReadSeek,
+};
+
 #[repr(C)]
 #[derive(Zeroable, Pod, Clone, Copy)]
 pub struct O65FixedHeader {
@@ -11,6 +23,49 @@ pub struct O65FixedHeader {
     mode: u16,
 }
 
+const MAGIC: [u8; 2] = [0x01, 0x00];
+const O65_MAGIC: [u8; 3] = *b"o65";
+
+// Bits of the `mode` word. Only the subset this implementation relies on is
+// named; the rest is round-tripped but otherwise ignored.
+const MODE_SIZE32: u16 = 0x0001; // addresses/sizes are 32, not 16, bits wide
+const MODE_OBJ: u16 = 0x0002; // file carries undef/export tables and relocs
+const MODE_CPU_SHIFT: u16 = 12;
+const MODE_CPU_MASK: u16 = 0x7000;
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum O65Cpu {
+    Mos6502,
+    W65C02,
+    W65C816,
+    W65Sc02,
+    Other(u16),
+}
+
+impl O65Cpu {
+    fn from_mode(mode: u16) -> Self {
+        match (mode & MODE_CPU_MASK) >> MODE_CPU_SHIFT {
+            0 => Self::Mos6502,
+            1 => Self::W65C02,
+            2 => Self::W65C816,
+            3 => Self::W65Sc02,
+            n => Self::Other(n),
+        }
+    }
+
+    fn to_mode_bits(self) -> u16 {
+        let n = match self {
+            Self::Mos6502 => 0,
+            Self::W65C02 => 1,
+            Self::W65C816 => 2,
+            Self::W65Sc02 => 3,
+            Self::Other(n) => n,
+        };
+        n << MODE_CPU_SHIFT
+    }
+}
+
 #[repr(C)]
 #[derive(Zeroable, Pod, Clone, Copy)]
 pub struct O65Header16 {
@@ -40,3 +95,637 @@ pub struct O65Header32 {
     zsize: u32,
     stack: u32,
 }
+
+/// Parametrizes [`O65Format`] over the 16-bit (`o65`) and 32-bit (`xo65`)
+/// address-field widths, the way [`crate::elf::ElfClass`] parametrizes the
+/// ELF reader/writer over 32/64-bit.
+pub trait O65Class: Sized + Send + Sync {
+    const SIZE_BIT: bool;
+    fn read_sized_header(
+        fixed: O65FixedHeader,
+        file: &mut (dyn ReadSeek + '_),
+    ) -> std::io::Result<SizedFields>;
+    fn write_sized_header(
+        out: &mut (dyn std::io::Write + '_),
+        fixed: O65FixedHeader,
+        fields: &SizedFields,
+    ) -> std::io::Result<()>;
+    fn read_u(file: &mut (dyn ReadSeek + '_)) -> std::io::Result<u32>;
+    fn write_u(out: &mut (dyn std::io::Write + '_), val: u32) -> std::io::Result<()>;
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SizedFields {
+    pub tbase: u32,
+    pub tsize: u32,
+    pub dbase: u32,
+    pub dsize: u32,
+    pub bbase: u32,
+    pub bsize: u32,
+    pub zbase: u32,
+    pub zsize: u32,
+    pub stack: u32,
+}
+
+pub struct Narrow;
+
+impl O65Class for Narrow {
+    const SIZE_BIT: bool = false;
+
+    fn read_sized_header(
+        fixed: O65FixedHeader,
+        file: &mut (dyn ReadSeek + '_),
+    ) -> std::io::Result<SizedFields> {
+        let mut hdr = O65Header16::zeroed();
+        hdr.fixed = fixed;
+        file.read_exact(&mut bytemuck::bytes_of_mut(&mut hdr)[size_of::<O65FixedHeader>()..])?;
+        Ok(SizedFields {
+            tbase: hdr.tbase as u32,
+            tsize: hdr.tsize as u32,
+            dbase: hdr.dbase as u32,
+            dsize: hdr.dsize as u32,
+            bbase: hdr.bbase as u32,
+            bsize: hdr.bsize as u32,
+            zbase: hdr.zbase as u32,
+            zsize: hdr.zsize as u32,
+            stack: hdr.stack as u32,
+        })
+    }
+
+    fn write_sized_header(
+        out: &mut (dyn std::io::Write + '_),
+        fixed: O65FixedHeader,
+        fields: &SizedFields,
+    ) -> std::io::Result<()> {
+        let hdr = O65Header16 {
+            fixed,
+            tbase: fields.tbase as u16,
+            tsize: fields.tsize as u16,
+            dbase: fields.dbase as u16,
+            dsize: fields.dsize as u16,
+            bbase: fields.bbase as u16,
+            bsize: fields.bsize as u16,
+            zbase: fields.zbase as u16,
+            zsize: fields.zsize as u16,
+            stack: fields.stack as u16,
+        };
+        out.write_all(bytemuck::bytes_of(&hdr))
+    }
+
+    fn read_u(file: &mut (dyn ReadSeek + '_)) -> std::io::Result<u32> {
+        let mut buf = [0u8; 2];
+        file.read_exact(&mut buf)?;
+        Ok(u16::from_le_bytes(buf) as u32)
+    }
+
+    fn write_u(out: &mut (dyn std::io::Write + '_), val: u32) -> std::io::Result<()> {
+        out.write_all(&(val as u16).to_le_bytes())
+    }
+}
+
+pub struct Wide;
+
+impl O65Class for Wide {
+    const SIZE_BIT: bool = true;
+
+    fn read_sized_header(
+        fixed: O65FixedHeader,
+        file: &mut (dyn ReadSeek + '_),
+    ) -> std::io::Result<SizedFields> {
+        let mut hdr = O65Header32::zeroed();
+        hdr.fixed = fixed;
+        file.read_exact(&mut bytemuck::bytes_of_mut(&mut hdr)[size_of::<O65FixedHeader>()..])?;
+        Ok(SizedFields {
+            tbase: hdr.tbase,
+            tsize: hdr.tsize,
+            dbase: hdr.dbase,
+            dsize: hdr.dsize,
+            bbase: hdr.bbase,
+            bsize: hdr.bsize,
+            zbase: hdr.zbase,
+            zsize: hdr.zsize,
+            stack: hdr.stack,
+        })
+    }
+
+    fn write_sized_header(
+        out: &mut (dyn std::io::Write + '_),
+        fixed: O65FixedHeader,
+        fields: &SizedFields,
+    ) -> std::io::Result<()> {
+        let hdr = O65Header32 {
+            fixed,
+            tbase: fields.tbase,
+            tsize: fields.tsize,
+            dbase: fields.dbase,
+            dsize: fields.dsize,
+            bbase: fields.bbase,
+            bsize: fields.bsize,
+            zbase: fields.zbase,
+            zsize: fields.zsize,
+            stack: fields.stack,
+        };
+        out.write_all(bytemuck::bytes_of(&hdr))
+    }
+
+    fn read_u(file: &mut (dyn ReadSeek + '_)) -> std::io::Result<u32> {
+        let mut buf = [0u8; 4];
+        file.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn write_u(out: &mut (dyn std::io::Write + '_), val: u32) -> std::io::Result<()> {
+        out.write_all(&val.to_le_bytes())
+    }
+}
+
+const SEG_UNDEF: u8 = 0;
+const SEG_ABS: u8 = 1;
+const SEG_TEXT: u8 = 2;
+const SEG_DATA: u8 = 3;
+const SEG_BSS: u8 = 4;
+const SEG_ZP: u8 = 5;
+
+const RELOC_WORD: u8 = 0x80;
+const RELOC_HIGH: u8 = 0x40;
+const RELOC_LOW: u8 = 0x20;
+const RELOC_TYPE_MASK: u8 = 0xE0;
+const RELOC_SEG_MASK: u8 = 0x1F;
+
+#[non_exhaustive]
+pub enum O65HowTo {
+    Word,
+    High,
+    Low,
+}
+
+impl HowTo for O65HowTo {
+    fn from_relnum<'a>(num: u32) -> Option<&'a Self>
+    where
+        Self: Sized + 'a,
+    {
+        match num {
+            0 => Some(&O65HowTo::Word),
+            1 => Some(&O65HowTo::High),
+            2 => Some(&O65HowTo::Low),
+            _ => None,
+        }
+    }
+
+    fn from_reloc_code<'a>(code: RelocCode) -> Option<&'a Self>
+    where
+        Self: Sized + 'a,
+    {
+        match code {
+            RelocCode::Abs { addr_width: 16 } => Some(&O65HowTo::Word),
+            RelocCode::AbsShifted {
+                addr_width: 8,
+                shift: 8,
+            } => Some(&O65HowTo::High),
+            RelocCode::Abs { addr_width: 8 } => Some(&O65HowTo::Low),
+            _ => None,
+        }
+    }
+
+    fn reloc_num(&self) -> u32 {
+        match self {
+            O65HowTo::Word => 0,
+            O65HowTo::High => 1,
+            O65HowTo::Low => 2,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            O65HowTo::Word => "O65_RELOC_WORD",
+            O65HowTo::High => "O65_RELOC_HIGH",
+            O65HowTo::Low => "O65_RELOC_LOW",
+        }
+    }
+
+    fn reloc_size(&self) -> usize {
+        match self {
+            O65HowTo::Word => 2,
+            O65HowTo::High | O65HowTo::Low => 1,
+        }
+    }
+
+    fn pcrel(&self) -> bool {
+        false
+    }
+
+    fn is_relax(&self) -> bool {
+        false
+    }
+
+    fn relax_size(&self, _addr: u128, _at_addr: u128) -> Option<usize> {
+        None
+    }
+
+    fn apply<'a>(
+        &self,
+        addr: u128,
+        _at_addr: u128,
+        region: &'a mut [u8],
+    ) -> Result<&'a mut [u8], HowToError> {
+        let bytes = addr.to_le_bytes();
+        match self {
+            O65HowTo::Word => region.copy_from_slice(&bytes[..2]),
+            O65HowTo::High => region.copy_from_slice(&bytes[1..2]),
+            O65HowTo::Low => region.copy_from_slice(&bytes[..1]),
+        }
+        Ok(region)
+    }
+
+    fn valid_in(&self, _output_ty: RelocOutput, _sym_vis: &Symbol) -> bool {
+        true
+    }
+}
+
+fn segment_id(name: &str) -> u8 {
+    match name {
+        ".text" => SEG_TEXT,
+        ".data" => SEG_DATA,
+        ".bss" => SEG_BSS,
+        ".zp" => SEG_ZP,
+        _ => SEG_ABS,
+    }
+}
+
+fn segment_name(id: u8) -> &'static str {
+    match id {
+        SEG_TEXT => ".text",
+        SEG_DATA => ".data",
+        SEG_BSS => ".bss",
+        SEG_ZP => ".zp",
+        _ => ".text",
+    }
+}
+
+fn read_option_headers(file: &mut (dyn ReadSeek + '_)) -> std::io::Result<()> {
+    loop {
+        let mut len = [0u8];
+        file.read_exact(&mut len)?;
+        if len[0] == 0 {
+            return Ok(());
+        }
+        let mut rest = vec![0u8; (len[0] as usize) - 1];
+        file.read_exact(&mut rest)?;
+    }
+}
+
+fn read_cstr(file: &mut (dyn ReadSeek + '_)) -> std::io::Result<String> {
+    let mut bytes = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        file.read_exact(&mut byte)?;
+        if byte[0] == 0 {
+            break;
+        }
+        bytes.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn write_cstr(out: &mut (dyn std::io::Write + '_), s: &str) -> std::io::Result<()> {
+    out.write_all(s.as_bytes())?;
+    out.write_all(&[0])
+}
+
+pub struct O65Format<Class> {
+    name: &'static str,
+    _class: core::marker::PhantomData<Class>,
+}
+
+pub fn create_format() -> O65Format<Narrow> {
+    create_o65_format("o65")
+}
+
+pub fn create_o65_format(name: &'static str) -> O65Format<Narrow> {
+    O65Format {
+        name,
+        _class: core::marker::PhantomData,
+    }
+}
+
+pub fn create_xo65_format(name: &'static str) -> O65Format<Wide> {
+    O65Format {
+        name,
+        _class: core::marker::PhantomData,
+    }
+}
+
+impl<Class: O65Class + 'static> Binfmt for O65Format<Class> {
+    fn relnum_to_howto(&self, relnum: u32) -> Option<&dyn HowTo> {
+        O65HowTo::from_relnum(relnum).map(|x| x as &dyn HowTo)
+    }
+
+    fn code_to_howto(&self, code: RelocCode) -> Option<&dyn HowTo> {
+        O65HowTo::from_reloc_code(code).map(|x| x as &dyn HowTo)
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn create_file(&self, ty: FileType) -> BinaryFile {
+        BinaryFile::create(self, Box::new(()), ty)
+    }
+
+    fn ident_file(&self, file: &mut (dyn std::io::Read + '_)) -> std::io::Result<bool> {
+        let mut fixed = O65FixedHeader::zeroed();
+        if file.read_exact(bytemuck::bytes_of_mut(&mut fixed)).is_err() {
+            return Ok(false);
+        }
+        Ok(fixed.id1 == MAGIC
+            && fixed.magic == O65_MAGIC
+            && ((fixed.mode & MODE_SIZE32) != 0) == Class::SIZE_BIT)
+    }
+
+    fn read_file(&self, file: &mut (dyn ReadSeek + '_)) -> crate::error::Result<Option<BinaryFile>> {
+        let mut fixed = O65FixedHeader::zeroed();
+        file.read_exact(bytemuck::bytes_of_mut(&mut fixed))?;
+
+        if fixed.id1 != MAGIC || fixed.magic != O65_MAGIC {
+            return Ok(None);
+        }
+        if ((fixed.mode & MODE_SIZE32) != 0) != Class::SIZE_BIT {
+            return Ok(None);
+        }
+
+        let fields = Class::read_sized_header(fixed, file)?;
+        read_option_headers(file)?;
+
+        let is_obj = (fixed.mode & MODE_OBJ) != 0;
+
+        let mut undef_names = Vec::new();
+        if is_obj {
+            let count = Class::read_u(file)?;
+            for _ in 0..count {
+                undef_names.push(read_cstr(file)?);
+            }
+        }
+
+        let ty = if is_obj {
+            FileType::Relocatable
+        } else {
+            FileType::Exec
+        };
+        let mut bfile = BinaryFile::create(self, Box::new(()), ty);
+
+        let mut text = vec![0u8; fields.tsize as usize];
+        file.read_exact(&mut text)?;
+        let mut data = vec![0u8; fields.dsize as usize];
+        file.read_exact(&mut data)?;
+
+        let text_secno = bfile
+            .add_section(Section {
+                name: ".text".to_string(),
+                align: 1,
+                ty: SectionType::ProgBits,
+                content: text,
+                ..Default::default()
+            })
+            .ok();
+        let data_secno = bfile
+            .add_section(Section {
+                name: ".data".to_string(),
+                align: 1,
+                ty: SectionType::ProgBits,
+                content: data,
+                ..Default::default()
+            })
+            .ok();
+        bfile
+            .add_section(Section {
+                name: ".bss".to_string(),
+                align: 1,
+                ty: SectionType::NoBits,
+                tail_size: fields.bsize as usize,
+                ..Default::default()
+            })
+            .ok();
+        bfile
+            .add_section(Section {
+                name: ".zp".to_string(),
+                align: 1,
+                ty: SectionType::NoBits,
+                tail_size: fields.zsize as usize,
+                ..Default::default()
+            })
+            .ok();
+
+        if is_obj {
+            for (idx, name) in undef_names.into_iter().enumerate() {
+                let _ = idx;
+                bfile
+                    .insert_symbol(Symbol::new_undef(
+                        name,
+                        SymbolType::Null,
+                        SymbolKind::Global,
+                    ))
+                    .ok();
+            }
+
+            let export_count = Class::read_u(file)?;
+            for _ in 0..export_count {
+                let name = read_cstr(file)?;
+                let mut seg = [0u8];
+                file.read_exact(&mut seg)?;
+                let value = Class::read_u(file)?;
+                let secno = match seg[0] {
+                    SEG_TEXT => text_secno,
+                    SEG_DATA => data_secno,
+                    _ => None,
+                };
+                let sym = if let Some(secno) = secno {
+                    Symbol::new(name, secno, value as u128, SymbolType::Null, SymbolKind::Global)
+                } else {
+                    Symbol::new_undef(name, SymbolType::Null, SymbolKind::Global)
+                };
+                bfile.insert_symbol(sym).ok();
+            }
+
+            for secno in [text_secno, data_secno].into_iter().flatten() {
+                let mut relocs = Vec::new();
+                let mut pos: u32 = 0;
+                loop {
+                    let mut delta = [0u8];
+                    file.read_exact(&mut delta)?;
+                    if delta[0] == 0 {
+                        break;
+                    }
+                    let mut advance = delta[0] as u32;
+                    while delta[0] == 0xFF {
+                        pos += 254;
+                        file.read_exact(&mut delta)?;
+                        if delta[0] == 0 {
+                            advance = 0;
+                            break;
+                        }
+                        advance = delta[0] as u32;
+                    }
+                    pos += advance.saturating_sub(1);
+
+                    let mut type_byte = [0u8];
+                    file.read_exact(&mut type_byte)?;
+                    let howto = match type_byte[0] & RELOC_TYPE_MASK {
+                        RELOC_WORD => &O65HowTo::Word,
+                        RELOC_HIGH => &O65HowTo::High,
+                        _ => &O65HowTo::Low,
+                    };
+                    let seg = type_byte[0] & RELOC_SEG_MASK;
+                    let symbol = if seg == SEG_UNDEF {
+                        let idx = Class::read_u(file)?;
+                        format!("$undef{idx}")
+                    } else {
+                        segment_name(seg).to_string()
+                    };
+
+                    relocs.push((pos, howto, symbol));
+                }
+
+                for (offset, howto, symbol) in relocs {
+                    let code = match howto {
+                        O65HowTo::Word => RelocCode::Abs { addr_width: 16 },
+                        O65HowTo::High => RelocCode::AbsShifted {
+                            addr_width: 8,
+                            shift: 8,
+                        },
+                        O65HowTo::Low => RelocCode::Abs { addr_width: 8 },
+                    };
+                    bfile
+                        .create_reloc(crate::howto::Reloc {
+                            code,
+                            symbol,
+                            addend: None,
+                            offset: offset as u64,
+                        })
+                        .ok();
+                }
+            }
+        }
+
+        Ok(Some(bfile))
+    }
+
+    fn write_file(
+        &self,
+        file: &mut (dyn std::io::Write + '_),
+        bfile: &BinaryFile,
+    ) -> crate::error::Result<()> {
+        let mut secs: HashMap<&str, &Section> = HashMap::new();
+        for sect in bfile.sections() {
+            secs.insert(sect.name.as_str(), sect);
+        }
+
+        let text = secs.get(".text").map(|s| &s.content[..]).unwrap_or(&[]);
+        let data = secs.get(".data").map(|s| &s.content[..]).unwrap_or(&[]);
+        let bsize = secs.get(".bss").map(|s| s.tail_size).unwrap_or(0);
+        let zsize = secs.get(".zp").map(|s| s.tail_size).unwrap_or(0);
+
+        let is_obj = matches!(bfile.file_type(), FileType::Relocatable);
+
+        let mode = (if Class::SIZE_BIT { MODE_SIZE32 } else { 0 })
+            | (if is_obj { MODE_OBJ } else { 0 })
+            | O65Cpu::W65C816.to_mode_bits();
+
+        let fixed = O65FixedHeader {
+            id1: MAGIC,
+            magic: O65_MAGIC,
+            ver: 0,
+            mode,
+        };
+
+        let fields = SizedFields {
+            tbase: 0,
+            tsize: text.len() as u32,
+            dbase: text.len() as u32,
+            dsize: data.len() as u32,
+            bbase: (text.len() + data.len()) as u32,
+            bsize: bsize as u32,
+            zbase: 0,
+            zsize: zsize as u32,
+            stack: 0,
+        };
+
+        Class::write_sized_header(file, fixed, &fields)?;
+        file.write_all(&[0])?; // empty option header list
+
+        if is_obj {
+            let undefs: Vec<&Symbol> = bfile.symbols().filter(|s| s.section().is_none()).collect();
+            Class::write_u(file, undefs.len() as u32)?;
+            for sym in &undefs {
+                write_cstr(file, sym.name())?;
+            }
+        }
+
+        file.write_all(text)?;
+        file.write_all(data)?;
+
+        if is_obj {
+            let exports: Vec<&Symbol> = bfile
+                .symbols()
+                .filter(|s| s.kind() == SymbolKind::Global && s.section().is_some())
+                .collect();
+            Class::write_u(file, exports.len() as u32)?;
+            for sym in &exports {
+                write_cstr(file, sym.name())?;
+                let seg = sym
+                    .section()
+                    .and_then(|secno| bfile.get_section(secno))
+                    .map(|s| segment_id(&s.name))
+                    .unwrap_or(SEG_ABS);
+                file.write_all(&[seg])?;
+                Class::write_u(file, sym.value().unwrap_or(0) as u32)?;
+            }
+
+            for sect in [secs.get(".text"), secs.get(".data")].into_iter().flatten() {
+                let mut last = 0u32;
+                let mut relocs: Vec<_> = sect.relocs.iter().collect();
+                relocs.sort_by_key(|r| r.offset);
+                for reloc in relocs {
+                    let mut delta = reloc.offset as u32 - last + 1;
+                    while delta > 255 {
+                        file.write_all(&[0xFF])?;
+                        delta -= 254;
+                    }
+                    file.write_all(&[delta as u8])?;
+                    last = reloc.offset as u32 + 1;
+
+                    let type_byte = match reloc.code {
+                        RelocCode::AbsShifted {
+                            addr_width: 8,
+                            shift: 8,
+                        } => RELOC_HIGH,
+                        RelocCode::Abs { addr_width: 8 } => RELOC_LOW,
+                        _ => RELOC_WORD,
+                    };
+                    let seg = segment_id(&reloc.symbol);
+                    file.write_all(&[type_byte | seg])?;
+                    if seg == SEG_UNDEF {
+                        Class::write_u(file, 0)?;
+                    }
+                }
+                file.write_all(&[0])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn has_sections(&self) -> bool {
+        true
+    }
+
+    fn create_symbol(&self, sym: &mut Symbol) -> Result<(), CallbackError> {
+        match sym.kind() {
+            SymbolKind::Global | SymbolKind::Local => Ok(()),
+            _ => Err(CallbackError::NotAccepted),
+        }
+    }
+
+    fn create_reloc(&self, _reloc: &mut crate::howto::Reloc) -> Result<(), CallbackError> {
+        Ok(())
+    }
+}
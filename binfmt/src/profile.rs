@@ -0,0 +1,111 @@
+//! Feature-gated parse-time/memory instrumentation, queried by tools such
+//! as `binfmt-test --profile`. This is deliberately cheap: a process-wide
+//! log of one [`ParseProfile`] per [`crate::open_file`] call, recording
+//! how long the winning format's `read_file` took, how many section bytes
+//! it materialized, and how many allocations happened while it ran --
+//! enough to tell the lazy-loading and zero-copy work which formats are
+//! worth optimizing first, without the format code itself knowing
+//! profiling exists.
+//!
+//! Allocation counts require routing the process's global allocator
+//! through [`CountingAllocator`]; a binary that wants them installs it
+//! with `#[global_allocator]` and the count will otherwise always read 0.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+
+/// One [`crate::open_file`] call's profiling data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseProfile {
+    pub format: &'static str,
+    pub duration: Duration,
+    pub bytes_materialized: usize,
+    pub allocations: u64,
+}
+
+lazy_static! {
+    static ref LOG: Mutex<Vec<ParseProfile>> = Mutex::new(Vec::new());
+}
+
+/// Appends a sample to the process-wide log. Called by [`crate::open_file`]
+/// after a successful parse; not meant to be called directly by format
+/// implementations.
+pub(crate) fn record(profile: ParseProfile) {
+    LOG.lock().unwrap().push(profile);
+}
+
+/// Returns every sample recorded so far, in the order `open_file` produced
+/// them, without clearing the log.
+pub fn samples() -> Vec<ParseProfile> {
+    LOG.lock().unwrap().clone()
+}
+
+/// Drains and returns every sample recorded so far.
+pub fn take_samples() -> Vec<ParseProfile> {
+    std::mem::take(&mut *LOG.lock().unwrap())
+}
+
+/// Global allocation counter fed by [`CountingAllocator`]. Reads 0 unless
+/// a `CountingAllocator` has been installed as `#[global_allocator]`.
+static ALLOC_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// The current value of the global allocation counter.
+pub fn allocation_count() -> u64 {
+    ALLOC_COUNT.load(Ordering::Relaxed)
+}
+
+/// A [`std::alloc::GlobalAlloc`] wrapper that counts every call to
+/// `alloc`/`alloc_zeroed`/`realloc` it forwards to `A`, so a binary can
+/// report allocation counts alongside [`ParseProfile::duration`] by doing:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOC: binfmt::profile::CountingAllocator<std::alloc::System> =
+///     binfmt::profile::CountingAllocator(std::alloc::System);
+/// ```
+pub struct CountingAllocator<A>(pub A);
+
+unsafe impl<A: std::alloc::GlobalAlloc> std::alloc::GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+        self.0.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: std::alloc::Layout) -> *mut u8 {
+        ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+        ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+        self.0.realloc(ptr, layout, new_size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_and_take_round_trips() {
+        take_samples();
+        record(ParseProfile {
+            format: "elf64",
+            duration: Duration::from_millis(1),
+            bytes_materialized: 42,
+            allocations: 3,
+        });
+        let samples = take_samples();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].format, "elf64");
+        assert_eq!(samples[0].bytes_materialized, 42);
+        assert!(take_samples().is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! The SNES cartridge ROM container, in its `LoROM`/`HiROM` layouts.
+//!
+//! Unlike iNES, there is no magic number: a cartridge header sits at a
+//! fixed offset (`0x7FC0` for `LoROM`, `0xFFC0` for `HiROM`) inside the ROM
+//! image itself, and is identified by its checksum/complement pair summing
+//! to `0xFFFF`. An optional 512-byte copier header may precede the image;
+//! [`ident_file`](Binfmt::ident_file) checks for the cartridge header with
+//! and without it present.
+//!
+//! The whole image (minus any copier header) is exposed as a single `.rom`
+//! section; `write_file` patches the checksum pair before writing it back
+//! out.
+
+use crate::{
+    fmt::{BinaryFile, Binfmt, CallbackError, FileType, Section, SectionType},
+    howto::{HowTo, RelocCode},
+    sym::Symbol,
+    traits::ReadSeek,
+};
+
+const COPIER_HEADER_SIZE: usize = 512;
+const LOROM_HEADER_OFFSET: usize = 0x7FC0;
+const HIROM_HEADER_OFFSET: usize = 0xFFC0;
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum RomLayout {
+    LoRom,
+    HiRom,
+}
+
+impl RomLayout {
+    pub fn header_offset(&self) -> usize {
+        match self {
+            RomLayout::LoRom => LOROM_HEADER_OFFSET,
+            RomLayout::HiRom => HIROM_HEADER_OFFSET,
+        }
+    }
+}
+
+/// Locates a valid cartridge header (checksum + complement summing to
+/// `0xFFFF`) in `rom`, trying `LoROM` before `HiROM`.
+fn find_layout(rom: &[u8]) -> Option<RomLayout> {
+    for layout in [RomLayout::LoRom, RomLayout::HiRom] {
+        let off = layout.header_offset();
+        if rom.len() < off + 32 {
+            continue;
+        }
+        let complement = u16::from_le_bytes([rom[off + 28], rom[off + 29]]);
+        let checksum = u16::from_le_bytes([rom[off + 30], rom[off + 31]]);
+        if checksum.wrapping_add(complement) == 0xFFFF && checksum != 0 {
+            return Some(layout);
+        }
+    }
+    None
+}
+
+fn compute_checksum(rom: &[u8]) -> u16 {
+    rom.iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16))
+}
+
+pub struct Snes;
+
+pub fn create_format() -> Snes {
+    Snes
+}
+
+impl Binfmt for Snes {
+    fn relnum_to_howto(&self, _relnum: u32) -> Option<&dyn HowTo> {
+        None
+    }
+
+    fn code_to_howto(&self, _code: RelocCode) -> Option<&dyn HowTo> {
+        None
+    }
+
+    fn name(&self) -> &'static str {
+        "snes"
+    }
+
+    fn create_file(&self, ty: FileType) -> BinaryFile {
+        BinaryFile::create(self, Box::new(RomLayout::LoRom), ty)
+    }
+
+    fn ident_file(&self, file: &mut (dyn std::io::Read + '_)) -> std::io::Result<bool> {
+        let mut rom = Vec::new();
+        file.read_to_end(&mut rom)?;
+        if find_layout(&rom).is_some() {
+            return Ok(true);
+        }
+        if rom.len() > COPIER_HEADER_SIZE && find_layout(&rom[COPIER_HEADER_SIZE..]).is_some() {
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn read_file(&self, file: &mut (dyn ReadSeek + '_)) -> crate::error::Result<Option<BinaryFile>> {
+        let mut rom = Vec::new();
+        file.read_to_end(&mut rom)?;
+
+        let (rom, has_copier) = if find_layout(&rom).is_some() {
+            (rom, false)
+        } else if rom.len() > COPIER_HEADER_SIZE && find_layout(&rom[COPIER_HEADER_SIZE..]).is_some()
+        {
+            (rom[COPIER_HEADER_SIZE..].to_vec(), true)
+        } else {
+            return Ok(None);
+        };
+
+        let layout = find_layout(&rom).expect("checked above");
+
+        let mut bfile = BinaryFile::create(self, Box::new(layout), FileType::Exec);
+        let _ = has_copier;
+        bfile
+            .add_section(Section {
+                name: ".rom".to_string(),
+                align: 1,
+                ty: SectionType::ProgBits,
+                content: rom,
+                ..Default::default()
+            })
+            .ok();
+
+        Ok(Some(bfile))
+    }
+
+    fn write_file(
+        &self,
+        file: &mut (dyn std::io::Write + '_),
+        bfile: &BinaryFile,
+    ) -> crate::error::Result<()> {
+        let layout = bfile
+            .data()
+            .downcast_ref::<RomLayout>()
+            .copied()
+            .unwrap_or(RomLayout::LoRom);
+
+        let Some(sect) = bfile.sections().find(|s| s.name == ".rom") else {
+            return Ok(());
+        };
+
+        let mut rom = sect.content.clone();
+        let off = layout.header_offset();
+        if rom.len() >= off + 32 {
+            rom[off + 28] = 0;
+            rom[off + 29] = 0;
+            rom[off + 30] = 0;
+            rom[off + 31] = 0;
+            let checksum = compute_checksum(&rom);
+            let complement = !checksum;
+            rom[off + 28..off + 30].copy_from_slice(&complement.to_le_bytes());
+            rom[off + 30..off + 32].copy_from_slice(&checksum.to_le_bytes());
+        }
+
+        Ok(file.write_all(&rom)?)
+    }
+
+    fn has_sections(&self) -> bool {
+        true
+    }
+
+    fn supports_relocs(&self) -> bool {
+        false
+    }
+
+    fn supports_debug(&self) -> bool {
+        false
+    }
+
+    fn create_symbol(&self, _sym: &mut Symbol) -> Result<(), CallbackError> {
+        Err(CallbackError::NotAccepted)
+    }
+}
@@ -0,0 +1,179 @@
+//! Layout/hash-quality statistics -- the computational half of what
+//! `readelf --histogram` prints: hash bucket chain-length distribution,
+//! symbol table composition, and relocation counts by kind.
+//!
+//! Nothing here formats or prints anything; that's left to whichever
+//! binary wants to report the numbers (currently `readobj`; `lcld`'s
+//! `--stats` computes its own figures and does not call into this
+//! module). Keeping the computation here lets another reporter get the
+//! same numbers without duplicating the logic.
+
+use std::collections::BTreeMap;
+
+use crate::elf::elf_hash;
+use crate::howto::{Reloc, RelocCode};
+use crate::sym::{Symbol, SymbolKind};
+
+/// Chain-length distribution for a SysV `.hash` bucket table built over
+/// `names` (the same input [`crate::elf::write_sysv_hash_section`]
+/// takes, including the mandatory `STN_UNDEF` entry at index 0) -- lets
+/// a caller judge whether `nbucket` is sized well (a good hash wants a
+/// low `max_chain_len` relative to `mean_chain_len`) before committing
+/// to writing the section out.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HashBucketStats {
+    pub nbucket: usize,
+    pub nsymbols: usize,
+    pub empty_buckets: usize,
+    pub max_chain_len: usize,
+    pub mean_chain_len: f64,
+}
+
+pub fn hash_bucket_stats(names: &[&str]) -> HashBucketStats {
+    let nchain = names.len().max(1);
+    let nbucket = nchain;
+    let nsymbols = names.len().saturating_sub(1);
+
+    let mut chain_len = vec![0usize; nbucket];
+    for name in names.iter().skip(1) {
+        let b = (elf_hash(name) as usize) % nbucket;
+        chain_len[b] += 1;
+    }
+
+    let empty_buckets = chain_len.iter().filter(|&&n| n == 0).count();
+    let max_chain_len = chain_len.iter().copied().max().unwrap_or(0);
+    let mean_chain_len = if nbucket == 0 {
+        0.0
+    } else {
+        nsymbols as f64 / nbucket as f64
+    };
+
+    HashBucketStats {
+        nbucket,
+        nsymbols,
+        empty_buckets,
+        max_chain_len,
+        mean_chain_len,
+    }
+}
+
+/// Symbol table composition, broken down by [`SymbolKind`] and
+/// defined/undefined status.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SymbolTableStats {
+    pub total: usize,
+    pub local: usize,
+    pub global: usize,
+    pub weak: usize,
+    pub undefined: usize,
+}
+
+pub fn symbol_table_stats<'a>(symbols: impl IntoIterator<Item = &'a Symbol>) -> SymbolTableStats {
+    let mut stats = SymbolTableStats::default();
+    for sym in symbols {
+        stats.total += 1;
+        match sym.kind() {
+            SymbolKind::Local => stats.local += 1,
+            SymbolKind::Global => stats.global += 1,
+            SymbolKind::Weak => stats.weak += 1,
+            SymbolKind::FormatSpecific(_) => {}
+        }
+        if sym.value().is_none() {
+            stats.undefined += 1;
+        }
+    }
+    stats
+}
+
+/// A stable name for `code`'s relocation kind, ignoring payload fields
+/// (`addr_width`, `shift`, ...) so e.g. every `RelocCode::Abs` groups
+/// together in [`reloc_counts_by_kind`] regardless of width.
+fn reloc_kind_name(code: &RelocCode) -> &'static str {
+    match code {
+        RelocCode::None => "None",
+        RelocCode::Abs { .. } => "Abs",
+        RelocCode::BaseRel { .. } => "BaseRel",
+        RelocCode::Rel { .. } => "Rel",
+        RelocCode::AbsShifted { .. } => "AbsShifted",
+        RelocCode::RelShifted { .. } => "RelShifted",
+        RelocCode::Got { .. } => "Got",
+        RelocCode::RelGot { .. } => "RelGot",
+        RelocCode::Plt { .. } => "Plt",
+        RelocCode::RelPlt { .. } => "RelPlt",
+        RelocCode::DynSymEntry { .. } => "DynSymEntry",
+        RelocCode::DtpRel { .. } => "DtpRel",
+        RelocCode::W65Direct => "W65Direct",
+        RelocCode::W65RelaxJsl => "W65RelaxJsl",
+        RelocCode::W65RelaxJml => "W65RelaxJml",
+        RelocCode::W65RelaxBrl => "W65RelaxBrl",
+        RelocCode::W65RelaxDirect => "W65RelaxDirect",
+        RelocCode::W65RelaxAbs => "W65RelaxAbs",
+        RelocCode::W65RelaxJmp => "W65RelaxJmp",
+        RelocCode::CleverShort => "CleverShort",
+        RelocCode::CleverShortPcrel => "CleverShortPcrel",
+        RelocCode::HbRelaxedRel => "HbRelaxedRel",
+    }
+}
+
+/// Relocation counts by kind, in alphabetical order by kind name.
+pub fn reloc_counts_by_kind<'a>(relocs: impl IntoIterator<Item = &'a Reloc>) -> Vec<(&'static str, usize)> {
+    let mut counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+    for reloc in relocs {
+        *counts.entry(reloc_kind_name(&reloc.code)).or_insert(0) += 1;
+    }
+    counts.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sym::SymbolType;
+
+    #[test]
+    fn hash_bucket_stats_excludes_stn_undef() {
+        let stats = hash_bucket_stats(&["", "foo", "bar"]);
+        assert_eq!(stats.nsymbols, 2);
+        assert_eq!(stats.nbucket, 3);
+    }
+
+    #[test]
+    fn symbol_table_stats_counts_by_kind_and_definedness() {
+        let symbols = vec![
+            Symbol::new("a".to_string(), 1, 0, SymbolType::Object, SymbolKind::Global),
+            Symbol::new("b".to_string(), 1, 0, SymbolType::Object, SymbolKind::Local),
+            Symbol::new_undef("c".to_string(), SymbolType::Object, SymbolKind::Weak),
+        ];
+        let stats = symbol_table_stats(&symbols);
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.global, 1);
+        assert_eq!(stats.local, 1);
+        assert_eq!(stats.weak, 1);
+        assert_eq!(stats.undefined, 1);
+    }
+
+    #[test]
+    fn reloc_counts_by_kind_groups_regardless_of_width() {
+        let relocs = vec![
+            Reloc {
+                code: RelocCode::Abs { addr_width: 32 },
+                symbol: "a".to_string(),
+                addend: None,
+                offset: 0,
+            },
+            Reloc {
+                code: RelocCode::Abs { addr_width: 64 },
+                symbol: "b".to_string(),
+                addend: None,
+                offset: 8,
+            },
+            Reloc {
+                code: RelocCode::Rel { addr_width: 32 },
+                symbol: "c".to_string(),
+                addend: None,
+                offset: 16,
+            },
+        ];
+        let counts = reloc_counts_by_kind(&relocs);
+        assert_eq!(counts, vec![("Abs", 2), ("Rel", 1)]);
+    }
+}
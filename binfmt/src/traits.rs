@@ -98,3 +98,81 @@ impl_numeric!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, usize, isize);
 pub trait ReadSeek: Read + Seek {}
 
 impl<T: Read + Seek + ?Sized> ReadSeek for T {}
+
+/// The byte order of a target's multi-byte fields -- factored out of
+/// ELF's `EI_DATA` (see [`crate::elf::consts::EiData`]) so non-ELF code,
+/// like a linker's relocation patcher writing a fixup into section
+/// bytes, can share the same swap primitive without depending on the
+/// ELF format at all.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    /// The host's own byte order -- [`Endianness::convert`] is a no-op
+    /// when converting to/from this.
+    pub const fn native() -> Self {
+        if cfg!(target_endian = "big") {
+            Self::Big
+        } else {
+            Self::Little
+        }
+    }
+
+    /// Converts `x` between `self`'s byte order and the host's native
+    /// one. The same function works both directions -- file bytes into
+    /// a value the host can do arithmetic on, or a host value into the
+    /// bytes a file of this endianness expects -- because "reverse the
+    /// bytes if the two orders disagree" is its own inverse.
+    pub fn convert<T: Pod>(self, x: T) -> T {
+        if self == Self::native() {
+            return x;
+        }
+
+        let bytes = bytemuck::bytes_of(&x);
+        let mut swapped = vec![0u8; bytes.len()];
+        for (dst, src) in swapped.iter_mut().zip(bytes.iter().rev()) {
+            *dst = *src;
+        }
+
+        bytemuck::pod_read_unaligned(&swapped)
+    }
+}
+
+#[cfg(test)]
+mod endian_tests {
+    use super::Endianness;
+
+    #[test]
+    fn convert_reverses_bytes_for_the_non_native_order() {
+        let opposite = if Endianness::native() == Endianness::Little {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        };
+
+        let native_value = 0x1122_3344u32;
+        let swapped = opposite.convert(native_value);
+        assert_eq!(swapped, native_value.swap_bytes());
+    }
+
+    #[test]
+    fn convert_is_a_no_op_for_the_native_order() {
+        let value = 0x1122_3344u32;
+        assert_eq!(Endianness::native().convert(value), value);
+    }
+
+    #[test]
+    fn convert_round_trips() {
+        let opposite = if Endianness::native() == Endianness::Little {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        };
+
+        let value = 0xDEAD_BEEFu32;
+        assert_eq!(opposite.convert(opposite.convert(value)), value);
+    }
+}
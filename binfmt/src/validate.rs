@@ -0,0 +1,147 @@
+//! Write-time layout validation.
+//!
+//! The ELF writer (and anything else that builds up a [`BinaryFile`] by
+//! hand before serializing it) works from whatever offsets, alignments,
+//! and cross-references the caller gave it; nothing stops those from
+//! being nonsensical. Checking them here, before a single byte is
+//! written, turns a file that only fails to `mmap` or load at runtime
+//! into a build-time error naming the offending section or segment.
+
+use std::fmt;
+
+use crate::elf::ElfProgramHeader;
+use crate::fmt::BinaryFile;
+use crate::traits::Numeric;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LayoutError {
+    /// A [`crate::fmt::SectionGroup`]'s `id_sym` doesn't name any symbol
+    /// in the file, so the group's signature symbol can't be resolved.
+    UnresolvedGroupSymbol { group: String, id_sym: String },
+    /// A [`crate::fmt::SectionGroup`] lists a section index past the end
+    /// of the file's section table.
+    GroupSectionOutOfRange { group: String, section: u32 },
+    /// A segment's alignment isn't a power of two (`0`/`1`, meaning "no
+    /// alignment requirement", are both fine).
+    SegmentAlignNotPow2 { index: usize, align: u64 },
+    /// A segment's `vaddr` and file `offset` don't agree modulo its
+    /// alignment, so the loader can't map the segment at a page-aligned
+    /// address without shifting its file contents.
+    SegmentMisaligned {
+        index: usize,
+        offset: u64,
+        vaddr: u64,
+        align: u64,
+    },
+    /// Two segments' file ranges overlap.
+    SegmentsOverlap { first: usize, second: usize },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::UnresolvedGroupSymbol { group, id_sym } => write!(
+                f,
+                "section group `{}` names signature symbol `{}`, which isn't defined in this file",
+                group, id_sym
+            ),
+            LayoutError::GroupSectionOutOfRange { group, section } => write!(
+                f,
+                "section group `{}` lists section index {}, which is past the end of the section table",
+                group, section
+            ),
+            LayoutError::SegmentAlignNotPow2 { index, align } => write!(
+                f,
+                "segment {} has alignment {}, which is not a power of two",
+                index, align
+            ),
+            LayoutError::SegmentMisaligned {
+                index,
+                offset,
+                vaddr,
+                align,
+            } => write!(
+                f,
+                "segment {} has file offset {:#x} and vaddr {:#x}, which disagree modulo its alignment {:#x}",
+                index, offset, vaddr, align
+            ),
+            LayoutError::SegmentsOverlap { first, second } => {
+                write!(f, "segments {} and {} overlap in the file", first, second)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// Checks every [`crate::fmt::SectionGroup`] in `file` against its
+/// section table and symbol table, naming the offending group by name.
+pub fn validate_sections(file: &BinaryFile) -> Vec<LayoutError> {
+    let mut errors = Vec::new();
+
+    let section_count = file.sections().count() as u32;
+
+    for group in file.section_groups() {
+        if !file.symbols().any(|sym| sym.name() == group.id_sym) {
+            errors.push(LayoutError::UnresolvedGroupSymbol {
+                group: group.name.clone(),
+                id_sym: group.id_sym.clone(),
+            });
+        }
+        for &section in &group.sections {
+            if section >= section_count {
+                errors.push(LayoutError::GroupSectionOutOfRange {
+                    group: group.name.clone(),
+                    section,
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+/// Checks `phdrs` for misaligned or overlapping segments, naming the
+/// offending segments by their index into `phdrs`.
+///
+/// Segments with a zero file size (pure `.bss`-style segments, or
+/// anything else the loader never reads from the file) never overlap
+/// with anything else -- only their alignment is checked.
+pub fn validate_program_headers<P: ElfProgramHeader>(phdrs: &[P]) -> Vec<LayoutError> {
+    let mut errors = Vec::new();
+
+    let mut ranges: Vec<(usize, u64, u64)> = Vec::new();
+
+    for (index, phdr) in phdrs.iter().enumerate() {
+        let align = phdr.align().as_u64();
+        let offset = phdr.offset().as_u64();
+        let vaddr = phdr.vaddr().as_u64();
+        let filesize = phdr.filesize().as_u64();
+
+        if align > 1 && !align.is_power_of_two() {
+            errors.push(LayoutError::SegmentAlignNotPow2 { index, align });
+        } else if align > 1 && offset % align != vaddr % align {
+            errors.push(LayoutError::SegmentMisaligned {
+                index,
+                offset,
+                vaddr,
+                align,
+            });
+        }
+
+        if filesize > 0 {
+            ranges.push((index, offset, offset + filesize));
+        }
+    }
+
+    for (i, &(first, start1, end1)) in ranges.iter().enumerate() {
+        for &(second, start2, end2) in &ranges[(i + 1)..] {
+            if start1 < end2 && start2 < end1 {
+                errors.push(LayoutError::SegmentsOverlap { first, second });
+            }
+        }
+    }
+
+    errors
+}
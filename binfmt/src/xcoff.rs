@@ -0,0 +1,311 @@
+//! The XCOFF object format used by AIX, in its 32-bit (`U802TOCMAGIC`)
+//! flavour. Covers the section/symbol layout needed to round-trip `.text`/
+//! `.data`/`.bss`; relocation processing is not yet implemented.
+//!
+//! XCOFF is always big-endian on the wire; this reader/writer assumes it is
+//! running on (or emulating) a big-endian host, matching the rest of the
+//! header layout described here.
+
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    fmt::{BinaryFile, Binfmt, CallbackError, FileType, Section, SectionType},
+    howto::{HowTo, RelocCode},
+    sym::{Symbol, SymbolKind, SymbolType},
+    traits::ReadSeek,
+};
+
+pub const U802TOCMAGIC: u16 = 0x01DF;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct XcoffFileHeader {
+    pub f_magic: u16,
+    pub f_nscns: u16,
+    pub f_timdat: u32,
+    pub f_symptr: u32,
+    pub f_nsyms: u32,
+    pub f_opthdr: u16,
+    pub f_flags: u16,
+}
+
+static_assertions::const_assert_eq!(core::mem::size_of::<XcoffFileHeader>(), 20);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct XcoffAoutHeader {
+    pub magic: u16,
+    pub vstamp: u16,
+    pub tsize: u32,
+    pub dsize: u32,
+    pub bsize: u32,
+    pub entry: u32,
+    pub text_start: u32,
+    pub data_start: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct XcoffSectionHeader {
+    pub s_name: [u8; 8],
+    pub s_paddr: u32,
+    pub s_vaddr: u32,
+    pub s_size: u32,
+    pub s_scnptr: u32,
+    pub s_relptr: u32,
+    pub s_lnnoptr: u32,
+    pub s_nreloc: u16,
+    pub s_nlnno: u16,
+    pub s_flags: u32,
+}
+
+static_assertions::const_assert_eq!(core::mem::size_of::<XcoffSectionHeader>(), 40);
+
+pub const STYP_TEXT: u32 = 0x0020;
+pub const STYP_DATA: u32 = 0x0040;
+pub const STYP_BSS: u32 = 0x0080;
+
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+pub struct XcoffSymbol {
+    pub n_name: [u8; 8],
+    pub n_value: u32,
+    pub n_scnum: i16,
+    pub n_type: u16,
+    pub n_sclass: u8,
+    pub n_numaux: u8,
+}
+
+static_assertions::const_assert_eq!(core::mem::size_of::<XcoffSymbol>(), 18);
+
+pub const C_EXT: u8 = 2;
+pub const C_STAT: u8 = 3;
+pub const C_HIDEXT: u8 = 107;
+
+fn section_name(bytes: &[u8; 8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(8);
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn name_bytes(name: &str) -> [u8; 8] {
+    let mut out = [0u8; 8];
+    let bytes = name.as_bytes();
+    let len = bytes.len().min(8);
+    out[..len].copy_from_slice(&bytes[..len]);
+    out
+}
+
+pub struct Xcoff;
+
+pub fn create_format() -> Xcoff {
+    Xcoff
+}
+
+impl Binfmt for Xcoff {
+    fn relnum_to_howto(&self, _relnum: u32) -> Option<&dyn HowTo> {
+        None
+    }
+
+    fn code_to_howto(&self, _code: RelocCode) -> Option<&dyn HowTo> {
+        None
+    }
+
+    fn name(&self) -> &'static str {
+        "xcoff"
+    }
+
+    fn create_file(&self, ty: FileType) -> BinaryFile {
+        BinaryFile::create(self, Box::new(()), ty)
+    }
+
+    fn ident_file(&self, file: &mut (dyn std::io::Read + '_)) -> std::io::Result<bool> {
+        let mut buf = [0u8; 2];
+        if file.read_exact(&mut buf).is_err() {
+            return Ok(false);
+        }
+        Ok(u16::from_be_bytes(buf) == U802TOCMAGIC)
+    }
+
+    fn read_file(&self, file: &mut (dyn ReadSeek + '_)) -> crate::error::Result<Option<BinaryFile>> {
+        let mut fhdr = XcoffFileHeader::zeroed();
+        file.read_exact(bytemuck::bytes_of_mut(&mut fhdr))?;
+        if fhdr.f_magic != U802TOCMAGIC {
+            return Ok(None);
+        }
+
+        if fhdr.f_opthdr > 0 {
+            let mut skip = vec![0u8; fhdr.f_opthdr as usize];
+            file.read_exact(&mut skip)?;
+        }
+
+        let mut sections = vec![XcoffSectionHeader::zeroed(); fhdr.f_nscns as usize];
+        file.read_exact(bytemuck::cast_slice_mut(&mut sections))?;
+
+        let mut bfile = BinaryFile::create(self, Box::new(()), FileType::Relocatable);
+        let mut secnos = Vec::new();
+
+        for shdr in &sections {
+            let name = section_name(&shdr.s_name);
+            let mut content = vec![0u8; shdr.s_size as usize];
+            if shdr.s_flags & STYP_BSS == 0 && shdr.s_scnptr != 0 {
+                file.seek(std::io::SeekFrom::Start(shdr.s_scnptr as u64))?;
+                file.read_exact(&mut content)?;
+            }
+            let ty = if shdr.s_flags & STYP_BSS != 0 {
+                SectionType::NoBits
+            } else {
+                SectionType::ProgBits
+            };
+            let secno = bfile
+                .add_section(Section {
+                    name,
+                    align: 4,
+                    ty,
+                    content: if ty == SectionType::NoBits {
+                        Vec::new()
+                    } else {
+                        content
+                    },
+                    tail_size: if ty == SectionType::NoBits {
+                        shdr.s_size as usize
+                    } else {
+                        0
+                    },
+                    ..Default::default()
+                })
+                .ok();
+            secnos.push(secno);
+        }
+
+        if fhdr.f_nsyms > 0 {
+            file.seek(std::io::SeekFrom::Start(fhdr.f_symptr as u64))?;
+            let mut syms = vec![XcoffSymbol::zeroed(); fhdr.f_nsyms as usize];
+            file.read_exact(bytemuck::cast_slice_mut(&mut syms))?;
+
+            let mut i = 0;
+            while i < syms.len() {
+                let sym = syms[i];
+                i += 1 + sym.n_numaux as usize;
+
+                let name = section_name(&sym.n_name);
+                if name.is_empty() {
+                    continue;
+                }
+                let kind = match sym.n_sclass {
+                    C_EXT => SymbolKind::Global,
+                    C_HIDEXT | C_STAT => SymbolKind::Local,
+                    _ => continue,
+                };
+                let secno = secnos
+                    .get((sym.n_scnum as i32 - 1).max(0) as usize)
+                    .copied()
+                    .flatten();
+                let out_sym = match secno {
+                    Some(secno) if sym.n_scnum > 0 => {
+                        Symbol::new(name, secno, sym.n_value as u128, SymbolType::Null, kind)
+                    }
+                    _ => Symbol::new_undef(name, SymbolType::Null, kind),
+                };
+                bfile.insert_symbol(out_sym).ok();
+            }
+        }
+
+        Ok(Some(bfile))
+    }
+
+    fn write_file(
+        &self,
+        file: &mut (dyn std::io::Write + '_),
+        bfile: &BinaryFile,
+    ) -> crate::error::Result<()> {
+        let sections: Vec<&Section> = bfile.sections().collect();
+
+        let mut shdrs = Vec::new();
+        let mut data_blobs = Vec::new();
+        let header_size = size_of::<XcoffFileHeader>();
+        let mut data_off = header_size + sections.len() * size_of::<XcoffSectionHeader>();
+
+        for sect in &sections {
+            let flags = if sect.ty == SectionType::NoBits {
+                STYP_BSS
+            } else if sect.name == ".text" {
+                STYP_TEXT
+            } else {
+                STYP_DATA
+            };
+            let size = if sect.ty == SectionType::NoBits {
+                sect.tail_size as u32
+            } else {
+                sect.content.len() as u32
+            };
+            let scnptr = if sect.ty == SectionType::NoBits {
+                0
+            } else {
+                let off = data_off;
+                data_off += sect.content.len();
+                off as u32
+            };
+            shdrs.push(XcoffSectionHeader {
+                s_name: name_bytes(&sect.name),
+                s_paddr: 0,
+                s_vaddr: 0,
+                s_size: size,
+                s_scnptr: scnptr,
+                s_relptr: 0,
+                s_lnnoptr: 0,
+                s_nreloc: 0,
+                s_nlnno: 0,
+                s_flags: flags,
+            });
+            if sect.ty != SectionType::NoBits {
+                data_blobs.push(&sect.content);
+            }
+        }
+
+        let mut symbols = Vec::new();
+        for sym in bfile.symbols() {
+            let scnum = sym.section().map(|secno| (secno + 1) as i16).unwrap_or(0);
+            symbols.push(XcoffSymbol {
+                n_name: name_bytes(sym.name()),
+                n_value: sym.value().unwrap_or(0) as u32,
+                n_scnum: scnum,
+                n_type: 0,
+                n_sclass: if sym.kind() == SymbolKind::Global {
+                    C_EXT
+                } else {
+                    C_HIDEXT
+                },
+                n_numaux: 0,
+            });
+        }
+
+        let fhdr = XcoffFileHeader {
+            f_magic: U802TOCMAGIC,
+            f_nscns: sections.len() as u16,
+            f_timdat: 0,
+            f_symptr: data_off as u32,
+            f_nsyms: symbols.len() as u32,
+            f_opthdr: 0,
+            f_flags: 0,
+        };
+
+        file.write_all(bytemuck::bytes_of(&fhdr))?;
+        file.write_all(bytemuck::cast_slice(&shdrs))?;
+        for blob in data_blobs {
+            file.write_all(blob)?;
+        }
+        file.write_all(bytemuck::cast_slice(&symbols))?;
+
+        Ok(())
+    }
+
+    fn has_sections(&self) -> bool {
+        true
+    }
+
+    fn create_symbol(&self, _sym: &mut Symbol) -> Result<(), CallbackError> {
+        Ok(())
+    }
+}
@@ -1 +1,9 @@
+//! The "extended" o65 object format: identical to [`crate::o65`] except that
+//! every base/size field in the header is 32, rather than 16, bits wide, to
+//! accommodate the 65816's bank-switched 24-bit address space.
 
+pub use crate::o65::O65Format;
+
+pub fn create_format() -> O65Format<crate::o65::Wide> {
+    crate::o65::create_xo65_format("xo65")
+}
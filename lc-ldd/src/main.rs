@@ -0,0 +1,198 @@
+//! `lc-ldd`: a `ldd`-style debugging tool for this project's own ELF
+//! output. Given an executable (or shared object), walks its `DT_NEEDED`
+//! closure against a configurable library search path, reports which
+//! object every undefined symbol resolves against, and flags symbols
+//! that more than one object in the closure defines -- the first
+//! definition in load order wins, the same way the dynamic linker's
+//! default (non-`RTLD_DEEPBIND`) global scope does, so every later
+//! definition of the same symbol is an interposition of the first.
+
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use binfmt::elf::{self, consts, Elf32, Elf64};
+use binfmt::fmt::{BinaryFile, SectionType};
+use binfmt::sym::SymbolKind;
+
+struct Object {
+    /// How this object was named, either on the command line or in some
+    /// other object's `DT_NEEDED` -- what the report refers to it as.
+    name: String,
+    path: PathBuf,
+    bytes: Vec<u8>,
+    file: BinaryFile<'static>,
+}
+
+fn read_ei_class(bytes: &[u8]) -> Option<consts::EiClass> {
+    if bytes.len() < 5 || bytes[0..4] != consts::ELFMAG {
+        return None;
+    }
+    match bytes[4] {
+        1 => Some(consts::ELFCLASS32),
+        2 => Some(consts::ELFCLASS64),
+        _ => None,
+    }
+}
+
+/// Reads the `DT_NEEDED` entries out of `file`'s `.dynamic` section, if it
+/// has one, resolving each one's name against `.dynstr` -- the string
+/// table `.dynamic`'s `sh_link` is supposed to point at, but since
+/// [`BinaryFile`]'s section indices are renumbered relative to the raw
+/// ELF section header table (which always has a reserved null section 0
+/// that never shows up here), `link` can't be used as a direct index
+/// into [`BinaryFile::get_section`] without knowing that offset; looking
+/// `.dynstr` up by its conventional name sidesteps the mismatch entirely.
+fn needed_libraries(bytes: &[u8], file: &BinaryFile) -> std::io::Result<Vec<String>> {
+    let Some(dynamic) = file.sections().find(|s| s.ty == SectionType::Dynamic) else {
+        return Ok(Vec::new());
+    };
+    let Some(dynstr) = file.sections().find(|s| s.name == ".dynstr") else {
+        return Ok(Vec::new());
+    };
+
+    match read_ei_class(bytes) {
+        Some(consts::ELFCLASS32) => {
+            let entries = elf::dynamic_entries::<Elf32>(&dynamic.content)?;
+            elf::needed_libraries(&entries, &dynstr.content)
+        }
+        Some(consts::ELFCLASS64) => {
+            let entries = elf::dynamic_entries::<Elf64>(&dynamic.content)?;
+            elf::needed_libraries(&entries, &dynstr.content)
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn resolve(name: &str, search_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if name.contains('/') {
+        let path = Path::new(name);
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    search_dirs.iter().map(|dir| dir.join(name)).find(|p| p.is_file())
+}
+
+fn open(name: &str, path: &Path) -> std::io::Result<Object> {
+    let bytes = fs::read(path)?;
+    let file = binfmt::open_file(Cursor::new(&bytes[..]))?;
+    Ok(Object {
+        name: name.to_string(),
+        path: path.to_path_buf(),
+        bytes,
+        file,
+    })
+}
+
+fn main() {
+    let mut args = std::env::args();
+    let prg_name = args.next().unwrap();
+
+    let mut search_dirs = Vec::new();
+    let mut input_file = None::<String>;
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-L" => search_dirs.push(PathBuf::from(args.next().unwrap_or_else(|| {
+                eprintln!("{}: Expected a directory after -L", prg_name);
+                std::process::exit(1)
+            }))),
+            x if x.starts_with("-L") => search_dirs.push(PathBuf::from(&x[2..])),
+            "--help" => {
+                eprintln!("USAGE: {} [-L <dir>].. <executable>", prg_name);
+                std::process::exit(0);
+            }
+            x => {
+                input_file = Some(x.to_string());
+                break;
+            }
+        }
+    }
+
+    let input_file = input_file.unwrap_or_else(|| {
+        eprintln!("USAGE: {} [-L <dir>].. <executable>", prg_name);
+        std::process::exit(1);
+    });
+
+    let root = open(&input_file, Path::new(&input_file)).unwrap_or_else(|e| {
+        eprintln!("{}: Failed to read {}: {}", prg_name, input_file, e);
+        std::process::exit(1)
+    });
+
+    // Breadth-first over the `DT_NEEDED` graph, in discovery order -- the
+    // same order `ld.so`'s default (non-`RTLD_DEEPBIND`) global symbol
+    // scope is built in, which is what makes "first definition wins"
+    // below correct.
+    let mut closure = Vec::new();
+    let mut queue = VecDeque::new();
+    let mut seen = HashSet::new();
+    seen.insert(input_file.clone());
+    queue.push_back(root);
+
+    while let Some(obj) = queue.pop_front() {
+        let needed = needed_libraries(&obj.bytes, &obj.file).unwrap_or_else(|e| {
+            eprintln!("{}: Failed to read dynamic section of {}: {}", prg_name, obj.name, e);
+            Vec::new()
+        });
+
+        for name in needed {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            match resolve(&name, &search_dirs) {
+                Some(path) => match open(&name, &path) {
+                    Ok(needed_obj) => queue.push_back(needed_obj),
+                    Err(e) => eprintln!("{}: Failed to read {} ({}): {}", prg_name, name, path.display(), e),
+                },
+                None => eprintln!("{}: {} => not found", prg_name, name),
+            }
+        }
+
+        closure.push(obj);
+    }
+
+    let mut defined_by: Vec<(String, usize)> = Vec::new();
+    let mut interposed = Vec::new();
+
+    for (idx, obj) in closure.iter().enumerate() {
+        for sym in obj.file.symbols() {
+            if sym.kind() == SymbolKind::Local || sym.value().is_none() {
+                continue;
+            }
+            if let Some((_, first_idx)) = defined_by.iter().find(|(n, _)| n == sym.name()) {
+                interposed.push((sym.name().to_string(), *first_idx, idx));
+            } else {
+                defined_by.push((sym.name().to_string(), idx));
+            }
+        }
+    }
+
+    println!("Load order:");
+    for (idx, obj) in closure.iter().enumerate() {
+        println!("\t{}: {} ({})", idx, obj.name, obj.path.display());
+    }
+
+    println!();
+    println!("Undefined symbol resolution (from {}):", closure[0].name);
+    for sym in closure[0].file.symbols() {
+        if sym.value().is_some() || sym.kind() == SymbolKind::Local {
+            continue;
+        }
+        match defined_by.iter().find(|(n, _)| n == sym.name()) {
+            Some((_, idx)) => println!("\t{} => {}", sym.name(), closure[*idx].name),
+            None => println!("\t{} => undefined", sym.name()),
+        }
+    }
+
+    println!();
+    println!("Interposed symbols:");
+    if interposed.is_empty() {
+        println!("\t(none)");
+    }
+    for (name, first_idx, shadowed_idx) in &interposed {
+        println!(
+            "\t{}: {} wins, shadows definition in {}",
+            name, closure[*first_idx].name, closure[*shadowed_idx].name
+        );
+    }
+}
@@ -6,7 +6,12 @@ use std::{
 
 use arch_ops::traits::InsnWrite;
 
-use crate::{expr::Expression, lex::Token, span::Spanned, targ::TargetMachine};
+use crate::{
+    expr::{BinaryOp, Expression, UnaryOp},
+    lex::Token,
+    span::Spanned,
+    targ::TargetMachine,
+};
 
 pub trait PeekToken: Iterator {
     fn peek(&mut self) -> Option<&Self::Item>;
@@ -50,12 +55,96 @@ impl<I: Iterator> PeekToken for Peekable<I> {
 pub trait AssemblerCallbacks {
     fn handle_directive(&self, asm: &mut Assembler, dir: &str) -> std::io::Result<()>;
     fn create_symbol_now(&self, asm: &mut Assembler, sym: &str);
+    /// Defines `sym` with the fixed value `value`, independent of the
+    /// current section/offset -- used by `.incbin`'s `_size` helper
+    /// symbol, whose value is a byte count rather than a position.
+    fn create_absolute_symbol(&self, asm: &mut Assembler, sym: &str, value: u64);
+    /// Looks up `sym` in the symbol table as it stands right now, returning
+    /// its byte offset within the section currently selected for output if
+    /// it was already defined earlier in that same section -- a backward
+    /// reference, whose real displacement a target can compute on the spot.
+    /// `None` for a forward reference, an external symbol, or one defined in
+    /// a different section, all of which still need a relocation resolved
+    /// at link time instead. See [`Assembler::resolve_local_backward`].
+    fn resolve_local_backward(&self, asm: &Assembler, sym: &str) -> Option<u64>;
+}
+
+/// Resolves `name` (as given to `.incbin`/`.include`) against `-I`'s
+/// search directories: `name` itself first (so an absolute path, or one
+/// already relative to the current directory, is used as-is), then each
+/// of `include_dirs` in order, joined with `name` -- the same precedence
+/// GNU `as`'s own `-I` gives a bare `.include "file"`. `None` if no
+/// candidate exists.
+pub fn resolve_include(name: &str, include_dirs: &[std::path::PathBuf]) -> Option<std::path::PathBuf> {
+    let direct = std::path::PathBuf::from(name);
+    if direct.is_file() {
+        return Some(direct);
+    }
+    include_dirs
+        .iter()
+        .map(|dir| dir.join(name))
+        .find(|p| p.is_file())
+}
+
+/// Sanitizes `path` into the form GNU `ld -b binary` uses for its
+/// `_binary_<name>_start`/`_end`/`_size` symbols: every byte that isn't an
+/// ASCII letter, digit, or underscore becomes an underscore.
+fn incbin_symbol_stem(path: &str) -> String {
+    path.bytes()
+        .map(|b| if b.is_ascii_alphanumeric() || b == b'_' { b as char } else { '_' })
+        .collect()
+}
+
+/// Copies `len` bytes (or, if `None`, everything to EOF) of `path` starting
+/// at `skip`, into `out`, without ever holding more than one buffer's worth
+/// of the file in memory. Returns the number of bytes actually copied.
+fn stream_incbin(
+    out: &mut dyn InsnWrite,
+    path: &std::path::Path,
+    skip: u64,
+    len: Option<u64>,
+) -> std::io::Result<u64> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = std::fs::File::open(path)?;
+    file.seek(SeekFrom::Start(skip))?;
+
+    let mut buf = [0u8; 64 * 1024];
+    let mut remaining = len;
+    let mut total = 0u64;
+
+    loop {
+        let want = match remaining {
+            Some(0) => break,
+            Some(r) => buf.len().min(r as usize),
+            None => buf.len(),
+        };
+
+        let n = file.read(&mut buf[..want])?;
+        if n == 0 {
+            break;
+        }
+
+        out.write_all(&buf[..n])?;
+        total += n as u64;
+        if let Some(r) = remaining.as_mut() {
+            *r -= n as u64;
+        }
+    }
+
+    Ok(total)
 }
 
 pub struct Assembler<'a> {
     state: AsState<'a>,
     as_data: Box<dyn Any>,
     as_callbacks: &'a dyn AssemblerCallbacks,
+    /// `-I` search directories, consulted by [`resolve_include`] for
+    /// `.incbin`'s path (and, by [`crate::lcas`][lcas]-level `.include`
+    /// expansion, for included source files too).
+    ///
+    /// [lcas]: https://github.com/lccc-project/lc-binutils
+    include_dirs: &'a [std::path::PathBuf],
 }
 
 impl<'a> Assembler<'a> {
@@ -65,6 +154,7 @@ impl<'a> Assembler<'a> {
         as_data: Box<dyn Any>,
         as_callbacks: &'a dyn AssemblerCallbacks,
         tokens: &'a mut (dyn Iterator<Item = Spanned<Token>> + 'a),
+        include_dirs: &'a [std::path::PathBuf],
     ) -> Assembler<'a> {
         Assembler {
             state: AsState {
@@ -75,6 +165,7 @@ impl<'a> Assembler<'a> {
             },
             as_data,
             as_callbacks,
+            include_dirs,
         }
     }
 
@@ -90,6 +181,72 @@ impl<'a> Assembler<'a> {
         self.state.output = output;
     }
 
+    /// Whether `sym` is already defined earlier in the section this
+    /// `Assembler` is currently writing to -- see
+    /// [`AssemblerCallbacks::resolve_local_backward`] for exactly what
+    /// counts. A target's [`TargetMachine::assemble_insn`][targ] uses this
+    /// to relax a branch/jump to its real, already-known size on the spot
+    /// rather than always emitting the widest encoding and a relocation.
+    ///
+    /// [targ]: crate::targ::TargetMachine::assemble_insn
+    pub fn resolve_local_backward(&self, sym: &str) -> Option<u64> {
+        self.as_callbacks.resolve_local_backward(self, sym)
+    }
+
+    /// Writes a comma-separated list of `width`-byte integers (`.byte`,
+    /// `.2byte`, `.4byte`/`.long`, `.8byte`/`.quad`), the same expression
+    /// grammar and one-or-more-values shape each of those directives
+    /// shares -- a symbol, optionally displaced by a constant, becomes a
+    /// relocation via [`symbol_with_const_disp`].
+    fn write_int_list(&mut self, width: usize) -> std::io::Result<()> {
+        loop {
+            let expr = crate::expr::parse_expression(self.iter());
+            let expr = self.eval_expr(expr);
+
+            if let Some((sym, disp)) = symbol_with_const_disp(&expr) {
+                self.output().write_addr(
+                    width * 8,
+                    arch_ops::traits::Address::Symbol { name: sym, disp },
+                    false,
+                )?;
+            } else if let Expression::Integer(val) = expr {
+                let mut bytes = [0u8; 16];
+                self.machine().int_to_bytes(val, &mut bytes[..width]);
+                self.output().write_all(&bytes[..width])?;
+            } else {
+                todo!("{:?}", expr)
+            }
+
+            match self.iter().peek().map(Spanned::body) {
+                Some(Token::Sigil(s)) if s == "," => {
+                    self.iter().next();
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Pads the output with `count` bytes of `fill`, used by `.skip`/
+    /// `.space`/`.org`'s optional fill-byte argument. Defers to
+    /// [`arch_ops::traits::InsnWrite::write_zeroes`] when `fill` is zero,
+    /// since most callers never give a fill byte and some `InsnWrite`
+    /// impls can special-case an all-zero run.
+    fn write_fill(&mut self, count: usize, fill: u8) -> std::io::Result<()> {
+        if fill == 0 {
+            return self.output().write_zeroes(count);
+        }
+
+        let buf = [fill; 1024];
+        let mut remaining = count;
+        while remaining > 0 {
+            let n = remaining.min(buf.len());
+            self.output().write_all(&buf[..n])?;
+            remaining -= n;
+        }
+        Ok(())
+    }
+
     pub fn assemble_instr(&mut self) -> Option<std::io::Result<()>> {
         let mnemonic;
 
@@ -116,7 +273,7 @@ impl<'a> Assembler<'a> {
                 Some(self.mach.handle_directive(&mnemonic, self))
             } else {
                 match &*mnemonic {
-                    ".asciz" => {
+                    ".asciz" | ".string" => {
                         let mut buf = match self.state.iter.next_ignore_newline()?.body() {
                             Token::StringLiteral(x) => x.bytes().collect::<Vec<_>>(),
                             tok => panic!("Unexpected token {:?}. Expected a string literal", tok),
@@ -131,98 +288,147 @@ impl<'a> Assembler<'a> {
                         };
                         Some(self.state.output.write_all(&buf))
                     }
+                    ".byte" => Some(self.write_int_list(1)),
+                    ".2byte" => Some(self.write_int_list(2)),
+                    ".4byte" => Some(self.write_int_list(4)),
                     ".long" => {
-                        loop {
-                            let expr = crate::expr::parse_expression(self.iter());
-                            let expr = self.eval_expr(expr);
-
-                            let len = self.state.mach.long_width();
-
-                            match expr {
-                                Expression::Symbol(sym) => {
-                                    let output = self.output();
-
-                                    match output.write_addr(
-                                        len * 8,
-                                        arch_ops::traits::Address::Symbol { name: sym, disp: 0 },
-                                        false,
-                                    ) {
-                                        Ok(_) => {}
-                                        Err(e) => return Some(Err(e)),
-                                    }
-                                }
-                                Expression::Integer(val) => {
-                                    let mut bytes = [0u8; 16];
-                                    self.machine().int_to_bytes(val, &mut bytes[..len]);
-                                    let output = self.output();
-                                    match output.write_all(&bytes[..len]) {
-                                        Ok(_) => {}
-                                        Err(e) => return Some(Err(e)),
-                                    }
-                                }
-                                expr => todo!("{:?}", expr),
-                            }
+                        let len = self.state.mach.long_width();
+                        Some(self.write_int_list(len))
+                    }
+                    ".8byte" | ".quad" => Some(self.write_int_list(8)),
+                    // `.dc.a`: Motorola-syntax "define constant, address
+                    // width" -- DWARF producers use it for vendor tables
+                    // (e.g. `.eh_frame_hdr` entries) that want a pointer
+                    // regardless of the target's actual address size. Same
+                    // value/relocation shape as `.4byte`/`.8byte`, just
+                    // sized off `long_width` instead of a literal width.
+                    ".dc.a" => {
+                        let len = self.state.mach.long_width();
+                        Some(self.write_int_list(len))
+                    }
+                    ".skip" | ".space" => {
+                        let expr = crate::expr::parse_expression(self.iter());
+                        let count = match self.eval_expr(expr) {
+                            Expression::Integer(i) => i as usize,
+                            expr => panic!("Invalid length expression for {}: {:?}", mnemonic, expr),
+                        };
 
-                            match self.iter().peek().map(Spanned::body) {
-                                Some(Token::Sigil(s)) if s == "," => {
-                                    self.iter().next();
+                        let fill = match self.iter().peek().map(Spanned::body) {
+                            Some(Token::Sigil(s)) if s == "," => {
+                                self.iter().next();
+                                let expr = crate::expr::parse_expression(self.iter());
+                                match self.eval_expr(expr) {
+                                    Expression::Integer(i) => i as u8,
+                                    expr => panic!(
+                                        "Invalid fill expression for {}: {:?}",
+                                        mnemonic, expr
+                                    ),
                                 }
-                                _ => break,
                             }
+                            _ => 0,
+                        };
+
+                        Some(self.write_fill(count, fill))
+                    }
+                    ".zero" => {
+                        let expr = crate::expr::parse_expression(self.iter());
+                        match self.eval_expr(expr) {
+                            Expression::Integer(i) => Some(self.output().write_zeroes(i as usize)),
+                            expr => panic!("Invalid expression for .zero: {:?}", expr),
                         }
-                        Some(Ok(()))
                     }
-                    ".quad" => {
-                        loop {
-                            let expr = crate::expr::parse_expression(self.iter());
-                            let expr = self.eval_expr(expr);
-
-                            match expr {
-                                Expression::Symbol(sym) => {
-                                    let output = self.output();
-
-                                    match output.write_addr(
-                                        64,
-                                        arch_ops::traits::Address::Symbol { name: sym, disp: 0 },
-                                        false,
-                                    ) {
-                                        Ok(_) => {}
-                                        Err(e) => return Some(Err(e)),
-                                    }
-                                }
-                                Expression::Integer(val) => {
-                                    let mut bytes = [0u8; 8];
-                                    self.machine().int_to_bytes(val, &mut bytes);
-                                    let output = self.output();
-                                    match output.write_all(&bytes) {
-                                        Ok(_) => {}
-                                        Err(e) => return Some(Err(e)),
-                                    }
-                                }
-                                expr => todo!("{:?}", expr),
-                            }
+                    ".org" => {
+                        let expr = crate::expr::parse_expression(self.iter());
+                        let target = match self.eval_expr(expr) {
+                            Expression::Integer(i) => i as usize,
+                            expr => panic!("Invalid expression for .org: {:?}", expr),
+                        };
 
-                            match self.iter().peek().map(Spanned::body) {
-                                Some(Token::Sigil(s)) if s == "," => {
-                                    self.iter().next();
+                        let fill = match self.iter().peek().map(Spanned::body) {
+                            Some(Token::Sigil(s)) if s == "," => {
+                                self.iter().next();
+                                let expr = crate::expr::parse_expression(self.iter());
+                                match self.eval_expr(expr) {
+                                    Expression::Integer(i) => i as u8,
+                                    expr => panic!("Invalid fill expression for .org: {:?}", expr),
                                 }
-                                _ => break,
                             }
+                            _ => 0,
+                        };
+
+                        let off = self.output().offset();
+                        if target < off {
+                            panic!(
+                                ".org cannot move the location counter backwards (from {} to {})",
+                                off, target
+                            );
                         }
-                        Some(Ok(()))
+
+                        Some(self.write_fill(target - off, fill))
                     }
-                    ".space" => {
-                        let expr = crate::expr::parse_expression(self.iter());
-                        let expr = self.eval_expr(expr);
+                    ".incbin" => {
+                        let path = match self.state.iter.next_ignore_newline()?.body() {
+                            Token::StringLiteral(s) => s.clone(),
+                            tok => panic!(
+                                "Unexpected token {:?}. Expected a string literal",
+                                tok
+                            ),
+                        };
 
-                        match expr {
-                            Expression::Integer(mut i) => {
-                                let output = self.output();
+                        let mut skip = 0u64;
+                        let mut len = None::<u64>;
 
-                                Some(output.write_zeroes(i.try_into().unwrap()))
+                        if matches!(
+                            self.iter().peek().map(Spanned::body),
+                            Some(Token::Sigil(s)) if s == ","
+                        ) {
+                            self.iter().next();
+                            let expr = crate::expr::parse_expression(self.iter());
+                            skip = match self.eval_expr(expr) {
+                                Expression::Integer(i) => i as u64,
+                                expr => panic!(
+                                    "Invalid offset expression for .incbin: {:?}",
+                                    expr
+                                ),
+                            };
+
+                            if matches!(
+                                self.iter().peek().map(Spanned::body),
+                                Some(Token::Sigil(s)) if s == ","
+                            ) {
+                                self.iter().next();
+                                let expr = crate::expr::parse_expression(self.iter());
+                                len = Some(match self.eval_expr(expr) {
+                                    Expression::Integer(i) => i as u64,
+                                    expr => panic!(
+                                        "Invalid length expression for .incbin: {:?}",
+                                        expr
+                                    ),
+                                });
                             }
-                            expr => panic!("Invalid expression for .space: {:?}", expr),
                         }
+
+                        let stem = incbin_symbol_stem(&path);
+                        self.as_callbacks
+                            .create_symbol_now(self, &format!("_binary_{}_start", stem));
+
+                        let resolved = resolve_include(&path, self.include_dirs)
+                            .unwrap_or_else(|| std::path::PathBuf::from(&path));
+
+                        let copied = match stream_incbin(self.output(), resolved.as_path(), skip, len) {
+                            Ok(n) => n,
+                            Err(e) => return Some(Err(e)),
+                        };
+
+                        self.as_callbacks
+                            .create_symbol_now(self, &format!("_binary_{}_end", stem));
+                        self.as_callbacks.create_absolute_symbol(
+                            self,
+                            &format!("_binary_{}_size", stem),
+                            copied,
+                        );
+
+                        Some(Ok(()))
                     }
                     _ => Some(self.as_callbacks.handle_directive(self, &mnemonic)),
                 }
@@ -274,8 +480,93 @@ impl<'a> AsState<'a> {
         &mut self.iter
     }
 
+    /// Constant-folds `expr`'s arithmetic, bitwise, shift and comparison
+    /// operators wherever their operands are already plain integers, so
+    /// e.g. `.byte 1 + 2` and `.org 0x100 - 4` no longer need a caller to
+    /// have pre-folded them. A [`Expression::Symbol`] -- a section-relative
+    /// label only the linker, via a relocation, can resolve -- is left as
+    /// is; see [`fold_expr`] for the handful of `symbol ± constant` shapes
+    /// that fold partially.
     pub fn eval_expr(&mut self, expr: Expression) -> Expression {
-        expr
+        fold_expr(expr)
+    }
+}
+
+/// Constant-folds the purely-numeric parts of `expr`. A [`Expression::Symbol`]
+/// only folds together with an adjacent integer addition/subtraction, giving
+/// callers like `write_int_list`'s [`symbol_with_const_disp`] a single
+/// `symbol ± constant` shape to recognize; anything else involving a symbol
+/// (in particular a difference of two labels) is left unfolded, since
+/// neither `Expression` nor [`arch_ops::traits::Address`] has a shape that
+/// could carry it.
+fn fold_expr(expr: Expression) -> Expression {
+    match expr {
+        Expression::Integer(_) | Expression::Symbol(_) => expr,
+        Expression::Group(_, inner) => fold_expr(*inner),
+        Expression::Unary(op, inner) => match fold_expr(*inner) {
+            Expression::Integer(v) => Expression::Integer(match op {
+                UnaryOp::Neg => v.wrapping_neg(),
+                UnaryOp::Umn => !v,
+            }),
+            inner => Expression::Unary(op, Box::new(inner)),
+        },
+        Expression::Binary(op, lhs, rhs) => {
+            match (fold_expr(*lhs), fold_expr(*rhs)) {
+                (Expression::Integer(l), Expression::Integer(r)) => {
+                    Expression::Integer(fold_binary_ints(op, l, r))
+                }
+                (lhs, rhs) => Expression::Binary(op, Box::new(lhs), Box::new(rhs)),
+            }
+        }
+    }
+}
+
+fn fold_binary_ints(op: BinaryOp, l: u128, r: u128) -> u128 {
+    match op {
+        BinaryOp::Add => l.wrapping_add(r),
+        BinaryOp::Sub => l.wrapping_sub(r),
+        BinaryOp::Mul => l.wrapping_mul(r),
+        BinaryOp::Div => l.checked_div(r).unwrap_or_else(|| {
+            eprintln!("Division by zero in constant expression");
+            std::process::exit(1)
+        }),
+        BinaryOp::Mod => l.checked_rem(r).unwrap_or_else(|| {
+            eprintln!("Division by zero in constant expression");
+            std::process::exit(1)
+        }),
+        BinaryOp::Lsh => l << r,
+        BinaryOp::Rsh => l >> r,
+        BinaryOp::And => l & r,
+        BinaryOp::Or => l | r,
+        BinaryOp::Xor => l ^ r,
+        BinaryOp::CmpEq => (l == r) as u128,
+        BinaryOp::CmpNe => (l != r) as u128,
+        BinaryOp::CmpLt => (l < r) as u128,
+        BinaryOp::CmpGt => (l > r) as u128,
+        BinaryOp::CmpLe => (l <= r) as u128,
+        BinaryOp::CmpGe => (l >= r) as u128,
+        BinaryOp::BoolAnd => ((l != 0) && (r != 0)) as u128,
+        BinaryOp::BoolOr => ((l != 0) || (r != 0)) as u128,
+    }
+}
+
+/// Recognizes the `symbol`, `symbol + constant`, `constant + symbol`, and
+/// `symbol - constant` shapes [`fold_expr`] leaves unfolded, returning the
+/// displacement [`arch_ops::traits::Address::Symbol`] already has a field
+/// for.
+fn symbol_with_const_disp(expr: &Expression) -> Option<(String, i64)> {
+    match expr {
+        Expression::Symbol(sym) => Some((sym.clone(), 0)),
+        Expression::Binary(BinaryOp::Add, lhs, rhs) => match (&**lhs, &**rhs) {
+            (Expression::Symbol(sym), Expression::Integer(n)) => Some((sym.clone(), *n as i64)),
+            (Expression::Integer(n), Expression::Symbol(sym)) => Some((sym.clone(), *n as i64)),
+            _ => None,
+        },
+        Expression::Binary(BinaryOp::Sub, lhs, rhs) => match (&**lhs, &**rhs) {
+            (Expression::Symbol(sym), Expression::Integer(n)) => Some((sym.clone(), -(*n as i64))),
+            _ => None,
+        },
+        _ => None,
     }
 }
 
@@ -0,0 +1,521 @@
+//! A small, self-contained C-preprocessor pass for `.S`-style input:
+//! `#include`, `#define` object/function macros, and `#if`/`#ifdef`/
+//! `#ifndef`/`#elif`/`#else`/`#endif` conditionals. Runs over raw source
+//! text, line by line, before lc-as's own [`lcas_core::lex::Lexer`] ever sees
+//! it -- the same division of labour an external `cpp` has with a real
+//! `as`. `main` only runs this for `.S` input files (or when
+//! `--preprocess` forces it), leaving `.s` files' AT&T-syntax `#`
+//! end-of-line comments alone.
+//!
+//! This is deliberately not a full C preprocessor: macro expansion is a
+//! single substitution pass per line (the replacement text isn't
+//! rescanned for further macro uses), there's no `#`/`##` stringize or
+//! token-paste operator, and `#if` expressions support only
+//! `defined(...)`, bare identifiers (an object macro's integer value),
+//! integer literals, parentheses, unary `!`/`-`, and
+//! `+ - * / == != < <= > >= && ||` -- enough for the guard-and-feature-flag
+//! patterns real `.S` files lean on, not arbitrary cpp usage.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use lcas_core::as_state::resolve_include;
+
+/// How many `#include`s [`preprocess`] will follow into each other before
+/// giving up -- the same kind of backstop `lcas.rs`'s own `.include`
+/// expansion gives itself.
+const MAX_INCLUDE_DEPTH: usize = 64;
+
+/// A `#define`d name: an object macro (`params` is `None`) substitutes
+/// `body` verbatim; a function macro substitutes `body` with each
+/// parameter word replaced by the corresponding argument at the call site.
+struct Macro {
+    params: Option<Vec<String>>,
+    body: String,
+}
+
+type Macros = HashMap<String, Macro>;
+
+/// Preprocesses the file at `path`, following `#include`s against
+/// `include_dirs` the same way `-I` already does for `.include`/`.incbin`,
+/// and returns the expanded source text ready to hand to [`lcas_core::lex::Lexer`].
+///
+/// Diagnostics produced while assembling the result carry line numbers
+/// into *this* expanded text, not the original file(s) -- a real `cpp`
+/// fixes that up with `# <line> "<file>"` marker lines its caller
+/// understands; lc-as's lexer has no such convention, so that mapping is
+/// intentionally not attempted here.
+pub fn preprocess(path: &Path, include_dirs: &[PathBuf]) -> String {
+    let mut macros = Macros::new();
+    let mut out = String::new();
+    expand_file(path, include_dirs, &mut macros, &mut out, 0);
+    out
+}
+
+fn expand_file(
+    path: &Path,
+    include_dirs: &[PathBuf],
+    macros: &mut Macros,
+    out: &mut String,
+    depth: usize,
+) {
+    if depth > MAX_INCLUDE_DEPTH {
+        eprintln!("Too many nested #includes (possible include cycle)");
+        std::process::exit(1);
+    }
+
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Unable to open input file {}: {}", path.display(), e);
+        std::process::exit(1)
+    });
+
+    // One entry per currently-open `#if`/`#ifdef`/`#ifndef`: whether this
+    // branch is the one being emitted, and whether any branch of it has
+    // been taken yet (so `#elif`/`#else` know whether they're still
+    // eligible once an earlier branch already won).
+    struct Cond {
+        taking: bool,
+        taken: bool,
+    }
+    let mut conds: Vec<Cond> = Vec::new();
+
+    let active = |conds: &[Cond]| conds.iter().all(|c| c.taking);
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix('#') {
+            let rest = rest.trim_start();
+            let (keyword, rest) = split_keyword(rest);
+            let was_active = active(&conds);
+            match keyword {
+                "define" if was_active => define_macro(rest, macros),
+                "undef" if was_active => {
+                    macros.remove(rest.trim());
+                }
+                "include" if was_active => {
+                    let name = parse_include_path(rest);
+                    let resolved = resolve_include(&name, include_dirs).unwrap_or_else(|| {
+                        eprintln!(
+                            "Unable to find included file {} (searched -I directories)",
+                            name
+                        );
+                        std::process::exit(1)
+                    });
+                    expand_file(&resolved, include_dirs, macros, out, depth + 1);
+                }
+                "if" => {
+                    let taking = was_active && eval_if_expr(rest, macros);
+                    conds.push(Cond { taking, taken: taking });
+                }
+                "ifdef" => {
+                    let taking = was_active && macros.contains_key(rest.trim());
+                    conds.push(Cond { taking, taken: taking });
+                }
+                "ifndef" => {
+                    let taking = was_active && !macros.contains_key(rest.trim());
+                    conds.push(Cond { taking, taken: taking });
+                }
+                "elif" => {
+                    if conds.is_empty() {
+                        eprintln!("#elif without a matching #if/#ifdef/#ifndef");
+                        std::process::exit(1);
+                    }
+                    let parent_active = conds[..conds.len() - 1].iter().all(|c| c.taking);
+                    let c = conds.last_mut().unwrap();
+                    if c.taken {
+                        c.taking = false;
+                    } else {
+                        c.taking = parent_active && eval_if_expr(rest, macros);
+                        c.taken |= c.taking;
+                    }
+                }
+                "else" => {
+                    if conds.is_empty() {
+                        eprintln!("#else without a matching #if/#ifdef/#ifndef");
+                        std::process::exit(1);
+                    }
+                    let parent_active = conds[..conds.len() - 1].iter().all(|c| c.taking);
+                    let c = conds.last_mut().unwrap();
+                    c.taking = parent_active && !c.taken;
+                    c.taken = true;
+                }
+                "endif" => {
+                    if conds.pop().is_none() {
+                        eprintln!("#endif without a matching #if/#ifdef/#ifndef");
+                        std::process::exit(1);
+                    }
+                }
+                // An unrecognized `#` line (or one under a branch that
+                // isn't being taken) is left for lc-as's own lexer --
+                // AT&T-syntax files use `#` for end-of-line comments, and
+                // a `#` directive we don't know about is more likely one
+                // of those than a typo.
+                _ => {
+                    if was_active {
+                        out.push_str(line);
+                        out.push('\n');
+                    } else {
+                        out.push('\n');
+                    }
+                }
+            }
+        } else if active(&conds) {
+            out.push_str(&expand_macros(line, macros));
+            out.push('\n');
+        } else {
+            // Keep line numbers in the expanded output aligned with the
+            // input file being read, even across a skipped conditional
+            // branch, so a diagnostic pointing at the expanded text still
+            // lands close to the corresponding source line.
+            out.push('\n');
+        }
+    }
+
+    if !conds.is_empty() {
+        eprintln!("Unexpected end of input: missing #endif");
+        std::process::exit(1);
+    }
+}
+
+/// Splits `rest` (the text after a line's leading `#`) into its directive
+/// keyword and whatever follows, trimmed of leading whitespace.
+fn split_keyword(rest: &str) -> (&str, &str) {
+    let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+    (&rest[..end], rest[end..].trim_start())
+}
+
+/// Parses `#include`'s argument: either `"path"` or `<path>`.
+fn parse_include_path(rest: &str) -> String {
+    let rest = rest.trim();
+    let close = match rest.chars().next() {
+        Some('"') => '"',
+        Some('<') => '>',
+        _ => {
+            eprintln!("Expected \"path\" or <path> after #include, got `{}`", rest);
+            std::process::exit(1)
+        }
+    };
+    let body = &rest[1..];
+    let end = body.find(close).unwrap_or_else(|| {
+        eprintln!("#include: missing closing `{}`", close);
+        std::process::exit(1)
+    });
+    body[..end].to_string()
+}
+
+/// Parses `#define`'s argument (with the `define` keyword already
+/// stripped): either `NAME(param, ...) body` (a function macro, no space
+/// allowed between the name and `(`, matching C) or `NAME [body]` (an
+/// object macro).
+fn define_macro(rest: &str, macros: &mut Macros) {
+    let name_end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+    let name = rest[..name_end].to_string();
+    let after_name = &rest[name_end..];
+
+    if let Some(params_and_body) = after_name.strip_prefix('(') {
+        let close = params_and_body.find(')').unwrap_or_else(|| {
+            eprintln!("#define {}(...): missing closing `)`", name);
+            std::process::exit(1)
+        });
+        let params: Vec<String> = params_and_body[..close]
+            .split(',')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+        let body = params_and_body[close + 1..].trim().to_string();
+        macros.insert(name, Macro { params: Some(params), body });
+    } else {
+        let body = after_name.trim().to_string();
+        macros.insert(name, Macro { params: None, body });
+    }
+}
+
+/// Whether `c` can appear in a C identifier.
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Substitutes every whole-word use of a macro defined in `macros` into
+/// `line`, in one left-to-right pass (a use inside the substituted text
+/// itself is not rescanned, unlike a real preprocessor's rescan pass).
+fn expand_macros(line: &str, macros: &Macros) -> String {
+    let mut out = String::with_capacity(line.len());
+    let bytes: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_ident_char(bytes[i]) && (i == 0 || !is_ident_char(bytes[i - 1])) {
+            let start = i;
+            while i < bytes.len() && is_ident_char(bytes[i]) {
+                i += 1;
+            }
+            let word: String = bytes[start..i].iter().collect();
+            match macros.get(&word) {
+                Some(Macro { params: None, body }) => out.push_str(body),
+                Some(Macro { params: Some(params), body }) if bytes.get(i) == Some(&'(') => {
+                    let (args, consumed) = split_call_args(&bytes[i + 1..]);
+                    i += 1 + consumed;
+                    out.push_str(&substitute_params(body, params, &args));
+                }
+                _ => out.push_str(&word),
+            }
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Splits a function macro call's arguments out of `rest` (the characters
+/// right after its opening `(`), respecting nested parens so an argument
+/// like `f(a, b)` isn't split at its inner comma. Returns the arguments
+/// and how many characters of `rest` (including the closing `)`) were
+/// consumed.
+fn split_call_args(rest: &[char]) -> (Vec<String>, usize) {
+    let mut depth = 0usize;
+    let mut args = Vec::new();
+    let mut current = String::new();
+    for (i, &c) in rest.iter().enumerate() {
+        match c {
+            '(' => {
+                depth += 1;
+                current.push(c);
+            }
+            ')' if depth == 0 => {
+                if !current.trim().is_empty() || !args.is_empty() {
+                    args.push(current.trim().to_string());
+                }
+                return (args, i + 1);
+            }
+            ')' => {
+                depth -= 1;
+                current.push(c);
+            }
+            ',' if depth == 0 => {
+                args.push(current.trim().to_string());
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    eprintln!("Unterminated macro call (missing closing `)`)");
+    std::process::exit(1)
+}
+
+/// Substitutes each of `params`' whole-word uses in `body` with the
+/// matching entry of `args`, positionally.
+fn substitute_params(body: &str, params: &[String], args: &[String]) -> String {
+    let mut out = String::with_capacity(body.len());
+    let bytes: Vec<char> = body.chars().collect();
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_ident_char(bytes[i]) && (i == 0 || !is_ident_char(bytes[i - 1])) {
+            let start = i;
+            while i < bytes.len() && is_ident_char(bytes[i]) {
+                i += 1;
+            }
+            let word: String = bytes[start..i].iter().collect();
+            match params.iter().position(|p| *p == word) {
+                Some(idx) => out.push_str(args.get(idx).map(String::as_str).unwrap_or("")),
+                None => out.push_str(&word),
+            }
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Evaluates a `#if`/`#elif` expression: `defined(NAME)` (checked against
+/// `macros` directly, *not* macro-expanded first, matching real cpp), any
+/// other bare identifier (an object macro's value, or `0` if undefined --
+/// also real cpp's rule), integer literals, parentheses, unary `!`/`-`, and
+/// `+ - * / == != < <= > >= && ||`, with C's relative precedence (`||`
+/// loosest, then `&&`, one non-chaining comparison, `+ -`, `* /`, and
+/// `!`/unary `-` tightest) and left associativity within a level -- enough for the
+/// simple guards real `.S` files write, not general C expression syntax.
+fn eval_if_expr(expr: &str, macros: &Macros) -> bool {
+    let mut p = CondParser { s: expr, pos: 0, macros };
+    let val = p.parse_or();
+    p.skip_ws();
+    if p.pos != p.s.len() {
+        eprintln!("#if: couldn't parse expression `{}`", expr);
+        std::process::exit(1);
+    }
+    val != 0
+}
+
+struct CondParser<'a> {
+    s: &'a str,
+    pos: usize,
+    macros: &'a Macros,
+}
+
+impl<'a> CondParser<'a> {
+    fn skip_ws(&mut self) {
+        while self.s[self.pos..].starts_with(|c: char| c.is_whitespace()) {
+            self.pos += self.s[self.pos..].chars().next().unwrap().len_utf8();
+        }
+    }
+
+    fn peek_op(&mut self, op: &str) -> bool {
+        self.skip_ws();
+        self.s[self.pos..].starts_with(op)
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        if self.peek_op(op) {
+            self.pos += op.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> i64 {
+        let mut lhs = self.parse_and();
+        while self.eat_op("||") {
+            let rhs = self.parse_and();
+            lhs = ((lhs != 0) || (rhs != 0)) as i64;
+        }
+        lhs
+    }
+
+    fn parse_and(&mut self) -> i64 {
+        let mut lhs = self.parse_cmp();
+        while self.eat_op("&&") {
+            let rhs = self.parse_cmp();
+            lhs = ((lhs != 0) && (rhs != 0)) as i64;
+        }
+        lhs
+    }
+
+    fn parse_cmp(&mut self) -> i64 {
+        let lhs = self.parse_add();
+        for op in ["==", "!=", "<=", ">=", "<", ">"] {
+            if self.eat_op(op) {
+                let rhs = self.parse_add();
+                return match op {
+                    "==" => (lhs == rhs) as i64,
+                    "!=" => (lhs != rhs) as i64,
+                    "<=" => (lhs <= rhs) as i64,
+                    ">=" => (lhs >= rhs) as i64,
+                    "<" => (lhs < rhs) as i64,
+                    ">" => (lhs > rhs) as i64,
+                    _ => unreachable!(),
+                };
+            }
+        }
+        lhs
+    }
+
+    fn parse_add(&mut self) -> i64 {
+        let mut lhs = self.parse_mul();
+        loop {
+            if self.eat_op("+") {
+                lhs += self.parse_mul();
+            } else if self.eat_op("-") {
+                lhs -= self.parse_mul();
+            } else {
+                break;
+            }
+        }
+        lhs
+    }
+
+    fn parse_mul(&mut self) -> i64 {
+        let mut lhs = self.parse_unary();
+        loop {
+            if self.eat_op("*") {
+                lhs *= self.parse_unary();
+            } else if self.eat_op("/") {
+                let rhs = self.parse_unary();
+                lhs = lhs.checked_div(rhs).unwrap_or_else(|| {
+                    eprintln!("#if: division by zero");
+                    std::process::exit(1)
+                });
+            } else {
+                break;
+            }
+        }
+        lhs
+    }
+
+    fn parse_unary(&mut self) -> i64 {
+        if self.eat_op("!") {
+            (self.parse_unary() == 0) as i64
+        } else if self.eat_op("-") {
+            -self.parse_unary()
+        } else {
+            self.parse_primary()
+        }
+    }
+
+    fn parse_primary(&mut self) -> i64 {
+        self.skip_ws();
+        if self.eat_op("(") {
+            let val = self.parse_or();
+            self.skip_ws();
+            if !self.eat_op(")") {
+                eprintln!("#if: missing closing `)`");
+                std::process::exit(1);
+            }
+            return val;
+        }
+
+        if self.s[self.pos..].starts_with("defined")
+            && !self.s[self.pos + "defined".len()..]
+                .starts_with(|c: char| is_ident_char(c))
+        {
+            self.pos += "defined".len();
+            self.skip_ws();
+            let paren = self.eat_op("(");
+            self.skip_ws();
+            let start = self.pos;
+            while self.pos < self.s.len()
+                && is_ident_char(self.s[self.pos..].chars().next().unwrap())
+            {
+                self.pos += 1;
+            }
+            let name = &self.s[start..self.pos];
+            let defined = self.macros.contains_key(name);
+            if paren {
+                self.skip_ws();
+                self.eat_op(")");
+            }
+            return defined as i64;
+        }
+
+        let start = self.pos;
+        if self.s[self.pos..].starts_with(|c: char| c.is_ascii_digit()) {
+            while self.pos < self.s.len()
+                && self.s[self.pos..].starts_with(|c: char| c.is_ascii_digit())
+            {
+                self.pos += 1;
+            }
+            return self.s[start..self.pos].parse().unwrap_or_else(|_| {
+                eprintln!("#if: integer literal out of range in `{}`", self.s);
+                std::process::exit(1)
+            });
+        }
+
+        if self.s[self.pos..].starts_with(|c: char| is_ident_char(c)) {
+            while self.pos < self.s.len()
+                && self.s[self.pos..].starts_with(|c: char| is_ident_char(c))
+            {
+                self.pos += 1;
+            }
+            let name = &self.s[start..self.pos];
+            return match self.macros.get(name) {
+                Some(Macro { params: None, body }) => body.trim().parse().unwrap_or(0),
+                _ => 0,
+            };
+        }
+
+        eprintln!("#if: couldn't parse expression `{}`", self.s);
+        std::process::exit(1)
+    }
+}
@@ -0,0 +1,229 @@
+//! Diagnostic collection for `lcas`, so a translation unit can report
+//! every syntax/semantic error it finds in one run instead of aborting at
+//! the first one.
+//!
+//! [`Diagnostics`] is a bounded collector: once [`Diagnostics::error_count`]
+//! reaches its `max_errors` cap, further errors are dropped and
+//! [`Diagnostics::truncated`] is set, so a badly garbled file doesn't
+//! produce thousands of lines of noise. [`Diagnostics::suppress_cascades_from`]
+//! marks a span (the body of a macro expansion, once `lcas` has a macro
+//! facility to call it from, the same unwired-extension-point shape
+//! [`crate::as_state`]'s other staged work uses) so only the first error
+//! found inside it is reported; later errors at the same span are
+//! dropped as cascades of that first one rather than repeated once per
+//! expansion.
+
+use std::fmt;
+
+use crate::span::Span;
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+
+    /// An error with no associated source location -- used for failures
+    /// (e.g. an I/O error from the output writer) that aren't tied to a
+    /// particular span in the input.
+    pub fn error_unspanned(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span: None,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) if !span.is_synthetic() => write!(
+                f,
+                "{}:{:?}: {}: {}",
+                span.file(),
+                span.begin(),
+                self.severity,
+                self.message
+            ),
+            _ => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Renders this diagnostic the same way [`fmt::Display`] does, plus (when
+    /// the span is real, and its starting line exists in `source_lines`) the
+    /// offending source line itself with a `^` under the starting column --
+    /// `source_lines` is indexed the same way `main`'s `-a`/`--listing`
+    /// support already reads it back: one entry per physical line, in
+    /// order, 1-based against [`crate::span::Pos::row`] plus one.
+    pub fn render(&self, source_lines: &[String]) -> String {
+        let mut out = self.to_string();
+
+        if let Some(span) = &self.span {
+            if !span.is_synthetic() {
+                let row = span.begin().row() as usize;
+                if let Some(line) = source_lines.get(row) {
+                    let col = span.begin().col() as usize;
+                    out.push('\n');
+                    out.push_str(line);
+                    out.push('\n');
+                    out.extend(std::iter::repeat(' ').take(col));
+                    out.push('^');
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// A span `contains` another if they share a file and `other` falls
+/// entirely within `self`'s range -- used to recognize a diagnostic as a
+/// cascade of one already reported for an enclosing span.
+fn span_contains(outer: &Span, inner: &Span) -> bool {
+    outer.file() == inner.file() && outer.begin() <= inner.begin() && inner.end() <= outer.end()
+}
+
+pub struct Diagnostics {
+    diags: Vec<Diagnostic>,
+    max_errors: usize,
+    error_count: usize,
+    dropped_errors: usize,
+    suppressed_spans: Vec<Span>,
+    /// Set by `-W`/`--no-warn`: drop warnings at `report` time instead of
+    /// collecting them.
+    suppress_warnings: bool,
+    /// Set by `--fatal-warnings`: every warning counts against
+    /// `max_errors`/`has_errors` the same as an error would, though it
+    /// keeps reporting (and rendering) as [`Severity::Warning`].
+    warnings_as_errors: bool,
+}
+
+impl Diagnostics {
+    pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+    pub fn new() -> Self {
+        Self::with_max_errors(Self::DEFAULT_MAX_ERRORS)
+    }
+
+    pub fn with_max_errors(max_errors: usize) -> Self {
+        Self {
+            diags: Vec::new(),
+            max_errors,
+            error_count: 0,
+            dropped_errors: 0,
+            suppressed_spans: Vec::new(),
+            suppress_warnings: false,
+            warnings_as_errors: false,
+        }
+    }
+
+    /// Corresponds to `-W`/`--no-warn`: once set, warnings are dropped at
+    /// [`Diagnostics::report`] time instead of collected.
+    pub fn set_suppress_warnings(&mut self, suppress: bool) {
+        self.suppress_warnings = suppress;
+    }
+
+    /// Corresponds to `--fatal-warnings`: once set, a reported warning
+    /// also counts against `max_errors` and makes [`Diagnostics::has_errors`]
+    /// return `true`, without changing how it's rendered.
+    pub fn set_warnings_as_errors(&mut self, fatal: bool) {
+        self.warnings_as_errors = fatal;
+    }
+
+    /// Marks `span` as already having produced an error, so further
+    /// diagnostics whose span falls within it are dropped as cascades
+    /// rather than reported again.
+    pub fn suppress_cascades_from(&mut self, span: Span) {
+        self.suppressed_spans.push(span);
+    }
+
+    pub fn report(&mut self, diag: Diagnostic) {
+        if let Some(span) = &diag.span {
+            if self.suppressed_spans.iter().any(|s| span_contains(s, span)) {
+                return;
+            }
+        }
+
+        if diag.severity == Severity::Warning && self.suppress_warnings {
+            return;
+        }
+
+        if diag.severity == Severity::Error
+            || (diag.severity == Severity::Warning && self.warnings_as_errors)
+        {
+            if self.error_count >= self.max_errors {
+                self.dropped_errors += 1;
+                return;
+            }
+            self.error_count += 1;
+        }
+
+        self.diags.push(diag);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count > 0
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.error_count
+    }
+
+    /// How many errors beyond `max_errors` were dropped rather than
+    /// collected.
+    pub fn dropped_errors(&self) -> usize {
+        self.dropped_errors
+    }
+
+    /// Whether any errors existed beyond `max_errors` and were dropped
+    /// rather than collected.
+    pub fn truncated(&self) -> bool {
+        self.dropped_errors > 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diags.iter()
+    }
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,199 +1,2530 @@
 use arch_ops::traits::InsnWrite;
 use binfmt::{
-    fmt::{BinaryFile, FileType, Section, SectionType},
-    sym::{Symbol, SymbolKind},
+    fmt::{FileType, Section, SectionFlag, SectionFlags, SectionType},
+    sym::{Symbol, SymbolKind, SymbolType},
 };
 use lcas_core::{
-    as_state::{Assembler, AssemblerCallbacks},
-    expr::Expression,
+    as_state::{resolve_include, Assembler, AssemblerCallbacks, PeekToken},
+    diag::Diagnostics,
+    expr::{BinaryOp, Expression, UnaryOp},
     lex::Token,
+    span::{Span, Spanned},
     sym::Symbol as LCasSymbol,
+    targ::TargetMachine,
 };
 use std::{
     cell::RefCell,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fs::File,
     io::{Read, Write},
+    path::PathBuf,
     ptr::NonNull,
     rc::Rc,
 };
 use target_tuples::Target;
 
+mod cpp;
+
+/// How many `.include`s [`expand_includes`] will follow into each other
+/// before giving up -- a cycle (`a.s` includes `b.s` includes `a.s`) would
+/// otherwise recurse until the process runs out of stack.
+const MAX_INCLUDE_DEPTH: usize = 64;
+
+/// Expands every `.include "path"` directive in `tokens` in place,
+/// splicing the included file's own (recursively expanded) tokens in
+/// place of the directive, the same preprocessing GNU `as`'s `.include`
+/// does before its real parser ever sees the file. `path` is resolved
+/// against `include_dirs` the same way [`resolve_include`] resolves
+/// `.incbin`'s path, so `-I` covers both directives identically.
+///
+/// This runs as a pass over the already-fully-lexed token stream (see
+/// `main`'s `lex.collect::<Vec<_>>()`) rather than inside [`Assembler`]
+/// itself, since `Assembler` is built around a single flat external
+/// token iterator with no way to splice tokens into the middle of it.
+fn expand_includes(
+    tokens: Vec<Spanned<Token>>,
+    targ_def: &dyn TargetMachine,
+    include_dirs: &[PathBuf],
+    depth: usize,
+) -> Vec<Spanned<Token>> {
+    if depth > MAX_INCLUDE_DEPTH {
+        eprintln!("Too many nested .include directives (possible include cycle)");
+        std::process::exit(1);
+    }
+
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut iter = tokens.into_iter().peekable();
+
+    while let Some(tok) = iter.next() {
+        match tok.body() {
+            Token::Identifier(id) if id == ".include" => {
+                let path = match iter.next() {
+                    Some(path_tok) => match path_tok.into_inner() {
+                        Token::StringLiteral(s) => s,
+                        tok => {
+                            eprintln!("Expected a string literal after .include, got {:?}", tok);
+                            std::process::exit(1)
+                        }
+                    },
+                    None => {
+                        eprintln!("Expected a string literal after .include, got end of input");
+                        std::process::exit(1)
+                    }
+                };
+
+                let resolved = resolve_include(&path, include_dirs).unwrap_or_else(|| {
+                    eprintln!("Unable to find included file {} (searched -I directories)", path);
+                    std::process::exit(1)
+                });
+
+                let bytes = std::fs::File::open(&resolved)
+                    .unwrap_or_else(|e| {
+                        eprintln!("Unable to open included file {}: {}", resolved.display(), e);
+                        std::process::exit(1)
+                    })
+                    .bytes()
+                    .map(|r| {
+                        r.unwrap_or_else(|e| {
+                            eprintln!("Failed to read included file {}: {}", resolved.display(), e);
+                            std::process::exit(1)
+                        })
+                    });
+
+                let file = LCasSymbol::intern(&resolved.to_string_lossy());
+                let mut input = utf::decode_utf8(bytes).map(|e| e.unwrap()).peekable();
+                let lex = lcas_core::lex::Lexer::new(targ_def, &mut input, file);
+                let included = lex.collect::<Vec<_>>();
+
+                out.extend(expand_includes(included, targ_def, include_dirs, depth + 1));
+            }
+            _ => out.push(tok),
+        }
+    }
+
+    out
+}
+
+/// The preprocessor-style constants [`expand_conditionals`] tracks, seeded
+/// from `-D`/`--define` and extended by `.define`/`.undef`. These are a
+/// separate namespace from `.equ`/`.set`'s assembler constants (see
+/// `eval_const`): those only exist once real assembly is running, with
+/// access to labels this preprocessing pass hasn't even lexed structure
+/// for, whereas `.if`/`.ifdef` need an answer before the assembler exists
+/// at all.
+type Defines = HashMap<String, u64>;
+
+/// Parses a `-D`/`--define` command-line argument (`name` or `name=value`)
+/// and records it in `defines`, the same constant `.define` would.
+fn parse_define(spec: &str, defines: &mut Defines) {
+    let (name, value) = match spec.split_once('=') {
+        Some((name, value)) => {
+            let value = value.parse::<u64>().unwrap_or_else(|_| {
+                eprintln!("Invalid value in -D{}: expected an integer", spec);
+                std::process::exit(1)
+            });
+            (name.to_string(), value)
+        }
+        None => (spec.to_string(), 1),
+    };
+
+    defines.insert(name, value);
+}
+
+/// How many `.if`/`.ifdef`/`.ifndef`/`.ifc` may nest before
+/// [`expand_conditionals`] gives up -- the same kind of backstop
+/// [`MAX_INCLUDE_DEPTH`] gives `.include`.
+const MAX_CONDITIONAL_DEPTH: usize = 64;
+
+const IF_DIRECTIVES: [&str; 4] = [".if", ".ifdef", ".ifndef", ".ifc"];
+
+/// Collects the tokens remaining on the current line, consuming (but not
+/// returning) the terminating [`Token::LineTerminator`], or running to the
+/// end of input.
+fn collect_line(iter: &mut TokIter) -> Vec<Spanned<Token>> {
+    let mut out = Vec::new();
+    while let Some(tok) = iter.peek() {
+        if matches!(tok.body(), Token::LineTerminator) {
+            iter.next();
+            break;
+        }
+        out.push(iter.next().unwrap());
+    }
+    out
+}
+
+/// Compares two token sequences by their [`Token`] payload only, ignoring
+/// source position -- the derived `Eq` on [`Spanned`] would otherwise make
+/// `.ifc`'s two operands compare unequal just for coming from different
+/// places in the source.
+fn tokens_body_eq(a: &[Spanned<Token>], b: &[Spanned<Token>]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.body() == y.body())
+}
+
+/// Evaluates `.ifc a, b`: splits on the first top-level comma and compares
+/// the two token sequences verbatim, the same string/token-equality test
+/// GNU `as`'s `.ifc` performs.
+fn eval_ifc_condition(line: &[Spanned<Token>]) -> bool {
+    let comma = line
+        .iter()
+        .position(|t| matches!(t.body(), Token::Sigil(s) if s == ","))
+        .unwrap_or_else(|| {
+            eprintln!("Expected '<a>, <b>' after .ifc");
+            std::process::exit(1)
+        });
+
+    tokens_body_eq(&line[..comma], &line[comma + 1..])
+}
+
+/// Parses and folds the expression on an `.if` line against `defines`.
+/// There's no lazy/relocatable form `.if` could produce instead -- its
+/// result has to be known before either of its branches can be chosen --
+/// so a name `eval_const` can't resolve (anything but a `-D`/`.define`
+/// constant) is a hard error here.
+fn eval_if_condition(iter: &mut TokIter, defines: &Defines) -> bool {
+    let line = collect_line(iter);
+    let mut it = line.into_iter().peekable();
+    let expr = lcas_core::expr::parse_expression(&mut it);
+
+    eval_const(&expr, defines).unwrap_or_else(|| {
+        eprintln!(
+            ".if condition refers to a name lc-as can't resolve at preprocessing time \
+             (only -D/.define constants are visible here, not .equ/.set or labels)"
+        );
+        std::process::exit(1)
+    }) != 0
+}
+
+/// Collects an `.if`/`.ifdef`/`.ifndef`/`.ifc` body up to its matching
+/// `.endif`, splitting it at a top-level `.else` into the taken and
+/// not-taken branches.
+fn collect_if_block(iter: &mut TokIter) -> (Vec<Spanned<Token>>, Vec<Spanned<Token>>) {
+    let mut depth = 0usize;
+    let mut then_body = Vec::new();
+    let mut else_body = Vec::new();
+    let mut in_else = false;
+
+    loop {
+        match iter.next() {
+            None => {
+                eprintln!("Unexpected end of input: missing .endif");
+                std::process::exit(1);
+            }
+            Some(tok) => match tok.body() {
+                Token::Identifier(id) if IF_DIRECTIVES.contains(&id.as_str()) => {
+                    depth += 1;
+                    (if in_else { &mut else_body } else { &mut then_body }).push(tok);
+                }
+                Token::Identifier(id) if id == ".endif" => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                    (if in_else { &mut else_body } else { &mut then_body }).push(tok);
+                }
+                Token::Identifier(id) if id == ".else" && depth == 0 => {
+                    in_else = true;
+                }
+                _ => (if in_else { &mut else_body } else { &mut then_body }).push(tok),
+            },
+        }
+    }
+
+    (then_body, else_body)
+}
+
+/// Renders a `.err`/`.error`/`.warning` message for display -- these almost
+/// always carry a single string literal, so that's rendered as-is (matching
+/// `.include`'s existing convention of keeping a string literal's
+/// surrounding quotes); anything else just names the directive.
+fn render_diag_message(directive: &str, tokens: &[Spanned<Token>]) -> String {
+    match tokens.first().map(Spanned::body) {
+        Some(Token::StringLiteral(s)) => s.clone(),
+        _ => format!("{} directive", directive),
+    }
+}
+
+/// Expands `.if`/`.ifdef`/`.ifndef`/`.ifc`/`.else`/`.endif` conditional
+/// blocks and handles the preprocessing-only `.define`/`.undef`/`.err`/
+/// `.error`/`.warning` directives that go with them, dropping whichever
+/// branch of each conditional isn't taken before [`expand_includes`] or
+/// [`expand_macros`] ever see it -- so an `.include` or `.macro` guarded by
+/// an untaken `.ifdef` is never opened/expanded, and so `.ifdef` can gate a
+/// `.macro` definition, the most common real use of the two together.
+///
+/// This runs first among the preprocessing passes for exactly that reason:
+/// `main`'s pass order is `expand_conditionals`, then `expand_includes`,
+/// then `expand_macros`.
+fn expand_conditionals(
+    tokens: Vec<Spanned<Token>>,
+    defines: &mut Defines,
+    depth: usize,
+) -> Vec<Spanned<Token>> {
+    if depth > MAX_CONDITIONAL_DEPTH {
+        eprintln!("Too many nested .if/.ifdef/.ifndef/.ifc directives");
+        std::process::exit(1);
+    }
+
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut iter = tokens.into_iter().peekable();
+
+    while let Some(tok) = iter.next() {
+        match tok.body() {
+            Token::Identifier(id) if id == ".if" => {
+                let cond = eval_if_condition(&mut iter, defines);
+                let (then_body, else_body) = collect_if_block(&mut iter);
+                let chosen = if cond { then_body } else { else_body };
+                out.extend(expand_conditionals(chosen, defines, depth + 1));
+            }
+            Token::Identifier(id) if id == ".ifdef" || id == ".ifndef" => {
+                let ctx = id.clone();
+                let name = expect_identifier(&mut iter, &ctx);
+                collect_line(&mut iter);
+                let cond = defines.contains_key(&name) == (ctx == ".ifdef");
+                let (then_body, else_body) = collect_if_block(&mut iter);
+                let chosen = if cond { then_body } else { else_body };
+                out.extend(expand_conditionals(chosen, defines, depth + 1));
+            }
+            Token::Identifier(id) if id == ".ifc" => {
+                let line = collect_line(&mut iter);
+                let cond = eval_ifc_condition(&line);
+                let (then_body, else_body) = collect_if_block(&mut iter);
+                let chosen = if cond { then_body } else { else_body };
+                out.extend(expand_conditionals(chosen, defines, depth + 1));
+            }
+            Token::Identifier(id) if id == ".else" || id == ".endif" => {
+                eprintln!("{} without a matching .if/.ifdef/.ifndef/.ifc", id);
+                std::process::exit(1);
+            }
+            Token::Identifier(id) if id == ".define" => {
+                let name = expect_identifier(&mut iter, ".define");
+                let rest = collect_line(&mut iter);
+                let value = if rest.is_empty() {
+                    1
+                } else {
+                    let mut it = rest.into_iter().peekable();
+                    let expr = lcas_core::expr::parse_expression(&mut it);
+                    eval_const(&expr, defines).unwrap_or_else(|| {
+                        eprintln!(
+                            ".define {} has a value lc-as can't resolve at preprocessing time",
+                            name
+                        );
+                        std::process::exit(1)
+                    })
+                };
+                defines.insert(name, value);
+            }
+            Token::Identifier(id) if id == ".undef" => {
+                let name = expect_identifier(&mut iter, ".undef");
+                collect_line(&mut iter);
+                defines.remove(&name);
+            }
+            Token::Identifier(id) if id == ".err" || id == ".error" => {
+                let msg = collect_line(&mut iter);
+                eprintln!("{}", render_diag_message(id, &msg));
+                std::process::exit(1);
+            }
+            Token::Identifier(id) if id == ".warning" => {
+                let id = id.clone();
+                let msg = collect_line(&mut iter);
+                eprintln!("{}", render_diag_message(&id, &msg));
+            }
+            _ => out.push(tok),
+        }
+    }
+
+    out
+}
+
+type TokIter = std::iter::Peekable<std::vec::IntoIter<Spanned<Token>>>;
+
+/// How deep macro expansion (a macro invoking itself, directly or through
+/// another macro, or a `.rept`/`.irp`/`.irpc` body invoking a macro) is
+/// allowed to go before [`expand_macros`] gives up -- the same kind of
+/// backstop [`MAX_INCLUDE_DEPTH`] gives `.include`.
+const MAX_MACRO_EXPANSION_DEPTH: usize = 64;
+
+/// One parameter of a `.macro` definition: its name (referenced in the body
+/// as `\name`) and, if given, the default value substituted when a call
+/// doesn't supply one.
+struct MacroParam {
+    name: String,
+    default: Option<Vec<Spanned<Token>>>,
+}
+
+/// A `.macro`/`.endm` definition, recorded by [`expand_macros`] and
+/// replayed, with parameters substituted, at every call site.
+struct MacroDef {
+    params: Vec<MacroParam>,
+    body: Vec<Spanned<Token>>,
+}
+
+/// One argument at a macro call site: either positional (bound to the next
+/// parameter that doesn't already have a named binding) or `name=value`.
+enum MacroArg {
+    Positional(Vec<Spanned<Token>>),
+    Named(String, Vec<Spanned<Token>>),
+}
+
+fn expect_identifier(iter: &mut TokIter, ctx: &str) -> String {
+    match iter.next() {
+        Some(tok) => match tok.into_inner() {
+            Token::Identifier(id) => id,
+            tok => {
+                eprintln!("Expected an identifier after {}, got {:?}", ctx, tok);
+                std::process::exit(1)
+            }
+        },
+        None => {
+            eprintln!("Expected an identifier after {}, got end of input", ctx);
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Parses `.rept`'s count. Only a literal integer is accepted -- no
+/// [`Assembler`] (and so no [`Expression`] evaluation) exists yet at this
+/// preprocessing stage, and real startup code almost always writes a
+/// literal here anyway.
+fn expect_rept_count(iter: &mut TokIter) -> u128 {
+    match iter.next() {
+        Some(tok) => match tok.into_inner() {
+            Token::IntegerLiteral(n) => n,
+            tok => {
+                eprintln!(
+                    "Expected a literal integer count after .rept, got {:?}",
+                    tok
+                );
+                std::process::exit(1)
+            }
+        },
+        None => {
+            eprintln!("Expected a literal integer count after .rept, got end of input");
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Collects tokens up to (but not including) the matching `ends` directive,
+/// treating any of `opens` as a nested block of the same kind needing its
+/// own matching end first.
+fn collect_block_body(iter: &mut TokIter, ends: &[&str], opens: &[&str]) -> Vec<Spanned<Token>> {
+    let mut depth = 0usize;
+    let mut body = Vec::new();
+
+    loop {
+        match iter.next() {
+            None => {
+                eprintln!("Unexpected end of input: missing {}", ends[0]);
+                std::process::exit(1);
+            }
+            Some(tok) => match tok.body() {
+                Token::Identifier(id) if opens.contains(&id.as_str()) => {
+                    depth += 1;
+                    body.push(tok);
+                }
+                Token::Identifier(id) if ends.contains(&id.as_str()) => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                    body.push(tok);
+                }
+                _ => body.push(tok),
+            },
+        }
+    }
+
+    body
+}
+
+/// Parses `.macro name [param[=default], ...]`'s parameter list, which runs
+/// to the end of the line.
+fn parse_macro_params(iter: &mut TokIter) -> Vec<MacroParam> {
+    let mut params = Vec::new();
+
+    loop {
+        match iter.peek().map(Spanned::body) {
+            None => break,
+            Some(Token::LineTerminator) => {
+                iter.next();
+                break;
+            }
+            _ => {}
+        }
+
+        let name = expect_identifier(iter, ".macro parameter list");
+        let default = if matches!(iter.peek().map(Spanned::body), Some(Token::Sigil(s)) if s == "=")
+        {
+            iter.next();
+            let mut value = Vec::new();
+            loop {
+                match iter.peek().map(Spanned::body) {
+                    None | Some(Token::LineTerminator) => break,
+                    Some(Token::Sigil(s)) if s == "," => break,
+                    _ => value.push(iter.next().unwrap()),
+                }
+            }
+            Some(value)
+        } else {
+            None
+        };
+
+        params.push(MacroParam { name, default });
+
+        match iter.peek().map(Spanned::body) {
+            Some(Token::Sigil(s)) if s == "," => {
+                iter.next();
+            }
+            _ => {}
+        }
+    }
+
+    params
+}
+
+fn classify_macro_arg(mut chunk: Vec<Spanned<Token>>) -> MacroArg {
+    if chunk.len() >= 2 {
+        if let Token::Identifier(name) = chunk[0].body() {
+            if matches!(chunk[1].body(), Token::Sigil(s) if s == "=") {
+                let name = name.clone();
+                chunk.drain(..2);
+                return MacroArg::Named(name, chunk);
+            }
+        }
+    }
+    MacroArg::Positional(chunk)
+}
+
+/// Parses a macro call's comma-separated argument list, which runs to the
+/// end of the line.
+fn parse_macro_args(iter: &mut TokIter) -> Vec<MacroArg> {
+    let mut args = Vec::new();
+
+    if matches!(iter.peek().map(Spanned::body), Some(Token::LineTerminator)) {
+        iter.next();
+        return args;
+    }
+    if iter.peek().is_none() {
+        return args;
+    }
+
+    loop {
+        let mut chunk = Vec::new();
+        loop {
+            match iter.peek().map(Spanned::body) {
+                None | Some(Token::LineTerminator) => break,
+                Some(Token::Sigil(s)) if s == "," => break,
+                _ => chunk.push(iter.next().unwrap()),
+            }
+        }
+
+        args.push(classify_macro_arg(chunk));
+
+        match iter.peek().map(Spanned::body) {
+            Some(Token::Sigil(s)) if s == "," => {
+                iter.next();
+            }
+            _ => break,
+        }
+    }
+
+    if matches!(iter.peek().map(Spanned::body), Some(Token::LineTerminator)) {
+        iter.next();
+    }
+
+    args
+}
+
+/// Binds a call's arguments to `params`: named arguments (`name=value`)
+/// bind directly, then remaining positional arguments fill whatever
+/// parameters are left, in declaration order; a parameter covered by
+/// neither falls back to its default, or is a hard error if it has none.
+/// Extra positional arguments beyond `params.len()` are ignored.
+fn bind_macro_args(
+    params: &[MacroParam],
+    args: Vec<MacroArg>,
+    macro_name: &str,
+) -> Vec<(String, Vec<Spanned<Token>>)> {
+    let mut named = HashMap::new();
+    let mut positionals = Vec::new();
+
+    for arg in args {
+        match arg {
+            MacroArg::Named(name, value) => {
+                named.insert(name, value);
+            }
+            MacroArg::Positional(value) => positionals.push(value),
+        }
+    }
+
+    let mut positionals = positionals.into_iter();
+    let mut bindings = Vec::with_capacity(params.len());
+
+    for param in params {
+        let value = if let Some(value) = named.remove(&param.name) {
+            value
+        } else if let Some(value) = positionals.next() {
+            value
+        } else if let Some(default) = &param.default {
+            default.clone()
+        } else {
+            eprintln!(
+                "Macro {} invoked without a value for parameter {} (no default given)",
+                macro_name, param.name
+            );
+            std::process::exit(1)
+        };
+        bindings.push((param.name.clone(), value));
+    }
+
+    bindings
+}
+
+/// Replaces every `\name` parameter reference, and `\@` (the counter unique
+/// to each macro invocation GNU `as` also gives macro bodies), inside
+/// `body` with its bound value, recursing into [`Token::Group`] so
+/// references inside parenthesized or bracketed operands are substituted
+/// too. A `\` not followed by a bound name (or `@`) is left as-is.
+fn substitute_params(
+    body: &[Spanned<Token>],
+    bindings: &[(String, Vec<Spanned<Token>>)],
+    counter: usize,
+) -> Vec<Spanned<Token>> {
+    let mut out = Vec::with_capacity(body.len());
+    let mut i = 0;
+
+    while i < body.len() {
+        match body[i].body() {
+            Token::Sigil(s) if s == "\\" => match body.get(i + 1).map(Spanned::body) {
+                Some(Token::Identifier(name)) => {
+                    match bindings.iter().find(|(n, _)| n == name) {
+                        Some((_, value)) => out.extend(value.iter().cloned()),
+                        None => {
+                            out.push(body[i].clone());
+                            out.push(body[i + 1].clone());
+                        }
+                    }
+                    i += 2;
+                }
+                Some(Token::Sigil(s)) if s == "@" => {
+                    out.push(Spanned::new(
+                        Token::IntegerLiteral(counter as u128),
+                        *body[i].span(),
+                    ));
+                    i += 2;
+                }
+                _ => {
+                    out.push(body[i].clone());
+                    i += 1;
+                }
+            },
+            Token::Group(open, inner) => {
+                let substituted = substitute_params(inner, bindings, counter);
+                out.push(Spanned::new(Token::Group(*open, substituted), *body[i].span()));
+                i += 1;
+            }
+            _ => {
+                out.push(body[i].clone());
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+fn is_statement_start(out: &[Spanned<Token>]) -> bool {
+    match out.last().map(Spanned::body) {
+        None => true,
+        Some(Token::LineTerminator) => true,
+        Some(Token::Sigil(s)) if s == ":" => true,
+        _ => false,
+    }
+}
+
+/// Expands every `.macro`/`.endm` (or `.endmacro`) definition, `.rept`/
+/// `.endr` repeat block, `.irp`/`.irpc`/`.endr` iteration block, and call to
+/// a macro once defined, the same kind of preprocessing pass
+/// [`expand_includes`] runs for `.include` -- `Assembler` never sees any of
+/// these directives. A macro call is only recognized where a mnemonic
+/// could appear (the start of a statement, or right after a label's `:`),
+/// so a macro name used as an ordinary symbol elsewhere is left alone.
+fn expand_macros(
+    tokens: Vec<Spanned<Token>>,
+    macros: &mut HashMap<String, MacroDef>,
+    counter: &mut usize,
+    depth: usize,
+) -> Vec<Spanned<Token>> {
+    if depth > MAX_MACRO_EXPANSION_DEPTH {
+        eprintln!("Macro expansion nested too deeply (possible recursive macro)");
+        std::process::exit(1);
+    }
+
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut iter = tokens.into_iter().peekable();
+
+    while let Some(tok) = iter.next() {
+        match tok.body() {
+            Token::Identifier(id) if id == ".macro" => {
+                let name = expect_identifier(&mut iter, ".macro");
+                let params = parse_macro_params(&mut iter);
+                let body = collect_block_body(&mut iter, &[".endm", ".endmacro"], &[".macro"]);
+                macros.insert(name, MacroDef { params, body });
+            }
+            Token::Identifier(id) if id == ".endm" || id == ".endmacro" => {
+                eprintln!("{} without a matching .macro", id);
+                std::process::exit(1);
+            }
+            Token::Identifier(id) if id == ".rept" => {
+                let count = expect_rept_count(&mut iter);
+                let body = collect_block_body(&mut iter, &[".endr"], &[".rept", ".irp", ".irpc"]);
+                for _ in 0..count {
+                    out.extend(expand_macros(body.clone(), macros, counter, depth + 1));
+                }
+            }
+            Token::Identifier(id) if id == ".irp" => {
+                let name = expect_identifier(&mut iter, ".irp");
+                let values = parse_macro_params_irp_values(&mut iter);
+                let body = collect_block_body(&mut iter, &[".endr"], &[".rept", ".irp", ".irpc"]);
+                for value in values {
+                    let bindings = [(name.clone(), value)];
+                    let substituted = substitute_params(&body, &bindings, *counter);
+                    out.extend(expand_macros(substituted, macros, counter, depth + 1));
+                }
+            }
+            Token::Identifier(id) if id == ".irpc" => {
+                let name = expect_identifier(&mut iter, ".irpc");
+                let text = parse_irpc_text(&mut iter);
+                let body = collect_block_body(&mut iter, &[".endr"], &[".rept", ".irp", ".irpc"]);
+                for ch in text.chars() {
+                    let value = vec![Spanned::new(
+                        Token::Identifier(ch.to_string()),
+                        Span::synthetic(),
+                    )];
+                    let bindings = [(name.clone(), value)];
+                    let substituted = substitute_params(&body, &bindings, *counter);
+                    out.extend(expand_macros(substituted, macros, counter, depth + 1));
+                }
+            }
+            Token::Identifier(id) if id == ".endr" => {
+                eprintln!(".endr without a matching .rept/.irp/.irpc");
+                std::process::exit(1);
+            }
+            Token::Identifier(id) if macros.contains_key(id) && is_statement_start(&out) => {
+                let name = id.clone();
+                let args = parse_macro_args(&mut iter);
+                let def = &macros[&name];
+                let bindings = bind_macro_args(&def.params, args, &name);
+                *counter += 1;
+                let substituted = substitute_params(&def.body, &bindings, *counter);
+                out.extend(expand_macros(substituted, macros, counter, depth + 1));
+            }
+            _ => out.push(tok),
+        }
+    }
+
+    out
+}
+
+/// Parses `.irp name, value, value, ...`'s comma-separated value list,
+/// which runs to the end of the line; `name` has already been consumed.
+fn parse_macro_params_irp_values(iter: &mut TokIter) -> Vec<Vec<Spanned<Token>>> {
+    if matches!(iter.peek().map(Spanned::body), Some(Token::Sigil(s)) if s == ",") {
+        iter.next();
+    }
+
+    let mut values = Vec::new();
+    loop {
+        let mut value = Vec::new();
+        loop {
+            match iter.peek().map(Spanned::body) {
+                None | Some(Token::LineTerminator) => break,
+                Some(Token::Sigil(s)) if s == "," => break,
+                _ => value.push(iter.next().unwrap()),
+            }
+        }
+        values.push(value);
+
+        match iter.peek().map(Spanned::body) {
+            Some(Token::Sigil(s)) if s == "," => {
+                iter.next();
+            }
+            _ => break,
+        }
+    }
+
+    if matches!(iter.peek().map(Spanned::body), Some(Token::LineTerminator)) {
+        iter.next();
+    }
+
+    values
+}
+
+/// Parses `.irpc name, text`'s source of characters to iterate over;
+/// `name` has already been consumed.
+fn parse_irpc_text(iter: &mut TokIter) -> String {
+    if matches!(iter.peek().map(Spanned::body), Some(Token::Sigil(s)) if s == ",") {
+        iter.next();
+    }
+
+    let text = match iter.next() {
+        Some(tok) => match tok.into_inner() {
+            Token::StringLiteral(s) => s,
+            Token::Identifier(s) => s,
+            tok => {
+                eprintln!("Expected a string or identifier after .irpc, got {:?}", tok);
+                std::process::exit(1)
+            }
+        },
+        None => {
+            eprintln!("Expected a string or identifier after .irpc, got end of input");
+            std::process::exit(1)
+        }
+    };
+
+    if matches!(iter.peek().map(Spanned::body), Some(Token::LineTerminator)) {
+        iter.next();
+    }
+
+    text
+}
+
+/// True if `tok` is exactly one column wide with nothing consumed before it
+/// on the same row. [`Span::begin`] always equals the previous token's
+/// `end` regardless of any gap between them -- the lexer folds a skipped
+/// gap into the *following* token's span rather than moving that token's
+/// start -- so the only way to tell "no whitespace before this token" from
+/// "whitespace before this token" is to check the token's own span width,
+/// which is 1 for a single-character token (`:`, a bare `b`/`f`) only when
+/// nothing was skipped to reach it.
+fn is_tight_single_char(tok: &Spanned<Token>) -> bool {
+    let span = tok.span();
+    span.begin().row() == span.end().row() && span.end().col() - span.begin().col() == 1
+}
+
+/// Rewrites GNU-`as`-style numeric local labels -- `1:` to define, `1b`/`1f`
+/// to reference the nearest preceding/following definition of `1` -- into
+/// ordinary, synthesized `.L`-prefixed symbols the rest of the assembler
+/// already knows how to define and reference. `N:`/`Nb`/`Nf` only count as
+/// such when `N` and the following `:`/`b`/`f` are directly adjacent (see
+/// [`is_tight_single_char`]); `1 b`, with a space, is left alone as the
+/// integer literal `1` followed by the unrelated identifier `b`.
+///
+/// This has to run over the fully expanded token stream, after
+/// [`expand_includes`] and [`expand_macros`], since a forward reference
+/// (`Nf`) needs to see every definition an `.include` or macro expansion
+/// could contribute before it can tell which one is nearest.
+fn expand_numeric_labels(tokens: Vec<Spanned<Token>>) -> Vec<Spanned<Token>> {
+    // Every `N:` definition's index into `tokens` and its digit value, in
+    // the order they appear in the source.
+    let mut defs: Vec<(usize, u128)> = Vec::new();
+    for i in 0..tokens.len().saturating_sub(1) {
+        if let (Token::IntegerLiteral(n), Token::Sigil(s)) = (tokens[i].body(), tokens[i + 1].body())
+        {
+            if s == ":" && is_tight_single_char(&tokens[i + 1]) {
+                defs.push((i, *n));
+            }
+        }
+    }
+
+    // The Kth (0-based) definition of digit `n` becomes `.Lnumeric_<n>_<k>`;
+    // the counter always grows monotonically per `n`, so the synthesized
+    // names are unique among themselves. A user-written label spelled the
+    // same way is not checked for here.
+    let mut next_index = HashMap::<u128, usize>::new();
+    let def_names: HashMap<usize, String> = defs
+        .iter()
+        .map(|&(i, n)| {
+            let k = next_index.entry(n).or_insert(0);
+            let name = format!(".Lnumeric_{}_{}", n, *k);
+            *k += 1;
+            (i, name)
+        })
+        .collect();
+
+    let resolve_ref = |ref_pos: usize, n: u128, forward: bool| -> String {
+        let found = if forward {
+            defs.iter()
+                .filter(|&&(i, dn)| dn == n && i > ref_pos)
+                .min_by_key(|&&(i, _)| i)
+        } else {
+            defs.iter()
+                .filter(|&&(i, dn)| dn == n && i < ref_pos)
+                .max_by_key(|&&(i, _)| i)
+        };
+
+        found.map(|&(i, _)| def_names[&i].clone()).unwrap_or_else(|| {
+            eprintln!(
+                "No {} numeric label {}{} found",
+                if forward { "forward" } else { "backward" },
+                n,
+                if forward { "f" } else { "b" }
+            );
+            std::process::exit(1)
+        })
+    };
+
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some(name) = def_names.get(&i) {
+            // `N:` -- keep the `:`, but rewrite the label to its synthetic name.
+            out.push(Spanned::new(Token::Identifier(name.clone()), Span::synthetic()));
+            out.push(tokens[i + 1].clone());
+            i += 2;
+            continue;
+        }
+
+        let reference = match (tokens[i].body(), tokens.get(i + 1).map(Spanned::body)) {
+            (Token::IntegerLiteral(n), Some(Token::Identifier(id)))
+                if (id == "b" || id == "f") && is_tight_single_char(&tokens[i + 1]) =>
+            {
+                Some((*n, id == "f"))
+            }
+            _ => None,
+        };
+
+        match reference {
+            Some((n, forward)) => {
+                let name = resolve_ref(i, n, forward);
+                out.push(Spanned::new(Token::Identifier(name), Span::synthetic()));
+                i += 2;
+            }
+            None => {
+                out.push(tokens[i].clone());
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
 pub struct Data {
-    binfile: BinaryFile<'static>,
     sections: HashMap<String, Rc<RefCell<Section>>>,
     curr_section: String,
     syms: HashMap<String, (String, usize)>,
+    /// Symbols whose value is a fixed constant rather than a position in a
+    /// section -- currently only `.incbin`'s `_size` helper symbol.
+    ///
+    /// These come out as [`binfmt::sym::Symbol::new_undef`] with the value
+    /// patched in, since [`binfmt::sym::Symbol`] has no `SHN_ABS`-style
+    /// "absolute, no section" representation yet; a reader that takes the
+    /// symbol's *address* as the value (the usual `_binary_*_size` trick)
+    /// still gets the right answer, even though the symbol technically
+    /// reads as undefined.
+    abs_syms: HashMap<String, u64>,
     global_syms: HashSet<String>,
     weak_syms: HashSet<String>,
+    /// Symbols named in a `.hidden` directive. `binfmt::sym::Symbol` has
+    /// no visibility field yet (only [`SymbolKind`]'s binding), so these
+    /// can't be encoded into the object file -- tracked here so `main`
+    /// can at least warn that they were requested, rather than silently
+    /// dropping the directive.
+    hidden_syms: HashSet<String>,
+    sym_types: HashMap<String, SymbolType>,
+    sym_sizes: HashMap<String, u64>,
+    /// Constants defined by `.equ`/`.set`, folded eagerly at the point of
+    /// definition (see [`eval_const`]) so a later `.equ`/`.set` can refer
+    /// to an earlier one. Forward references to section-relative labels
+    /// aren't supported, since nothing in this assembler defers constant
+    /// folding past the directive that needs the value.
+    consts: HashMap<String, u64>,
+    /// The CFI unwind-info region opened by `.cfi_startproc`, if one is
+    /// currently open; closed (and written out to `.eh_frame`) by the
+    /// matching `.cfi_endproc`.
+    cfi_proc: Option<CfiProc>,
+    /// Byte offset of this file's single shared CIE within `.eh_frame`,
+    /// filled in the first time `.cfi_startproc` runs.
+    cfi_cie_offset: Option<u64>,
+    /// Incremented by every `.cfi_startproc`, so each procedure's synthetic
+    /// start symbol gets a name no user-written label could collide with.
+    cfi_proc_counter: u64,
+    /// Source file names recorded by `.file N "name"` (or `.file "name"`,
+    /// treated as `N == 1`), keyed by the DWARF file-table index `N` --
+    /// consumed by [`build_debug_line`] once assembly finishes.
+    dbg_files: BTreeMap<u64, String>,
+    /// One line-number program sequence per contiguous run of `.loc`
+    /// directives issued without the current section changing in between.
+    dbg_seqs: Vec<DbgSeq>,
+    /// Which section the currently-open `dbg_seqs` entry belongs to, so
+    /// the next `.loc` knows whether it continues that sequence or needs
+    /// to start a new one. `None` until the first `.loc`.
+    dbg_cur_section: Option<String>,
+    /// Incremented by every new `.loc` sequence, so each one's synthetic
+    /// anchor symbol gets a name no user-written label could collide with.
+    dbg_anchor_counter: u64,
+}
+
+/// One row `.loc` adds to its sequence's line-number program: `disp` is
+/// relative to the sequence's anchor symbol (see [`DbgSeq`]), since the
+/// absolute address isn't known until link time.
+struct DbgLocRow {
+    disp: i64,
+    file: u64,
+    line: u64,
+    column: u64,
+    is_stmt: bool,
+}
+
+/// One `.debug_line` sequence: a contiguous run of code, in one section,
+/// covered by a single `DW_LNE_set_address` relocated against
+/// `anchor_symbol` (a synthetic local label created at the first `.loc`
+/// that opened this sequence) followed by ordinary address/line-advancing
+/// opcodes for the rest of its rows.
+struct DbgSeq {
+    anchor_symbol: String,
+    anchor_offset: u64,
+    rows: Vec<DbgLocRow>,
+}
+
+/// One statement's worth of `-a`/`--listing` output: the source line it
+/// came from, the section and offset its first byte (if any) landed at,
+/// and the bytes it actually emitted there.
+///
+/// Recorded by `main`'s assembly loop around each [`Assembler::assemble_instr`]
+/// call rather than inside [`Callbacks::handle_directive`] itself, since every
+/// directive and instruction already funnels through that one call site --
+/// no per-directive instrumentation is needed to cover all of them.
+///
+/// `assemble_instr` swallows any `label:` tokens leading a statement before
+/// returning, so a label written on its own line (rather than sharing a
+/// line with the instruction it precedes) makes that instruction's entry
+/// report the label's line number instead of its own.
+struct ListingEntry {
+    line: u32,
+    section: String,
+    offset: u64,
+    bytes: Vec<u8>,
 }
 
-pub struct SharedSection(Rc<RefCell<Section>>);
+/// Writes a GNU-`as`-`-al`-style side-by-side listing of `entries` to `out`:
+/// source line number, the section offset and encoded bytes (if the
+/// statement wrote any, and stayed within one section), and the original
+/// source text for that line (looked up in `source_lines`, 1-indexed to
+/// match `line`; blank if the line came from a macro expansion or other
+/// synthetic span with no corresponding line of real source).
+///
+/// This covers the common case GNU `as`'s listing is actually used for --
+/// eyeballing encodings on a new target -- rather than its full `-a[cdhlmns]`
+/// suboption set (conditional/macro-expansion visibility toggles, high-level
+/// source interleaving, symbol table dump); those aren't implemented.
+fn write_listing(
+    out: &mut dyn Write,
+    entries: &[ListingEntry],
+    source_lines: &[String],
+) -> std::io::Result<()> {
+    writeln!(out, "   line  section          offset  bytes")?;
+    for entry in entries {
+        let source = source_lines
+            .get(entry.line.saturating_sub(1) as usize)
+            .map(String::as_str)
+            .unwrap_or("");
+
+        let bytes = entry
+            .bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        writeln!(
+            out,
+            "{:6}  {:<15} {:6x}  {:<24}  {}",
+            entry.line, entry.section, entry.offset, bytes, source
+        )?;
+    }
+    Ok(())
+}
+
+pub struct SharedSection(Rc<RefCell<Section>>);
+
+impl Write for SharedSection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+}
+
+impl InsnWrite for SharedSection {
+    fn offset(&self) -> usize {
+        self.0.borrow().offset()
+    }
+
+    fn write_addr(
+        &mut self,
+        size: usize,
+        addr: arch_ops::traits::Address,
+        rel: bool,
+    ) -> std::io::Result<()> {
+        self.0.borrow_mut().write_addr(size, addr, rel)
+    }
+
+    fn write_reloc(&mut self, reloc: arch_ops::traits::Reloc) -> std::io::Result<()> {
+        self.0.borrow_mut().write_reloc(reloc)
+    }
+}
+
+/// Pads the current section up to the next multiple of `align` bytes
+/// (which must be a power of two, for the bit-mask trick below to be
+/// correct) with `fill`. Shared by `.align`, `.balign`, and `.p2align`
+/// once each has turned its own argument into a power-of-two byte count.
+fn pad_to_align(asm: &mut Assembler, align: usize, fill: u8) {
+    let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+    let mut sec = data.sections[&data.curr_section].borrow_mut();
+
+    if sec.align < align {
+        sec.align = align;
+    }
+
+    let off = sec.offset();
+    let nlen = (off + (align - 1)) & !(align - 1);
+    sec.content.resize(nlen, fill);
+}
+
+/// Folds `expr` against `consts` (populated by prior `.equ`/`.set`
+/// directives) into a plain value, or returns `None` if it refers to
+/// anything else -- a section-relative label, for instance -- that only
+/// the linker (via relocations), not this assembler, could resolve.
+fn eval_const(expr: &Expression, consts: &HashMap<String, u64>) -> Option<u64> {
+    match expr {
+        Expression::Integer(i) => Some(*i as u64),
+        Expression::Symbol(s) => consts.get(s).copied(),
+        Expression::Group(_, inner) => eval_const(inner, consts),
+        Expression::Unary(op, inner) => {
+            let v = eval_const(inner, consts)?;
+            Some(match op {
+                UnaryOp::Neg => v.wrapping_neg(),
+                UnaryOp::Umn => !v,
+            })
+        }
+        Expression::Binary(op, lhs, rhs) => {
+            let l = eval_const(lhs, consts)?;
+            let r = eval_const(rhs, consts)?;
+            Some(match op {
+                BinaryOp::Add => l.wrapping_add(r),
+                BinaryOp::Sub => l.wrapping_sub(r),
+                BinaryOp::Mul => l.wrapping_mul(r),
+                BinaryOp::Div => l.checked_div(r)?,
+                BinaryOp::Mod => l.checked_rem(r)?,
+                BinaryOp::Lsh => l << r,
+                BinaryOp::Rsh => l >> r,
+                BinaryOp::And => l & r,
+                BinaryOp::Or => l | r,
+                BinaryOp::Xor => l ^ r,
+                BinaryOp::CmpEq => (l == r) as u64,
+                BinaryOp::CmpNe => (l != r) as u64,
+                BinaryOp::CmpLt => (l < r) as u64,
+                BinaryOp::CmpGt => (l > r) as u64,
+                BinaryOp::CmpLe => (l <= r) as u64,
+                BinaryOp::CmpGe => (l >= r) as u64,
+                BinaryOp::BoolAnd => ((l != 0) && (r != 0)) as u64,
+                BinaryOp::BoolOr => ((l != 0) || (r != 0)) as u64,
+            })
+        }
+    }
+}
+
+/// Appends `value` to `out` as an unsigned LEB128 integer, the variable-width
+/// encoding DWARF uses for every CFI operand that isn't a fixed-size field.
+fn write_uleb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        } else {
+            out.push(byte | 0x80);
+        }
+    }
+}
+
+/// Appends `value` to `out` as a signed LEB128 integer (DWARF's
+/// `data_alignment_factor` and every offset derived from it).
+fn write_sleb128(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let sign_bit_set = byte & 0x40 != 0;
+        if (value == 0 && !sign_bit_set) || (value == -1 && sign_bit_set) {
+            out.push(byte);
+            break;
+        } else {
+            out.push(byte | 0x80);
+        }
+    }
+}
+
+// DWARF Call Frame Instruction opcodes used by the `.cfi_*` directives
+// below. Only the subset needed to encode raw-register-number CFI (no
+// register-name resolution, no `DW_CFA_expression`) is named here.
+const DW_CFA_NOP: u8 = 0x00;
+const DW_CFA_ADVANCE_LOC1: u8 = 0x02;
+const DW_CFA_ADVANCE_LOC2: u8 = 0x03;
+const DW_CFA_ADVANCE_LOC4: u8 = 0x04;
+const DW_CFA_OFFSET_EXTENDED: u8 = 0x05;
+const DW_CFA_RESTORE_EXTENDED: u8 = 0x06;
+const DW_CFA_UNDEFINED: u8 = 0x07;
+const DW_CFA_SAME_VALUE: u8 = 0x08;
+const DW_CFA_REMEMBER_STATE: u8 = 0x0a;
+const DW_CFA_RESTORE_STATE: u8 = 0x0b;
+const DW_CFA_DEF_CFA: u8 = 0x0c;
+const DW_CFA_DEF_CFA_REGISTER: u8 = 0x0d;
+const DW_CFA_DEF_CFA_OFFSET: u8 = 0x0e;
+
+/// State for the CFI unwind-info region opened by `.cfi_startproc` and
+/// closed by the matching `.cfi_endproc`. Only one can be open at a time --
+/// GNU `as` doesn't allow nesting them either.
+struct CfiProc {
+    /// Synthetic local symbol marking the procedure's first byte, created
+    /// the same way an ordinary label would be (see
+    /// [`Callbacks::create_symbol_now`]) so the FDE's `pc_begin` field can
+    /// reference it as an ordinary relocation.
+    start_symbol: String,
+    section: String,
+    start_offset: u64,
+    /// Running CFA-relative-to-register offset, tracked so
+    /// `.cfi_adjust_cfa_offset` and `.cfi_rel_offset` have something to
+    /// adjust/subtract against.
+    cfa_offset: i64,
+    /// Byte offset (within `section`) of the last instruction that was
+    /// encoded, so the next one can be preceded by the right
+    /// `DW_CFA_advance_loc*`.
+    last_offset: u64,
+    /// Raw DWARF CFA opcode bytes accumulated so far, replayed verbatim
+    /// into the FDE at `.cfi_endproc`.
+    instrs: Vec<u8>,
+}
+
+/// Appends a `DW_CFA_advance_loc*` covering the gap between `proc`'s last
+/// recorded instruction and `offset`, if any, and updates `last_offset`.
+/// Every other `.cfi_*` directive below calls this before appending its own
+/// opcode, so each instruction in the FDE ends up tagged with the address it
+/// takes effect at.
+fn cfi_advance_loc(proc: &mut CfiProc, offset: u64) {
+    let delta = offset - proc.last_offset;
+    if delta == 0 {
+        return;
+    }
+    if delta <= 0x3f {
+        proc.instrs.push(0x40 | delta as u8);
+    } else if delta <= 0xff {
+        proc.instrs.push(DW_CFA_ADVANCE_LOC1);
+        proc.instrs.push(delta as u8);
+    } else if delta <= 0xffff {
+        proc.instrs.push(DW_CFA_ADVANCE_LOC2);
+        proc.instrs.extend_from_slice(&(delta as u16).to_le_bytes());
+    } else {
+        proc.instrs.push(DW_CFA_ADVANCE_LOC4);
+        proc.instrs.extend_from_slice(&(delta as u32).to_le_bytes());
+    }
+    proc.last_offset = offset;
+}
+
+/// Pads `buf` with `DW_CFA_nop`/zero bytes up to the next multiple of
+/// `addr_size`. Since the CIE is always the first thing written into a
+/// freshly-created `.eh_frame` section, and every CIE/FDE pads itself this
+/// way, `buf.len()` staying address-size-aligned after each record is an
+/// invariant every later record can rely on.
+fn pad_cfi_to_align(buf: &mut Vec<u8>, addr_size: usize) {
+    while buf.len() % addr_size != 0 {
+        buf.push(DW_CFA_NOP);
+    }
+}
+
+/// Writes the single CIE this file's CFI directives share -- one CIE per
+/// object, since nothing here needs per-procedure personality routines or
+/// augmentation data. Returns the byte offset the CIE starts at, for the
+/// FDE's `CIE_pointer` field to reference later.
+///
+/// Deliberately omits the `'z'`-augmented pointer-encoding scheme real
+/// compilers use to make `pc_begin` PC-relative: this assembler's
+/// relocation model already has a clean way to express an absolute
+/// relocation against a symbol (see `write_int_list`'s `symbol_with_const_disp`),
+/// so there's no need for the augmentation-length byte and `R`-encoding byte
+/// that scheme requires.
+fn emit_cie(
+    eh_frame: &mut Section,
+    return_address_register: u8,
+    data_alignment_factor: i64,
+    addr_size: usize,
+) -> std::io::Result<u64> {
+    let start = eh_frame.content.len() as u64;
+
+    eh_frame.content.extend_from_slice(&0u32.to_le_bytes()); // length (patched below)
+    eh_frame.content.extend_from_slice(&0u32.to_le_bytes()); // CIE_id == 0
+    eh_frame.content.push(1); // version
+    eh_frame.content.push(0); // augmentation string: empty
+    write_uleb128(&mut eh_frame.content, 1); // code_alignment_factor
+    write_sleb128(&mut eh_frame.content, data_alignment_factor);
+    write_uleb128(&mut eh_frame.content, return_address_register as u64);
+    // No initial instructions: a `.cfi_startproc` sequence is expected to
+    // establish the initial CFA itself (real compiler output always does,
+    // as its very first directive).
+
+    pad_cfi_to_align(&mut eh_frame.content, addr_size);
+
+    let len = eh_frame.content.len() as u64 - start - 4;
+    eh_frame.content[start as usize..start as usize + 4]
+        .copy_from_slice(&(len as u32).to_le_bytes());
+
+    Ok(start)
+}
+
+/// Writes one procedure's FDE: its `pc_begin`/`pc_range`, a pointer back to
+/// the shared CIE, and the accumulated CFA instruction stream.
+fn emit_fde(
+    eh_frame: &mut Section,
+    cie_offset: u64,
+    addr_size: usize,
+    start_symbol: &str,
+    pc_range: u64,
+    instrs: &[u8],
+) -> std::io::Result<()> {
+    let start = eh_frame.content.len() as u64;
+
+    eh_frame.content.extend_from_slice(&0u32.to_le_bytes()); // length (patched below)
+
+    let cie_pointer_field_pos = eh_frame.content.len() as u64;
+    eh_frame
+        .content
+        .extend_from_slice(&((cie_pointer_field_pos - cie_offset) as u32).to_le_bytes());
+
+    eh_frame.write_addr(
+        addr_size * 8,
+        arch_ops::traits::Address::Symbol {
+            name: start_symbol.to_string(),
+            disp: 0,
+        },
+        false,
+    )?;
+
+    let range_bytes = pc_range.to_le_bytes();
+    eh_frame.content.extend_from_slice(&range_bytes[..addr_size]);
+
+    eh_frame.content.extend_from_slice(instrs);
+
+    pad_cfi_to_align(&mut eh_frame.content, addr_size);
+
+    let len = eh_frame.content.len() as u64 - start - 4;
+    eh_frame.content[start as usize..start as usize + 4]
+        .copy_from_slice(&(len as u32).to_le_bytes());
+
+    Ok(())
+}
+
+/// Parses a single integer argument, shared by every `.cfi_*`/`.file`/
+/// `.loc` directive below -- always a raw DWARF register number, file
+/// index, or plain constant, never a hardware register mnemonic (resolving
+/// those would mean teaching all four `TargetMachine`s their own DWARF
+/// register numbering, which is out of proportion for this directive set).
+fn parse_int_arg(asm: &mut Assembler) -> i64 {
+    let expr = lcas_core::expr::parse_expression(asm.iter());
+    match asm.eval_expr(expr) {
+        Expression::Integer(i) => i as i64,
+        expr => panic!("Invalid integer expression for directive: {:?}", expr),
+    }
+}
+
+/// A partially-evaluated `.uleb128`/`.sleb128`/`.dc.a` operand: `value` is
+/// the running integer, and `section` (if set) names the one section
+/// `value` is still relative to. Adding/subtracting two [`Loc`]s only makes
+/// sense if `section` cancels out to `None` -- either both sides were
+/// already plain constants, or a `Sub` pairs two symbols in the *same*
+/// section (a label difference, e.g. `.Lend - .Lstart`) -- anything else
+/// would need an actual relocation, which none of these directives have a
+/// width/encoding for.
+struct Loc {
+    section: Option<String>,
+    value: i128,
+}
+
+/// Evaluates `expr` for `.uleb128`/`.sleb128`/`.dc.a`, additionally folding
+/// a same-section label difference into the constant distance between the
+/// two symbols -- the one shape [`crate::as_state`]'s own `fold_expr`
+/// deliberately leaves unfolded, since it has no access to where symbols
+/// actually land. Panics on anything else involving a symbol, the same way
+/// every other directive here panics on an expression shape it can't
+/// represent.
+fn eval_loc(asm: &mut Assembler, expr: Expression) -> Loc {
+    match expr {
+        Expression::Integer(i) => Loc {
+            section: None,
+            value: i as i128,
+        },
+        Expression::Symbol(name) => {
+            let data = asm.as_data().downcast_ref::<Data>().unwrap();
+            let (section, offset) = data.syms.get(&name).unwrap_or_else(|| {
+                panic!("Undefined symbol `{}` in label-difference expression", name)
+            });
+            Loc {
+                section: Some(section.clone()),
+                value: *offset as i128,
+            }
+        }
+        Expression::Unary(UnaryOp::Neg, inner) => {
+            let inner = eval_loc(asm, *inner);
+            if inner.section.is_some() {
+                panic!("Cannot negate a section-relative symbol in a label-difference expression");
+            }
+            Loc {
+                section: None,
+                value: -inner.value,
+            }
+        }
+        Expression::Binary(op @ (BinaryOp::Add | BinaryOp::Sub), lhs, rhs) => {
+            let lhs = eval_loc(asm, *lhs);
+            let rhs = eval_loc(asm, *rhs);
+
+            let section = match (op, lhs.section, rhs.section) {
+                (_, None, None) => None,
+                (BinaryOp::Add, Some(s), None) | (BinaryOp::Add, None, Some(s)) => Some(s),
+                (BinaryOp::Sub, Some(s), None) => Some(s),
+                (BinaryOp::Sub, Some(a), Some(b)) if a == b => None,
+                _ => panic!(
+                    "Unsupported symbol combination in label-difference expression (not a same-section label difference)"
+                ),
+            };
+
+            let value = if op == BinaryOp::Add {
+                lhs.value + rhs.value
+            } else {
+                lhs.value - rhs.value
+            };
+
+            Loc { section, value }
+        }
+        expr => panic!("Unsupported expression in label-difference context: {:?}", expr),
+    }
+}
+
+/// Evaluates `expr` the way [`eval_loc`] does, then requires the result to
+/// have fully cancelled out to a plain constant -- used by directives that
+/// write a fixed-width or LEB128 integer with no relocation fallback.
+fn eval_loc_const(asm: &mut Assembler, expr: Expression) -> i128 {
+    let loc = eval_loc(asm, expr);
+    if loc.section.is_some() {
+        panic!("Expression must resolve to a constant, not a bare section-relative symbol");
+    }
+    loc.value
+}
+
+/// Consumes the whitespace-separated list of identifiers `.global`/`.weak`/
+/// `.local`/`.hidden` all take, calling `f` with each one in turn, until a
+/// line terminator ends the directive.
+fn parse_identifier_list(
+    asm: &mut Assembler,
+    dir: &str,
+    mut f: impl FnMut(&mut Assembler, String),
+) -> std::io::Result<()> {
+    while let Some(spanned) = asm.iter().next() {
+        match spanned.into_inner() {
+            Token::Identifier(id) => f(asm, id),
+            Token::LineTerminator => break,
+            tok => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "Unexpected token for {} directive: {:?}, expected an identifier",
+                        dir, tok
+                    ),
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Consumes a single identifier token, the shape of the first argument to
+/// `.type`/`.size`/`.comm`/`.lcomm`/`.equ`/`.set`. `what` names the argument
+/// for the error message (e.g. `"a symbol name"`).
+fn parse_identifier_arg(asm: &mut Assembler, dir: &str, what: &str) -> std::io::Result<String> {
+    match asm.iter().next() {
+        Some(spanned) => match spanned.into_inner() {
+            Token::Identifier(id) => Ok(id),
+            tok => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unexpected token for {} directive: {:?}, expected {}", dir, tok, what),
+            )),
+        },
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Expected {} for {} directive, found end of input", what, dir),
+        )),
+    }
+}
+
+/// Consumes the `,` separating the arguments to `.type`/`.size`/`.comm`/
+/// `.lcomm`/`.equ`/`.set`.
+fn expect_comma(asm: &mut Assembler, dir: &str) -> std::io::Result<()> {
+    match asm.iter().next() {
+        Some(spanned) => match spanned.into_inner() {
+            Token::Sigil(s) if s == "," => Ok(()),
+            tok => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unexpected token for {} directive: {:?}, expected `,`", dir, tok),
+            )),
+        },
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Expected `,` for {} directive, found end of input", dir),
+        )),
+    }
+}
+
+fn cfi_expect_comma(asm: &mut Assembler, dir: &str) -> std::io::Result<()> {
+    match asm.iter().next() {
+        Some(spanned) => match spanned.into_inner() {
+            Token::Sigil(s) if s == "," => Ok(()),
+            tok => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unexpected token for {} directive: {:?}, expected `,`", dir, tok),
+            )),
+        },
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Expected `,` for {} directive, found end of input", dir),
+        )),
+    }
+}
+
+/// Fetches the in-progress `.cfi_startproc`/`.cfi_endproc` state for a
+/// `.cfi_*` directive that only makes sense inside such a pair.
+fn cfi_require_proc<'a>(data: &'a mut Data, dir: &str) -> std::io::Result<&'a mut CfiProc> {
+    data.cfi_proc.as_mut().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} outside of a .cfi_startproc/.cfi_endproc pair", dir),
+        )
+    })
+}
+
+/// Appends a `DW_CFA_offset`/`DW_CFA_offset_extended` recording that
+/// `register` is saved at `raw_offset` bytes from the CFA, shared by
+/// `.cfi_offset` and `.cfi_rel_offset` once each has turned its own operand
+/// into a CFA-relative offset.
+fn cfi_emit_offset(proc: &mut CfiProc, offset: u64, register: u64, raw_offset: i64, data_alignment_factor: i64) {
+    cfi_advance_loc(proc, offset);
+    if register < 64 {
+        proc.instrs.push(0x80 | register as u8);
+    } else {
+        proc.instrs.push(DW_CFA_OFFSET_EXTENDED);
+        write_uleb128(&mut proc.instrs, register);
+    }
+    write_sleb128(&mut proc.instrs, raw_offset / data_alignment_factor);
+}
+
+// DWARF line-number header constants matching GCC's own defaults -- and,
+// not coincidentally, the exact `standard_opcode_lengths` table
+// `dbg-info::dwarf5`'s own parser tests hard-code -- so a `.debug_line`
+// section this assembler emits round-trips through this workspace's own
+// reader, not just an external `addr2line`.
+const DWARF_LINE_BASE: i8 = -5;
+const DWARF_LINE_RANGE: u8 = 14;
+const DWARF_OPCODE_BASE: u8 = 13;
+const DWARF_STANDARD_OPCODE_LENGTHS: [u8; 12] = [0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+
+const DW_LNS_COPY: u8 = 1;
+const DW_LNS_ADVANCE_PC: u8 = 2;
+const DW_LNS_ADVANCE_LINE: u8 = 3;
+const DW_LNS_SET_FILE: u8 = 4;
+const DW_LNS_SET_COLUMN: u8 = 5;
+const DW_LNS_NEGATE_STMT: u8 = 6;
+
+const DW_LNE_END_SEQUENCE: u8 = 1;
+const DW_LNE_SET_ADDRESS: u8 = 2;
+
+/// Builds a full DWARF5 `.debug_line` section body (a single compilation
+/// unit's header, followed by one line-number program sequence per
+/// [`DbgSeq`]) out of everything `.file`/`.loc` recorded.
+///
+/// Each sequence's `DW_LNE_set_address` needs an absolute relocation
+/// against that sequence's anchor symbol, but nothing else in the program
+/// does -- every later row only needs the *difference* in address since
+/// the last one, which is already known at assembly time since it's just
+/// two offsets into the same section. Rather than writing straight into a
+/// [`Section`] the way [`emit_fde`] does, those relocations are returned
+/// alongside the finished byte buffer as `(offset, symbol)` pairs, since
+/// the header's own length (and so where the program actually starts)
+/// isn't known until the whole buffer is laid out.
+fn build_debug_line(
+    files: &BTreeMap<u64, String>,
+    seqs: &[DbgSeq],
+    addr_size: usize,
+) -> (Vec<u8>, Vec<(usize, String)>) {
+    let mut header_rest = vec![
+        1u8,                  // minimum_instruction_length
+        1u8,                  // maximum_operations_per_instruction
+        1u8,                  // default_is_stmt
+        DWARF_LINE_BASE as u8,
+        DWARF_LINE_RANGE,
+        DWARF_OPCODE_BASE,
+    ];
+    header_rest.extend_from_slice(&DWARF_STANDARD_OPCODE_LENGTHS);
+
+    // Directory table: a single entry, "." -- `.file` only ever records a
+    // bare name, never a directory to go with it.
+    header_rest.push(1); // directory_entry_format_count
+    header_rest.push(1); // DW_LNCT_path
+    header_rest.push(0x08); // DW_FORM_string
+    write_uleb128(&mut header_rest, 1); // directories_count
+    header_rest.extend_from_slice(b".\0");
+
+    // File table. DWARF5 reserves slot 0 for the CU's primary source
+    // file; this assembler has no separate notion of that beyond whatever
+    // `.file 1` named, so slot 0 is just a copy of it (the same thing GNU
+    // `as` does). Slots between 1 and the highest `.file` index used that
+    // were never assigned get an empty name.
+    let max_file = files.keys().copied().max().unwrap_or(1).max(1);
+    header_rest.push(2); // file_name_entry_format_count
+    header_rest.push(1); // DW_LNCT_path
+    header_rest.push(0x08); // DW_FORM_string
+    header_rest.push(2); // DW_LNCT_directory_index
+    header_rest.push(0x0f); // DW_FORM_udata
+    write_uleb128(&mut header_rest, max_file + 1); // file_names_count
+
+    let primary = files.get(&1).cloned().unwrap_or_default();
+    header_rest.extend_from_slice(primary.as_bytes());
+    header_rest.push(0);
+    write_uleb128(&mut header_rest, 0);
+    for n in 1..=max_file {
+        let name = files.get(&n).cloned().unwrap_or_default();
+        header_rest.extend_from_slice(name.as_bytes());
+        header_rest.push(0);
+        write_uleb128(&mut header_rest, 0);
+    }
+
+    let header_length = header_rest.len() as u32;
+
+    let mut program = Vec::new();
+    let mut relocs = Vec::new();
+
+    for seq in seqs {
+        let mut line = 1i64;
+        let mut file = 1u64;
+        let mut column = 0u64;
+        let mut is_stmt = true;
+        let mut last_disp = 0i64;
+
+        program.push(0);
+        write_uleb128(&mut program, 1 + addr_size as u64);
+        program.push(DW_LNE_SET_ADDRESS);
+        relocs.push((program.len(), seq.anchor_symbol.clone()));
+        program.resize(program.len() + addr_size, 0);
+
+        for row in &seq.rows {
+            if row.file != file {
+                program.push(DW_LNS_SET_FILE);
+                write_uleb128(&mut program, row.file);
+                file = row.file;
+            }
+            if row.column != column {
+                program.push(DW_LNS_SET_COLUMN);
+                write_uleb128(&mut program, row.column);
+                column = row.column;
+            }
+            if row.is_stmt != is_stmt {
+                program.push(DW_LNS_NEGATE_STMT);
+                is_stmt = row.is_stmt;
+            }
+
+            let delta = row.disp - last_disp;
+            if delta < 0 {
+                panic!(".loc addresses must not go backward within a section");
+            }
+            let addr_advance = delta as u64;
+            let line_advance = row.line as i64 - line;
+            last_disp = row.disp;
+            line = row.line as i64;
+
+            // Try to fold the address/line advance into a single special
+            // opcode; fall back to the standard opcodes for whatever a
+            // special opcode's narrow encoding range can't cover.
+            let adjusted = line_advance - DWARF_LINE_BASE as i64;
+            let opcode = DWARF_OPCODE_BASE as i64 + adjusted + addr_advance as i64 * DWARF_LINE_RANGE as i64;
+            if (0..DWARF_LINE_RANGE as i64).contains(&adjusted) && (DWARF_OPCODE_BASE as i64..=255).contains(&opcode)
+            {
+                program.push(opcode as u8);
+                continue;
+            }
+
+            if addr_advance != 0 {
+                program.push(DW_LNS_ADVANCE_PC);
+                write_uleb128(&mut program, addr_advance);
+            }
+            if line_advance != 0 {
+                program.push(DW_LNS_ADVANCE_LINE);
+                write_sleb128(&mut program, line_advance);
+            }
+            program.push(DW_LNS_COPY);
+        }
+
+        program.push(0);
+        program.push(1);
+        program.push(DW_LNE_END_SEQUENCE);
+    }
+
+    let mut unit = Vec::new();
+    unit.extend_from_slice(&5u16.to_le_bytes()); // version
+    unit.push(addr_size as u8);
+    unit.push(0); // segment_selector_size
+    unit.extend_from_slice(&header_length.to_le_bytes());
+    unit.extend_from_slice(&header_rest);
+    let program_start = unit.len();
+    unit.extend_from_slice(&program);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&(unit.len() as u32).to_le_bytes());
+    out.extend_from_slice(&unit);
+
+    let base = 4 + program_start;
+    let relocs = relocs
+        .into_iter()
+        .map(|(offset, symbol)| (base + offset, symbol))
+        .collect();
+
+    (out, relocs)
+}
+
+pub struct Callbacks;
+
+impl AssemblerCallbacks for Callbacks {
+    fn handle_directive(&self, asm: &mut Assembler, dir: &str) -> std::io::Result<()> {
+        match dir {
+            ".text" | ".rodata" | ".data" | ".bss" => {
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let sect = if let Some(sect) = data.sections.get(dir) {
+                    sect.clone()
+                } else {
+                    let ty = if dir == ".bss" {
+                        SectionType::NoBits
+                    } else {
+                        SectionType::ProgBits
+                    };
+                    let sect = Section {
+                        name: dir.to_string(),
+                        align: asm.machine().def_section_alignment() as usize,
+                        ty,
+                        ..Default::default()
+                    };
+                    let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+
+                    let sect = Rc::new(RefCell::new(sect));
+
+                    data.sections.insert(dir.to_string(), sect.clone());
+
+                    sect
+                };
+
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+
+                data.curr_section = dir.to_string();
+
+                asm.set_output(Box::new(SharedSection(sect)));
+
+                Ok(())
+            }
+            ".section" => {
+                let name = match asm.iter().next() {
+                    Some(spanned) => match spanned.into_inner() {
+                        Token::Identifier(tok) => tok,
+                        tok => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                format!("Invalid token after .section: Expected an identifier {:?}", tok),
+                            ))
+                        }
+                    },
+                    None => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "Expected a section name after .section, found end of input",
+                        ))
+                    }
+                };
+
+                // `.section name, "flags", @type` -- GNU `as`'s flag
+                // letters beyond `a`/`w`/`x` (merge, strings, group, TLS,
+                // ...) have no representation in `SectionFlag` yet, so
+                // they're accepted without error but don't affect the
+                // resulting section.
+                let mut flags = None::<SectionFlags>;
+                let mut ty = None::<SectionType>;
+
+                if matches!(
+                    asm.iter().peek().map(Spanned::body),
+                    Some(Token::Sigil(s)) if s == ","
+                ) {
+                    asm.iter().next();
+                    let lit = match asm.iter().next() {
+                        Some(spanned) => match spanned.into_inner() {
+                            Token::StringLiteral(lit) => lit,
+                            tok => {
+                                return Err(std::io::Error::new(
+                                    std::io::ErrorKind::InvalidData,
+                                    format!(
+                                        "Invalid token after .section name: Expected a flags string {:?}",
+                                        tok
+                                    ),
+                                ))
+                            }
+                        },
+                        None => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                "Expected a flags string after .section name, found end of input",
+                            ))
+                        }
+                    };
+
+                    let mut parsed = SectionFlags::default();
+                    for c in lit.trim_matches('"').chars() {
+                        parsed = match c {
+                            'a' => parsed | SectionFlag::Alloc,
+                            'w' => parsed | SectionFlag::Writable,
+                            'x' => parsed | SectionFlag::Executable,
+                            _ => parsed,
+                        };
+                    }
+                    flags = Some(parsed);
+
+                    if matches!(
+                        asm.iter().peek().map(Spanned::body),
+                        Some(Token::Sigil(s)) if s == ","
+                    ) {
+                        asm.iter().next();
+                        if matches!(
+                            asm.iter().peek().map(Spanned::body),
+                            Some(Token::Sigil(s)) if s == "@"
+                        ) {
+                            asm.iter().next();
+                        }
+                        ty = Some(match asm.iter().next() {
+                            Some(spanned) => match spanned.into_inner() {
+                                Token::Identifier(kind) => match &*kind {
+                                    "progbits" => SectionType::ProgBits,
+                                    "nobits" => SectionType::NoBits,
+                                    "note" => SectionType::Note,
+                                    "init_array" => SectionType::InitArray,
+                                    "fini_array" => SectionType::FiniArray,
+                                    "preinit_array" => SectionType::PreinitArray,
+                                    kind => {
+                                        return Err(std::io::Error::new(
+                                            std::io::ErrorKind::InvalidData,
+                                            format!("Unrecognized .section type {}", kind),
+                                        ))
+                                    }
+                                },
+                                tok => {
+                                    return Err(std::io::Error::new(
+                                        std::io::ErrorKind::InvalidData,
+                                        format!(
+                                            "Invalid token after .section flags: Expected a section type {:?}",
+                                            tok
+                                        ),
+                                    ))
+                                }
+                            },
+                            None => {
+                                return Err(std::io::Error::new(
+                                    std::io::ErrorKind::InvalidData,
+                                    "Expected a section type after .section flags, found end of input",
+                                ))
+                            }
+                        });
+                        // Any further `@type`-specific arguments (entsize,
+                        // group name, linkage, ...) aren't supported.
+                    }
+                }
+
+                let def_align = asm.machine().def_section_alignment() as usize;
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let sect = if let Some(sect) = data.sections.get(&name) {
+                    if let Some(flags) = flags {
+                        sect.borrow_mut().flags = Some(flags);
+                    }
+                    if let Some(ty) = ty {
+                        sect.borrow_mut().ty = ty;
+                    }
+                    sect.clone()
+                } else {
+                    let sect = Section {
+                        name: name.clone(),
+                        align: def_align,
+                        ty: ty.unwrap_or(SectionType::ProgBits),
+                        flags,
+                        ..Default::default()
+                    };
+
+                    let sect = Rc::new(RefCell::new(sect));
+
+                    data.sections.insert(name.clone(), sect.clone());
+
+                    sect
+                };
+
+                data.curr_section = name;
+
+                asm.set_output(Box::new(SharedSection(sect)));
+
+                Ok(())
+            }
+            ".global" | ".globl" => parse_identifier_list(asm, dir, |asm, id| {
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                data.global_syms.insert(id);
+            }),
+            ".weak" => parse_identifier_list(asm, dir, |asm, id| {
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                data.weak_syms.insert(id);
+            }),
+            ".align" | ".balign" => {
+                let expr = lcas_core::expr::parse_expression(asm.iter());
+                let align = match asm.eval_expr(expr) {
+                    Expression::Integer(i) => i as usize,
+                    expr => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("Invalid expression for {}: {:?}", dir, expr),
+                        ))
+                    }
+                };
+
+                // A trailing `, fill[, max]` is accepted the way GNU `as`
+                // accepts it for `.balign`; `max` (skip the alignment
+                // entirely if it would take more than `max` bytes of
+                // padding) is parsed but not applied -- the padding is
+                // always performed.
+                let fill = if matches!(
+                    asm.iter().peek().map(Spanned::body),
+                    Some(Token::Sigil(s)) if s == ","
+                ) {
+                    asm.iter().next();
+                    let expr = lcas_core::expr::parse_expression(asm.iter());
+                    match asm.eval_expr(expr) {
+                        Expression::Integer(i) => i as u8,
+                        expr => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                format!("Invalid fill expression for {}: {:?}", dir, expr),
+                            ))
+                        }
+                    }
+                } else {
+                    0
+                };
+                if matches!(
+                    asm.iter().peek().map(Spanned::body),
+                    Some(Token::Sigil(s)) if s == ","
+                ) {
+                    asm.iter().next();
+                    lcas_core::expr::parse_expression(asm.iter());
+                }
+
+                pad_to_align(asm, align, fill);
+                Ok(())
+            }
+            ".p2align" => {
+                let expr = lcas_core::expr::parse_expression(asm.iter());
+                let shift = match asm.eval_expr(expr) {
+                    Expression::Integer(i) => i as u32,
+                    expr => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("Invalid expression for .p2align: {:?}", expr),
+                        ))
+                    }
+                };
+
+                let fill = if matches!(
+                    asm.iter().peek().map(Spanned::body),
+                    Some(Token::Sigil(s)) if s == ","
+                ) {
+                    asm.iter().next();
+                    let expr = lcas_core::expr::parse_expression(asm.iter());
+                    match asm.eval_expr(expr) {
+                        Expression::Integer(i) => i as u8,
+                        expr => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                format!("Invalid fill expression for .p2align: {:?}", expr),
+                            ))
+                        }
+                    }
+                } else {
+                    0
+                };
+                if matches!(
+                    asm.iter().peek().map(Spanned::body),
+                    Some(Token::Sigil(s)) if s == ","
+                ) {
+                    asm.iter().next();
+                    lcas_core::expr::parse_expression(asm.iter());
+                }
+
+                pad_to_align(asm, 1usize << shift, fill);
+                Ok(())
+            }
+            ".local" => parse_identifier_list(asm, dir, |asm, id| {
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                data.global_syms.remove(&id);
+                data.weak_syms.remove(&id);
+            }),
+            ".hidden" => parse_identifier_list(asm, dir, |asm, id| {
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                data.hidden_syms.insert(id);
+            }),
+            ".type" => {
+                let sym = parse_identifier_arg(asm, dir, "a symbol name")?;
+                expect_comma(asm, dir)?;
+                if matches!(
+                    asm.iter().peek().map(Spanned::body),
+                    Some(Token::Sigil(s)) if s == "@"
+                ) {
+                    asm.iter().next();
+                }
+                let kind = parse_identifier_arg(asm, dir, "a type keyword")?;
+                let ty = match &*kind {
+                    "function" | "STT_FUNC" => SymbolType::Function,
+                    "object" | "STT_OBJECT" => SymbolType::Object,
+                    "tls_object" | "STT_TLS" => SymbolType::Tls,
+                    "common" | "STT_COMMON" => SymbolType::Common,
+                    "notype" | "STT_NOTYPE" => SymbolType::Null,
+                    kind => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("Unrecognized .type kind {}", kind),
+                        ))
+                    }
+                };
+
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                data.sym_types.insert(sym, ty);
+                Ok(())
+            }
+            ".size" => {
+                let sym = parse_identifier_arg(asm, dir, "a symbol name")?;
+                expect_comma(asm, dir)?;
+                // Only a literal constant is supported -- GNU `as` also
+                // allows `. - sym`, but this assembler's `Expression` has
+                // no "current location counter" term to fold that against.
+                let expr = lcas_core::expr::parse_expression(asm.iter());
+                let expr = asm.eval_expr(expr);
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let size = eval_const(&expr, &data.consts).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Invalid expression for .size: {:?}", expr),
+                    )
+                })?;
+                data.sym_sizes.insert(sym, size);
+                Ok(())
+            }
+            ".comm" | ".lcomm" => {
+                let sym = parse_identifier_arg(asm, dir, "a symbol name")?;
+                expect_comma(asm, dir)?;
+                let expr = lcas_core::expr::parse_expression(asm.iter());
+                let size = match asm.eval_expr(expr) {
+                    Expression::Integer(i) => i as usize,
+                    expr => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("Invalid size expression for {}: {:?}", dir, expr),
+                        ))
+                    }
+                };
+                let align = if matches!(
+                    asm.iter().peek().map(Spanned::body),
+                    Some(Token::Sigil(s)) if s == ","
+                ) {
+                    asm.iter().next();
+                    let expr = lcas_core::expr::parse_expression(asm.iter());
+                    match asm.eval_expr(expr) {
+                        Expression::Integer(i) => (i as usize).max(1),
+                        expr => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                format!("Invalid alignment expression for {}: {:?}", dir, expr),
+                            ))
+                        }
+                    }
+                } else {
+                    1
+                };
+
+                // `binfmt::fmt::Section` has no `SHN_COMMON`-style
+                // representation a linker could later merge multiple
+                // definitions of the same common symbol into, so (like a
+                // build with `-fno-common`) each `.comm`/`.lcomm` just
+                // reserves its own space in `.bss` immediately.
+                let def_align = asm.machine().def_section_alignment() as usize;
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let bss = data
+                    .sections
+                    .entry(".bss".to_string())
+                    .or_insert_with(|| {
+                        Rc::new(RefCell::new(Section {
+                            name: ".bss".to_string(),
+                            align: def_align,
+                            ty: SectionType::NoBits,
+                            ..Default::default()
+                        }))
+                    })
+                    .clone();
+
+                let offset = {
+                    let mut sec = bss.borrow_mut();
+                    if sec.align < align {
+                        sec.align = align;
+                    }
+                    let off = (sec.offset() + (align - 1)) & !(align - 1);
+                    sec.content.resize(off + size, 0);
+                    off
+                };
+
+                data.syms.insert(sym.clone(), (".bss".to_string(), offset));
+                data.sym_types.insert(sym.clone(), SymbolType::Object);
+                if dir == ".comm" {
+                    data.global_syms.insert(sym);
+                }
+                Ok(())
+            }
+            ".equ" | ".set" => {
+                let sym = parse_identifier_arg(asm, dir, "a symbol name")?;
+                expect_comma(asm, dir)?;
+                let expr = lcas_core::expr::parse_expression(asm.iter());
+                let expr = asm.eval_expr(expr);
+
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let value = eval_const(&expr, &data.consts).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "{} requires a constant expression (no forward references to labels): {:?}",
+                            dir, expr
+                        ),
+                    )
+                })?;
+                data.consts.insert(sym.clone(), value);
+                data.abs_syms.insert(sym, value);
+                Ok(())
+            }
+            ".cfi_startproc" => {
+                // An optional bare `simple` argument tells GNU `as` not to
+                // fill in a target-default initial CFI state; this
+                // assembler never does that anyway, so `simple` is accepted
+                // and ignored.
+                if matches!(
+                    asm.iter().peek().map(Spanned::body),
+                    Some(Token::Identifier(id)) if id == "simple"
+                ) {
+                    asm.iter().next();
+                }
+
+                let def_align = asm.machine().def_section_alignment() as usize;
+                let ret_addr_reg = asm.machine().eh_frame_return_address_register();
+                let data_align = asm.machine().eh_frame_data_alignment_factor();
+                let addr_size = asm.machine().long_width();
 
-impl Write for SharedSection {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.0.borrow_mut().write(buf)
-    }
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
 
-    fn flush(&mut self) -> std::io::Result<()> {
-        self.0.borrow_mut().flush()
-    }
-}
+                if data.cfi_proc.is_some() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        ".cfi_startproc without a matching .cfi_endproc for the previous one",
+                    ));
+                }
 
-impl InsnWrite for SharedSection {
-    fn offset(&self) -> usize {
-        self.0.borrow().offset()
-    }
+                let eh_frame = data
+                    .sections
+                    .entry(".eh_frame".to_string())
+                    .or_insert_with(|| {
+                        Rc::new(RefCell::new(Section {
+                            name: ".eh_frame".to_string(),
+                            align: def_align,
+                            ty: SectionType::ProgBits,
+                            flags: Some(SectionFlags::default() | SectionFlag::Alloc),
+                            ..Default::default()
+                        }))
+                    })
+                    .clone();
+
+                if data.cfi_cie_offset.is_none() {
+                    let mut eh_frame = eh_frame.borrow_mut();
+                    let offset = emit_cie(&mut eh_frame, ret_addr_reg, data_align, addr_size)?;
+                    data.cfi_cie_offset = Some(offset);
+                }
 
-    fn write_addr(
-        &mut self,
-        size: usize,
-        addr: arch_ops::traits::Address,
-        rel: bool,
-    ) -> std::io::Result<()> {
-        self.0.borrow_mut().write_addr(size, addr, rel)
-    }
+                let section = data.curr_section.clone();
+                let start_offset = data.sections[&section].borrow().offset() as u64;
 
-    fn write_reloc(&mut self, reloc: arch_ops::traits::Reloc) -> std::io::Result<()> {
-        self.0.borrow_mut().write_reloc(reloc)
-    }
-}
+                data.cfi_proc_counter += 1;
+                let start_symbol = format!(".Lcfi_startproc{}", data.cfi_proc_counter);
 
-pub struct Callbacks;
+                data.cfi_proc = Some(CfiProc {
+                    start_symbol: start_symbol.clone(),
+                    section,
+                    start_offset,
+                    cfa_offset: 0,
+                    last_offset: start_offset,
+                    instrs: Vec::new(),
+                });
 
-impl AssemblerCallbacks for Callbacks {
-    fn handle_directive(&self, asm: &mut Assembler, dir: &str) -> std::io::Result<()> {
-        match dir {
-            ".text" | ".rodata" | ".data" | ".bss" => {
+                self.create_symbol_now(asm, &start_symbol);
+
+                Ok(())
+            }
+            ".cfi_endproc" => {
+                let addr_size = asm.machine().long_width();
                 let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
-                let sect = if let Some(sect) = data.sections.get(dir) {
-                    sect.clone()
-                } else {
-                    let ty = if dir == ".bss" {
-                        SectionType::NoBits
-                    } else {
-                        SectionType::ProgBits
-                    };
-                    let sect = Section {
-                        name: dir.to_string(),
-                        align: asm.machine().def_section_alignment() as usize,
-                        ty,
-                        ..Default::default()
-                    };
-                    let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
 
-                    let sect = Rc::new(RefCell::new(sect));
+                let proc = data.cfi_proc.take().ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        ".cfi_endproc without a matching .cfi_startproc",
+                    )
+                })?;
+                let cie_offset = data.cfi_cie_offset.unwrap();
+
+                let end_offset = data.sections[&proc.section].borrow().offset() as u64;
+                let pc_range = end_offset - proc.start_offset;
+
+                let eh_frame = data.sections[".eh_frame"].clone();
+                emit_fde(
+                    &mut eh_frame.borrow_mut(),
+                    cie_offset,
+                    addr_size,
+                    &proc.start_symbol,
+                    pc_range,
+                    &proc.instrs,
+                )?;
 
-                    data.sections.insert(dir.to_string(), sect.clone());
+                Ok(())
+            }
+            ".cfi_def_cfa" => {
+                let register = parse_int_arg(asm) as u64;
+                cfi_expect_comma(asm, dir)?;
+                let offset = parse_int_arg(asm);
 
-                    sect
-                };
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let cur_offset = data.sections[&section].borrow().offset() as u64;
+                let proc = cfi_require_proc(data, dir)?;
+
+                cfi_advance_loc(proc, cur_offset);
+                proc.instrs.push(DW_CFA_DEF_CFA);
+                write_uleb128(&mut proc.instrs, register);
+                write_uleb128(&mut proc.instrs, offset as u64);
+                proc.cfa_offset = offset;
+
+                Ok(())
+            }
+            ".cfi_def_cfa_register" => {
+                let register = parse_int_arg(asm) as u64;
 
                 let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let cur_offset = data.sections[&section].borrow().offset() as u64;
+                let proc = cfi_require_proc(data, dir)?;
 
-                data.curr_section = dir.to_string();
+                cfi_advance_loc(proc, cur_offset);
+                proc.instrs.push(DW_CFA_DEF_CFA_REGISTER);
+                write_uleb128(&mut proc.instrs, register);
 
-                asm.set_output(Box::new(SharedSection(sect)));
+                Ok(())
+            }
+            ".cfi_def_cfa_offset" => {
+                let offset = parse_int_arg(asm);
+
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let cur_offset = data.sections[&section].borrow().offset() as u64;
+                let proc = cfi_require_proc(data, dir)?;
+
+                cfi_advance_loc(proc, cur_offset);
+                proc.instrs.push(DW_CFA_DEF_CFA_OFFSET);
+                write_uleb128(&mut proc.instrs, offset as u64);
+                proc.cfa_offset = offset;
 
                 Ok(())
             }
-            ".section" => match asm.iter().next().unwrap().into_inner() {
-                Token::Identifier(tok) => {
-                    let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
-                    let sect = if let Some(sect) = data.sections.get(&tok) {
-                        sect.clone()
-                    } else {
-                        let sect = Section {
-                            name: tok.clone(),
-                            align: asm.machine().def_section_alignment() as usize,
-                            ty: SectionType::ProgBits,
-                            ..Default::default()
-                        };
-                        let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+            ".cfi_adjust_cfa_offset" => {
+                let delta = parse_int_arg(asm);
 
-                        let sect = Rc::new(RefCell::new(sect));
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let cur_offset = data.sections[&section].borrow().offset() as u64;
+                let proc = cfi_require_proc(data, dir)?;
 
-                        data.sections.insert(tok.clone(), sect.clone());
+                proc.cfa_offset += delta;
+                let new_offset = proc.cfa_offset;
 
-                        sect
-                    };
+                cfi_advance_loc(proc, cur_offset);
+                proc.instrs.push(DW_CFA_DEF_CFA_OFFSET);
+                write_uleb128(&mut proc.instrs, new_offset as u64);
 
-                    let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                Ok(())
+            }
+            ".cfi_offset" => {
+                let register = parse_int_arg(asm) as u64;
+                cfi_expect_comma(asm, dir)?;
+                let offset = parse_int_arg(asm);
 
-                    data.curr_section = tok;
+                let data_align = asm.machine().eh_frame_data_alignment_factor();
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let cur_offset = data.sections[&section].borrow().offset() as u64;
+                let proc = cfi_require_proc(data, dir)?;
 
-                    asm.set_output(Box::new(SharedSection(sect)));
+                cfi_emit_offset(proc, cur_offset, register, offset, data_align);
 
-                    dbg!(asm.iter().peek());
+                Ok(())
+            }
+            ".cfi_rel_offset" => {
+                let register = parse_int_arg(asm) as u64;
+                cfi_expect_comma(asm, dir)?;
+                let offset = parse_int_arg(asm);
 
-                    Ok(())
-                }
-                tok => panic!(
-                    "Invalid token after .section: Exception an identifier {:?}",
-                    tok
-                ),
-            },
-            ".global" | ".globl" => {
-                loop {
-                    match asm.iter().next().unwrap().into_inner() {
-                        Token::Identifier(id) => {
-                            let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
-                            data.global_syms.insert(id);
-                        }
-                        Token::LineTerminator => break,
-                        tok => panic!(
-                            "Unexpected token for .global directive: {:?}, expected an identifier",
-                            tok
-                        ),
-                    }
+                let data_align = asm.machine().eh_frame_data_alignment_factor();
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let cur_offset = data.sections[&section].borrow().offset() as u64;
+                let proc = cfi_require_proc(data, dir)?;
+
+                let raw_offset = offset - proc.cfa_offset;
+                cfi_emit_offset(proc, cur_offset, register, raw_offset, data_align);
+
+                Ok(())
+            }
+            ".cfi_restore" => {
+                let register = parse_int_arg(asm) as u64;
+
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let cur_offset = data.sections[&section].borrow().offset() as u64;
+                let proc = cfi_require_proc(data, dir)?;
+
+                cfi_advance_loc(proc, cur_offset);
+                if register < 64 {
+                    proc.instrs.push(0xc0 | register as u8);
+                } else {
+                    proc.instrs.push(DW_CFA_RESTORE_EXTENDED);
+                    write_uleb128(&mut proc.instrs, register);
                 }
 
                 Ok(())
             }
-            ".weak" => {
+            ".cfi_same_value" | ".cfi_undefined" => {
+                let register = parse_int_arg(asm) as u64;
+
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let cur_offset = data.sections[&section].borrow().offset() as u64;
+                let proc = cfi_require_proc(data, dir)?;
+
+                cfi_advance_loc(proc, cur_offset);
+                proc.instrs.push(if dir == ".cfi_same_value" {
+                    DW_CFA_SAME_VALUE
+                } else {
+                    DW_CFA_UNDEFINED
+                });
+                write_uleb128(&mut proc.instrs, register);
+
+                Ok(())
+            }
+            ".cfi_remember_state" | ".cfi_restore_state" => {
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let cur_offset = data.sections[&section].borrow().offset() as u64;
+                let proc = cfi_require_proc(data, dir)?;
+
+                cfi_advance_loc(proc, cur_offset);
+                proc.instrs.push(if dir == ".cfi_remember_state" {
+                    DW_CFA_REMEMBER_STATE
+                } else {
+                    DW_CFA_RESTORE_STATE
+                });
+
+                Ok(())
+            }
+            ".file" => {
+                // `.file N "name"` assigns DWARF file-table index `N`;
+                // the bare `.file "name"` form (no number) sets the
+                // implicit primary source file, which this table treats
+                // as index 1 -- the line-number program's `file` register
+                // already defaults to 1 (see `dbg-info::dwarf5`'s
+                // `Registers::initial`), so that's the index a consumer
+                // will look at if no `.loc` ever names one explicitly.
+                let number = match asm.iter().peek().map(Spanned::body) {
+                    Some(Token::StringLiteral(_)) => 1,
+                    _ => parse_int_arg(asm) as u64,
+                };
+                let name = match asm.iter().next().unwrap().into_inner() {
+                    Token::StringLiteral(s) => s.trim_matches('"').to_string(),
+                    tok => panic!(
+                        "Unexpected token for .file directive: {:?}, expected a filename string",
+                        tok
+                    ),
+                };
+                // Any further GNU `as` `.file` operands (directory index,
+                // timestamp, size, MD5 checksum) aren't recorded.
+
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                data.dbg_files.insert(number, name);
+                Ok(())
+            }
+            ".loc" => {
+                let file = parse_int_arg(asm) as u64;
+                let line = parse_int_arg(asm) as u64;
+                let column = if matches!(
+                    asm.iter().peek().map(Spanned::body),
+                    Some(Token::IntegerLiteral(_))
+                ) {
+                    parse_int_arg(asm) as u64
+                } else {
+                    0
+                };
+
+                let mut is_stmt = true;
                 loop {
-                    match asm.iter().next().unwrap().into_inner() {
-                        Token::Identifier(id) => {
-                            let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
-                            data.weak_syms.insert(id);
+                    match asm.iter().peek().map(Spanned::body) {
+                        Some(Token::Identifier(id)) if id == "is_stmt" => {
+                            asm.iter().next();
+                            is_stmt = parse_int_arg(asm) != 0;
                         }
-                        Token::LineTerminator => break,
-                        tok => panic!(
-                            "Unexpected token for .weak directive: {:?}, expected an identifier",
-                            tok
-                        ),
+                        Some(Token::Identifier(id))
+                            if matches!(&**id, "basic_block" | "prologue_end" | "epilogue_begin") =>
+                        {
+                            asm.iter().next();
+                        }
+                        Some(Token::Identifier(id)) if matches!(&**id, "isa" | "discriminator") => {
+                            asm.iter().next();
+                            parse_int_arg(asm);
+                        }
+                        _ => break,
                     }
                 }
 
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let section = data.curr_section.clone();
+                let offset = data.sections[&section].borrow().offset() as u64;
+                let continues = data.dbg_cur_section.as_deref() == Some(&*section);
+
+                let new_anchor = if continues {
+                    None
+                } else {
+                    data.dbg_anchor_counter += 1;
+                    let anchor_symbol = format!(".Lloc_anchor{}", data.dbg_anchor_counter);
+                    data.dbg_seqs.push(DbgSeq {
+                        anchor_symbol: anchor_symbol.clone(),
+                        anchor_offset: offset,
+                        rows: Vec::new(),
+                    });
+                    data.dbg_cur_section = Some(section);
+                    Some(anchor_symbol)
+                };
+
+                if let Some(anchor_symbol) = &new_anchor {
+                    self.create_symbol_now(asm, anchor_symbol);
+                }
+
+                let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+                let seq = data.dbg_seqs.last_mut().unwrap();
+                let disp = offset as i64 - seq.anchor_offset as i64;
+                seq.rows.push(DbgLocRow {
+                    disp,
+                    file,
+                    line,
+                    column,
+                    is_stmt,
+                });
+
                 Ok(())
             }
-            ".align" => {
-                let expr = lcas_core::expr::parse_expression(asm.iter());
-                let expr = asm.eval_expr(expr);
-
-                match expr {
-                    Expression::Integer(mut i) => {
-                        let align = i as usize;
 
-                        let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+            // `.uleb128`/`.sleb128`: DWARF's variable-length integer
+            // encodings -- used for everything from `.debug_line`'s
+            // address/line advances (this assembler emits those itself,
+            // see `build_debug_line`, but a hand-written `.debug_info` or
+            // a compiler targeting `lcas` directly needs the raw
+            // directives) to `.cfi_escape` byte sequences. Each operand is
+            // either a plain constant or a same-section label difference
+            // (see `eval_loc`); a bare relocatable symbol is rejected,
+            // since there's no LEB128 relocation kind to defer it to the
+            // linker with.
+            ".uleb128" | ".sleb128" => {
+                loop {
+                    let expr = lcas_core::expr::parse_expression(asm.iter());
+                    let value = eval_loc_const(asm, expr);
 
-                        let mut sec = data.sections[&data.curr_section].borrow_mut();
+                    let mut buf = Vec::new();
+                    if dir == ".uleb128" {
+                        write_uleb128(&mut buf, value as u64);
+                    } else {
+                        write_sleb128(&mut buf, value as i64);
+                    }
+                    asm.output().write_all(&buf)?;
 
-                        if sec.align < align {
-                            sec.align = align;
+                    match asm.iter().peek().map(Spanned::body) {
+                        Some(Token::Sigil(s)) if s == "," => {
+                            asm.iter().next();
                         }
+                        _ => break,
+                    }
+                }
 
-                        let off = sec.offset();
+                Ok(())
+            }
 
-                        let nlen = (off + (align - 1)) & !(align - 1);
+            // `.dtprelword`/`.dtpreldword`: a 4-/8-byte TLS symbol offset
+            // relative to its module's thread-local storage block (used by
+            // DWARF CFI/location expressions for thread-local variables,
+            // `DW_OP_GNU_push_tls_address`'s operand in particular), always
+            // relocated -- unlike `.uleb128`/`.sleb128`'s fold-or-reject
+            // rule, this directive's whole purpose is deferring the offset
+            // to link time, so it only accepts a bare symbol.
+            ".dtprelword" | ".dtpreldword" => {
+                let width = if dir == ".dtprelword" { 4 } else { 8 };
+                loop {
+                    let expr = lcas_core::expr::parse_expression(asm.iter());
+                    let sym = match asm.eval_expr(expr) {
+                        Expression::Symbol(sym) => sym,
+                        expr => panic!(
+                            "Invalid expression for {}: {:?}, expected a bare TLS symbol",
+                            dir, expr
+                        ),
+                    };
 
-                        sec.content.resize(nlen, 0);
-                        Ok(())
+                    let data = asm.as_data().downcast_ref::<Data>().unwrap();
+                    let mut sec = data.sections[&data.curr_section].borrow_mut();
+                    let offset = sec.content.len() as u64;
+                    sec.content.extend(std::iter::repeat(0u8).take(width));
+                    sec.relocs.push(arch_ops::traits::Reloc {
+                        code: arch_ops::traits::RelocCode::DtpRel {
+                            addr_width: width * 8,
+                        },
+                        symbol: sym,
+                        addend: Some(0),
+                        offset,
+                    });
+                    drop(sec);
+
+                    match asm.iter().peek().map(Spanned::body) {
+                        Some(Token::Sigil(s)) if s == "," => {
+                            asm.iter().next();
+                        }
+                        _ => break,
                     }
-                    expr => panic!("Invalid expression for .space: {:?}", expr),
                 }
+
+                Ok(())
             }
-            x => todo!("Unrecognized directive {}", dir),
+
+            dir => todo!("Unrecognized directive {}", dir),
         }
     }
 
@@ -211,14 +2542,35 @@ impl AssemblerCallbacks for Callbacks {
             panic!("Duplicate label {}", sym)
         }
     }
+
+    fn create_absolute_symbol(&self, asm: &mut Assembler, sym: &str, value: u64) {
+        let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
+
+        if data.abs_syms.insert(sym.to_string(), value).is_some() {
+            panic!("Duplicate label {}", sym)
+        }
+    }
+
+    fn resolve_local_backward(&self, asm: &Assembler, sym: &str) -> Option<u64> {
+        let data = asm.as_data().downcast_ref::<Data>().unwrap();
+        let (sec, offset) = data.syms.get(sym)?;
+        (*sec == data.curr_section).then_some(*offset as u64)
+    }
 }
 
 fn main() {
     let deftarg = target_tuples::from_env!("default_target");
     let mut targ = None;
-    let mut binfmt = None;
+    let mut output_fmts = Vec::new();
     let mut input_files = Vec::new();
-    let mut output_name = "a.out".to_string();
+    let mut output_names = Vec::new();
+    let mut include_dirs = Vec::new();
+    let mut defines = Defines::new();
+    let mut keep_locals = false;
+    let mut listing_file: Option<Option<String>> = None;
+    let mut suppress_warnings = false;
+    let mut fatal_warnings = false;
+    let mut force_preprocess = false;
 
     let mut args = std::env::args().map(|s| {
         eprint!("{} ", s);
@@ -247,11 +2599,11 @@ fn main() {
                 targ = Some(Target::parse(t));
             }
             "--output-fmt" => {
-                binfmt = Some(args.next().unwrap());
+                output_fmts.push(args.next().unwrap());
             }
             x if x.starts_with("--output-fmt=") => {
                 let t = &x[13..];
-                binfmt = Some(t.to_string());
+                output_fmts.push(t.to_string());
             }
             "--version" => {
                 eprintln!("lcas v{}", std::env!("CARGO_PKG_VERSION"));
@@ -281,9 +2633,30 @@ fn main() {
                     deftarg
                 );
                 eprintln!(
-                    "\t--output-fmt <binfmt>: Specify the output format (default {})",
+                    "\t--output-fmt <binfmt>: Specify an output format to emit (default {}); may be given more than once to emit the same assembly as several object formats in one invocation",
                     binfmt::def_vec_for(targ.as_ref().unwrap_or(&deftarg)).name()
                 );
+                eprintln!(
+                    "\t-I <dir>: Add <dir> to the search path for .include and .incbin; may be given more than once"
+                );
+                eprintln!(
+                    "\t-D <name>[=<value>]: Define <name> (default value 1) for .ifdef/.ifndef/.if; may be given more than once"
+                );
+                eprintln!(
+                    "\t--keep-locals, -L: Keep `.L`-prefixed local symbols (including those synthesized for numeric labels) in the output symbol table even when unreferenced by a relocation"
+                );
+                eprintln!(
+                    "\t--listing[=<file>], -a, -al: Emit a side-by-side listing of source lines, section offsets, and encoded bytes (to stdout, unless a file is given)"
+                );
+                eprintln!(
+                    "\t--no-warn, -W: Suppress warning messages; --warn: Don't (default)"
+                );
+                eprintln!(
+                    "\t--fatal-warnings: Treat warnings as errors"
+                );
+                eprintln!(
+                    "\t--preprocess: Run every input file through lc-as's built-in #include/#define/#if preprocessor before assembling, even if it doesn't end in `.S` (which gets this automatically)"
+                );
 
                 eprint!("lcas is compiled with support for the following binfmts: ");
 
@@ -299,15 +2672,56 @@ fn main() {
                 std::process::exit(0);
             }
             "--output-file" | "-o" => {
-                output_name = args.next().unwrap();
+                output_names.push(args.next().unwrap());
             }
             x if x.starts_with("--output-file=") => {
                 let t = &x[14..];
-                output_name = t.to_string();
+                output_names.push(t.to_string());
             }
             x if x.starts_with("-o") => {
                 let t = &x[2..];
-                output_name = t.to_string();
+                output_names.push(t.to_string());
+            }
+            "--include-dir" | "-I" => {
+                include_dirs.push(PathBuf::from(args.next().unwrap()));
+            }
+            x if x.starts_with("--include-dir=") => {
+                let t = &x[14..];
+                include_dirs.push(PathBuf::from(t));
+            }
+            x if x.starts_with("-I") => {
+                let t = &x[2..];
+                include_dirs.push(PathBuf::from(t));
+            }
+            "--define" | "-D" => {
+                parse_define(&args.next().unwrap(), &mut defines);
+            }
+            x if x.starts_with("--define=") => {
+                parse_define(&x[9..], &mut defines);
+            }
+            x if x.starts_with("-D") => {
+                parse_define(&x[2..], &mut defines);
+            }
+            "--keep-locals" | "-L" => {
+                keep_locals = true;
+            }
+            "--listing" | "-a" | "-al" => {
+                listing_file = Some(None);
+            }
+            x if x.starts_with("--listing=") => {
+                listing_file = Some(Some(x[10..].to_string()));
+            }
+            "--no-warn" | "-W" => {
+                suppress_warnings = true;
+            }
+            "--warn" => {
+                suppress_warnings = false;
+            }
+            "--fatal-warnings" => {
+                fatal_warnings = true;
+            }
+            "--preprocess" => {
+                force_preprocess = true;
             }
             x if x.starts_with("-") => {
                 eprintln!("Unrecognized option: {}", x);
@@ -330,16 +2744,44 @@ fn main() {
 
     eprintln!("Targetting: {}", targ);
 
-    let binfmt = if let Some(fmt) = binfmt {
-        binfmt::format_by_name(&fmt).unwrap_or_else(|| {
-            eprintln!("Unknown or invalid binfmt name {}", fmt);
+    let fmts: Vec<&'static dyn binfmt::fmt::Binfmt> = if output_fmts.is_empty() {
+        vec![binfmt::def_vec_for(&targ)]
+    } else {
+        output_fmts
+            .iter()
+            .map(|fmt| {
+                binfmt::format_by_name(fmt).unwrap_or_else(|| {
+                    eprintln!("Unknown or invalid binfmt name {}", fmt);
+
+                    std::process::exit(1)
+                })
+            })
+            .collect()
+    };
 
-            std::process::exit(1)
-        })
+    let output_names = if output_names.is_empty() {
+        vec!["a.out".to_string()]
+    } else {
+        output_names
+    };
+
+    let output_names: Vec<String> = if fmts.len() > 1 && output_names.len() == 1 {
+        fmts.iter()
+            .map(|fmt| format!("{}.{}", output_names[0], fmt.name()))
+            .collect()
     } else {
-        binfmt::def_vec_for(&targ)
+        output_names
     };
 
+    if output_names.len() != fmts.len() {
+        eprintln!(
+            "Expected either one --output-file or exactly as many as --output-fmt ({}), got {}",
+            fmts.len(),
+            output_names.len()
+        );
+        std::process::exit(1)
+    }
+
     if input_files.is_empty() {
         eprintln!("At least one input file must be specified");
         std::process::exit(1)
@@ -350,29 +2792,43 @@ fn main() {
         std::process::exit(1)
     });
 
-    let file = LCasSymbol::intern(input_files.first().unwrap());
-
-    let mut input = utf::decode_utf8(
-        input_files
-            .into_iter()
-            .map(|s| {
-                std::fs::File::open(&s).unwrap_or_else(|e| {
+    // Each input file's source text, after running it through the `#include`/
+    // `#define`/`#if` preprocessor in `cpp` when it's named `*.S` (matching
+    // the gas/cpp convention that only the capitalized extension gets
+    // C-preprocessed) or `--preprocess` forces every file to. The read
+    // itself happens eagerly here, rather than the lazy byte-at-a-time
+    // stream `Lexer` otherwise works from, since `cpp::preprocess` needs a
+    // whole file's text up front to resolve conditionals/macros against.
+    let file_sources: Vec<String> = input_files
+        .iter()
+        .map(|s| {
+            if force_preprocess || s.ends_with(".S") {
+                cpp::preprocess(std::path::Path::new(s), &include_dirs)
+            } else {
+                std::fs::read_to_string(s).unwrap_or_else(|e| {
                     eprintln!("Unable to open input file {}: {}", s, e);
 
                     std::process::exit(1)
                 })
-            })
-            .flat_map(|s| s.bytes())
-            .map(|r| {
-                r.unwrap_or_else(|e| {
-                    eprintln!("Failed to read input file: {}", e);
+            }
+        })
+        .collect();
+
+    // The source text for `-a`/`--listing` and diagnostic source snippets
+    // alike, independent of the token stream below -- by the time a
+    // statement's bytes (or an error) are known, the tokens that produced
+    // them are long gone. Only the first input file's (expanded) text is
+    // used, matching `file` below: spans already only ever name that one
+    // file, regardless of how many are concatenated.
+    let source_lines: Vec<String> = file_sources
+        .first()
+        .map(|s| s.lines().map(str::to_string).collect())
+        .unwrap_or_default();
 
-                    std::process::exit(1)
-                })
-            }),
-    )
-    .map(|e| e.unwrap())
-    .peekable();
+    let file = LCasSymbol::intern(input_files.first().unwrap());
+
+    let source = file_sources.concat();
+    let mut input = source.chars().peekable();
 
     let mut lex = lcas_core::lex::Lexer::new(targ_def, &mut input, file);
 
@@ -387,18 +2843,31 @@ fn main() {
     let mut sections = HashMap::new();
     sections.insert(".text".to_string(), text.clone());
 
-    let binfile = binfmt.create_file(FileType::Relocatable);
-
     let mut data = Data {
-        binfile,
         sections,
         curr_section: ".text".to_string(),
         syms: HashMap::new(),
+        abs_syms: HashMap::new(),
         global_syms: HashSet::new(),
         weak_syms: HashSet::new(),
+        hidden_syms: HashSet::new(),
+        sym_types: HashMap::new(),
+        sym_sizes: HashMap::new(),
+        consts: HashMap::new(),
+        cfi_proc: None,
+        cfi_cie_offset: None,
+        cfi_proc_counter: 0,
+        dbg_files: BTreeMap::new(),
+        dbg_seqs: Vec::new(),
+        dbg_cur_section: None,
+        dbg_anchor_counter: 0,
     };
 
     let toks = lex.collect::<Vec<_>>();
+    let toks = expand_conditionals(toks, &mut defines, 0);
+    let toks = expand_includes(toks, targ_def, &include_dirs, 0);
+    let toks = expand_macros(toks, &mut HashMap::new(), &mut 0usize, 0);
+    let toks = expand_numeric_labels(toks);
 
     let mut iter = toks.into_iter();
 
@@ -408,51 +2877,192 @@ fn main() {
         Box::new(data),
         &Callbacks,
         &mut iter,
+        &include_dirs,
     );
 
-    while let Some(res) = asm.assemble_instr() {
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.set_suppress_warnings(suppress_warnings);
+    diagnostics.set_warnings_as_errors(fatal_warnings);
+    let mut listing_entries = Vec::new();
+
+    loop {
+        let line = listing_file.is_some()
+            .then(|| asm.iter().peek_ignore_newline().map(|tok| tok.span().begin().row() + 1))
+            .flatten();
+
+        let before = listing_file.is_some().then(|| {
+            let data = asm.as_data().downcast_ref::<Data>().unwrap();
+            let section = data.curr_section.clone();
+            let offset = data.sections[&section].borrow().offset() as u64;
+            (section, offset)
+        });
+
+        let Some(res) = asm.assemble_instr() else {
+            break;
+        };
+
         if let Err(e) = res {
-            eprintln!("Failed to assemble: {}", e);
-            std::process::exit(1)
+            diagnostics.report(lcas_core::diag::Diagnostic::error_unspanned(format!(
+                "Failed to assemble: {}",
+                e
+            )));
+        } else if let (Some(line), Some((section, start))) = (line, before) {
+            let data = asm.as_data().downcast_ref::<Data>().unwrap();
+            let sec = data.sections[&section].borrow();
+            let bytes = if data.curr_section == section && sec.offset() as u64 >= start {
+                sec.content[start as usize..].to_vec()
+            } else {
+                Vec::new()
+            };
+            listing_entries.push(ListingEntry {
+                line,
+                section,
+                offset: start,
+                bytes,
+            });
         }
     }
 
-    let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
-
-    let binfile = &mut data.binfile;
+    for diag in diagnostics.iter() {
+        eprintln!("{}", diag.render(&source_lines));
+    }
 
-    let mut secnos = HashMap::new();
+    if diagnostics.truncated() {
+        eprintln!(
+            "{} more errors were found, but not shown (limit: {})",
+            diagnostics.dropped_errors(),
+            Diagnostics::DEFAULT_MAX_ERRORS
+        );
+    }
 
-    for (name, sec) in &data.sections {
-        let section = core::mem::take(&mut *sec.borrow_mut());
+    if diagnostics.has_errors() {
+        std::process::exit(1)
+    }
 
-        let no = binfile.add_section(section).unwrap();
+    if let Some(listing_file) = &listing_file {
+        let result = match listing_file {
+            Some(path) => File::create(path)
+                .and_then(|mut f| write_listing(&mut f, &listing_entries, &source_lines)),
+            None => write_listing(&mut std::io::stdout(), &listing_entries, &source_lines),
+        };
 
-        secnos.insert(name.clone(), no);
+        if let Err(e) = result {
+            eprintln!("Failed to write listing: {}", e);
+            std::process::exit(1)
+        }
     }
 
-    for (name, (sec, offset)) in &data.syms {
-        let sec = secnos[sec];
-        let sym = Symbol::new(
-            name.clone(),
-            sec,
-            *offset as u128,
-            binfmt::sym::SymbolType::Object,
-            if data.global_syms.contains(name) {
-                SymbolKind::Global
-            } else {
-                SymbolKind::Local
-            },
-        );
+    let data = asm.as_data_mut().downcast_mut::<Data>().unwrap();
 
-        *binfile.get_or_create_symbol(name).unwrap() = sym;
+    if !data.dbg_seqs.is_empty() {
+        let addr_size = targ_def.long_width();
+        let (content, relocs) = build_debug_line(&data.dbg_files, &data.dbg_seqs, addr_size);
+        let section = Section {
+            name: ".debug_line".to_string(),
+            align: 1,
+            ty: SectionType::ProgBits,
+            content,
+            relocs: relocs
+                .into_iter()
+                .map(|(offset, symbol)| arch_ops::traits::Reloc {
+                    code: arch_ops::traits::RelocCode::Abs {
+                        addr_width: addr_size * 8,
+                    },
+                    symbol,
+                    addend: Some(0),
+                    offset: offset as u64,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        data.sections
+            .insert(".debug_line".to_string(), Rc::new(RefCell::new(section)));
     }
 
-    for name in &data.weak_syms {
-        *binfile.get_or_create_symbol(name).unwrap().kind_mut() = SymbolKind::Weak;
+    // Each output format gets its own `BinaryFile`, since format-specific
+    // data (e.g. an ELF class) can't be shared between formats; the
+    // generic sections and symbols gathered from assembly are cloned into
+    // each one.
+    let sections: Vec<(String, Section)> = data
+        .sections
+        .iter()
+        .map(|(name, sec)| (name.clone(), core::mem::take(&mut *sec.borrow_mut())))
+        .collect();
+
+    // `.L`-prefixed symbols (GNU `as`'s "local label" convention, also what
+    // `expand_numeric_labels` synthesizes for `1:`/`1b`/`1f`) are dropped
+    // from the emitted symbol table unless `--keep-locals` was given or the
+    // symbol is the target of a relocation still living in one of these
+    // sections -- dropping a relocation's target symbol would leave nothing
+    // for the relocation to resolve against, since this object is always
+    // written out relocatable.
+    let locally_referenced: HashSet<&str> = sections
+        .iter()
+        .flat_map(|(_, sec)| sec.relocs.iter().map(|r| r.symbol.as_str()))
+        .collect();
+
+    let mut syms = data.syms.clone();
+    if !keep_locals {
+        syms.retain(|name, _| !name.starts_with(".L") || locally_referenced.contains(name.as_str()));
     }
+    let abs_syms = data.abs_syms.clone();
+    let global_syms = data.global_syms.clone();
+    let weak_syms = data.weak_syms.clone();
+    let sym_types = data.sym_types.clone();
+    let sym_sizes = data.sym_sizes.clone();
+    let hidden_syms = data.hidden_syms.clone();
+
+    for (fmt, output_name) in fmts.iter().zip(output_names.iter()) {
+        let mut binfile = fmt.create_file(FileType::Relocatable);
+
+        let mut secnos = HashMap::new();
 
-    let mut output = File::create(output_name).unwrap();
+        for (name, sec) in &sections {
+            let no = binfile.add_section(sec.clone()).unwrap();
 
-    binfmt.write_file(&mut output, binfile).unwrap();
+            secnos.insert(name.clone(), no);
+        }
+
+        for (name, (sec, offset)) in &syms {
+            let sec = secnos[sec];
+            let sym = binfile.get_or_create_symbol(name).unwrap();
+            *sym = Symbol::new(
+                name.clone(),
+                sec,
+                *offset as u128,
+                sym_types.get(name).copied().unwrap_or(SymbolType::Object),
+                if global_syms.contains(name) {
+                    SymbolKind::Global
+                } else {
+                    SymbolKind::Local
+                },
+            );
+            if let Some(size) = sym_sizes.get(name) {
+                *sym.size_mut() = Some(*size);
+            }
+        }
+
+        for (name, value) in &abs_syms {
+            let mut sym =
+                Symbol::new_undef(name.clone(), binfmt::sym::SymbolType::Object, SymbolKind::Global);
+            *sym.value_mut() = Some(*value as u128);
+
+            *binfile.get_or_create_symbol(name).unwrap() = sym;
+        }
+
+        for name in &weak_syms {
+            *binfile.get_or_create_symbol(name).unwrap().kind_mut() = SymbolKind::Weak;
+        }
+
+        let mut output = File::create(output_name).unwrap();
+
+        fmt.write_file(&mut output, &binfile).unwrap();
+    }
+
+    if !hidden_syms.is_empty() {
+        eprintln!(
+            "note: {} symbol(s) marked .hidden; visibility isn't encoded into the object file yet (binfmt::sym::Symbol has no visibility field)",
+            hidden_syms.len()
+        );
+    }
 }
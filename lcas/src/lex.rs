@@ -117,7 +117,7 @@ impl<I: Iterator<Item = char>, A: ?Sized + TargetMachine> Iterator for Lexer<'_,
                 end_pos = lexer.3;
                 Some(Token::Group(x, tokens))
             }
-            ':' | ',' | ';' | '#' | '?' => {
+            ':' | ',' | ';' | '#' | '?' | '@' | '\\' => {
                 let sigil = String::from(c);
                 Some(Token::Sigil(sigil))
             }
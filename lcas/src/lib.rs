@@ -1,4 +1,5 @@
 pub mod as_state;
+pub mod diag;
 pub mod expr;
 pub mod lex;
 pub mod span;
@@ -1,6 +1,6 @@
 use std::any::Any;
 
-use crate::as_state::AsState;
+use crate::as_state::{Assembler, AsState};
 
 use target_tuples::Architecture;
 
@@ -18,7 +18,12 @@ pub trait TargetMachine {
 
     fn long_width(&self) -> usize;
 
-    fn assemble_insn(&self, opc: &str, state: &mut AsState) -> std::io::Result<()>;
+    /// Takes `state` as the full [`Assembler`] (which derefs to [`AsState`]
+    /// for every target that doesn't need anything more) rather than just
+    /// `AsState`, so a target that relaxes a branch/jump encoding can call
+    /// [`Assembler::resolve_local_backward`] to size it from a real,
+    /// already-known displacement instead of always assuming the worst case.
+    fn assemble_insn(&self, opc: &str, state: &mut Assembler) -> std::io::Result<()>;
     fn directive_names(&self) -> &[&str];
     fn handle_directive(&self, dir: &str, state: &mut AsState) -> std::io::Result<()>;
 
@@ -31,6 +36,29 @@ pub trait TargetMachine {
     fn newline_sensitive(&self) -> bool {
         true
     }
+
+    /// The DWARF register number CFI unwind info (`.cfi_*` directives, emitted
+    /// into `.eh_frame`) uses for the return address, i.e. the register
+    /// `DW_CFA_def_cfa`-relative unwinders restore `pc`/`ip` from.
+    ///
+    /// There's no universally-agreed number for a target that doesn't have an
+    /// established platform ABI to borrow one from, so this defaults to `0`
+    /// as an honest placeholder; only targets with a real published DWARF
+    /// register mapping should override it.
+    fn eh_frame_return_address_register(&self) -> u8 {
+        0
+    }
+
+    /// The CIE `data_alignment_factor` CFI unwind info divides every
+    /// `DW_CFA_offset`/`DW_CFA_offset_extended` operand by before encoding it.
+    ///
+    /// Defaults to "always push a full machine word", the convention most
+    /// real-world ABIs use (e.g. x86-64 SysV's `-8`), derived from
+    /// [`long_width`](Self::long_width) so it stays in step with whatever
+    /// that target considers its natural word width.
+    fn eh_frame_data_alignment_factor(&self) -> i64 {
+        -(self.long_width() as i64)
+    }
 }
 
 macro_rules! targ_defs{
@@ -50,4 +78,5 @@ targ_defs! {
     #[cfg(feature = "clever")]      arch clever;
     #[cfg(feature = "w65")]         arch w65;
     #[cfg(feature = "holey-bytes")] arch holeybytes;
+    #[cfg(feature = "x86")]         arch x86_64;
 }
@@ -15,6 +15,8 @@ use arch_ops::{
     traits::Address,
 };
 
+use smallvec::{smallvec, SmallVec};
+
 use super::TargetMachine;
 
 #[derive(Default, Clone, Hash, PartialEq, Eq)]
@@ -148,7 +150,7 @@ impl TargetMachine for CleverTargetMachine {
     fn assemble_insn(
         &self,
         opc: &str,
-        state: &mut crate::as_state::AsState,
+        state: &mut crate::as_state::Assembler,
     ) -> std::io::Result<()> {
         let insn = parse_insn(None, opc, state).ok_or_else(|| {
             std::io::Error::new(
@@ -471,7 +473,7 @@ fn parse_insn(
     state: &mut crate::as_state::AsState,
 ) -> Option<CleverInstruction> {
     if opc == "nop" {
-        let mut oprs = Vec::new();
+        let mut oprs = SmallVec::<[CleverOperand; 3]>::new();
         if let Some(Token::LineTerminator) | None = state.iter().peek().map(Spanned::body) {
         } else {
             for _ in 0..3 {
@@ -490,13 +492,13 @@ fn parse_insn(
         let opc = 0x7c8f;
         return Some(CleverInstruction::new(
             CleverOpcode::from_opcode(opc).unwrap(),
-            vec![],
+            smallvec![],
         ));
     } else if opc == "fret" {
         let opc = 0x7c8e;
         return Some(CleverInstruction::new(
             CleverOpcode::from_opcode(opc).unwrap(),
-            vec![],
+            smallvec![],
         ));
     }
     let opc = parse_mnemonic(opc)?;
@@ -512,7 +514,7 @@ fn parse_insn(
                     }
                     parse_operand(state, false)
                 })
-                .collect::<Option<Vec<_>>>()?;
+                .collect::<Option<SmallVec<[CleverOperand; 3]>>>()?;
 
             Some(CleverInstruction::new(opc, operands))
         }
@@ -540,7 +542,7 @@ fn parse_insn(
                 CleverOpcode::from_opcode(opc).unwrap()
             };
 
-            Some(CleverInstruction::new(opc, vec![op]))
+            Some(CleverInstruction::new(opc, smallvec![op]))
         }
         arch_ops::clever::CleverOperandKind::Size => {
             let size = match state.iter().next()?.into_inner() {
@@ -571,7 +573,7 @@ fn parse_insn(
 
             Some(CleverInstruction::new(
                 CleverOpcode::from_opcode(opc).unwrap(),
-                Vec::new(),
+                smallvec![],
             ))
         }
         arch_ops::clever::CleverOperandKind::Insn => {
@@ -598,7 +600,7 @@ fn parse_insn(
 
             Some(CleverInstruction::new(
                 CleverOpcode::from_opcode(opc).unwrap(),
-                Vec::new(),
+                smallvec![],
             ))
         }
         CleverOperandKind::HImmediate => {
@@ -614,7 +616,7 @@ fn parse_insn(
 
             Some(CleverInstruction::new(
                 CleverOpcode::from_opcode(opc).unwrap(),
-                Vec::new(),
+                smallvec![],
             ))
         }
     }
@@ -752,22 +754,6 @@ fn parse_uf(opc: &mut u16, mnemonic: &str) -> Option<()> {
     }
 }
 
-fn parse_size_suffix(opc: &mut u16, mnemonic: &str) -> Option<()> {
-    if mnemonic.starts_with('.') {
-        let suffix = &mnemonic[1..];
-        match suffix {
-            "8" | "byte" => (),
-            "16" | "half" => *opc |= 0x01,
-            "32" | "single" => *opc |= 0x02,
-            "64" | "double" => *opc |= 0x03,
-            _ => None?,
-        }
-        Some(())
-    } else {
-        None
-    }
-}
-
 fn parse_callsm(opc: &mut u16, mnemonic: &str) -> Option<()> {
     if mnemonic.starts_with('.') {
         let suffix = &mnemonic[1..];
@@ -810,8 +796,8 @@ clever_mnemonics! {
     ["movif",0x022,parse_uf],
     ["movfi",0x024,parse_uf],
     ["cvtf",0x026,parse_l00f],
-    ["repc",0x028,parse_none],
-    ["repi",0x029,parse_cc],
+    ["repbi",0x028,parse_cc],
+    ["repbc",0x029,parse_none],
     ["bcpy",0x02a,parse_none],
     ["bsto",0x02b,parse_none],
     ["bsca",0x02c,parse_none],
@@ -859,9 +845,9 @@ clever_mnemonics! {
 
     ["rpoll",0x230,parse_none],
 
-    ["vec",0x400,parse_size_suffix],
+    ["vec",0x400,parse_none],
     ["vmov",0x401,parse_none],
-    ["vshuffle",0x402,parse_size_suffix],
+    ["vshuffle",0x402,parse_none],
     ["vextract",0x403,parse_none],
     ["vcmp",0x404,parse_none],
     ["vtest",0x405,parse_none],
@@ -1,7 +1,7 @@
 use {
     super::TargetMachine,
     crate::{
-        as_state::{float_to_bytes_le, int_to_bytes_le, AsState},
+        as_state::{float_to_bytes_le, int_to_bytes_le, Assembler, AsState},
         expr::{parse_simple_expr, BinaryOp, Expression},
         lex::Token,
         span::Spanned,
@@ -63,7 +63,7 @@ impl TargetMachine for HbTargetMachine {
     }
 
     #[inline]
-    fn assemble_insn(&self, opc: &str, state: &mut AsState) -> std::io::Result<()> {
+    fn assemble_insn(&self, opc: &str, state: &mut Assembler) -> std::io::Result<()> {
         let opcode = Opcode::from_str(opc)
             .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid opcode"))?;
 
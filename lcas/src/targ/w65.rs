@@ -1,6 +1,13 @@
 use super::TargetMachine;
-use crate::as_state::int_to_bytes_le;
-use arch_ops::w65::W65Mode;
+use crate::as_state::{int_to_bytes_le, Assembler, AsState};
+use crate::expr::{parse_expression, BinaryOp, Expression};
+use crate::lex::Token;
+use crate::span::Spanned;
+
+use arch_ops::traits::Address;
+use arch_ops::w65::{
+    W65Address, W65Encoder, W65Instruction, W65Mode, W65Opcode, W65Operand, W65Register,
+};
 
 pub struct W65TargetMachine;
 
@@ -35,7 +42,7 @@ impl TargetMachine for W65TargetMachine {
         int_to_bytes_le(val, buf)
     }
 
-    fn float_to_bytes<'a>(&self, val: f64, buf: &'a mut [u8]) -> &'a mut [u8] {
+    fn float_to_bytes<'a>(&self, _val: f64, _buf: &'a mut [u8]) -> &'a mut [u8] {
         todo!()
     }
 
@@ -43,68 +50,37 @@ impl TargetMachine for W65TargetMachine {
         4
     }
 
-    fn assemble_insn(
-        &self,
-        opc: &str,
-        state: &mut crate::as_state::AsState,
-    ) -> std::io::Result<()> {
-        todo!()
+    fn assemble_insn(&self, opc: &str, state: &mut Assembler) -> std::io::Result<()> {
+        let insn = parse_insn(opc, state).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Could not parse w65 instruction `{}`", opc),
+            )
+        })?;
+
+        let mode = state.mach_data().downcast_ref::<W65Data>().unwrap().mode;
+
+        let mut enc = W65Encoder::new(state.output());
+        enc.set_mode_flags(mode);
+        enc.write_insn(insn)
     }
 
     fn directive_names(&self) -> &[&str] {
         &[
             ".acc8", ".acc16", ".idx8", ".idx16", ".m8", ".m16", ".x8", ".x16", ".mx8", ".mx16",
+            ".a8", ".a16", ".i8", ".i16",
         ]
     }
 
-    fn handle_directive(
-        &self,
-        dir: &str,
-        state: &mut crate::as_state::AsState,
-    ) -> std::io::Result<()> {
+    fn handle_directive(&self, dir: &str, state: &mut AsState) -> std::io::Result<()> {
+        let data = state.mach_data_mut().downcast_mut::<W65Data>().unwrap();
         match dir {
-            ".acc8" | ".m8" => {
-                state
-                    .mach_data_mut()
-                    .downcast_mut::<W65Data>()
-                    .unwrap()
-                    .mode |= W65Mode::M;
-            }
-            ".acc16" | ".m16" => {
-                state
-                    .mach_data_mut()
-                    .downcast_mut::<W65Data>()
-                    .unwrap()
-                    .mode &= !W65Mode::M;
-            }
-            ".idx8" | ".x8" => {
-                state
-                    .mach_data_mut()
-                    .downcast_mut::<W65Data>()
-                    .unwrap()
-                    .mode |= W65Mode::X;
-            }
-            ".idx16" | ".x16" => {
-                state
-                    .mach_data_mut()
-                    .downcast_mut::<W65Data>()
-                    .unwrap()
-                    .mode &= !W65Mode::X;
-            }
-            ".mx8" => {
-                state
-                    .mach_data_mut()
-                    .downcast_mut::<W65Data>()
-                    .unwrap()
-                    .mode |= W65Mode::M | W65Mode::X;
-            }
-            ".mx16" => {
-                state
-                    .mach_data_mut()
-                    .downcast_mut::<W65Data>()
-                    .unwrap()
-                    .mode &= !(W65Mode::M | W65Mode::X);
-            }
+            ".acc8" | ".m8" | ".a8" => data.mode |= W65Mode::M,
+            ".acc16" | ".m16" | ".a16" => data.mode &= !W65Mode::M,
+            ".idx8" | ".x8" | ".i8" => data.mode |= W65Mode::X,
+            ".idx16" | ".x16" | ".i16" => data.mode &= !W65Mode::X,
+            ".mx8" => data.mode |= W65Mode::M | W65Mode::X,
+            ".mx16" => data.mode &= !(W65Mode::M | W65Mode::X),
             _ => unreachable!(),
         }
         Ok(())
@@ -119,6 +95,457 @@ pub fn get_target_def() -> &'static W65TargetMachine {
     &W65TargetMachine
 }
 
-pub enum W65Expression {
-    Immediate(u16),
+/// Whether an operand token stream decodes to a plain address-mode
+/// instruction (most of the ISA), an instruction that may be used bare
+/// to mean "operate on the accumulator" (`asl`/`lsr`/`rol`/`ror`/`inc`/
+/// `dec`), a no-operand instruction, or a relative branch.
+enum OpClass {
+    Implied,
+    AccOrAddr,
+    Addr,
+    Branch,
+}
+
+/// Maps a mnemonic to its [`W65Opcode`] and [`OpClass`] -- a bounded,
+/// commonly-used subset of the 65C816 covering loads/stores, the ALU
+/// group, increment/decrement, stack ops, flag ops, unconditional and
+/// conditional control flow, and the accumulator shift/rotate group.
+/// Block-move (`mvn`/`mvp`), COP/WDM, and the `rep`/`sep` status-bit
+/// instructions aren't covered yet.
+fn opcode_for(mnemonic: &str) -> Option<(W65Opcode, OpClass)> {
+    use OpClass::*;
+    use W65Opcode::*;
+    Some(match mnemonic {
+        "lda" => (Lda, Addr),
+        "sta" => (Sta, Addr),
+        "ldx" => (Ldx, Addr),
+        "ldy" => (Ldy, Addr),
+        "stx" => (Stx, Addr),
+        "sty" => (Sty, Addr),
+        "stz" => (Stz, Addr),
+        "adc" => (Adc, Addr),
+        "sbc" => (Sbc, Addr),
+        "cmp" => (Cmp, Addr),
+        "cpx" => (Cpx, Addr),
+        "cpy" => (Cpy, Addr),
+        "and" => (And, Addr),
+        "ora" => (Ora, Addr),
+        "eor" => (Eor, Addr),
+        "bit" => (Bit, Addr),
+        "jmp" => (Jmp, Addr),
+        "jsr" => (Jsr, Addr),
+        "asl" => (Asl, AccOrAddr),
+        "lsr" => (Lsr, AccOrAddr),
+        "rol" => (Rol, AccOrAddr),
+        "ror" => (Ror, AccOrAddr),
+        "inc" => (Inc, AccOrAddr),
+        "dec" => (Dec, AccOrAddr),
+        "inx" => (Inx, Implied),
+        "iny" => (Iny, Implied),
+        "dex" => (Dex, Implied),
+        "dey" => (Dey, Implied),
+        "tax" => (Tax, Implied),
+        "tay" => (Tay, Implied),
+        "txa" => (Txa, Implied),
+        "tya" => (Tya, Implied),
+        "txs" => (Txs, Implied),
+        "tsx" => (Tsx, Implied),
+        "pha" => (Pha, Implied),
+        "pla" => (Pla, Implied),
+        "phx" => (Phx, Implied),
+        "plx" => (Plx, Implied),
+        "phy" => (Phy, Implied),
+        "ply" => (Ply, Implied),
+        "php" => (Php, Implied),
+        "plp" => (Plp, Implied),
+        "rts" => (Rts, Implied),
+        "rtl" => (Rtl, Implied),
+        "rti" => (Rti, Implied),
+        "brk" => (Brk, Implied),
+        "nop" => (Nop, Implied),
+        "clc" => (Clc, Implied),
+        "sec" => (Sec, Implied),
+        "cld" => (Cld, Implied),
+        "sei" => (Sei, Implied),
+        "cli" => (Cli, Implied),
+        "clv" => (Clv, Implied),
+        "sed" => (Sed, Implied),
+        "bcc" => (Bcc, Branch),
+        "bcs" => (Bcs, Branch),
+        "beq" => (Beq, Branch),
+        "bne" => (Bne, Branch),
+        "bmi" => (Bmi, Branch),
+        "bpl" => (Bpl, Branch),
+        "bvc" => (Bvc, Branch),
+        "bvs" => (Bvs, Branch),
+        "bra" => (Bra, Branch),
+        _ => None?,
+    })
+}
+
+fn parse_insn(mnemonic: &str, state: &mut Assembler) -> Option<W65Instruction> {
+    let (opc, class) = opcode_for(mnemonic)?;
+
+    let at_end = matches!(
+        state.iter().peek().map(Spanned::body),
+        Some(Token::LineTerminator) | None
+    );
+
+    let opr = match class {
+        OpClass::Implied => W65Operand::Implied,
+        OpClass::AccOrAddr if at_end => W65Operand::Register(W65Register::A),
+        OpClass::AccOrAddr | OpClass::Addr => parse_operand(state)?,
+        OpClass::Branch => {
+            let pc = state.output().offset() as u64;
+            let target = parse_target_addr(state)?;
+            branch_operand(opc, target, pc, state)
+        }
+    };
+
+    Some(W65Instruction::new(opc, opr))
+}
+
+/// Picks the real encoding for a branch's target, relaxing it on the spot
+/// wherever lc-as can prove that's safe.
+///
+/// `bra` is the only branch with more than one hardware encoding to choose
+/// from (`Rel8`, 0x80, or `Rel16`/`brl`, 0x82 -- every conditional branch is
+/// `Rel8`-only and always was). A backward reference to a label already
+/// defined in the current section (see [`Assembler::resolve_local_backward`])
+/// has a known displacement right now, so it gets the smallest encoding that
+/// actually reaches -- instead of this target's previous behavior of always
+/// emitting `Rel8` regardless of distance, which would fail to link (or, for
+/// `bra`, needlessly refuse to assemble) any backward `bra` more than 127
+/// bytes back even though the hardware can reach it.
+///
+/// A forward reference, or a symbol from another section/object entirely,
+/// can't be sized here: lc-as assembles in a single streaming pass over the
+/// token stream and never revisits bytes it has already written, so there's
+/// no way to learn such a label's final address before this instruction has
+/// to be emitted. Those get the pessimistic `Rel16` form through the
+/// ordinary `R_WC65C816_REL16` relocation, which the linker already resolves
+/// (and errors on cleanly if even that doesn't reach) -- correct, if not
+/// maximally small. Shrinking that relocation back down to `Rel8` at link
+/// time when the real displacement turns out to fit, the way
+/// `Elf32W65HowTo::RelaxJmp` already does for an oversized `jmp`, is a
+/// natural follow-on but out of scope here.
+fn branch_operand(
+    opc: W65Opcode,
+    target: Address,
+    pc: u64,
+    state: &mut Assembler,
+) -> W65Operand {
+    if opc == W65Opcode::Bra {
+        if let Address::Symbol { name, disp: 0 } = &target {
+            if let Some(target_off) = state.resolve_local_backward(name) {
+                let rel8 = (target_off as i64) - (pc as i64 + 2);
+                if let Ok(rel8) = i8::try_from(rel8) {
+                    return W65Operand::Address(W65Address::Rel8(Address::Disp(rel8 as i64)));
+                }
+
+                let rel16 = (target_off as i64) - (pc as i64 + 3);
+                if let Ok(rel16) = i16::try_from(rel16) {
+                    return W65Operand::Address(W65Address::Rel16(Address::Disp(rel16 as i64)));
+                }
+            }
+        }
+
+        return W65Operand::Address(W65Address::Rel16(target));
+    }
+
+    W65Operand::Address(W65Address::Rel8(target))
+}
+
+#[derive(Clone, Copy)]
+enum AddrWidth {
+    Direct,
+    Abs,
+    Long,
+}
+
+/// An explicit width keyword before an address expression -- a direct-page
+/// operand and a bank-0 absolute operand can share the same numeric value,
+/// so (like the `byte`/`half`/... keywords the Clever target uses) a
+/// leading `direct`/`abs`/`long` keyword lets the programmer force which
+/// one is meant instead of relying on the value-magnitude heuristic in
+/// [`width_for`]. [`parse_operand`] only looks for it at the start of a
+/// `(...)`/`[...]` group; a bare operand's width always comes from
+/// [`width_for`].
+fn take_width_prefix<I: Iterator<Item = Spanned<Token>>>(
+    it: &mut std::iter::Peekable<I>,
+) -> Option<AddrWidth> {
+    match it.peek()?.body() {
+        Token::Identifier(id) => {
+            let width = match &**id {
+                "direct" => AddrWidth::Direct,
+                "abs" | "absolute" => AddrWidth::Abs,
+                "long" => AddrWidth::Long,
+                _ => return None,
+            };
+            it.next();
+            Some(width)
+        }
+        _ => None,
+    }
+}
+
+fn expr_to_address(expr: Expression) -> Option<Address> {
+    match expr {
+        Expression::Integer(val) => Some(Address::Abs(val)),
+        Expression::Symbol(name) => Some(Address::Symbol { name, disp: 0 }),
+        Expression::Binary(BinaryOp::Add, lhs, rhs) => match (expr_to_address(*lhs)?, *rhs) {
+            (Address::Symbol { name, disp }, Expression::Integer(off)) => Some(Address::Symbol {
+                name,
+                disp: disp.wrapping_add(off as i64),
+            }),
+            (Address::Abs(base), Expression::Integer(off)) => {
+                Some(Address::Abs(base.wrapping_add(off)))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn width_for(addr: &Address, width: Option<AddrWidth>) -> AddrWidth {
+    width.unwrap_or(match addr {
+        Address::Abs(val) if *val <= 0xFF => AddrWidth::Direct,
+        Address::Abs(val) if *val <= 0xFFFF => AddrWidth::Abs,
+        Address::Abs(_) => AddrWidth::Long,
+        _ => AddrWidth::Abs,
+    })
+}
+
+fn sized_address(addr: Address, width: Option<AddrWidth>) -> W65Address {
+    match width_for(&addr, width) {
+        AddrWidth::Direct => W65Address::Direct(addr),
+        AddrWidth::Abs => W65Address::Absolute(addr),
+        AddrWidth::Long => W65Address::Long(addr),
+    }
+}
+
+fn parse_target_addr(state: &mut AsState) -> Option<Address> {
+    let expr = parse_expression(state.iter());
+    expr_to_address(expr)
+}
+
+/// Parses one operand: `#expr` (immediate), a bare `a` for
+/// accumulator-addressed shifts, `(expr)`/`(expr,x)`/`(expr),y` indirect
+/// forms, `[expr],y` indirect-long-indexed, `expr,x`/`expr,y` indexed, or
+/// a plain `expr` (direct/absolute/long, chosen per [`width_for`]).
+/// Bare `[expr]` (indirect-long with no index) isn't supported: encoding
+/// it hits an unimplemented path in `arch_ops::w65::W65Instruction::addr_mode`.
+fn parse_operand(state: &mut AsState) -> Option<W65Operand> {
+    match state.iter().peek()?.body() {
+        Token::Sigil(s) if s == "#" => {
+            state.iter().next();
+            let expr = parse_expression(state.iter());
+            Some(W65Operand::Immediate(expr_to_u16(expr)?))
+        }
+        Token::Identifier(id) if id == "a" || id == "A" => {
+            state.iter().next();
+            Some(W65Operand::Register(W65Register::A))
+        }
+        Token::Group('(' | '[', _) => {
+            let open = match state.iter().peek()?.body() {
+                Token::Group(open, _) => *open,
+                _ => unreachable!(),
+            };
+            let group = match state.iter().next().unwrap().into_inner() {
+                Token::Group(_, group) => group,
+                _ => unreachable!(),
+            };
+            let mut inner = group.into_iter().peekable();
+            let width = take_width_prefix(&mut inner);
+            let expr = parse_expression(&mut inner);
+            let base = expr_to_address(expr)?;
+
+            let indexed_x = matches!(
+                inner.peek().map(Spanned::body),
+                Some(Token::Sigil(s)) if s == ","
+            );
+            if indexed_x {
+                inner.next();
+                match inner.next()?.into_inner() {
+                    Token::Identifier(id) if id.eq_ignore_ascii_case("x") => {}
+                    _ => None?,
+                }
+            }
+
+            let has_trailing_comma = matches!(
+                state.iter().peek().map(Spanned::body),
+                Some(Token::Sigil(s)) if s == ","
+            );
+            let trailing_y = if has_trailing_comma {
+                state.iter().next();
+                match state.iter().next()?.into_inner() {
+                    Token::Identifier(id) if id.eq_ignore_ascii_case("y") => true,
+                    _ => None?,
+                }
+            } else {
+                false
+            };
+
+            let addr = match (open, indexed_x, trailing_y) {
+                ('(', false, false) => W65Address::Indirect(Box::new(sized_address(base, width))),
+                ('(', true, false) => W65Address::IndexedX(Box::new(W65Address::Indirect(
+                    Box::new(sized_address(base, width)),
+                ))),
+                ('(', false, true) => W65Address::IndexedY(Box::new(W65Address::Indirect(
+                    Box::new(sized_address(base, width)),
+                ))),
+                ('[', false, true) => W65Address::IndexedY(Box::new(W65Address::IndirectLong(
+                    Box::new(sized_address(base, width)),
+                ))),
+                _ => None?,
+            };
+
+            Some(W65Operand::Address(addr))
+        }
+        _ => {
+            let expr = parse_expression(state.iter());
+            let base = expr_to_address(expr)?;
+
+            match state.iter().peek().map(Spanned::body) {
+                Some(Token::Sigil(s)) if s == "," => {
+                    state.iter().next();
+                    match state.iter().next()?.into_inner() {
+                        Token::Identifier(id) if id.eq_ignore_ascii_case("x") => Some(
+                            W65Operand::Address(W65Address::IndexedX(Box::new(sized_address(
+                                base, None,
+                            )))),
+                        ),
+                        Token::Identifier(id) if id.eq_ignore_ascii_case("y") => Some(
+                            W65Operand::Address(W65Address::IndexedY(Box::new(sized_address(
+                                base, None,
+                            )))),
+                        ),
+                        _ => None,
+                    }
+                }
+                _ => Some(W65Operand::Address(sized_address(base, None))),
+            }
+        }
+    }
+}
+
+fn expr_to_u16(expr: Expression) -> Option<u16> {
+    match expr {
+        Expression::Integer(val) => Some(val as u16),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::as_state::AssemblerCallbacks;
+
+    /// Reports `backward` from `resolve_local_backward`, the one callback
+    /// `branch_operand` actually calls; every other directive isn't
+    /// exercised by these tests.
+    struct TestCallbacks {
+        backward: Option<u64>,
+    }
+
+    impl AssemblerCallbacks for TestCallbacks {
+        fn handle_directive(&self, _asm: &mut Assembler, _dir: &str) -> std::io::Result<()> {
+            unreachable!()
+        }
+        fn create_symbol_now(&self, _asm: &mut Assembler, _sym: &str) {
+            unreachable!()
+        }
+        fn create_absolute_symbol(&self, _asm: &mut Assembler, _sym: &str, _value: u64) {
+            unreachable!()
+        }
+        fn resolve_local_backward(&self, _asm: &Assembler, _sym: &str) -> Option<u64> {
+            self.backward
+        }
+    }
+
+    /// `branch_operand` never touches the output (its `pc` comes in as a
+    /// plain argument), so this only needs to satisfy `Assembler::new`'s
+    /// signature.
+    struct NullWriter;
+
+    impl std::io::Write for NullWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl arch_ops::traits::InsnWrite for NullWriter {
+        fn write_addr(&mut self, _size: usize, _addr: Address, _rel: bool) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn write_reloc(&mut self, _reloc: arch_ops::traits::Reloc) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn offset(&self) -> usize {
+            0
+        }
+    }
+
+    fn assembler_with<'a>(
+        callbacks: &'a TestCallbacks,
+        tokens: &'a mut std::iter::Empty<Spanned<Token>>,
+        include_dirs: &'a [std::path::PathBuf],
+    ) -> Assembler<'a> {
+        Assembler::new(
+            get_target_def(),
+            Box::new(NullWriter),
+            Box::new(()),
+            callbacks,
+            tokens,
+            include_dirs,
+        )
+    }
+
+    #[test]
+    fn short_backward_bra_uses_rel8() {
+        let callbacks = TestCallbacks { backward: Some(90) };
+        let mut tokens = std::iter::empty();
+        let include_dirs = [];
+        let mut state = assembler_with(&callbacks, &mut tokens, &include_dirs);
+
+        let target = Address::Symbol { name: "L1".to_string(), disp: 0 };
+        let opr = branch_operand(W65Opcode::Bra, target, 100, &mut state);
+
+        assert_eq!(
+            opr,
+            W65Operand::Address(W65Address::Rel8(Address::Disp(90 - (100 + 2))))
+        );
+    }
+
+    #[test]
+    fn backward_bra_over_127_bytes_falls_back_to_rel16() {
+        let callbacks = TestCallbacks { backward: Some(0) };
+        let mut tokens = std::iter::empty();
+        let include_dirs = [];
+        let mut state = assembler_with(&callbacks, &mut tokens, &include_dirs);
+
+        let target = Address::Symbol { name: "L1".to_string(), disp: 0 };
+        let opr = branch_operand(W65Opcode::Bra, target, 1000, &mut state);
+
+        assert_eq!(
+            opr,
+            W65Operand::Address(W65Address::Rel16(Address::Disp(0 - (1000 + 3))))
+        );
+    }
+
+    #[test]
+    fn forward_or_external_bra_gets_rel16_relocation() {
+        let callbacks = TestCallbacks { backward: None };
+        let mut tokens = std::iter::empty();
+        let include_dirs = [];
+        let mut state = assembler_with(&callbacks, &mut tokens, &include_dirs);
+
+        let target = Address::Symbol { name: "later".to_string(), disp: 0 };
+        let opr = branch_operand(W65Opcode::Bra, target.clone(), 100, &mut state);
+
+        assert_eq!(opr, W65Operand::Address(W65Address::Rel16(target)));
+    }
 }
@@ -0,0 +1,492 @@
+use arch_ops::traits::Address;
+use arch_ops::x86::codegen::{
+    X86CodegenOpcode, X86Displacement, X86Encoder, X86Instruction, X86MemoryOperand, X86Operand,
+};
+use arch_ops::x86::{X86Mode, X86Register, X86RegisterClass};
+
+use crate::as_state::{int_to_bytes_le, Assembler, AsState};
+use crate::expr::{BinaryOp, Expression, UnaryOp};
+use crate::lex::Token;
+use crate::span::Spanned;
+
+use super::TargetMachine;
+
+/// Whether `.att_syntax` or `.intel_syntax` is currently in effect -- this
+/// is the only thing `X86Data` tracks, since everything else (the target
+/// word width, what instructions exist) is fixed for the `x86_64` target.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum X86Syntax {
+    Att,
+    Intel,
+}
+
+pub struct X86Data {
+    syntax: X86Syntax,
+}
+
+pub struct X86TargetMachine;
+
+impl TargetMachine for X86TargetMachine {
+    fn group_chars(&self) -> &[char] {
+        &['(', '[']
+    }
+
+    fn comment_chars(&self) -> &[char] {
+        &['#']
+    }
+
+    fn extra_sym_chars(&self) -> &[char] {
+        &['.', '$']
+    }
+
+    fn extra_sym_part_chars(&self) -> &[char] {
+        &['.', '$']
+    }
+
+    fn extra_sigil_chars(&self) -> &[char] {
+        &['%', '$']
+    }
+
+    fn create_data(&self) -> Box<dyn std::any::Any> {
+        Box::new(X86Data {
+            syntax: X86Syntax::Att,
+        })
+    }
+
+    fn int_to_bytes<'a>(&self, val: u128, buf: &'a mut [u8]) -> &'a mut [u8] {
+        int_to_bytes_le(val, buf)
+    }
+
+    fn float_to_bytes<'a>(&self, _val: f64, _buf: &'a mut [u8]) -> &'a mut [u8] {
+        todo!("float_to_bytes")
+    }
+
+    fn long_width(&self) -> usize {
+        8
+    }
+
+    fn eh_frame_return_address_register(&self) -> u8 {
+        16
+    }
+
+    fn assemble_insn(&self, opc: &str, state: &mut Assembler) -> std::io::Result<()> {
+        let syntax = state
+            .mach_data()
+            .downcast_ref::<X86Data>()
+            .unwrap()
+            .syntax;
+
+        let insn = parse_insn(opc, syntax, state).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Could not parse x86_64 instruction `{}`", opc),
+            )
+        })?;
+
+        let mut enc = X86Encoder::new(state.output(), X86Mode::Long);
+        enc.write_insn(insn)
+    }
+
+    fn directive_names(&self) -> &[&str] {
+        &[".att_syntax", ".intel_syntax"]
+    }
+
+    fn handle_directive(&self, dir: &str, state: &mut AsState) -> std::io::Result<()> {
+        let syntax = match dir {
+            ".att_syntax" => X86Syntax::Att,
+            ".intel_syntax" => X86Syntax::Intel,
+            _ => unreachable!(),
+        };
+
+        state
+            .mach_data_mut()
+            .downcast_mut::<X86Data>()
+            .unwrap()
+            .syntax = syntax;
+
+        Ok(())
+    }
+}
+
+pub fn get_target_def() -> &'static X86TargetMachine {
+    &X86TargetMachine
+}
+
+/// The 64/32/16/8-bit general-purpose registers this target recognizes by
+/// name. Segment, control/debug, and vector registers aren't covered --
+/// this is a general-purpose-integer-code subset, not the full ISA.
+fn parse_gpr(name: &str) -> Option<X86Register> {
+    use X86Register::*;
+    Some(match name {
+        "al" => Al,
+        "cl" => Cl,
+        "dl" => Dl,
+        "bl" => Bl,
+        "ah" => Ah,
+        "ch" => Ch,
+        "dh" => Dh,
+        "bh" => Bh,
+        "spl" => Spl,
+        "bpl" => Bpl,
+        "sil" => Sil,
+        "dil" => Dil,
+        "r8b" => R8b,
+        "r9b" => R9b,
+        "r10b" => R10b,
+        "r11b" => R11b,
+        "r12b" => R12b,
+        "r13b" => R13b,
+        "r14b" => R14b,
+        "r15b" => R15b,
+        "ax" => Ax,
+        "cx" => Cx,
+        "dx" => Dx,
+        "bx" => Bx,
+        "sp" => Sp,
+        "bp" => Bp,
+        "si" => Si,
+        "di" => Di,
+        "r8w" => R8w,
+        "r9w" => R9w,
+        "r10w" => R10w,
+        "r11w" => R11w,
+        "r12w" => R12w,
+        "r13w" => R13w,
+        "r14w" => R14w,
+        "r15w" => R15w,
+        "eax" => Eax,
+        "ecx" => Ecx,
+        "edx" => Edx,
+        "ebx" => Ebx,
+        "esp" => Esp,
+        "ebp" => Ebp,
+        "esi" => Esi,
+        "edi" => Edi,
+        "r8d" => R8d,
+        "r9d" => R9d,
+        "r10d" => R10d,
+        "r11d" => R11d,
+        "r12d" => R12d,
+        "r13d" => R13d,
+        "r14d" => R14d,
+        "r15d" => R15d,
+        "rax" => Rax,
+        "rcx" => Rcx,
+        "rdx" => Rdx,
+        "rbx" => Rbx,
+        "rsp" => Rsp,
+        "rbp" => Rbp,
+        "rsi" => Rsi,
+        "rdi" => Rdi,
+        "r8" => R8,
+        "r9" => R9,
+        "r10" => R10,
+        "r11" => R11,
+        "r12" => R12,
+        "r13" => R13,
+        "r14" => R14,
+        "r15" => R15,
+        _ => return None,
+    })
+}
+
+/// The mnemonics this target knows how to assemble, and how many operands
+/// each expects. This is a deliberately bounded common subset (integer
+/// arithmetic/compare/data-movement and straight-line control flow) --
+/// SSE/AVX and string instructions aren't supported yet. A memory operand
+/// takes its size from a sibling register, or else from an AT&T mnemonic
+/// suffix (see [`memory_class`]); Intel syntax has neither a suffix nor a
+/// `ptr` size keyword here, so an Intel memory+immediate pair has no size
+/// and fails to parse.
+fn opcode_for(mnemonic: &str) -> Option<X86CodegenOpcode> {
+    use X86CodegenOpcode::*;
+    Some(match mnemonic {
+        "mov" => Mov,
+        "add" => Add,
+        "sub" => Sub,
+        "cmp" => Cmp,
+        "test" => Test,
+        "push" => Push,
+        "pop" => Pop,
+        "inc" => Inc,
+        "dec" => Dec,
+        "lea" => Lea,
+        "call" => Call,
+        "jmp" => Jmp,
+        "jo" => Jo,
+        "jno" => Jno,
+        "jb" | "jc" | "jnae" => Jb,
+        "jnb" | "jnc" | "jae" => Jnb,
+        "jz" | "je" => Jz,
+        "jnz" | "jne" => Jnz,
+        "jbe" | "jna" => Jbe,
+        "jnbe" | "ja" => Jnbe,
+        "js" => Js,
+        "jns" => Jns,
+        "jp" | "jpe" => Jp,
+        "jnp" | "jpo" => Jnp,
+        "jl" | "jnge" => Jl,
+        "jnl" | "jge" => Jnl,
+        "jle" | "jng" => Jle,
+        "jnle" | "jg" => Jnle,
+        "ret" => Ret,
+        "nop" => Nop,
+        "int3" => Int3,
+        _ => return None,
+    })
+}
+
+/// AT&T mnemonics carry an optional size suffix (`movl`, `addq`, ...) that
+/// matters only when every operand is a bare immediate or memory location
+/// (registers already say their own size). Strips a recognized suffix and
+/// reports the class it named, so memory-only operands still have
+/// somewhere to get a size from.
+fn strip_att_suffix(mnemonic: &str) -> (&str, Option<X86RegisterClass>) {
+    if mnemonic.len() <= 1 {
+        return (mnemonic, None);
+    }
+    let (base, suffix) = mnemonic.split_at(mnemonic.len() - 1);
+    let class = match suffix {
+        "b" => Some(X86RegisterClass::Byte),
+        "w" => Some(X86RegisterClass::Word),
+        "l" => Some(X86RegisterClass::Double),
+        "q" => Some(X86RegisterClass::Quad),
+        _ => None,
+    };
+    match class {
+        Some(class) if opcode_for(base).is_some() => (base, Some(class)),
+        _ => (mnemonic, None),
+    }
+}
+
+/// An operand not yet tied to a concrete [`X86Operand`] -- memory operands
+/// need a size class that isn't known until every operand in the
+/// instruction has been parsed (it's taken from a sibling register, or
+/// from an AT&T mnemonic suffix).
+enum RawOperand {
+    Register(X86Register),
+    Immediate(i64),
+    Memory(X86MemoryOperand),
+    Symbol(String, i64),
+}
+
+/// A small algebra for the addresses that can appear inside `(...)`/`[...]`
+/// groups or as bare operands -- mirrors `clever.rs`'s `CleverExpr`, scaled
+/// down to what x86's simple base+displacement addressing needs (no
+/// scale/index support yet).
+enum X86Expr {
+    Register(X86Register),
+    RegDisp(X86Register, i64),
+    Immediate(i128),
+    Symbol(String, i64),
+}
+
+fn convert_expr(ex: Expression) -> Option<X86Expr> {
+    match ex {
+        Expression::Symbol(s) => Some(match parse_gpr(&s) {
+            Some(reg) => X86Expr::Register(reg),
+            None => X86Expr::Symbol(s, 0),
+        }),
+        Expression::Integer(v) => Some(X86Expr::Immediate(v as i128)),
+        Expression::Unary(UnaryOp::Neg | UnaryOp::Umn, inner) => match convert_expr(*inner)? {
+            X86Expr::Immediate(v) => Some(X86Expr::Immediate(-v)),
+            _ => None,
+        },
+        Expression::Binary(BinaryOp::Add, left, right) => {
+            match (convert_expr(*left)?, convert_expr(*right)?) {
+                (X86Expr::Register(reg), X86Expr::Immediate(disp))
+                | (X86Expr::Immediate(disp), X86Expr::Register(reg)) => {
+                    Some(X86Expr::RegDisp(reg, disp as i64))
+                }
+                (X86Expr::RegDisp(reg, d), X86Expr::Immediate(disp))
+                | (X86Expr::Immediate(disp), X86Expr::RegDisp(reg, d)) => {
+                    Some(X86Expr::RegDisp(reg, d + disp as i64))
+                }
+                (X86Expr::Symbol(s, d), X86Expr::Immediate(v))
+                | (X86Expr::Immediate(v), X86Expr::Symbol(s, d)) => {
+                    Some(X86Expr::Symbol(s, d + v as i64))
+                }
+                (X86Expr::Immediate(a), X86Expr::Immediate(b)) => {
+                    Some(X86Expr::Immediate(a + b))
+                }
+                _ => None,
+            }
+        }
+        Expression::Binary(BinaryOp::Sub, left, right) => match convert_expr(*right)? {
+            X86Expr::Immediate(v) => {
+                convert_expr(Expression::Binary(
+                    BinaryOp::Add,
+                    left,
+                    Box::new(Expression::Integer((-v) as u128)),
+                ))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn expr_to_memory(ex: X86Expr) -> Option<X86MemoryOperand> {
+    Some(match ex {
+        X86Expr::Register(reg) => X86MemoryOperand::Indirect { reg, disp: None },
+        X86Expr::RegDisp(reg, disp) => X86MemoryOperand::Indirect {
+            reg,
+            disp: Some(X86Displacement::Offset(disp as i32)),
+        },
+        X86Expr::Symbol(name, disp) => X86MemoryOperand::AbsAddr(Address::Symbol { name, disp }),
+        X86Expr::Immediate(v) => X86MemoryOperand::AbsAddr(Address::Abs(v as u128)),
+    })
+}
+
+/// Parses one operand in AT&T order (`%reg`, `$imm`, `(%base)`, or a
+/// bare symbol for branch targets). There is no `disp(%base)` form: a
+/// displacement written before the group is not parsed as part of a
+/// memory operand.
+fn parse_operand_att(state: &mut AsState) -> Option<RawOperand> {
+    let iter = state.iter();
+    match iter.peek()?.body() {
+        Token::Sigil(s) if s == "%" => {
+            iter.next();
+            match iter.next()?.into_inner() {
+                Token::Identifier(name) => parse_gpr(&name).map(RawOperand::Register),
+                _ => None,
+            }
+        }
+        Token::Sigil(s) if s == "$" => {
+            iter.next();
+            let expr = crate::expr::parse_expression(iter);
+            match convert_expr(expr)? {
+                X86Expr::Immediate(v) => Some(RawOperand::Immediate(v as i64)),
+                _ => None,
+            }
+        }
+        Token::Group('(', _) => match iter.next().unwrap().into_inner() {
+            Token::Group('(', group) => {
+                let mut inner = group.into_iter().peekable();
+                match inner.next()?.into_inner() {
+                    Token::Sigil(s) if s == "%" => match inner.next()?.into_inner() {
+                        Token::Identifier(name) => {
+                            let reg = parse_gpr(&name)?;
+                            Some(RawOperand::Memory(X86MemoryOperand::Indirect {
+                                reg,
+                                disp: None,
+                            }))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            _ => unreachable!(),
+        },
+        _ => {
+            let expr = crate::expr::parse_expression(iter);
+            match convert_expr(expr)? {
+                X86Expr::Symbol(name, disp) => Some(RawOperand::Symbol(name, disp)),
+                X86Expr::Immediate(v) => Some(RawOperand::Immediate(v as i64)),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Parses one operand in Intel order (bare register name, bare immediate,
+/// `[base]`/`[base+disp]`, or a bare symbol for branch targets). A leading
+/// displacement before the group, as in AT&T's `disp(%base)`, isn't
+/// Intel syntax, so it's not accepted here.
+fn parse_operand_intel(state: &mut AsState) -> Option<RawOperand> {
+    let iter = state.iter();
+    match iter.peek()?.body() {
+        Token::Group('[', _) => match iter.next().unwrap().into_inner() {
+            Token::Group('[', group) => {
+                let mut inner = group.into_iter().peekable();
+                let expr = crate::expr::parse_expression(&mut inner);
+                expr_to_memory(convert_expr(expr)?).map(RawOperand::Memory)
+            }
+            _ => unreachable!(),
+        },
+        Token::Identifier(id) if parse_gpr(id).is_some() => {
+            let name = id.clone();
+            iter.next();
+            Some(RawOperand::Register(parse_gpr(&name)?))
+        }
+        _ => {
+            let expr = crate::expr::parse_expression(iter);
+            match convert_expr(expr)? {
+                X86Expr::Symbol(name, disp) => Some(RawOperand::Symbol(name, disp)),
+                X86Expr::Immediate(v) => Some(RawOperand::Immediate(v as i64)),
+                X86Expr::Register(reg) => Some(RawOperand::Register(reg)),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn parse_operand(state: &mut AsState, syntax: X86Syntax) -> Option<RawOperand> {
+    match syntax {
+        X86Syntax::Att => parse_operand_att(state),
+        X86Syntax::Intel => parse_operand_intel(state),
+    }
+}
+
+/// Picks the memory operand's size class off a sibling register operand,
+/// or falls back to the class named by an AT&T mnemonic suffix.
+fn memory_class(oprs: &[RawOperand], suffix: Option<X86RegisterClass>) -> Option<X86RegisterClass> {
+    oprs.iter()
+        .find_map(|o| match o {
+            RawOperand::Register(reg) => Some(reg.class()),
+            _ => None,
+        })
+        .or(suffix)
+}
+
+fn finish_operands(
+    raw: Vec<RawOperand>,
+    suffix: Option<X86RegisterClass>,
+) -> Option<smallvec::SmallVec<[X86Operand; 3]>> {
+    let class = memory_class(&raw, suffix);
+    raw.into_iter()
+        .map(|o| {
+            Some(match o {
+                RawOperand::Register(reg) => X86Operand::Register(reg),
+                RawOperand::Immediate(v) => X86Operand::Immediate(v),
+                RawOperand::Symbol(name, disp) => {
+                    X86Operand::RelOffset(Address::Symbol { name, disp })
+                }
+                RawOperand::Memory(mem) => X86Operand::Memory(class?, None, mem),
+            })
+        })
+        .collect()
+}
+
+fn parse_insn(opc: &str, syntax: X86Syntax, state: &mut AsState) -> Option<X86Instruction> {
+    let (mnemonic, suffix) = match syntax {
+        X86Syntax::Att => strip_att_suffix(opc),
+        X86Syntax::Intel => (opc, None),
+    };
+    let opcode = opcode_for(mnemonic)?;
+
+    let mut raw = Vec::with_capacity(2);
+    if !matches!(
+        state.iter().peek().map(Spanned::body),
+        Some(Token::LineTerminator) | None
+    ) {
+        loop {
+            raw.push(parse_operand(state, syntax)?);
+            match state.iter().peek().map(Spanned::body) {
+                Some(Token::Sigil(s)) if s == "," => {
+                    state.iter().next();
+                    continue;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    if syntax == X86Syntax::Att && raw.len() == 2 {
+        raw.swap(0, 1);
+    }
+
+    let oprs = finish_operands(raw, suffix)?;
+    Some(X86Instruction::new(opcode, oprs))
+}
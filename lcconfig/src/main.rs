@@ -0,0 +1,65 @@
+//! `lc-config`: a small utility exposing the same target/binfmt
+//! recognition `lc-binutils` uses internally, so external build tooling
+//! (build systems, CI scripts) can validate targets the same way without
+//! having to embed its own copy of the rules.
+//!
+//! `--print-targets` lists the binfmts this build was compiled to
+//! recognize; `--validate-target <tuple>` parses a tuple the way
+//! `target-tuples` does and echoes back its canonical components. Full
+//! serde support and a generated database of every tuple `target-tuples`
+//! recognizes aren't provided here: `target-tuples` is a separate crate
+//! pulled in from the registry, not part of this workspace, and its
+//! current release exposes neither a `serde` feature nor an API to
+//! enumerate its known architectures/systems -- only to parse one tuple
+//! at a time.
+
+use target_tuples::Target;
+
+fn print_help(prg_name: &str) {
+    eprintln!("USAGE: {} [OPTIONS]", prg_name);
+    eprintln!("Options:");
+    eprintln!("\t--print-targets: Print the binfmts this build recognizes");
+    eprintln!("\t--validate-target <tuple>: Parse <tuple> and print its canonical components");
+    eprintln!("\t--help: Print this message");
+    eprintln!("\t--version: Print version information");
+}
+
+fn main() {
+    let mut args = std::env::args();
+
+    let prg_name = args.next().unwrap();
+
+    let Some(arg) = args.next() else {
+        print_help(&prg_name);
+        std::process::exit(1);
+    };
+
+    match &*arg {
+        "--version" => {
+            eprintln!("lc-config (lc-binutils {})", std::env!("CARGO_PKG_VERSION"));
+            eprintln!("Copyright (c) 2022 Lightning Creations");
+            eprintln!("Released under the terms of the BSD 2 Clause + Patent License");
+        }
+        "--help" => print_help(&prg_name),
+        "--print-targets" => {
+            for fmt in binfmt::formats() {
+                println!("{}", fmt.name());
+            }
+        }
+        "--validate-target" => {
+            let Some(tuple) = args.next() else {
+                eprintln!("{}: --validate-target requires an argument", prg_name);
+                std::process::exit(1);
+            };
+            let target = Target::parse(&tuple);
+            println!("name: {}", target.get_name());
+            println!("arch: {}", target.arch_name());
+            println!("vendor: {}", target.vendor_name());
+            println!("sys: {}", target.sys());
+        }
+        _ => {
+            print_help(&prg_name);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! CleverOS's ELF64 shared-object ABI: the GOT-equivalent table layout and
+//! the PLT stub/lazy-resolver instruction sequences that make an
+//! `elf64-clever` `ET_DYN` output loadable by it.
+//!
+//! Modeled on the same shape as the classic x86-64 PLT: a reserved GOT
+//! prologue (`.dynamic`'s address, the loader's link-map pointer, and the
+//! loader's resolver entry point) followed by one slot per imported
+//! function, and a PLT made of one fixed-size stub per import plus a
+//! shared resolver stub.
+//!
+//! Every stub below is written out using `arch_ops::clever`'s real
+//! encoder, against any [`InsnWrite`] sink (a [`binfmt::fmt::Section`]
+//! satisfies that) -- the GOT slot addresses are [`Address::Symbol`]
+//! references the instruction stream's own relocation mechanism resolves,
+//! the same way [`InsnWrite::write_addr`] resolves any other symbolic
+//! address. Like [`crate::got`], nothing in the link driver calls this
+//! yet; it's the instruction sequences the relocation-processing stage
+//! will need once `elf64-clever` dynamic linking output is wired up.
+
+use std::io;
+
+use arch_ops::clever::{
+    CleverEncoder, CleverImmediate, CleverIndex, CleverInstruction, CleverOpcode, CleverOperand, CleverRegister,
+};
+use arch_ops::traits::{Address, InsnWrite};
+
+/// Bit width of every GOT slot and general-purpose register used below --
+/// `elf64-clever` is a 64-bit format, so every slot is a pointer-sized
+/// word.
+const WORD_BITS: u16 = 64;
+
+/// `GOT[0]`: the address of the output's `.dynamic` section, the same
+/// convention `DT_PLTGOT`-based ABIs (e.g. x86-64's `.got.plt`) use so the
+/// loader can find its own dynamic section from the GOT alone.
+pub const GOT_DYNAMIC_SLOT: usize = 0;
+/// `GOT[1]`: the loader's link-map pointer for this module, filled in by
+/// the loader before any lazy resolution happens.
+pub const GOT_LINKMAP_SLOT: usize = 1;
+/// `GOT[2]`: the loader's resolver entry point, filled in by the loader.
+pub const GOT_RESOLVER_SLOT: usize = 2;
+/// Number of reserved slots before the first imported function's GOT
+/// entry.
+pub const GOT_RESERVED_ENTRIES: usize = 3;
+
+/// Scratch registers the stubs below clobber -- callers through the PLT
+/// may not have anything live in these across a call, the same
+/// constraint x86-64's `%r11` being PLT-clobbered imposes.
+const SCRATCH_ADDR: CleverRegister = CleverRegister::r12;
+const SCRATCH_CALL: CleverRegister = CleverRegister::r13;
+
+fn got_slot_address(got_symbol: &str, slot: usize) -> Address {
+    Address::Symbol {
+        name: got_symbol.to_string(),
+        disp: (slot * (WORD_BITS as usize / 8)) as i64,
+    }
+}
+
+fn reg_operand(reg: CleverRegister) -> CleverOperand {
+    CleverOperand::Register { size: WORD_BITS, reg }
+}
+
+fn deref_operand(base: CleverRegister) -> CleverOperand {
+    CleverOperand::Indirect {
+        size: WORD_BITS,
+        base,
+        scale: 1,
+        index: CleverIndex::Abs(0),
+    }
+}
+
+fn addr_operand(addr: Address) -> CleverOperand {
+    CleverOperand::Immediate(CleverImmediate::LongAddr(WORD_BITS, addr))
+}
+
+fn imm_operand(val: u64) -> CleverOperand {
+    CleverOperand::Immediate(CleverImmediate::Long(WORD_BITS, val))
+}
+
+/// Materializes `GOT[slot]`'s current value into `dst`: `lea`s the slot's
+/// own address into a scratch register (a link-time constant, fixed up by
+/// a relocation against `got_symbol` the same as any other symbolic
+/// address), then `mov`s through it.
+fn load_got_slot<W: InsnWrite>(
+    enc: &mut CleverEncoder<&mut W>,
+    got_symbol: &str,
+    slot: usize,
+    dst: CleverRegister,
+) -> io::Result<()> {
+    enc.write_instruction(CleverInstruction::new(
+        CleverOpcode::Lea,
+        vec![reg_operand(SCRATCH_ADDR), addr_operand(got_slot_address(got_symbol, slot))],
+    ))?;
+    enc.write_instruction(CleverInstruction::new(
+        CleverOpcode::Mov,
+        vec![reg_operand(dst), deref_operand(SCRATCH_ADDR)],
+    ))
+}
+
+/// Emits PLT entry `index`'s stub: loads the function pointer currently
+/// in `GOT[GOT_RESERVED_ENTRIES + index]` and `icall`s through it.
+///
+/// Before the dynamic linker resolves this import, that slot holds the
+/// address of [`emit_lazy_stub`]'s `index`th stub instead of the real
+/// function, so the first call traps into the resolver; every call after
+/// that goes straight to the resolved function, the same lazy-binding
+/// behavior `DT_BIND_NOW`-less x86-64 `.plt` stubs have.
+pub fn emit_plt_entry<W: InsnWrite>(out: &mut W, got_symbol: &str, index: usize) -> io::Result<()> {
+    let mut enc = CleverEncoder::new(out);
+    load_got_slot(&mut enc, got_symbol, GOT_RESERVED_ENTRIES + index, SCRATCH_CALL)?;
+    enc.write_instruction(CleverInstruction::new(CleverOpcode::IcallR { r: SCRATCH_CALL }, vec![]))
+}
+
+/// Emits import `index`'s lazy-binding stub: stashes `index` (the
+/// resolver needs it to know which relocation to apply) and falls into
+/// the shared resolver stub ([`emit_resolver_stub`]).
+///
+/// `GOT[GOT_RESERVED_ENTRIES + index]` is initialized, by the static
+/// linker at output-writing time (not by anything in this module), to
+/// point here rather than at the real function, until the first call
+/// through [`emit_plt_entry`]'s stub resolves it and overwrites the slot.
+pub fn emit_lazy_stub<W: InsnWrite>(out: &mut W, resolver_symbol: &str, index: usize) -> io::Result<()> {
+    let mut enc = CleverEncoder::new(out);
+    enc.write_instruction(CleverInstruction::new(
+        CleverOpcode::Mov,
+        vec![reg_operand(SCRATCH_ADDR), imm_operand(index as u64)],
+    ))?;
+    enc.write_instruction(CleverInstruction::new(
+        CleverOpcode::Lea,
+        vec![
+            reg_operand(SCRATCH_CALL),
+            addr_operand(Address::Symbol {
+                name: resolver_symbol.to_string(),
+                disp: 0,
+            }),
+        ],
+    ))?;
+    enc.write_instruction(CleverInstruction::new(CleverOpcode::IcallR { r: SCRATCH_CALL }, vec![]))
+}
+
+/// Emits the shared resolver stub every [`emit_lazy_stub`] falls into:
+/// loads the loader's link-map pointer and resolver entry point out of
+/// their reserved GOT slots and `icall`s the resolver, which (per the
+/// calling convention CleverOS's loader and this stub agree on) reads the
+/// relocation index out of `SCRATCH_ADDR` and the link map out of `r14`,
+/// set by [`emit_lazy_stub`] and this stub respectively before either of
+/// them gets clobbered by the call itself.
+pub fn emit_resolver_stub<W: InsnWrite>(out: &mut W, got_symbol: &str) -> io::Result<()> {
+    let mut enc = CleverEncoder::new(out);
+    // Each slot is loaded through its own destination register, not
+    // `load_got_slot`'s `SCRATCH_ADDR`, which still holds the relocation
+    // index `emit_lazy_stub` left there.
+    for (slot, dst) in [(GOT_LINKMAP_SLOT, CleverRegister::r14), (GOT_RESOLVER_SLOT, SCRATCH_CALL)] {
+        enc.write_instruction(CleverInstruction::new(
+            CleverOpcode::Lea,
+            vec![reg_operand(dst), addr_operand(got_slot_address(got_symbol, slot))],
+        ))?;
+        enc.write_instruction(CleverInstruction::new(CleverOpcode::Mov, vec![reg_operand(dst), deref_operand(dst)]))?;
+    }
+    enc.write_instruction(CleverInstruction::new(CleverOpcode::IcallR { r: SCRATCH_CALL }, vec![]))
+}
@@ -0,0 +1,7 @@
+//! Target-specific shared-object ABI support (PLT/GOT-equivalent layout and
+//! stub code generation) that doesn't fit [`crate::got`]'s MIPS-specific
+//! layout or anything else generic enough to live outside an `arch`
+//! submodule.
+
+#[cfg(feature = "clever")]
+pub mod clever;
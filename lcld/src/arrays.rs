@@ -0,0 +1,203 @@
+//! Collection and layout of the `.preinit_array`/`.init_array`/`.fini_array`
+//! family of sections.
+//!
+//! GNU ld accepts per-translation-unit pieces named e.g. `.init_array.NNNNN`,
+//! where `NNNNN` is a priority, and concatenates them (lowest priority
+//! first) into a single output `.init_array` section, synthesizing
+//! `__init_array_start`/`__init_array_end` symbols bounding it. Without this,
+//! C++ static constructors registered via `.init_array` are never run.
+
+use binfmt::fmt::{Section, SectionType};
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ArrayKind {
+    Preinit,
+    Init,
+    Fini,
+}
+
+impl ArrayKind {
+    pub fn output_name(&self) -> &'static str {
+        match self {
+            ArrayKind::Preinit => ".preinit_array",
+            ArrayKind::Init => ".init_array",
+            ArrayKind::Fini => ".fini_array",
+        }
+    }
+
+    pub fn start_symbol(&self) -> &'static str {
+        match self {
+            ArrayKind::Preinit => "__preinit_array_start",
+            ArrayKind::Init => "__init_array_start",
+            ArrayKind::Fini => "__fini_array_start",
+        }
+    }
+
+    pub fn end_symbol(&self) -> &'static str {
+        match self {
+            ArrayKind::Preinit => "__preinit_array_end",
+            ArrayKind::Init => "__init_array_end",
+            ArrayKind::Fini => "__fini_array_end",
+        }
+    }
+
+    fn base_name(&self) -> &'static str {
+        match self {
+            ArrayKind::Preinit => ".preinit_array",
+            ArrayKind::Init => ".init_array",
+            ArrayKind::Fini => ".fini_array",
+        }
+    }
+
+    /// The legacy pre-array-section name this kind is converted from by
+    /// [`convert_legacy_arrays`], or `None` for [`ArrayKind::Preinit`],
+    /// which has no `.ctors`/`.dtors`-style predecessor.
+    fn legacy_base_name(&self) -> Option<&'static str> {
+        match self {
+            ArrayKind::Preinit => None,
+            ArrayKind::Init => Some(".ctors"),
+            ArrayKind::Fini => Some(".dtors"),
+        }
+    }
+
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        section_matches(self.base_name(), name)
+    }
+
+    /// Whether `name` is this kind's legacy `.ctors`/`.dtors`-style
+    /// predecessor (see [`convert_legacy_arrays`]).
+    pub(crate) fn legacy_matches(&self, name: &str) -> bool {
+        self.legacy_base_name()
+            .is_some_and(|base| section_matches(base, name))
+    }
+
+    /// The priority of an input section name, with unsuffixed sections
+    /// (`.init_array` itself) sorting after all prioritized pieces, matching
+    /// GNU ld's `.init_array.*` ordering.
+    fn priority(&self, name: &str) -> Option<u16> {
+        section_priority(self.base_name(), name)
+    }
+}
+
+/// Whether `name` is `base` itself, or one of its `base.NNNNN` prioritized
+/// pieces.
+fn section_matches(base: &str, name: &str) -> bool {
+    name == base || name.strip_prefix(base).is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// The `NNNNN` priority suffix of a `base.NNNNN`-named section, or `None`
+/// for the unsuffixed `base` itself (or anything that doesn't match `base`
+/// at all).
+fn section_priority(base: &str, name: &str) -> Option<u16> {
+    name.strip_prefix(base)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .and_then(|suffix| suffix.parse::<u16>().ok())
+}
+
+/// Collects all sections of `kind` out of `sections`, in the order GNU ld
+/// would place them (ascending priority, with the unsuffixed section last),
+/// and concatenates their contents.
+///
+/// Returns the merged output section plus the names of the bounding start
+/// and end symbols to synthesize at its boundaries; the region is a good
+/// candidate to be mapped inside the `PT_GNU_RELRO` segment, since the
+/// array contents are only supposed to be written by the dynamic linker.
+pub fn merge_array_sections(kind: ArrayKind, sections: Vec<Section>) -> Option<(Section, &'static str, &'static str)> {
+    let mut pieces: Vec<Section> = sections
+        .into_iter()
+        .filter(|sect| kind.matches(&sect.name))
+        .collect();
+
+    if pieces.is_empty() {
+        return None;
+    }
+
+    pieces.sort_by_key(|sect| kind.priority(&sect.name).unwrap_or(u16::MAX));
+
+    let mut content = Vec::new();
+    for piece in &pieces {
+        content.extend_from_slice(&piece.content);
+    }
+
+    let merged = Section {
+        name: kind.output_name().to_string(),
+        align: pieces.iter().map(|s| s.align).max().unwrap_or(1),
+        ty: match kind {
+            ArrayKind::Preinit => SectionType::PreinitArray,
+            ArrayKind::Init => SectionType::InitArray,
+            ArrayKind::Fini => SectionType::FiniArray,
+        },
+        content,
+        ..Section::default()
+    };
+
+    Some((merged, kind.start_symbol(), kind.end_symbol()))
+}
+
+/// Converts a compiler's legacy `.ctors`/`.ctors.NNNNN` (or
+/// `.dtors`/`.dtors.NNNNN`) input sections into the `.init_array`/
+/// `.fini_array` output `kind` calls for, for objects from toolchains
+/// that predate the array sections (GCC built with
+/// `--enable-initfini-array=no`, and a number of freestanding/embedded
+/// toolchains still default to `.ctors`/`.dtors` today). Returns `None`
+/// for [`ArrayKind::Preinit`] (no legacy equivalent exists) or if
+/// `sections` has no matching legacy input.
+///
+/// `.ctors`/`.dtors` order their function pointers the opposite way
+/// `.init_array`/`.fini_array` do: a compiler emits each translation
+/// unit's entry under `.ctors.NNNNN`, where a *lower* `NNNNN` must run
+/// *later* (the classic crtstuff runtime walks the merged array
+/// back-to-front), whereas an `.init_array.NNNNN`'s `NNNNN` runs
+/// earlier-first. Sections are still collected lowest-priority-first
+/// like [`merge_array_sections`], but the merged entries (`ptr_size`-wide
+/// chunks) are then reversed end-to-end to land in the order
+/// `.init_array`/`.fini_array` expect.
+///
+/// This doesn't special-case the sentinel entry (`-1` or `0`, depending
+/// on the toolchain) that `.ctors`/`.dtors` traditionally rely on to mark
+/// the end of the list: the converted section is always bounded by a
+/// synthesized start/end symbol pair instead, the same as a real
+/// `.init_array`, so a leftover sentinel word would be called as a
+/// constructor. Objects whose `crtbegin`/`crtend` still provide one
+/// shouldn't be mixed with this conversion.
+pub fn convert_legacy_arrays(
+    kind: ArrayKind,
+    sections: Vec<Section>,
+    ptr_size: usize,
+) -> Option<(Section, &'static str, &'static str)> {
+    let base = kind.legacy_base_name()?;
+
+    let mut pieces: Vec<Section> = sections
+        .into_iter()
+        .filter(|sect| section_matches(base, &sect.name))
+        .collect();
+
+    if pieces.is_empty() {
+        return None;
+    }
+
+    pieces.sort_by_key(|sect| section_priority(base, &sect.name).unwrap_or(u16::MAX));
+
+    let mut content = Vec::new();
+    for piece in &pieces {
+        content.extend_from_slice(&piece.content);
+    }
+
+    let mut entries: Vec<&[u8]> = content.chunks(ptr_size.max(1)).collect();
+    entries.reverse();
+    let content = entries.concat();
+
+    let merged = Section {
+        name: kind.output_name().to_string(),
+        align: pieces.iter().map(|s| s.align).max().unwrap_or(1),
+        ty: match kind {
+            ArrayKind::Preinit => SectionType::PreinitArray,
+            ArrayKind::Init => SectionType::InitArray,
+            ArrayKind::Fini => SectionType::FiniArray,
+        },
+        content,
+        ..Section::default()
+    };
+
+    Some((merged, kind.start_symbol(), kind.end_symbol()))
+}
@@ -0,0 +1,152 @@
+//! `--error-limit=N` and `--noinhibit-exec`: turning a flat
+//! [`LinkError`] list into something a user can actually read when a
+//! single missing symbol or a botched relocation template shows up
+//! thousands of times, and deciding whether those errors should still
+//! stop the link from writing an output image.
+//!
+//! [`group_by_symbol`] collapses repeated diagnostics against the same
+//! symbol into one [`GroupedDiagnostic`] with a count, the same
+//! deduplication GNU `ld`/`lld` do before printing -- a project with one
+//! missing declaration can otherwise produce one error per call site.
+//! [`cap`] then applies `--error-limit=N` on top of the grouped list.
+//! Like [`crate::gc`]/[`crate::icf`], nothing in the driver calls this
+//! yet: [`LinkState::resolve_symbols`][crate::link::LinkState::resolve_symbols]
+//! and [`crate::reloc::apply_relocations`] still return their errors as
+//! a flat `Vec<LinkError>` for the caller to print however it likes, and
+//! this module is that printing policy, staged on its own.
+
+use std::collections::HashMap;
+
+use crate::link::LinkError;
+
+/// The symbol a [`LinkError`] should be grouped under, if it names one.
+fn group_key(err: &LinkError) -> Option<&str> {
+    match err {
+        LinkError::MultiplyDefined { symbol, .. } => Some(symbol),
+        LinkError::Undefined { symbol, .. } => Some(symbol),
+        LinkError::Relocation { symbol, .. } => Some(symbol),
+    }
+}
+
+/// One or more [`LinkError`]s that [`group_by_symbol`] folded together
+/// because they named the same symbol.
+#[derive(Debug, Clone, Copy)]
+pub struct GroupedDiagnostic<'a> {
+    /// The first occurrence, representative of the whole group -- its
+    /// message is what gets printed.
+    pub first: &'a LinkError,
+    /// How many [`LinkError`]s (including `first`) shared `first`'s
+    /// symbol.
+    pub count: usize,
+}
+
+/// Groups `errors` by [`group_key`], keeping first-seen order so the
+/// earliest occurrence of each symbol is what's reported. An error with
+/// no grouping key (were a future [`LinkError`] variant to lack one)
+/// gets a group of its own rather than being dropped.
+pub fn group_by_symbol(errors: &[LinkError]) -> Vec<GroupedDiagnostic<'_>> {
+    let mut order: Vec<GroupedDiagnostic<'_>> = Vec::new();
+    let mut index_of: HashMap<&str, usize> = HashMap::new();
+
+    for err in errors {
+        match group_key(err) {
+            Some(symbol) => match index_of.get(symbol) {
+                Some(&idx) => order[idx].count += 1,
+                None => {
+                    index_of.insert(symbol, order.len());
+                    order.push(GroupedDiagnostic { first: err, count: 1 });
+                }
+            },
+            None => order.push(GroupedDiagnostic { first: err, count: 1 }),
+        }
+    }
+
+    order
+}
+
+/// Applies `--error-limit=N` to an already-[`group_by_symbol`]ed list:
+/// the first `limit` groups to report, and how many further groups were
+/// elided. `limit` of `None` means unlimited, same as GNU `ld`'s
+/// `--error-limit=0`.
+pub fn cap<'a, 'b>(
+    grouped: &'a [GroupedDiagnostic<'b>],
+    limit: Option<usize>,
+) -> (&'a [GroupedDiagnostic<'b>], usize) {
+    match limit {
+        Some(limit) if limit < grouped.len() => (&grouped[..limit], grouped.len() - limit),
+        _ => (grouped, 0),
+    }
+}
+
+/// Whether a link that produced `errors` should still write an output
+/// image: either there were none, or `--noinhibit-exec` asked for one
+/// anyway (for the sake of, e.g., disassembling the partial result to
+/// see what's missing) -- matching GNU `ld`'s own "still link" behavior
+/// under that flag.
+pub fn should_write_output(errors: &[LinkError], noinhibit_exec: bool) -> bool {
+    errors.is_empty() || noinhibit_exec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn undefined(symbol: &str) -> LinkError {
+        LinkError::Undefined {
+            symbol: symbol.to_string(),
+            referenced_by: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn repeated_symbol_collapses_into_one_group_with_a_count() {
+        let errors = vec![undefined("foo"), undefined("foo"), undefined("bar")];
+        let grouped = group_by_symbol(&errors);
+
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0].count, 2);
+        assert_eq!(grouped[1].count, 1);
+    }
+
+    #[test]
+    fn cap_reports_how_many_groups_were_elided() {
+        let errors = vec![undefined("a"), undefined("b"), undefined("c")];
+        let grouped = group_by_symbol(&errors);
+
+        let (shown, elided) = cap(&grouped, Some(2));
+        assert_eq!(shown.len(), 2);
+        assert_eq!(elided, 1);
+    }
+
+    #[test]
+    fn cap_with_no_limit_shows_everything() {
+        let errors = vec![undefined("a"), undefined("b")];
+        let grouped = group_by_symbol(&errors);
+
+        let (shown, elided) = cap(&grouped, None);
+        assert_eq!(shown.len(), 2);
+        assert_eq!(elided, 0);
+    }
+
+    #[test]
+    fn cap_limit_larger_than_group_count_elides_nothing() {
+        let errors = vec![undefined("a")];
+        let grouped = group_by_symbol(&errors);
+
+        let (shown, elided) = cap(&grouped, Some(10));
+        assert_eq!(shown.len(), 1);
+        assert_eq!(elided, 0);
+    }
+
+    #[test]
+    fn no_errors_always_writes_output() {
+        assert!(should_write_output(&[], false));
+    }
+
+    #[test]
+    fn errors_block_output_unless_noinhibit_exec() {
+        let errors = vec![undefined("foo")];
+        assert!(!should_write_output(&errors, false));
+        assert!(should_write_output(&errors, true));
+    }
+}
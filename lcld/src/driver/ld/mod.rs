@@ -33,6 +33,20 @@ pub enum InputSet {
     NoAsNeeded,
 }
 
+/// Resolves one of `cfg.search_paths`' built-in default directories
+/// against `sysroot`: unlike a `-L`/`SEARCH_DIR` path (which only moves
+/// into the sysroot on an explicit `=`/`$SYSROOT` prefix, see
+/// [`crate::script::resolve_sysroot_path`]), every one of these defaults
+/// is implicitly sysroot-relative, the same way GNU `ld` treats its own
+/// compiled-in default library directories.
+fn resolve_default_search_path(path: &Path, sysroot: &Path) -> PathBuf {
+    if sysroot.as_os_str().is_empty() || !path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        sysroot.join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
 fn find_library<P: AsRef<Path>>(
     lib: &str,
     search_dirs: impl IntoIterator<Item = P>,
@@ -85,18 +99,55 @@ fn find_library<P: AsRef<Path>>(
     }
 }
 
+/// Walks `p` in the same order [`print_input_and_ident`] will, resolving
+/// each [`InputSet::Library`] against `search_dirs` and threading `status`
+/// through exactly as that function does, to build the flat, ordered list
+/// of paths that will actually need identifying.
+///
+/// Keeping this as its own pass (instead of identifying as we go) is what
+/// lets the caller hand the whole list to
+/// [`crate::input::ident_inputs_parallel`] at once: `-static`/`-Bdynamic`
+/// resolve sequentially since later lookups depend on earlier ones
+/// toggling `status.prefer_mode`, but the identification of the resolved
+/// files has no such dependency.
+fn collect_input_paths<'a, R, P: AsRef<Path>>(
+    p: &InputSet,
+    search_dirs: &'a R,
+    info: &TargetInfo,
+    status: &mut InputStatus,
+    paths: &mut Vec<PathBuf>,
+) -> std::io::Result<()>
+where
+    &'a R: IntoIterator<Item = P>,
+{
+    match p {
+        InputSet::Single(path) => paths.push(path.clone()),
+        InputSet::Group(inputs) => {
+            for input in inputs {
+                collect_input_paths(input, search_dirs, info, status, paths)?;
+            }
+        }
+        InputSet::Library(lib) => paths.push(find_library(lib, search_dirs, info, status)?),
+        InputSet::LinkStatic => status.prefer_mode = LibraryType::Static,
+        InputSet::LinkDynamic => status.prefer_mode = LibraryType::Dynamic,
+        _ => {}
+    }
+    Ok(())
+}
+
 fn print_input_and_ident<'a, R, P: AsRef<Path>>(
     p: &InputSet,
     search_dirs: &'a R,
     info: &TargetInfo,
     status: &mut InputStatus,
+    idents: &mut impl Iterator<Item = std::io::Result<crate::input::InputFileType>>,
 ) -> std::io::Result<()>
 where
     &'a R: IntoIterator<Item = P>,
 {
     match p {
         InputSet::Single(path) => {
-            let ty = crate::input::ident_input(path)?;
+            let ty = idents.next().expect("one ident per collected path")?;
             eprint!("{} {{{}}}", path.display(), ty);
         }
         InputSet::Group(inputs) => {
@@ -104,13 +155,13 @@ where
             for input in inputs {
                 eprint!("{}", sep);
                 sep = " ";
-                print_input_and_ident(input, search_dirs, info, status)?;
+                print_input_and_ident(input, search_dirs, info, status, idents)?;
             }
             eprint!(")");
         }
         InputSet::Library(lib) => {
             let file = find_library(lib, search_dirs, info, status)?;
-            let ty = crate::input::ident_input(&file)?;
+            let ty = idents.next().expect("one ident per collected path")?;
             eprint!("-l{}: {} {{{}}}", lib, file.display(), ty);
         }
         InputSet::LinkStatic => status.prefer_mode = LibraryType::Static,
@@ -120,6 +171,198 @@ where
     Ok(())
 }
 
+/// Parses a `--defsym` argument's `sym=expr` form, exiting with a
+/// diagnostic on malformed input -- the same failure style every other
+/// option in this driver uses, rather than threading a `Result` through
+/// just for this one option.
+fn parse_defsym(prg_name: &str, s: &str) -> (String, crate::script::Expr) {
+    let (sym, expr) = s.split_once('=').unwrap_or_else(|| {
+        eprintln!("{}: Expected sym=expr after --defsym, found `{}`", prg_name, s);
+        std::process::exit(1)
+    });
+    let expr = crate::script::parse_expr_str(expr).unwrap_or_else(|e| {
+        eprintln!("{}: --defsym {}: {}", prg_name, s, e);
+        std::process::exit(1)
+    });
+    (sym.to_string(), expr)
+}
+
+/// Parses a `--defsym-version` argument's `sym=version` form, exiting
+/// with a diagnostic on malformed input -- the same failure style
+/// [`parse_defsym`] uses for its own `sym=expr` form.
+fn parse_defsym_version(prg_name: &str, s: &str) -> (String, String) {
+    let (sym, version) = s.split_once('=').unwrap_or_else(|| {
+        eprintln!(
+            "{}: Expected sym=version after --defsym-version, found `{}`",
+            prg_name, s
+        );
+        std::process::exit(1)
+    });
+    (sym.to_string(), version.to_string())
+}
+
+/// One output section [`print_dry_run_report`] would place data into,
+/// aggregated across every input that contributes to it.
+struct PlannedSection {
+    name: String,
+    size: u64,
+    n_inputs: usize,
+}
+
+fn merge_planned_section(sections: &mut Vec<PlannedSection>, name: &str, size: u64) {
+    match sections.iter_mut().find(|s| s.name == name) {
+        Some(sec) => {
+            sec.size += size;
+            sec.n_inputs += 1;
+        }
+        None => sections.push(PlannedSection {
+            name: name.to_string(),
+            size,
+            n_inputs: 1,
+        }),
+    }
+}
+
+/// Implements `--dry-run`: opens and parses every resolved input exactly as
+/// a real link would, then prints the combined per-section sizes -- the
+/// closest thing to a would-be memory map this driver can produce without
+/// a section-placement/relocation engine wired in (nothing in this driver
+/// currently calls [`crate::link::LinkState::resolve_symbols`] or lays
+/// sections out at real addresses, so there's no PLT/GOT synthesis or
+/// final layout to report yet -- see that type's doc comments). Archives,
+/// linker scripts, and text stubs are listed by name but not expanded,
+/// since deciding which archive members actually get pulled in requires
+/// the symbol resolution this driver doesn't perform.
+fn print_dry_run_report(
+    prg_name: &str,
+    output_file: &str,
+    output_type: Option<OutputType>,
+    paths: &[PathBuf],
+) {
+    use crate::input::InputFileType;
+
+    eprintln!("{}: --dry-run: planned output {:?}", prg_name, output_file);
+    eprintln!(
+        "{}: --dry-run: output type: {:?}",
+        prg_name,
+        output_type.unwrap_or(OutputType::PieExecutable)
+    );
+
+    let mut planned = Vec::<PlannedSection>::new();
+    let mut errors = Vec::new();
+
+    for (path, ident) in paths.iter().zip(crate::input::ident_inputs_parallel(paths)) {
+        let ty = match ident {
+            Ok(ty) => ty,
+            Err(e) => {
+                errors.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+
+        match ty {
+            InputFileType::Object(fmt) => match std::fs::File::open(path) {
+                Ok(mut file) => match fmt.read_file(&mut file) {
+                    Ok(Some(bin)) => {
+                        for sect in bin.sections() {
+                            merge_planned_section(
+                                &mut planned,
+                                &sect.name,
+                                (sect.content.len() + sect.tail_size) as u64,
+                            );
+                        }
+                    }
+                    Ok(None) => errors.push(format!("{}: not a {} file", path.display(), fmt.name())),
+                    Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+                },
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            },
+            InputFileType::Archive | InputFileType::LinkerScript | InputFileType::TextStub | InputFileType::LtoInput(_) => {
+                eprintln!("{}: --dry-run: {}: {} (not expanded)", prg_name, path.display(), ty);
+            }
+        }
+    }
+
+    eprintln!("{}: --dry-run: planned memory map:", prg_name);
+    for sect in &planned {
+        eprintln!(
+            "{}:   {:<16} {:#x} bytes ({} input section{})",
+            prg_name,
+            sect.name,
+            sect.size,
+            sect.n_inputs,
+            if sect.n_inputs == 1 { "" } else { "s" }
+        );
+    }
+
+    if !errors.is_empty() {
+        eprintln!("{}: --dry-run: prospective errors:", prg_name);
+        for e in &errors {
+            eprintln!("{}:   {}", prg_name, e);
+        }
+    }
+}
+
+/// Reads and parses the linker script at `path`, exiting with a
+/// diagnostic on I/O or parse failure -- the same failure style every
+/// other option in this driver uses.
+fn load_script(prg_name: &str, path: &Path) -> crate::script::ParsedScript {
+    let src = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("{}: {}: {}", prg_name, path.display(), e);
+        std::process::exit(1)
+    });
+    crate::script::parse(&src).unwrap_or_else(|e| {
+        eprintln!("{}: {}: {}", prg_name, path.display(), e);
+        std::process::exit(1)
+    })
+}
+
+/// Flags accumulated from `-z <keyword>`, controlling program-header and
+/// dynamic-section generation. GNU ld defines far more `-z` keywords
+/// than this; the rest fall through to the "unrecognized" diagnostic in
+/// [`parse_z_option`] rather than being silently accepted.
+#[derive(Clone, Debug, Default)]
+struct ZOptions {
+    relro: Option<bool>,
+    bind_now: Option<bool>,
+    exec_stack: Option<bool>,
+    defs: bool,
+    origin: bool,
+    max_page_size: Option<u64>,
+}
+
+/// Applies a single `-z` keyword to `opts`, exiting with a diagnostic if
+/// `max-page-size=...` has a malformed value. An unrecognized keyword is
+/// reported but not fatal, since this covers only a handful of the
+/// keywords real `-z` supports.
+fn parse_z_option(prg_name: &str, keyword: &str, opts: &mut ZOptions) {
+    match keyword {
+        "relro" => opts.relro = Some(true),
+        "norelro" => opts.relro = Some(false),
+        "now" => opts.bind_now = Some(true),
+        "lazy" => opts.bind_now = Some(false),
+        "noexecstack" => opts.exec_stack = Some(false),
+        "execstack" => opts.exec_stack = Some(true),
+        "defs" => opts.defs = true,
+        "origin" => opts.origin = true,
+        x if x.starts_with("max-page-size=") => {
+            let val = &x["max-page-size=".len()..];
+            opts.max_page_size = Some(
+                crate::script::parse_int(val)
+                    .ok()
+                    .and_then(|n| u64::try_from(n).ok())
+                    .unwrap_or_else(|| {
+                        eprintln!("{}: Invalid value for -z max-page-size: `{}`", prg_name, val);
+                        std::process::exit(1)
+                    }),
+            );
+        }
+        _ => {
+            eprintln!("{}: Unrecognized -z keyword `{}`, ignoring", prg_name, keyword);
+        }
+    }
+}
+
 #[allow(unused_variables, unused_assignments)]
 pub fn main() -> Result<(), IOError> {
     let mut default_targ = target_tuples::from_env!("default_target");
@@ -133,6 +376,7 @@ pub fn main() -> Result<(), IOError> {
     let mut output_file = "a.out".to_string();
     let mut output_type = None::<OutputType>;
     let mut suppout = None::<String>;
+    let mut soname = None::<String>;
 
     let mut inputs = Vec::new();
 
@@ -140,7 +384,26 @@ pub fn main() -> Result<(), IOError> {
 
     let mut add_search_dirs = Vec::new();
 
+    let mut script_override = None::<PathBuf>;
     let mut default_script = None::<PathBuf>;
+    let mut verbose = false;
+
+    let mut keep_sections = Vec::<String>::new();
+
+    let mut print_stats = false;
+    let mut dry_run = false;
+
+    let mut wrapped_symbols = Vec::<String>::new();
+
+    let mut entry = None::<String>;
+    let mut defsyms = Vec::<(String, crate::script::Expr)>::new();
+    let mut undefined_syms = Vec::<String>::new();
+    let mut z_opts = ZOptions::default();
+    let mut defsym_versions = Vec::<(String, String)>::new();
+    let mut default_symver = false;
+    let mut sysroot_override = None::<PathBuf>;
+    let mut rpaths = Vec::<String>::new();
+    let mut rpath_links = Vec::<String>::new();
 
     if let Some((left, _)) = prg_name.rsplit_once('-') {
         if let Ok(targ) = left.parse() {
@@ -166,6 +429,42 @@ pub fn main() -> Result<(), IOError> {
             "-shared" => {
                 output_type = Some(OutputType::Shared);
             }
+            "-pie" => {
+                output_type = Some(OutputType::PieExecutable);
+            }
+            "-soname" | "-h" => {
+                soname = Some(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a name after -soname", prg_name);
+                    std::process::exit(1)
+                }));
+            }
+            x if x.starts_with("-soname=") => {
+                soname = Some(x["-soname=".len()..].to_string());
+            }
+            "--keep-section" => {
+                keep_sections.push(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a glob after --keep-section", prg_name);
+                    std::process::exit(1)
+                }));
+            }
+            x if x.starts_with("--keep-section=") => {
+                keep_sections.push(x["--keep-section=".len()..].to_string());
+            }
+            "--stats" => {
+                print_stats = true;
+            }
+            "--dry-run" => {
+                dry_run = true;
+            }
+            "--wrap" => {
+                wrapped_symbols.push(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a symbol name after --wrap", prg_name);
+                    std::process::exit(1)
+                }));
+            }
+            x if x.starts_with("--wrap=") => {
+                wrapped_symbols.push(x["--wrap=".len()..].to_string());
+            }
             "--target" => {
                 targ = Some(args.next().and_then(|s| s.parse().ok()).unwrap_or_else(|| {
                     eprintln!("{}: Expected a target after --target", prg_name);
@@ -190,6 +489,33 @@ pub fn main() -> Result<(), IOError> {
             x if x.starts_with("-L") => {
                 add_search_dirs.push(x[2..].to_string());
             }
+            "--sysroot" => {
+                sysroot_override = Some(PathBuf::from(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a path after --sysroot", prg_name);
+                    std::process::exit(1)
+                })));
+            }
+            x if x.starts_with("--sysroot=") => {
+                sysroot_override = Some(PathBuf::from(&x["--sysroot=".len()..]));
+            }
+            "-rpath" | "--rpath" => {
+                rpaths.push(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a path after {}", prg_name, arg);
+                    std::process::exit(1)
+                }));
+            }
+            x if x.starts_with("-rpath=") => {
+                rpaths.push(x["-rpath=".len()..].to_string());
+            }
+            "-rpath-link" | "--rpath-link" => {
+                rpath_links.push(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a path after {}", prg_name, arg);
+                    std::process::exit(1)
+                }));
+            }
+            x if x.starts_with("-rpath-link=") => {
+                rpath_links.push(x["-rpath-link=".len()..].to_string());
+            }
             "-l" => {
                 if let Some(group) = &mut cur_group {
                     group.push(InputSet::Library(args.next().unwrap_or_else(|| {
@@ -234,12 +560,81 @@ pub fn main() -> Result<(), IOError> {
             "--flavour" | "-flavour" | "--flavor" | "-flavor" => {
                 args.next(); // consume the argument to flavour, but we're committed on the unix driver now
             }
-            "-T" => {
+            "-T" | "--script" => {
+                script_override = Some(PathBuf::from(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a file name after {}", prg_name, arg);
+                    std::process::exit(1)
+                })));
+            }
+            x if x.starts_with("--script=") => {
+                script_override = Some(PathBuf::from(&x["--script=".len()..]));
+            }
+            "-dT" | "--default-script" => {
                 default_script = Some(PathBuf::from(args.next().unwrap_or_else(|| {
-                    eprintln!("{}: Expected a file name after -T", prg_name);
+                    eprintln!("{}: Expected a file name after {}", prg_name, arg);
                     std::process::exit(1)
                 })));
             }
+            x if x.starts_with("--default-script=") => {
+                default_script = Some(PathBuf::from(&x["--default-script=".len()..]));
+            }
+            "--verbose" | "-v" => {
+                verbose = true;
+            }
+            "-e" | "--entry" => {
+                entry = Some(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a symbol name after {}", prg_name, arg);
+                    std::process::exit(1)
+                }));
+            }
+            x if x.starts_with("--entry=") => {
+                entry = Some(x["--entry=".len()..].to_string());
+            }
+            "--defsym" => {
+                let def = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected sym=expr after --defsym", prg_name);
+                    std::process::exit(1)
+                });
+                defsyms.push(parse_defsym(&prg_name, &def));
+            }
+            x if x.starts_with("--defsym=") => {
+                defsyms.push(parse_defsym(&prg_name, &x["--defsym=".len()..]));
+            }
+            "--defsym-version" => {
+                let def = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected sym=version after --defsym-version", prg_name);
+                    std::process::exit(1)
+                });
+                defsym_versions.push(parse_defsym_version(&prg_name, &def));
+            }
+            x if x.starts_with("--defsym-version=") => {
+                defsym_versions.push(parse_defsym_version(&prg_name, &x["--defsym-version=".len()..]));
+            }
+            "--default-symver" => {
+                default_symver = true;
+            }
+            "-u" | "--undefined" => {
+                undefined_syms.push(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a symbol name after {}", prg_name, arg);
+                    std::process::exit(1)
+                }));
+            }
+            x if x.starts_with("--undefined=") => {
+                undefined_syms.push(x["--undefined=".len()..].to_string());
+            }
+            x if x.starts_with("-u") && x.len() > 2 => {
+                undefined_syms.push(x[2..].to_string());
+            }
+            "-z" => {
+                let keyword = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a keyword after -z", prg_name);
+                    std::process::exit(1)
+                });
+                parse_z_option(&prg_name, &keyword, &mut z_opts);
+            }
+            x if x.starts_with("-z") && x.len() > 2 => {
+                parse_z_option(&prg_name, &x[2..], &mut z_opts);
+            }
             x if x.starts_with('-') => todo!("opts"),
             _ => {
                 if let Some(group) = &mut cur_group {
@@ -271,11 +666,17 @@ pub fn main() -> Result<(), IOError> {
         allow_static: true,
         allow_dynamic: true,
     };
+    let sysroot = sysroot_override.unwrap_or_else(|| PathBuf::from(cfg.sysroot));
+
     let mut search_dirs = Vec::new();
-    cfg.search_paths
+    let default_search_paths: Vec<PathBuf> = cfg
+        .search_paths
         .iter()
-        .copied()
-        .map(Path::new)
+        .map(|p| resolve_default_search_path(Path::new(p), &sysroot))
+        .collect();
+    default_search_paths
+        .iter()
+        .map(PathBuf::as_path)
         .flat_map(|p| core::iter::repeat(p).zip(cfg.libdirs.iter().copied()))
         .for_each(|pair| {
             if cfg.use_target {
@@ -323,17 +724,83 @@ pub fn main() -> Result<(), IOError> {
                 }));
             }
         });
-    search_dirs.extend(add_search_dirs.into_iter().map(PathBuf::from));
+    search_dirs.extend(
+        add_search_dirs
+            .into_iter()
+            .map(|dir| crate::script::resolve_sysroot_path(Path::new(&dir), &sysroot)),
+    );
 
     eprintln!("{}: Search Paths: {:?}", prg_name, search_dirs);
+    if !rpaths.is_empty() {
+        eprintln!("{}: -rpath: {:?}", prg_name, rpaths);
+    }
+    if !rpath_links.is_empty() {
+        eprintln!("{}: -rpath-link: {:?}", prg_name, rpath_links);
+    }
+
+    let mut collect_status = status;
+    let mut paths = Vec::new();
+    for input in &inputs {
+        collect_input_paths(input, &search_dirs, info, &mut collect_status, &mut paths)?;
+    }
+
+    let mut idents = crate::input::ident_inputs_parallel(&paths).into_iter();
 
     eprint!("{}: Input Files: ", prg_name);
     for input in &inputs {
-        print_input_and_ident(input, &search_dirs, info, &mut status)?;
+        print_input_and_ident(input, &search_dirs, info, &mut status, &mut idents)?;
         eprint!(" ");
     }
 
     eprintln!();
 
+    // `-T`/`--script` fully replaces whatever script would otherwise be in
+    // effect; `-dT`/`--default-script` only takes effect when `-T` wasn't
+    // given, the same precedence GNU `ld` gives the two flags.
+    let effective_script = script_override
+        .as_deref()
+        .or(default_script.as_deref())
+        .map(|path| (path, load_script(&prg_name, path)));
+    if verbose {
+        match &effective_script {
+            Some((path, script)) => {
+                eprintln!("{}: Using linker script {}:", prg_name, path.display());
+                for cmd in &script.command {
+                    eprintln!("{}:   {:?}", prg_name, cmd);
+                }
+            }
+            None => eprintln!("{}: Using linker script: <none>", prg_name),
+        }
+    }
+
+    if let Some(entry) = &entry {
+        eprintln!("{}: Entry: {}", prg_name, entry);
+    }
+    for (sym, expr) in &defsyms {
+        eprintln!("{}: --defsym {} = {:?}", prg_name, sym, expr);
+    }
+    if !undefined_syms.is_empty() {
+        eprintln!("{}: Forced undefined: {:?}", prg_name, undefined_syms);
+    }
+    eprintln!("{}: -z options: {:?}", prg_name, z_opts);
+
+    let mut versions = crate::symver::VersionTable::new();
+    for (sym, version) in &defsym_versions {
+        versions.set_version(sym, version, true);
+    }
+    if default_symver {
+        let default_version = soname.clone().unwrap_or_else(|| output_file.clone());
+        let exported: Vec<String> = defsyms.iter().map(|(sym, _)| sym.clone()).collect();
+        versions.apply_default(&exported, &default_version);
+    }
+    if !versions.definitions().is_empty() {
+        eprintln!("{}: Symbol versions: {:?}", prg_name, versions.definitions());
+    }
+
+    if dry_run {
+        print_dry_run_report(&prg_name, &output_file, output_type, &paths);
+        return Ok(());
+    }
+
     Ok(())
 }
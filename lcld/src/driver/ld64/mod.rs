@@ -1,6 +1,378 @@
-use std::io::{Error as IOError, ErrorKind};
+use std::{
+    fs,
+    io::{Error as IOError, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// Page protection flags as written in `-segprot`'s `max-prot`/`init-prot`
+/// arguments: some combination of `r`, `w`, `x`, or `-` for "none".
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct SegProt {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+fn parse_segprot(prg_name: &str, flag: &str, s: &str) -> SegProt {
+    let mut prot = SegProt::default();
+    for c in s.chars() {
+        match c {
+            'r' => prot.read = true,
+            'w' => prot.write = true,
+            'x' => prot.execute = true,
+            '-' => {}
+            c => {
+                eprintln!(
+                    "{}: Invalid permission '{}' in {} (expected some combination of r, w, x, or -)",
+                    prg_name, c, flag
+                );
+                std::process::exit(1)
+            }
+        }
+    }
+    prot
+}
+
+/// A `-sectcreate seg sect file` request: splice the raw bytes of `file`
+/// into the output as a new section `sect` of segment `seg`, verbatim,
+/// with no relocation processing.
+#[derive(Clone, Debug)]
+pub struct SectCreate {
+    pub segment: String,
+    pub section: String,
+    pub file: PathBuf,
+}
+
+/// A `-segprot seg max-prot init-prot` request, restricting the maximum
+/// and initial page protection the kernel will map `seg` with.
+#[derive(Clone, Debug)]
+pub struct SegmentProtection {
+    pub segment: String,
+    pub max_prot: SegProt,
+    pub init_prot: SegProt,
+}
+
+/// A `-platform_version platform min_version sdk_version` request,
+/// recording which OS the output targets and which SDK it was built
+/// against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlatformVersion {
+    pub platform: String,
+    pub min_version: String,
+    pub sdk_version: String,
+}
+
+const DYLIB_SUFFIXES: &[&str] = &[".dylib", ".tbd"];
+const STATICLIB_SUFFIXES: &[&str] = &[".a"];
+
+/// Resolves `-lname` against `search_dirs`, trying `libname.dylib` and
+/// `libname.tbd` (a text stub standing in for a dylib not shipped on
+/// disk) before falling back to the static `libname.a`, mirroring
+/// `ld64`'s own dynamic-over-static preference.
+fn find_library(prg_name: &str, lib: &str, search_dirs: &[PathBuf]) -> PathBuf {
+    for dir in search_dirs {
+        for suffix in DYLIB_SUFFIXES.iter().chain(STATICLIB_SUFFIXES) {
+            let path = dir.join(format!("lib{}{}", lib, suffix));
+            match fs::metadata(&path) {
+                Ok(_) => return path,
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => {
+                    eprintln!("{}: {}: {}", prg_name, path.display(), e);
+                    std::process::exit(1)
+                }
+            }
+        }
+    }
+    eprintln!("{}: library not found for -l{}", prg_name, lib);
+    std::process::exit(1)
+}
+
+/// Resolves `-framework Name` against `search_dirs`, looking for
+/// `Name.framework/Name`, the executable slice a framework bundle
+/// actually links against.
+fn find_framework(prg_name: &str, name: &str, search_dirs: &[PathBuf]) -> PathBuf {
+    for dir in search_dirs {
+        let path = dir.join(format!("{}.framework", name)).join(name);
+        match fs::metadata(&path) {
+            Ok(_) => return path,
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => {
+                eprintln!("{}: {}: {}", prg_name, path.display(), e);
+                std::process::exit(1)
+            }
+        }
+    }
+    eprintln!("{}: framework not found for -framework {}", prg_name, name);
+    std::process::exit(1)
+}
+
+/// Parses `path` as a `.tbd` text stub and reports its install name and
+/// exported-symbol count, the same summary the real binary-dylib
+/// resolution path would eventually produce once symbol resolution
+/// against `.tbd` inputs is wired up.
+fn report_tbd(prg_name: &str, path: &Path) -> Result<(), IOError> {
+    let content = fs::read_to_string(path)?;
+    let stub = crate::tbd::parse_tbd(&content).unwrap_or_else(|e| {
+        eprintln!("{}: {}: {}", prg_name, path.display(), e);
+        std::process::exit(1)
+    });
+    eprintln!(
+        "{}: {}: text stub for {} ({} exported symbol(s))",
+        prg_name,
+        path.display(),
+        stub.install_name.as_deref().unwrap_or("<unknown>"),
+        stub.exported_symbols.len()
+    );
+    Ok(())
+}
 
 pub fn main() -> Result<(), IOError> {
+    let mut args = std::env::args();
+
+    let prg_name = args.next().unwrap();
+
+    let mut inputs = Vec::new();
+    let mut output_file = "a.out".to_string();
+    let mut order_file = None::<Vec<String>>;
+    let mut sectcreates = Vec::new();
+    let mut segprots = Vec::new();
+    let mut arch = None::<String>;
+    let mut platform_version = None::<PlatformVersion>;
+    let mut install_name = None::<String>;
+    let mut search_dirs = Vec::new();
+    let mut framework_dirs = Vec::new();
+    let mut libraries = Vec::new();
+    let mut frameworks = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-arch" => {
+                arch = Some(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected an architecture name after -arch", prg_name);
+                    std::process::exit(1)
+                }));
+            }
+            "-platform_version" => {
+                let platform = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a platform name after -platform_version", prg_name);
+                    std::process::exit(1)
+                });
+                let min_version = args.next().unwrap_or_else(|| {
+                    eprintln!(
+                        "{}: Expected a minimum OS version after -platform_version {}",
+                        prg_name, platform
+                    );
+                    std::process::exit(1)
+                });
+                let sdk_version = args.next().unwrap_or_else(|| {
+                    eprintln!(
+                        "{}: Expected an SDK version after -platform_version {} {}",
+                        prg_name, platform, min_version
+                    );
+                    std::process::exit(1)
+                });
+                platform_version = Some(PlatformVersion {
+                    platform,
+                    min_version,
+                    sdk_version,
+                });
+            }
+            "-install_name" => {
+                install_name = Some(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a path after -install_name", prg_name);
+                    std::process::exit(1)
+                }));
+            }
+            "-L" => {
+                search_dirs.push(PathBuf::from(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a directory after -L", prg_name);
+                    std::process::exit(1)
+                })));
+            }
+            x if x.starts_with("-L") => {
+                search_dirs.push(PathBuf::from(&x[2..]));
+            }
+            "-F" => {
+                framework_dirs.push(PathBuf::from(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a directory after -F", prg_name);
+                    std::process::exit(1)
+                })));
+            }
+            x if x.starts_with("-F") => {
+                framework_dirs.push(PathBuf::from(&x[2..]));
+            }
+            "-framework" => {
+                frameworks.push(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a name after -framework", prg_name);
+                    std::process::exit(1)
+                }));
+            }
+            "-l" => {
+                libraries.push(args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a library name after -l", prg_name);
+                    std::process::exit(1)
+                }));
+            }
+            x if x.starts_with("-l") => {
+                libraries.push(x[2..].to_string());
+            }
+            "-o" => {
+                output_file = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a file name after -o", prg_name);
+                    std::process::exit(1)
+                });
+            }
+            "-order_file" => {
+                let file = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a file name after -order_file", prg_name);
+                    std::process::exit(1)
+                });
+                let contents = fs::read_to_string(&file).unwrap_or_else(|e| {
+                    eprintln!("{}: Failed to read order file {}: {}", prg_name, file, e);
+                    std::process::exit(1)
+                });
+                order_file = Some(
+                    contents
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(str::to_string)
+                        .collect(),
+                );
+            }
+            "-sectcreate" => {
+                let segment = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a segment name after -sectcreate", prg_name);
+                    std::process::exit(1)
+                });
+                let section = args.next().unwrap_or_else(|| {
+                    eprintln!(
+                        "{}: Expected a section name after -sectcreate {}",
+                        prg_name, segment
+                    );
+                    std::process::exit(1)
+                });
+                let file = args.next().unwrap_or_else(|| {
+                    eprintln!(
+                        "{}: Expected a file name after -sectcreate {} {}",
+                        prg_name, segment, section
+                    );
+                    std::process::exit(1)
+                });
+                sectcreates.push(SectCreate {
+                    segment,
+                    section,
+                    file: PathBuf::from(file),
+                });
+            }
+            "-segprot" => {
+                let segment = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a segment name after -segprot", prg_name);
+                    std::process::exit(1)
+                });
+                let max_prot_arg = args.next().unwrap_or_else(|| {
+                    eprintln!(
+                        "{}: Expected a maximum protection after -segprot {}",
+                        prg_name, segment
+                    );
+                    std::process::exit(1)
+                });
+                let init_prot_arg = args.next().unwrap_or_else(|| {
+                    eprintln!(
+                        "{}: Expected an initial protection after -segprot {} {}",
+                        prg_name, segment, max_prot_arg
+                    );
+                    std::process::exit(1)
+                });
+                let max_prot = parse_segprot(&prg_name, "-segprot", &max_prot_arg);
+                let init_prot = parse_segprot(&prg_name, "-segprot", &init_prot_arg);
+                segprots.push(SegmentProtection {
+                    segment,
+                    max_prot,
+                    init_prot,
+                });
+            }
+            x if x.starts_with('-') => { /* not yet recognized by this driver */ }
+            x => inputs.push(PathBuf::from(x)),
+        }
+    }
+
+    if inputs.is_empty() {
+        eprintln!("{}: Expected at least one input file", prg_name);
+        std::process::exit(1)
+    }
+
+    eprintln!("{}: Output: {}", prg_name, output_file);
+    eprintln!("{}: Input Files: {:?}", prg_name, inputs);
+    if let Some(order) = &order_file {
+        eprintln!(
+            "{}: Symbol order ({} symbols): {:?}",
+            prg_name,
+            order.len(),
+            order
+        );
+    }
+    for sect in &sectcreates {
+        eprintln!(
+            "{}: -sectcreate {},{} <- {}",
+            prg_name,
+            sect.segment,
+            sect.section,
+            sect.file.display()
+        );
+    }
+    for prot in &segprots {
+        eprintln!(
+            "{}: -segprot {} max={:?} init={:?}",
+            prg_name, prot.segment, prot.max_prot, prot.init_prot
+        );
+    }
+    if let Some(arch) = &arch {
+        eprintln!("{}: Architecture: {}", prg_name, arch);
+    }
+    if let Some(platform_version) = &platform_version {
+        eprintln!(
+            "{}: Platform: {} (min {}, sdk {})",
+            prg_name,
+            platform_version.platform,
+            platform_version.min_version,
+            platform_version.sdk_version
+        );
+    }
+    if let Some(install_name) = &install_name {
+        eprintln!("{}: Install Name: {}", prg_name, install_name);
+    }
+
+    let mut resolved_libs = Vec::new();
+    for lib in &libraries {
+        let path = find_library(&prg_name, lib, &search_dirs);
+        eprintln!("{}: -l{} -> {}", prg_name, lib, path.display());
+        if path.extension().is_some_and(|ext| ext == "tbd") {
+            report_tbd(&prg_name, &path)?;
+        }
+        resolved_libs.push(path);
+    }
+    let mut resolved_frameworks = Vec::new();
+    for framework in &frameworks {
+        let path = find_framework(&prg_name, framework, &framework_dirs);
+        eprintln!(
+            "{}: -framework {} -> {}",
+            prg_name, framework, path.display()
+        );
+        resolved_frameworks.push(path);
+    }
+
+    // There's no Mach-O writer yet to hand these off to -- `binfmt::macho`
+    // only has the header layout so far, with no `Binfmt` impl -- but
+    // `order_file`/`sectcreates`/`segprots` are exactly the inputs such a
+    // writer will need: symbol order feeds the section-layout pass,
+    // `sectcreates` become literal appended sections, `segprots` set the
+    // `SEGMENT`/`SEGMENT_64` load command's `maxprot`/`initprot`, `arch`
+    // and `platform_version` become the `CPU_TYPE`/`CPU_SUBTYPE` fields
+    // and a `LC_BUILD_VERSION` command, `install_name` becomes the
+    // `LC_ID_DYLIB` command, and `resolved_libs`/`resolved_frameworks`
+    // each become an `LC_LOAD_DYLIB` command pointing at their resolved
+    // path.
     Err(std::io::Error::new(
         ErrorKind::Unsupported,
         "darwin driver not implemented",
@@ -1,6 +1,155 @@
-use std::io::{Error as IOError, ErrorKind};
+use std::{
+    io::{Error as IOError, ErrorKind},
+    path::PathBuf,
+};
+
+/// The `/SUBSYSTEM:` value, selecting which Windows subsystem loads the
+/// output image. `version` is the optional `,major[.minor]` suffix MSVC
+/// link.exe accepts (the minimum subsystem version); `None` lets the
+/// linker pick its default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Subsystem {
+    pub kind: SubsystemKind,
+    pub version: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SubsystemKind {
+    Console,
+    Windows,
+    Native,
+    PosixConsole,
+    EfiApplication,
+    EfiBootServiceDriver,
+    EfiRuntimeDriver,
+    EfiRom,
+}
+
+fn parse_subsystem(prg_name: &str, s: &str) -> Subsystem {
+    let (name, version) = match s.split_once(',') {
+        Some((name, version)) => (name, Some(version.to_string())),
+        None => (s, None),
+    };
+    let kind = match &*name.to_ascii_uppercase() {
+        "CONSOLE" => SubsystemKind::Console,
+        "WINDOWS" => SubsystemKind::Windows,
+        "NATIVE" => SubsystemKind::Native,
+        "POSIX" => SubsystemKind::PosixConsole,
+        "EFI_APPLICATION" => SubsystemKind::EfiApplication,
+        "EFI_BOOT_SERVICE_DRIVER" => SubsystemKind::EfiBootServiceDriver,
+        "EFI_RUNTIME_DRIVER" => SubsystemKind::EfiRuntimeDriver,
+        "EFI_ROM" => SubsystemKind::EfiRom,
+        _ => {
+            eprintln!("{}: Unknown /SUBSYSTEM value {}", prg_name, name);
+            std::process::exit(1)
+        }
+    };
+    Subsystem { kind, version }
+}
 
 pub fn main() -> Result<(), IOError> {
+    let mut args = std::env::args();
+
+    let prg_name = args.next().unwrap();
+
+    let mut inputs = Vec::new();
+    let mut output_file = None::<String>;
+    let mut entry = None::<String>;
+    let mut subsystem = None::<Subsystem>;
+    let mut def_file = None::<PathBuf>;
+    let mut dll = false;
+    let mut libpaths = Vec::new();
+
+    for arg in args {
+        match arg.split_once(':') {
+            Some((opt, value)) if opt.eq_ignore_ascii_case("/OUT") => {
+                output_file = Some(value.to_string());
+            }
+            Some((opt, value)) if opt.eq_ignore_ascii_case("/ENTRY") => {
+                entry = Some(value.to_string());
+            }
+            Some((opt, value)) if opt.eq_ignore_ascii_case("/SUBSYSTEM") => {
+                subsystem = Some(parse_subsystem(&prg_name, value));
+            }
+            Some((opt, value)) if opt.eq_ignore_ascii_case("/DEF") => {
+                def_file = Some(PathBuf::from(value));
+            }
+            Some((opt, value)) if opt.eq_ignore_ascii_case("/LIBPATH") => {
+                libpaths.push(PathBuf::from(value));
+            }
+            _ if arg.eq_ignore_ascii_case("/DLL") => {
+                dll = true;
+            }
+            _ if arg.starts_with('/') || arg.starts_with('-') => {
+                // not yet recognized by this driver
+            }
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if inputs.is_empty() {
+        eprintln!("{}: Expected at least one input file", prg_name);
+        std::process::exit(1)
+    }
+
+    let output_file = output_file.unwrap_or_else(|| {
+        let stem = inputs[0]
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("a")
+            .to_string();
+        stem + if dll { ".dll" } else { ".exe" }
+    });
+
+    eprintln!("{}: Output: {}", prg_name, output_file);
+    eprintln!("{}: Input Files: {:?}", prg_name, inputs);
+    if let Some(entry) = &entry {
+        eprintln!("{}: Entry: {}", prg_name, entry);
+    }
+    if let Some(subsystem) = &subsystem {
+        eprintln!("{}: Subsystem: {:?}", prg_name, subsystem);
+    }
+    if !libpaths.is_empty() {
+        eprintln!("{}: Library Paths: {:?}", prg_name, libpaths);
+    }
+    eprintln!("{}: DLL: {}", prg_name, dll);
+
+    if let Some(def_path) = &def_file {
+        let content = std::fs::read_to_string(def_path)?;
+        let module_def = crate::windows::parse_def_file(&content).unwrap_or_else(|e| {
+            eprintln!("{}: {}: {}", prg_name, def_path.display(), e);
+            std::process::exit(1)
+        });
+
+        eprintln!(
+            "{}: Module-Definition File: {} ({} export(s))",
+            prg_name,
+            def_path.display(),
+            module_def.exports.len()
+        );
+
+        if dll {
+            let dll_name = module_def.library.clone().unwrap_or_else(|| output_file.clone());
+            let machine = 0x8664; // IMAGE_FILE_MACHINE_AMD64, until target selection reaches this driver
+            let import_lib =
+                crate::windows::build_import_library(&dll_name, &module_def.exports, machine);
+            eprintln!(
+                "{}: Would write import library for {} with {} member(s)",
+                prg_name,
+                dll_name,
+                import_lib.members().len()
+            );
+        }
+    }
+
+    // There's no PE writer yet to hand these off to -- `binfmt::pe` only
+    // re-exports the COFF header layout so far, with no `Binfmt` impl --
+    // but `entry`/`subsystem`/`def_file`/`dll` are exactly the inputs such
+    // a writer will need: `entry` and `subsystem` become the optional
+    // header's `AddressOfEntryPoint`/`Subsystem` fields, `dll` selects
+    // `IMAGE_FILE_DLL` in the characteristics, and the parsed `.def`
+    // exports feed the export directory and (via
+    // `windows::build_import_library`) the companion import library.
     Err(std::io::Error::new(
         ErrorKind::Unsupported,
         "link.exe driver not implemented",
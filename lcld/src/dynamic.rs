@@ -0,0 +1,207 @@
+//! Dynamic-linking bookkeeping: which shared libraries an output needs,
+//! and how its Global Offset Table/Procedure Linkage Table slots are laid
+//! out, ahead of writing a `.dynamic`/`.got`/`.plt` section.
+//!
+//! Like [`crate::got`] and [`crate::arch::clever`], nothing in the link
+//! driver calls this yet -- [`driver::ld::main`](crate::driver::ld::main)
+//! doesn't invoke the link/relocation/output pipeline at all today. This
+//! is the layout algorithm for when it does: [`NeededLibraries`] collects
+//! `DT_NEEDED` entries in link-command order (what [`binfmt::elf::write_dynamic_entries`]
+//! ultimately serializes), [`PltGotLayout`] assigns each
+//! dynamically-resolved symbol its GOT and PLT slot indices, reserving
+//! the leading slots an ABI's PLT stub template expects to find, and
+//! [`GotOnlyLayout`] does the same for symbols only ever reached through
+//! a PLT-less `GOTPCREL` call (`-fno-plt`).
+
+use std::collections::HashSet;
+
+use binfmt::elf::consts;
+
+use crate::output::OutputType;
+
+/// Tracks which shared libraries an output needs `DT_NEEDED` entries for,
+/// preserving the order libraries were first referenced in (the same
+/// order `ld` lists them in, which callers depend on for `dlopen`-style
+/// symbol search order) while still deduplicating repeats.
+#[derive(Clone, Debug, Default)]
+pub struct NeededLibraries {
+    order: Vec<String>,
+    seen: HashSet<String>,
+}
+
+impl NeededLibraries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `soname` as needed, if it hasn't already been recorded.
+    pub fn add(&mut self, soname: &str) {
+        if self.seen.insert(soname.to_string()) {
+            self.order.push(soname.to_string());
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.order.iter().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+}
+
+/// The number of GOT entries an x86_64-style PLT-using ABI reserves ahead
+/// of the first symbol slot: the address of `.dynamic`, then the link map
+/// pointer and the lazy-resolver entry point, the latter two filled in by
+/// the dynamic linker at load time.
+pub const GOT_PLT_RESERVED_ENTRIES: usize = 3;
+
+/// A single dynamically-resolved symbol's assigned slots: its index into
+/// `.got.plt` (relative to the reserved entries) and, for lazily-bound
+/// symbols, its index into `.rela.plt`/`.plt` (they're assigned together,
+/// one PLT stub per GOT-PLT entry).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PltGotSlot {
+    pub got_index: usize,
+    pub plt_index: usize,
+}
+
+/// Assigns PLT/GOT slots to dynamically-resolved symbols in the order
+/// they're first requested, mirroring [`crate::got::Got`]'s
+/// add-and-dedupe approach for the classic MIPS GOT.
+#[derive(Clone, Debug, Default)]
+pub struct PltGotLayout {
+    symbols: Vec<String>,
+}
+
+impl PltGotLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assigns `symbol` its slot, or returns the slot already assigned to
+    /// it.
+    pub fn add(&mut self, symbol: &str) -> PltGotSlot {
+        let index = match self.symbols.iter().position(|s| s == symbol) {
+            Some(index) => index,
+            None => {
+                self.symbols.push(symbol.to_string());
+                self.symbols.len() - 1
+            }
+        };
+
+        PltGotSlot {
+            got_index: GOT_PLT_RESERVED_ENTRIES + index,
+            plt_index: index,
+        }
+    }
+
+    /// The number of dynamically-resolved symbols assigned a slot so far.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.symbols.iter().map(String::as_str)
+    }
+}
+
+/// GOT slots for calls that bypass the PLT entirely: `-fno-plt` compilers
+/// emit `call *sym@GOTPCREL(%rip)` instead of `call sym@PLT`, so the
+/// symbol needs a `.got` entry holding its resolved address (filled in
+/// eagerly, via `R_X86_64_GLOB_DAT`, the same as a data symbol) but no
+/// PLT stub and no `.got.plt`/`.rela.plt` slot.
+///
+/// Kept separate from [`PltGotLayout`] rather than folded into it, since
+/// the two tables live in different sections with different relocation
+/// types; a symbol referenced both ways (a `-fno-plt` translation unit
+/// calling through the GOT, another calling through the PLT) gets a slot
+/// in each.
+#[derive(Clone, Debug, Default)]
+pub struct GotOnlyLayout {
+    symbols: Vec<String>,
+}
+
+impl GotOnlyLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assigns `symbol` its `.got` slot index, or returns the index
+    /// already assigned to it.
+    pub fn add(&mut self, symbol: &str) -> usize {
+        match self.symbols.iter().position(|s| s == symbol) {
+            Some(index) => index,
+            None => {
+                self.symbols.push(symbol.to_string());
+                self.symbols.len() - 1
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.symbols.iter().map(String::as_str)
+    }
+}
+
+/// Builds the tag/value pairs for a `.dynamic` section's `DT_NEEDED`,
+/// `DT_SONAME`, and `DT_FLAGS_1` entries, in the order `readelf`/`ld`
+/// conventionally emit them (`DT_SONAME` before the `DT_NEEDED` list).
+///
+/// `strtab_offset(name)` resolves a string to its offset into the
+/// `.dynstr` the caller is building alongside this -- this module
+/// doesn't lay out string tables itself, the same way [`crate::got`]
+/// doesn't lay out the sections its tables end up in.
+///
+/// The result is handed to [`binfmt::elf::write_dynamic_entries`], which
+/// appends the `DT_NULL` terminator; nothing here needs to.
+pub fn build_dynamic_entries(
+    output_type: OutputType,
+    needed: &NeededLibraries,
+    soname: Option<&str>,
+    bind_now: bool,
+    strtab_offset: impl Fn(&str) -> u64,
+) -> Vec<(u64, u64)> {
+    let mut entries = Vec::new();
+
+    if let Some(soname) = soname {
+        entries.push((consts::DT_SONAME, strtab_offset(soname)));
+    }
+
+    for lib in needed.iter() {
+        entries.push((consts::DT_NEEDED, strtab_offset(lib)));
+    }
+
+    if bind_now {
+        entries.push((consts::DT_FLAGS, consts::DF_BIND_NOW));
+    }
+
+    let mut flags_1 = 0;
+    if output_type == OutputType::PieExecutable {
+        flags_1 |= consts::DF_1_PIE;
+    }
+    if bind_now {
+        flags_1 |= consts::DF_1_NOW;
+    }
+    if flags_1 != 0 {
+        entries.push((consts::DT_FLAGS_1, flags_1));
+    }
+
+    entries
+}
@@ -0,0 +1,179 @@
+//! `--gc-sections` reachability analysis: which output sections survive
+//! garbage collection.
+//!
+//! Like [`crate::got`] and [`crate::dynamic`], nothing in the link
+//! driver runs this yet. Given the merged sections, a set of
+//! always-live roots (the entry point, exported dynamic symbols, ...),
+//! and a way to look up which section defines a given symbol,
+//! [`reachable_sections`] does the mark phase: every section reachable
+//! from the roots by following relocations to whatever section defines
+//! their target symbol, plus any section [`is_retained`] says must
+//! survive unconditionally regardless of whether anything references
+//! it.
+
+use std::collections::HashSet;
+
+use binfmt::fmt::SectionFlag;
+
+use crate::output::OutputSection;
+
+/// Whether `pattern` (a `--keep-section=<glob>`-style pattern -- `*`
+/// matches any run of characters including none, `?` matches exactly
+/// one, everything else matches literally) matches `name` in full.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    fn go(p: &[u8], n: &[u8]) -> bool {
+        match (p.first(), n.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => go(&p[1..], n) || (!n.is_empty() && go(p, &n[1..])),
+            (Some(b'?'), Some(_)) => go(&p[1..], &n[1..]),
+            (Some(pc), Some(nc)) if pc == nc => go(&p[1..], &n[1..]),
+            _ => false,
+        }
+    }
+    go(pattern.as_bytes(), name.as_bytes())
+}
+
+/// Whether `section` must survive `--gc-sections` unconditionally: it
+/// carries [`binfmt::elf::consts::SHF_GNU_RETAIN`] (the
+/// `__attribute__((retain))`/`SHF_GNU_RETAIN` marker), or its name
+/// matches one of `keep_patterns` (`--keep-section=<glob>`, the same
+/// role `KEEP()` plays for a linker-script output section).
+pub fn is_retained(section: &OutputSection, keep_patterns: &[String]) -> bool {
+    let gnu_retain = section.flags.into_iter().flatten().any(|f| {
+        f == SectionFlag::FormatSpecific(binfmt::elf::consts::SHF_GNU_RETAIN as u32)
+    });
+
+    gnu_retain || keep_patterns.iter().any(|p| glob_match(p, &section.name))
+}
+
+/// Computes which sections in `sections` survive `--gc-sections`:
+/// every section [`is_retained`] says must be kept, plus everything
+/// reachable from `roots` (indices into `sections`) by following each
+/// live section's relocations to `section_of(symbol)`'s defining
+/// section, transitively.
+///
+/// Returns the surviving section indices.
+pub fn reachable_sections(
+    sections: &[OutputSection],
+    roots: &[usize],
+    section_of: &dyn Fn(&str) -> Option<usize>,
+    keep_patterns: &[String],
+) -> HashSet<usize> {
+    let mut live = HashSet::new();
+    let mut queue = Vec::new();
+
+    for (i, sect) in sections.iter().enumerate() {
+        if is_retained(sect, keep_patterns) && live.insert(i) {
+            queue.push(i);
+        }
+    }
+    for &root in roots {
+        if live.insert(root) {
+            queue.push(root);
+        }
+    }
+
+    while let Some(i) = queue.pop() {
+        for piece in &sections[i].pieces {
+            for reloc in &piece.relocs {
+                if let Some(target) = section_of(&reloc.symbol) {
+                    if live.insert(target) {
+                        queue.push(target);
+                    }
+                }
+            }
+        }
+    }
+
+    live
+}
+
+#[cfg(test)]
+mod tests {
+    use binfmt::howto::{Reloc, RelocCode};
+
+    use super::*;
+    use crate::output::MergedPiece;
+
+    fn section(name: &str, refs: &[&str]) -> OutputSection {
+        OutputSection {
+            name: name.to_string(),
+            ty: Default::default(),
+            flags: None,
+            align: 1,
+            content: Vec::new(),
+            pieces: vec![MergedPiece {
+                offset: 0,
+                size: 0,
+                relocs: refs
+                    .iter()
+                    .map(|sym| Reloc {
+                        code: RelocCode::None,
+                        symbol: sym.to_string(),
+                        addend: None,
+                        offset: 0,
+                    })
+                    .collect(),
+                entry_offsets: None,
+            }],
+            vaddr: 0,
+            file_offset: 0,
+        }
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match(".text.*", ".text.foo"));
+        assert!(!glob_match(".text.*", ".data.foo"));
+        assert!(glob_match(".rodata.?", ".rodata.a"));
+        assert!(!glob_match(".rodata.?", ".rodata.ab"));
+        assert!(glob_match(".note", ".note"));
+    }
+
+    #[test]
+    fn unreferenced_section_is_collected() {
+        let sections = vec![section(".text", &["used"]), section(".text.dead", &[])];
+        let section_of = |sym: &str| if sym == "used" { Some(0) } else { None };
+
+        let live = reachable_sections(&sections, &[0], &section_of, &[]);
+        assert_eq!(live, [0].into_iter().collect());
+    }
+
+    #[test]
+    fn reachable_section_survives_transitively() {
+        let sections = vec![
+            section("a", &["b_sym"]),
+            section("b", &["c_sym"]),
+            section("c", &[]),
+        ];
+        let section_of = |sym: &str| match sym {
+            "b_sym" => Some(1),
+            "c_sym" => Some(2),
+            _ => None,
+        };
+
+        let live = reachable_sections(&sections, &[0], &section_of, &[]);
+        assert_eq!(live, [0, 1, 2].into_iter().collect());
+    }
+
+    #[test]
+    fn keep_section_pattern_retains_unreferenced_section() {
+        let sections = vec![section(".linker_set.foo", &[])];
+        let keep = vec![".linker_set.*".to_string()];
+
+        let live = reachable_sections(&sections, &[], &|_| None, &keep);
+        assert_eq!(live, [0].into_iter().collect());
+    }
+
+    #[test]
+    fn gnu_retain_flag_retains_unreferenced_section() {
+        let mut sect = section(".retained", &[]);
+        sect.flags = Some(
+            SectionFlag::FormatSpecific(binfmt::elf::consts::SHF_GNU_RETAIN as u32).into(),
+        );
+
+        let live = reachable_sections(&[sect], &[], &|_| None, &[]);
+        assert_eq!(live, [0].into_iter().collect());
+    }
+}
@@ -0,0 +1,123 @@
+//! Classic MIPS ABI (`o32`)-style Global Offset Table layout.
+//!
+//! The classic ABI GOT begins with two reserved entries (the lazy-resolver
+//! stub pointer and the module's own link-map pointer), followed by one slot
+//! per locally-resolved `%got`/`%hi`/`%lo` reference, followed by one slot
+//! per referenced global symbol, in the same order those symbols appear in
+//! the tail of the dynamic symbol table (`DT_MIPS_GOTSYM`). A single GOT can
+//! only be addressed via a 16-bit signed offset from `$gp`, so when an
+//! output module's GOT would overflow that range, it is split into a
+//! primary GOT plus one secondary GOT per overflowing input object
+//! ([`MultiGot`]).
+//!
+//! No MIPS backend exists in `arch-ops` yet, so nothing in the link driver
+//! constructs these types today; this module lays out the layout algorithm
+//! so the relocation-processing stage can adopt it once one does.
+
+pub const RESERVED_ENTRIES: usize = 2;
+
+/// The largest number of entries a single GOT may hold before `$gp`-relative
+/// addressing (a 16-bit signed word offset) can no longer reach every entry.
+pub const MAX_ENTRIES_PER_GOT: usize = 0xFFF0 / 4;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum GotEntry {
+    /// A link-time-resolved address, not exported to the dynamic symbol
+    /// table.
+    Local { addend: i64 },
+    /// A slot resolved (possibly lazily) by the dynamic linker.
+    Global { symbol: String },
+}
+
+/// A single Global Offset Table: a sequence of local entries followed by
+/// global entries, per the classic ABI ordering.
+#[derive(Clone, Debug, Default)]
+pub struct Got {
+    locals: Vec<GotEntry>,
+    globals: Vec<GotEntry>,
+}
+
+impl Got {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_local(&mut self, addend: i64) -> usize {
+        self.locals.push(GotEntry::Local { addend });
+        RESERVED_ENTRIES + self.locals.len() - 1
+    }
+
+    /// Adds a global entry, or returns the index of an existing one for the
+    /// same symbol.
+    pub fn add_global(&mut self, symbol: &str) -> usize {
+        if let Some(pos) = self.globals.iter().position(|e| matches!(e, GotEntry::Global { symbol: s } if s == symbol))
+        {
+            return RESERVED_ENTRIES + self.locals.len() + pos;
+        }
+        self.globals.push(GotEntry::Global {
+            symbol: symbol.to_string(),
+        });
+        RESERVED_ENTRIES + self.locals.len() + self.globals.len() - 1
+    }
+
+    /// Total number of entries, including the two reserved slots.
+    pub fn len(&self) -> usize {
+        RESERVED_ENTRIES + self.locals.len() + self.globals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The index of the first global entry: the value `DT_MIPS_GOTSYM`
+    /// wants, once the output's dynamic symbol table is laid out so this
+    /// GOT's globals form its tail.
+    pub fn gotsym_index(&self) -> usize {
+        RESERVED_ENTRIES + self.locals.len()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &GotEntry> {
+        self.locals.iter().chain(self.globals.iter())
+    }
+}
+
+/// Splits a single output module's GOT usage across multiple [`Got`]s,
+/// keyed per input object, once a single table would overflow
+/// [`MAX_ENTRIES_PER_GOT`]. The first table built is the primary GOT, shared
+/// by every input object; overflowing objects get their own secondary GOT.
+#[derive(Clone, Debug, Default)]
+pub struct MultiGot {
+    tables: Vec<Got>,
+    current: usize,
+}
+
+impl MultiGot {
+    pub fn new() -> Self {
+        Self {
+            tables: vec![Got::new()],
+            current: 0,
+        }
+    }
+
+    /// Reserves space for `local_count` local entries and `global_count`
+    /// global entries belonging to one input object, moving on to a new
+    /// secondary GOT if the current one would overflow.
+    pub fn begin_object(&mut self, local_count: usize, global_count: usize) -> &mut Got {
+        let needed = local_count + global_count;
+        if self.tables[self.current].len() + needed > MAX_ENTRIES_PER_GOT
+            && self.tables[self.current].len() > RESERVED_ENTRIES
+        {
+            self.tables.push(Got::new());
+            self.current += 1;
+        }
+        &mut self.tables[self.current]
+    }
+
+    pub fn tables(&self) -> &[Got] {
+        &self.tables
+    }
+
+    pub fn is_multi(&self) -> bool {
+        self.tables.len() > 1
+    }
+}
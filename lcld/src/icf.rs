@@ -0,0 +1,291 @@
+//! `--icf=safe`/`--icf=all` identical code folding: finding sections
+//! whose contents and relocations are indistinguishable from another
+//! section's, so only one copy needs to survive in the output --
+//! shrinking template-heavy C++ the way `lld`'s `--icf` does.
+//!
+//! Like [`crate::gc`], this runs over one [`OutputSection`] per input
+//! section, before [`crate::output::merge_sections`] combines same-named
+//! sections together: GC and ICF are both whole-section passes over the
+//! same per-input-section granularity, just with different equivalence
+//! relations (reachability for GC, byte-for-byte sameness for ICF).
+//! [`fold_sections`] does the fixed-point partition refinement: two
+//! sections start out equivalent if their content and relocation shapes
+//! match, and stay equivalent only if every relocation they make also
+//! targets equivalent sections, transitively, the same algorithm
+//! `gold`/`lld` use so that two mutually-recursive identical functions
+//! still fold.
+
+use std::collections::HashMap;
+
+use binfmt::fmt::SectionFlag;
+use binfmt::howto::RelocCode;
+
+use crate::output::OutputSection;
+
+/// Which sections [`fold_sections`] is allowed to consider folding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IcfMode {
+    /// Only fold non-writable sections: nothing can observe that two
+    /// originally-distinct read-only functions/constants now share one
+    /// address, since nothing can write through a pointer to compare
+    /// identity the way code can compare function pointers. Matches
+    /// `lld`'s `--icf=safe`.
+    Safe,
+    /// Fold any identical section regardless of writability, the same
+    /// risk `--icf=all` accepts: code that compares two originally-
+    /// distinct function or object addresses for identity can observe
+    /// the folding.
+    All,
+}
+
+/// Whether `section` is eligible for folding under `mode` at all --
+/// independent of whether anything else happens to match it.
+fn is_foldable(section: &OutputSection, mode: IcfMode) -> bool {
+    if section.content.is_empty() && section.pieces.is_empty() {
+        return false;
+    }
+    match mode {
+        IcfMode::All => true,
+        IcfMode::Safe => !section
+            .flags
+            .into_iter()
+            .flatten()
+            .any(|f| f == SectionFlag::Writable),
+    }
+}
+
+/// Where a relocation within a foldable section points, in a form that's
+/// stable across folding passes: either the current partition class of
+/// another foldable section (so two sections that each call a folded
+/// pair still compare equal), or the raw symbol name for anything that
+/// isn't itself a candidate (external symbols, sections excluded by
+/// `mode`).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum RelocTarget {
+    Class(usize),
+    Symbol(String),
+}
+
+/// A section's folding-relevant content, relative to the current
+/// partition: its bytes and the shape of its relocations. Two sections
+/// with equal keys are indistinguishable as far as this pass can tell.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PartitionKey {
+    content: Vec<u8>,
+    relocs: Vec<(u64, RelocCode, Option<i64>, RelocTarget)>,
+}
+
+fn partition_key(
+    index: usize,
+    sections: &[OutputSection],
+    classes: &[usize],
+    foldable: &[bool],
+    section_of: &dyn Fn(&str) -> Option<usize>,
+) -> PartitionKey {
+    let section = &sections[index];
+
+    let relocs = section
+        .pieces
+        .iter()
+        .flat_map(|piece| &piece.relocs)
+        .map(|reloc| {
+            let target = match section_of(&reloc.symbol) {
+                Some(t) if foldable[t] => RelocTarget::Class(classes[t]),
+                _ => RelocTarget::Symbol(reloc.symbol.clone()),
+            };
+            (reloc.offset, reloc.code, reloc.addend, target)
+        })
+        .collect();
+
+    PartitionKey {
+        content: section.content.clone(),
+        relocs,
+    }
+}
+
+/// Refines `classes` once: sections whose [`PartitionKey`] is still
+/// equal (given the *previous* round's classes) keep the same class,
+/// and any split that a changed relocation target causes is reflected
+/// in the freshly assigned class ids. Singleton (unfoldable) sections
+/// are each kept in their own class, bitwise-disjoint from the foldable
+/// range so they never compare equal to anything.
+fn refine(
+    sections: &[OutputSection],
+    classes: &[usize],
+    foldable: &[bool],
+    section_of: &dyn Fn(&str) -> Option<usize>,
+) -> Vec<usize> {
+    let mut key_to_class = HashMap::new();
+    let mut next = vec![0; sections.len()];
+
+    for i in 0..sections.len() {
+        if !foldable[i] {
+            next[i] = usize::MAX - i;
+            continue;
+        }
+        let key = partition_key(i, sections, classes, foldable, section_of);
+        let id = key_to_class.len();
+        next[i] = *key_to_class.entry(key).or_insert(id);
+    }
+
+    next
+}
+
+/// Finds every section that can be folded away under `mode`: sections
+/// whose content and relocation shapes are equal, transitively through
+/// what they reference, iterated to a fixed point.
+///
+/// `section_of(symbol)` resolves a relocation's target symbol to the
+/// index of the section that defines it, the same role it plays in
+/// [`crate::gc::reachable_sections`].
+///
+/// Returns a map from each folded section's index to the index of the
+/// single survivor its class was folded into (the lowest index in the
+/// class, so folding is deterministic regardless of input order).
+/// Indices absent from the map were not folded -- either ineligible
+/// under `mode`, or the only section in their equivalence class.
+pub fn fold_sections(
+    sections: &[OutputSection],
+    section_of: &dyn Fn(&str) -> Option<usize>,
+    mode: IcfMode,
+) -> HashMap<usize, usize> {
+    let foldable: Vec<bool> = sections.iter().map(|s| is_foldable(s, mode)).collect();
+    let mut classes = vec![0; sections.len()];
+
+    loop {
+        let next = refine(sections, &classes, &foldable, section_of);
+        if next == classes {
+            break;
+        }
+        classes = next;
+    }
+
+    let mut survivor_of_class: HashMap<usize, usize> = HashMap::new();
+    for (i, &class) in classes.iter().enumerate() {
+        if foldable[i] {
+            survivor_of_class.entry(class).or_insert(i);
+        }
+    }
+
+    let mut group_size: HashMap<usize, usize> = HashMap::new();
+    for (i, &class) in classes.iter().enumerate() {
+        if foldable[i] {
+            *group_size.entry(class).or_insert(0) += 1;
+        }
+    }
+
+    classes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &class)| {
+            if !foldable[i] || group_size[&class] < 2 {
+                return None;
+            }
+            let survivor = survivor_of_class[&class];
+            (survivor != i).then_some((i, survivor))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use binfmt::howto::Reloc;
+
+    use super::*;
+    use crate::output::MergedPiece;
+
+    fn section(name: &str, content: &[u8], refs: &[&str]) -> OutputSection {
+        OutputSection {
+            name: name.to_string(),
+            ty: Default::default(),
+            flags: None,
+            align: 1,
+            content: content.to_vec(),
+            pieces: vec![MergedPiece {
+                offset: 0,
+                size: content.len(),
+                relocs: refs
+                    .iter()
+                    .map(|sym| Reloc {
+                        code: RelocCode::None,
+                        symbol: sym.to_string(),
+                        addend: None,
+                        offset: 0,
+                    })
+                    .collect(),
+                entry_offsets: None,
+            }],
+            vaddr: 0,
+            file_offset: 0,
+        }
+    }
+
+    #[test]
+    fn identical_sections_fold_to_the_first() {
+        let sections = vec![
+            section("f1", &[1, 2, 3], &[]),
+            section("f2", &[1, 2, 3], &[]),
+            section("f3", &[9, 9, 9], &[]),
+        ];
+
+        let folded = fold_sections(&sections, &|_| None, IcfMode::All);
+        assert_eq!(folded.get(&1), Some(&0));
+        assert_eq!(folded.get(&0), None);
+        assert_eq!(folded.get(&2), None);
+    }
+
+    #[test]
+    fn differing_relocation_targets_prevent_folding() {
+        let sections = vec![
+            section("f1", &[0, 0, 0, 0], &["a"]),
+            section("f2", &[0, 0, 0, 0], &["b"]),
+            section("a", &[1], &[]),
+            section("b", &[2], &[]),
+        ];
+        let section_of = |sym: &str| match sym {
+            "a" => Some(2),
+            "b" => Some(3),
+            _ => None,
+        };
+
+        let folded = fold_sections(&sections, &section_of, IcfMode::All);
+        assert!(!folded.contains_key(&0));
+        assert!(!folded.contains_key(&1));
+    }
+
+    #[test]
+    fn transitively_identical_callees_allow_folding() {
+        // f1 -> a, f2 -> b, and a/b are themselves identical, so f1/f2
+        // should fold even though they reference different symbols.
+        let sections = vec![
+            section("f1", &[0, 0, 0, 0], &["a"]),
+            section("f2", &[0, 0, 0, 0], &["b"]),
+            section("a", &[1, 2, 3], &[]),
+            section("b", &[1, 2, 3], &[]),
+        ];
+        let section_of = |sym: &str| match sym {
+            "a" => Some(2),
+            "b" => Some(3),
+            _ => None,
+        };
+
+        let folded = fold_sections(&sections, &section_of, IcfMode::All);
+        assert_eq!(folded.get(&3), Some(&2));
+        assert_eq!(folded.get(&1), Some(&0));
+    }
+
+    #[test]
+    fn safe_mode_excludes_writable_sections() {
+        let mut data = section("d1", &[1, 2, 3], &[]);
+        data.flags = Some(SectionFlag::Writable.into());
+        let mut data2 = section("d2", &[1, 2, 3], &[]);
+        data2.flags = Some(SectionFlag::Writable.into());
+
+        let folded = fold_sections(&[data, data2], &|_| None, IcfMode::Safe);
+        assert!(folded.is_empty());
+
+        let sections = vec![section("d1", &[1, 2, 3], &[]), section("d2", &[1, 2, 3], &[])];
+        let folded = fold_sections(&sections, &|_| None, IcfMode::Safe);
+        assert_eq!(folded.get(&1), Some(&0));
+    }
+}
@@ -0,0 +1,308 @@
+//! Incremental linking: caching enough of a previous link's state to
+//! skip re-laying-out inputs that haven't changed since, the speedup
+//! `--incremental`-style modes give iterative builds.
+//!
+//! Like [`crate::gc`] and [`crate::dynamic`], nothing in the link driver
+//! calls this yet -- [`driver::ld::main`](crate::driver::ld::main)
+//! doesn't invoke the link/layout pipeline at all today. This is the
+//! cache format and change-detection logic for when it does:
+//! [`fingerprint_input`] records enough about an input file to notice if
+//! it changed, [`diff_fingerprints`] turns last time's fingerprints and
+//! this time's into the set of inputs that need re-processing, and
+//! [`LinkCache`] is the side-file format a relink loads to decide
+//! whether it can skip straight to patching rather than laying out from
+//! scratch.
+//!
+//! The cache is a small hand-rolled line-based text format, one
+//! space-separated `<kind> <fields...>` record per line (no `serde`
+//! dependency exists outside this crate's build script), the same style
+//! [`crate::tbd`] and [`crate::windows`] already use for their own
+//! text formats.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Enough about one input file, as of a past link, to notice whether it
+/// changed: its size and modification time. Content hashing would catch
+/// touch-without-edit false positives, but `make`/`ninja`-driven rebuilds
+/// already only re-invoke the linker when an input's mtime moved, so
+/// matching that granularity is enough here.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputFingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    pub mtime: u64,
+}
+
+/// Seconds since the Unix epoch for `path`'s last modification, and its
+/// size in bytes, as of right now.
+pub fn fingerprint_input(path: &Path) -> io::Result<InputFingerprint> {
+    let meta = fs::metadata(path)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    Ok(InputFingerprint {
+        path: path.to_path_buf(),
+        size: meta.len(),
+        mtime,
+    })
+}
+
+/// Which inputs changed between a previous link's fingerprints and this
+/// one's: added, removed, or present in both but with a different size
+/// or modification time. Everything else can, as far as this check is
+/// concerned, be patched rather than relaid-out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FingerprintDiff {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+}
+
+impl FingerprintDiff {
+    /// Whether every input is unchanged -- an incremental relink can
+    /// reuse the previous layout verbatim.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+pub fn diff_fingerprints(previous: &[InputFingerprint], current: &[InputFingerprint]) -> FingerprintDiff {
+    let prev_by_path: HashMap<&Path, &InputFingerprint> =
+        previous.iter().map(|f| (f.path.as_path(), f)).collect();
+    let cur_by_path: HashMap<&Path, &InputFingerprint> =
+        current.iter().map(|f| (f.path.as_path(), f)).collect();
+
+    let mut diff = FingerprintDiff::default();
+
+    for fp in current {
+        match prev_by_path.get(fp.path.as_path()) {
+            None => diff.added.push(fp.path.clone()),
+            Some(prev) if prev.size != fp.size || prev.mtime != fp.mtime => {
+                diff.modified.push(fp.path.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    for fp in previous {
+        if !cur_by_path.contains_key(fp.path.as_path()) {
+            diff.removed.push(fp.path.clone());
+        }
+    }
+
+    diff
+}
+
+/// The address a previous link assigned to a symbol or output section,
+/// keyed by name -- what a patched relink reuses for everything that
+/// [`FingerprintDiff`] didn't mark as needing a fresh layout.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LinkCache {
+    pub inputs: Vec<InputFingerprint>,
+    pub section_addresses: HashMap<String, u64>,
+    pub symbol_addresses: HashMap<String, u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CacheParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for CacheParseError {}
+
+/// Serializes `cache` as `key value` lines, one record per input/section/
+/// symbol, each prefixed by its kind so [`parse_cache`] can tell them
+/// apart without a schema.
+pub fn write_cache(cache: &LinkCache) -> String {
+    let mut out = String::new();
+    for fp in &cache.inputs {
+        out.push_str(&format!("input {} {} {}\n", fp.size, fp.mtime, fp.path.display()));
+    }
+    for (name, addr) in &cache.section_addresses {
+        out.push_str(&format!("section {:#x} {}\n", addr, name));
+    }
+    for (name, addr) in &cache.symbol_addresses {
+        out.push_str(&format!("symbol {:#x} {}\n", addr, name));
+    }
+    out
+}
+
+/// Parses the format [`write_cache`] produces. Unrecognized record
+/// kinds are rejected rather than skipped, since a cache from an
+/// incompatible future version silently misread is worse than one
+/// that's rejected outright, forcing a from-scratch relink.
+pub fn parse_cache(content: &str) -> Result<LinkCache, CacheParseError> {
+    let mut cache = LinkCache::default();
+
+    for (i, line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (kind, rest) = line.split_once(' ').ok_or_else(|| CacheParseError {
+            line: line_no,
+            message: "expected `<kind> ...`".to_string(),
+        })?;
+
+        match kind {
+            "input" => {
+                let mut parts = rest.splitn(3, ' ');
+                let size = parts.next().unwrap_or_default();
+                let mtime = parts.next().unwrap_or_default();
+                let path = parts.next().ok_or_else(|| CacheParseError {
+                    line: line_no,
+                    message: "expected `input <size> <mtime> <path>`".to_string(),
+                })?;
+                cache.inputs.push(InputFingerprint {
+                    path: PathBuf::from(path),
+                    size: size.parse().map_err(|_| CacheParseError {
+                        line: line_no,
+                        message: format!("invalid size `{}`", size),
+                    })?,
+                    mtime: mtime.parse().map_err(|_| CacheParseError {
+                        line: line_no,
+                        message: format!("invalid mtime `{}`", mtime),
+                    })?,
+                });
+            }
+            "section" | "symbol" => {
+                let (addr, name) = rest.split_once(' ').ok_or_else(|| CacheParseError {
+                    line: line_no,
+                    message: format!("expected `{} <address> <name>`", kind),
+                })?;
+                let addr = addr
+                    .strip_prefix("0x")
+                    .ok_or_else(|| CacheParseError {
+                        line: line_no,
+                        message: format!("expected a `0x`-prefixed address, found `{}`", addr),
+                    })
+                    .and_then(|hex| {
+                        u64::from_str_radix(hex, 16).map_err(|_| CacheParseError {
+                            line: line_no,
+                            message: format!("invalid address `{}`", addr),
+                        })
+                    })?;
+                let table = if kind == "section" {
+                    &mut cache.section_addresses
+                } else {
+                    &mut cache.symbol_addresses
+                };
+                table.insert(name.to_string(), addr);
+            }
+            _ => {
+                return Err(CacheParseError {
+                    line: line_no,
+                    message: format!("unrecognized record kind `{}`", kind),
+                })
+            }
+        }
+    }
+
+    Ok(cache)
+}
+
+/// Loads the cache at `path`, or `None` if it doesn't exist yet --
+/// which a first (non-incremental) link at this output path always
+/// hits.
+pub fn load_cache(path: &Path) -> io::Result<Option<LinkCache>> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse_cache(&content)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn store_cache(path: &Path, cache: &LinkCache) -> io::Result<()> {
+    fs::write(path, write_cache(cache))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_inputs_produce_an_empty_diff() {
+        let prev = vec![InputFingerprint {
+            path: PathBuf::from("a.o"),
+            size: 10,
+            mtime: 100,
+        }];
+        let diff = diff_fingerprints(&prev, &prev);
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn modified_mtime_is_reported() {
+        let prev = vec![InputFingerprint {
+            path: PathBuf::from("a.o"),
+            size: 10,
+            mtime: 100,
+        }];
+        let cur = vec![InputFingerprint {
+            path: PathBuf::from("a.o"),
+            size: 10,
+            mtime: 200,
+        }];
+        let diff = diff_fingerprints(&prev, &cur);
+        assert_eq!(diff.modified, vec![PathBuf::from("a.o")]);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+    }
+
+    #[test]
+    fn added_and_removed_inputs_are_reported() {
+        let prev = vec![InputFingerprint {
+            path: PathBuf::from("old.o"),
+            size: 1,
+            mtime: 1,
+        }];
+        let cur = vec![InputFingerprint {
+            path: PathBuf::from("new.o"),
+            size: 1,
+            mtime: 1,
+        }];
+        let diff = diff_fingerprints(&prev, &cur);
+        assert_eq!(diff.added, vec![PathBuf::from("new.o")]);
+        assert_eq!(diff.removed, vec![PathBuf::from("old.o")]);
+    }
+
+    #[test]
+    fn cache_round_trips_through_its_text_format() {
+        let mut cache = LinkCache {
+            inputs: vec![InputFingerprint {
+                path: PathBuf::from("a.o"),
+                size: 42,
+                mtime: 1000,
+            }],
+            ..Default::default()
+        };
+        cache.section_addresses.insert(".text".to_string(), 0x401000);
+        cache.symbol_addresses.insert("main".to_string(), 0x401010);
+
+        let text = write_cache(&cache);
+        let parsed = parse_cache(&text).unwrap();
+        assert_eq!(parsed, cache);
+    }
+
+    #[test]
+    fn unrecognized_record_kind_is_rejected() {
+        assert!(parse_cache("bogus 1 2 3\n").is_err());
+    }
+}
@@ -13,6 +13,9 @@ pub enum InputFileType {
     Archive,
     LtoInput(&'static dyn LtoProvider),
     LinkerScript,
+    /// An Apple `.tbd` text-based stub, standing in for a dylib that
+    /// isn't shipped as a real binary -- see [`crate::tbd`].
+    TextStub,
 }
 
 impl core::fmt::Display for InputFileType {
@@ -25,10 +28,49 @@ impl core::fmt::Display for InputFileType {
                 f.write_str(prov.name())
             }
             Self::LinkerScript => f.write_str("script"),
+            Self::TextStub => f.write_str("text-stub"),
         }
     }
 }
 
+/// Runs [`ident_input`] over every path in `paths` concurrently, returning
+/// results in the same order as `paths`.
+///
+/// Each call is dominated by file-open and header-read latency rather than
+/// CPU work, so splitting `paths` across a small pool of threads (one per
+/// available core, capped to the number of paths) lets those I/O waits
+/// overlap instead of serializing. Callers that need a deterministic link
+/// order -- symbol resolution, diagnostic printing -- get one for free,
+/// since the result `Vec` lines up index-for-index with `paths`.
+pub fn ident_inputs_parallel(paths: &[impl AsRef<Path> + Sync]) -> Vec<std::io::Result<InputFileType>> {
+    let nthreads = std::thread::available_parallelism()
+        .map(std::num::NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(paths.len().max(1));
+    let chunk_size = paths.len().div_ceil(nthreads).max(1);
+
+    let mut results: Vec<Option<std::io::Result<InputFileType>>> =
+        (0..paths.len()).map(|_| None).collect();
+
+    std::thread::scope(|scope| {
+        for (path_chunk, result_chunk) in paths
+            .chunks(chunk_size)
+            .zip(results.chunks_mut(chunk_size))
+        {
+            scope.spawn(move || {
+                for (path, slot) in path_chunk.iter().zip(result_chunk.iter_mut()) {
+                    *slot = Some(ident_input(path.as_ref()));
+                }
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|r| r.expect("every path is covered by exactly one chunk"))
+        .collect()
+}
+
 #[allow(clippy::unused_io_amount)]
 pub fn ident_input(p: &Path) -> std::io::Result<InputFileType> {
     let file = File::open(p)?;
@@ -42,8 +84,11 @@ pub fn ident_input(p: &Path) -> std::io::Result<InputFileType> {
         (&file).seek(SeekFrom::Start(0))?;
         if arch_buf == *b"!<arch>\n" {
             Ok(InputFileType::Archive)
+        } else if arch_buf.starts_with(b"---") {
+            Ok(InputFileType::TextStub)
+        } else if let Some(provider) = crate::lto::identify(&arch_buf) {
+            Ok(InputFileType::LtoInput(provider))
         } else {
-            // todo: Identify Lto Input objects
             Ok(InputFileType::LinkerScript)
         }
     }
@@ -1,26 +1,92 @@
-use std::{io, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt, io,
+    io::Cursor,
+    path::PathBuf,
+};
 
 use binfmt::{
     ar::Archive,
-    fmt::{BinaryFile, Section},
+    fmt::{BinaryFile, Binfmt, GroupType, Section},
+    howto::{HowTo, RelocCode},
+    sym::{Symbol, SymbolKind, SymbolType},
 };
 use indexmap::IndexMap;
 
 use crate::{input::InputFileType, script::ParsedScript};
 
+/// Runs [`Binfmt::segment_security_audit`] against the final output file and
+/// prints the standard GNU-style warnings for `RWX` `LOAD` segments and an
+/// executable stack. Intended to be called right before (or after) the
+/// output file is written out, once output emission is implemented.
+pub fn warn_on_insecure_segments(fmt: &dyn Binfmt, out: &BinaryFile, output_file: &str) {
+    let audit = fmt.segment_security_audit(out);
+
+    if !audit.rwx_segments.is_empty() {
+        eprintln!(
+            "{}: warning: has a LOAD segment with RWX permissions",
+            output_file
+        );
+    }
+
+    if audit.executable_stack {
+        eprintln!(
+            "{}: warning: missing .note.GNU-stack section implies executable stack",
+            output_file
+        );
+    }
+}
+
+/// Runs [`HowTo::relax`] against a resolved relocation, rewriting `region`
+/// in place and returning the [`RelocCode`] that should be used to finish
+/// applying it -- the relaxed code if relaxation took effect, or `code`
+/// unchanged otherwise. Called by [`crate::reloc::apply_relocations`]
+/// immediately before [`HowTo::apply`].
+pub fn relax_reloc(
+    howto: &dyn HowTo,
+    region: &mut [u8],
+    code: RelocCode,
+    is_local: bool,
+) -> RelocCode {
+    howto.relax(region, is_local).unwrap_or(code)
+}
+
 pub enum LinkInput {
     Unopened(PathBuf),
     Object(BinaryFile<'static>),
+    /// A `--start-lib`/`--end-lib` object that hasn't been needed (yet):
+    /// parsed like any other object, but not folded into the symbol
+    /// table until [`LinkState::resolve_symbols`] finds that one of its
+    /// definitions satisfies an undefined reference, same as an archive
+    /// member.
+    LazyObject(BinaryFile<'static>),
     Archive(Archive),
     ParsedScript(ParsedScript),
+    TextStub(crate::tbd::TbdFile),
     GroupStartMarker,
     Group(InputId),
 }
 
 pub struct InputFile {
+    /// The path `input` was (or will be) read from, kept around after
+    /// [`InputFile::open`] replaces `input` with the parsed form so
+    /// diagnostics (multiply-defined/undefined symbol errors) can still
+    /// name the object that was responsible.
+    pub name: PathBuf,
     pub input: LinkInput,
     pub ty: InputFileType,
     pub as_needed: bool,
+    /// Set for a bare object file that appeared between `--start-lib` and
+    /// `--end-lib`: [`InputFile::open`] parses it the same as any other
+    /// object but stores it as [`LinkInput::LazyObject`] instead of
+    /// [`LinkInput::Object`], so [`LinkState::resolve_symbols`] only pulls
+    /// it into the link if one of its definitions is actually needed --
+    /// what `--start-lib`/`--end-lib` exist for: letting a build system
+    /// pass loose object files with archive-style "only link what's used"
+    /// semantics, without having to `ar`-archive them into a real static
+    /// library first.
+    pub lazy: bool,
 }
 
 impl InputFile {
@@ -29,14 +95,33 @@ impl InputFile {
             let mut file = std::fs::File::open(path)?;
             match self.ty {
                 InputFileType::Archive => self.input = LinkInput::Archive(Archive::read(file)?),
-                InputFileType::LinkerScript => todo!("Parse Linker Script"),
+                InputFileType::LinkerScript => {
+                    let mut src = String::new();
+                    io::Read::read_to_string(&mut file, &mut src)?;
+                    let script = crate::script::parse(&src)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+                    self.input = LinkInput::ParsedScript(script);
+                }
                 InputFileType::Object(fmt) => {
-                    self.input = LinkInput::Object(fmt.read_file(&mut file)?.ok_or_else(|| {
+                    let parsed = fmt.read_file(&mut file)?.ok_or_else(|| {
                         io::Error::new(
                             io::ErrorKind::InvalidData,
                             format!("Failed to open: {} (detected as {fmt:?})", path.display()),
                         )
-                    })?);
+                    })?;
+                    self.input = if self.lazy {
+                        LinkInput::LazyObject(parsed)
+                    } else {
+                        LinkInput::Object(parsed)
+                    };
+                }
+                InputFileType::TextStub => {
+                    let mut src = String::new();
+                    io::Read::read_to_string(&mut file, &mut src)?;
+                    let stub = crate::tbd::parse_tbd(&src).map_err(|e| {
+                        io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+                    })?;
+                    self.input = LinkInput::TextStub(stub);
                 }
                 InputFileType::LtoInput(lto) => Err(io::Error::new(
                     io::ErrorKind::InvalidInput,
@@ -117,9 +202,11 @@ impl LinkState {
         self.inputs.insert(
             id,
             InputFile {
+                name: PathBuf::from("<start-group>"),
                 input: LinkInput::GroupStartMarker,
                 ty: InputFileType::LinkerScript,
                 as_needed: false,
+                lazy: false,
             },
         );
 
@@ -133,6 +220,7 @@ impl LinkState {
         self.inputs.insert(
             id,
             InputFile {
+                name: PathBuf::from("<end-group>"),
                 input: LinkInput::Group(
                     self.group_head_stack
                         .pop()
@@ -140,6 +228,7 @@ impl LinkState {
                 ),
                 ty: InputFileType::LinkerScript,
                 as_needed: false,
+                lazy: false,
             },
         );
     }
@@ -152,4 +241,583 @@ impl LinkState {
 
         Ok(())
     }
+
+    /// Selects which `SHT_GROUP`/`GRP_COMDAT`-style link-once section
+    /// groups to keep when more than one input defines a group under the
+    /// same signature symbol -- the mechanism C++ compilers rely on so an
+    /// inline function or template instantiation emitted into every
+    /// translation unit that uses it doesn't collide with every other
+    /// TU's copy at link time. The first input to define a given
+    /// signature wins; every later input's sections for that signature
+    /// are returned as `(input, section index)` pairs to discard.
+    ///
+    /// Must run before folding symbols into the global table: a symbol
+    /// defined only inside a discarded section should never reach
+    /// [`symbol_binding`], or it would look like an ordinary multiple
+    /// definition instead of the COMDAT duplicate it actually is.
+    /// [`GroupType::Normal`] groups (not a "pick one" COMDAT group, just
+    /// sections generated together) are left alone. Only considers
+    /// inputs already open at the time it's called, so archive members
+    /// pulled in later by [`LinkState::resolve_symbols`]'s lazy search
+    /// aren't covered -- in practice this matches GNU ld closely enough,
+    /// since COMDAT groups come from compiler-generated object code, not
+    /// the static libraries a linker searches for missing symbols.
+    fn select_comdat_groups(&self) -> HashSet<(InputId, u32)> {
+        let mut kept = HashMap::<&str, InputId>::new();
+        let mut discarded = HashSet::new();
+
+        for (&id, input) in &self.inputs {
+            let LinkInput::Object(file) = &input.input else {
+                continue;
+            };
+            for group in file.section_groups() {
+                if !matches!(group.group_type, GroupType::LinkOnce) {
+                    continue;
+                }
+                match kept.get(group.id_sym.as_str()) {
+                    Some(_) => discarded.extend(group.sections.iter().map(|&sec| (id, sec))),
+                    None => {
+                        kept.insert(&group.id_sym, id);
+                    }
+                }
+            }
+        }
+
+        discarded
+    }
+
+    /// Which archives fall between some `--start-group` and its matching
+    /// `--end-group` -- [`LinkInput::GroupStartMarker`] and
+    /// [`LinkInput::Group`] are ordinary entries in `self.inputs`, so their
+    /// position relative to the archives around them (tracked here as a
+    /// nesting depth, since `group_head_stack` supports nested groups)
+    /// says which archives [`Self::resolve_symbols`] should keep
+    /// re-scanning after the first pass.
+    fn grouped_archives(&self) -> HashSet<InputId> {
+        let mut in_group = HashSet::new();
+        let mut depth = 0usize;
+
+        for (&id, input) in &self.inputs {
+            match &input.input {
+                LinkInput::GroupStartMarker => depth += 1,
+                LinkInput::Group(_) => depth = depth.saturating_sub(1),
+                LinkInput::Archive(_) if depth > 0 => {
+                    in_group.insert(id);
+                }
+                _ => {}
+            }
+        }
+
+        in_group
+    }
+
+    /// Builds the global symbol table from every currently-open input.
+    ///
+    /// Objects are folded in input order with the usual strong/weak/common
+    /// precedence (a strong definition always wins and collides with
+    /// another strong definition; a weak definition yields to a later
+    /// strong one; a common symbol yields to either and otherwise merges
+    /// by taking the larger size). Archives are then lazily searched: a
+    /// member is parsed and fully pulled in (all of its symbols, not just
+    /// the one needed) the first time one of its defined symbols would
+    /// satisfy a currently-undefined reference. `binfmt::ar` doesn't build
+    /// a ranlib-style symbol index, so "lazily" here means a member is
+    /// parsed once to check what it defines rather than being skipped
+    /// outright -- not that unneeded members go unparsed.
+    ///
+    /// Every archive gets one such pass, in input order, matching GNU
+    /// `ld`'s default (each archive is searched once, left to right).
+    /// An extraction can leave new symbols undefined that an *earlier*
+    /// archive could have satisfied, which is exactly what
+    /// `--start-group`/`--end-group` exists to fix: any archive enclosed
+    /// in a group (see [`Self::grouped_archives`]) is re-scanned, along
+    /// with the rest of its group, for as long as doing so keeps
+    /// extracting new members -- GNU's cyclic-archive-dependency
+    /// semantics. Archives outside any group are never revisited after
+    /// their first pass, even if a later extraction would satisfy one of
+    /// their members' references too; wrapping them in their own
+    /// `--start-group`/`--end-group` is how a caller opts them into that.
+    ///
+    /// [`LinkInput::LazyObject`] entries (`--start-lib`/`--end-lib`
+    /// objects) are searched the same lazy way, but every one of them is
+    /// reconsidered on every pass regardless of grouping, since each
+    /// stands in for its own one-member archive rather than being part
+    /// of a real archive whose *other* members might need the same
+    /// re-scanning treatment.
+    ///
+    /// Every multiply-defined and still-undefined symbol is collected and
+    /// returned together, rather than stopping at the first one, since
+    /// that's what's actually useful to a caller reporting link errors.
+    pub fn resolve_symbols(&mut self) -> Result<(), Vec<LinkError>> {
+        let mut bindings = HashMap::<String, Binding>::new();
+        let mut errors = Vec::new();
+
+        let discarded_comdat = self.select_comdat_groups();
+        let grouped_archives = self.grouped_archives();
+
+        let object_ids: Vec<InputId> = self
+            .inputs
+            .iter()
+            .filter(|(_, f)| matches!(f.input, LinkInput::Object(_)))
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in object_ids {
+            let file = match &self.inputs[&id].input {
+                LinkInput::Object(file) => file,
+                _ => continue,
+            };
+            for sym in file.symbols() {
+                if sym
+                    .section()
+                    .is_some_and(|sec| discarded_comdat.contains(&(id, sec)))
+                {
+                    continue;
+                }
+                if let Some(binding) = symbol_binding(sym, id) {
+                    merge_into(&mut bindings, &mut errors, sym.name(), binding, &self.inputs);
+                }
+            }
+        }
+
+        let mut parsed_members = HashMap::<(InputId, String), BinaryFile<'static>>::new();
+        let mut extracted = HashSet::<(InputId, String)>::new();
+        let mut first_pass = true;
+
+        loop {
+            if !bindings.values().any(|b| matches!(b, Binding::Undefined(_))) {
+                break;
+            }
+
+            let archive_ids: Vec<InputId> = self
+                .inputs
+                .iter()
+                .filter(|(id, f)| {
+                    matches!(f.input, LinkInput::Archive(_))
+                        && (first_pass || grouped_archives.contains(id))
+                })
+                .map(|(id, _)| *id)
+                .collect();
+
+            let mut progressed = false;
+
+            for archive_id in archive_ids {
+                let member_contents: Vec<(String, Vec<u8>)> = match &self.inputs[&archive_id].input
+                {
+                    LinkInput::Archive(archive) => archive
+                        .members()
+                        .iter()
+                        .map(|m| {
+                            (
+                                m.get_name().to_string_lossy().into_owned(),
+                                m.content().to_vec(),
+                            )
+                        })
+                        .collect(),
+                    _ => continue,
+                };
+
+                for (member_name, content) in member_contents {
+                    let key = (archive_id, member_name.clone());
+                    if extracted.contains(&key) {
+                        continue;
+                    }
+
+                    if !parsed_members.contains_key(&key) {
+                        match binfmt::open_file(Cursor::new(&*content)) {
+                            Ok(file) => {
+                                parsed_members.insert(key.clone(), file);
+                            }
+                            // Not an object format lcld recognizes (e.g. a
+                            // linker script or an LTO bitcode member) --
+                            // skip it, it can't resolve a missing symbol
+                            // either way.
+                            Err(_) => continue,
+                        }
+                    }
+
+                    let satisfies = parsed_members[&key].symbols().any(|sym| {
+                        sym.value().is_some()
+                            && matches!(bindings.get(sym.name()), Some(Binding::Undefined(_)))
+                    });
+
+                    if !satisfies {
+                        continue;
+                    }
+
+                    let file = parsed_members.remove(&key).unwrap();
+                    extracted.insert(key.clone());
+                    progressed = true;
+
+                    let member_input_id = InputId(self.next_input_id);
+                    self.next_input_id += 1;
+
+                    for sym in file.symbols() {
+                        if let Some(binding) = symbol_binding(sym, member_input_id) {
+                            merge_into(
+                                &mut bindings,
+                                &mut errors,
+                                sym.name(),
+                                binding,
+                                &self.inputs,
+                            );
+                        }
+                    }
+
+                    let input_fmt = file.fmt();
+                    let archive_name = self.inputs[&archive_id].name.clone();
+                    let as_needed = self.inputs[&archive_id].as_needed;
+                    self.inputs.insert(
+                        member_input_id,
+                        InputFile {
+                            name: PathBuf::from(format!(
+                                "{}({})",
+                                archive_name.display(),
+                                key.1
+                            )),
+                            input: LinkInput::Object(file),
+                            ty: InputFileType::Object(input_fmt),
+                            as_needed,
+                            lazy: false,
+                        },
+                    );
+                }
+            }
+
+            // `--start-lib`/`--end-lib` objects: each one stands in for a
+            // one-member archive, so (unlike a real archive, which only
+            // gets a second look when `--start-group`/`--end-group`
+            // wraps it) every lazy object is reconsidered on every pass
+            // for as long as the loop keeps making progress.
+            let lazy_ids: Vec<InputId> = self
+                .inputs
+                .iter()
+                .filter(|(_, f)| matches!(f.input, LinkInput::LazyObject(_)))
+                .map(|(id, _)| *id)
+                .collect();
+
+            for lazy_id in lazy_ids {
+                let satisfies = match &self.inputs[&lazy_id].input {
+                    LinkInput::LazyObject(file) => file.symbols().any(|sym| {
+                        sym.value().is_some()
+                            && matches!(bindings.get(sym.name()), Some(Binding::Undefined(_)))
+                    }),
+                    _ => false,
+                };
+
+                if !satisfies {
+                    continue;
+                }
+
+                let LinkInput::LazyObject(file) = std::mem::replace(
+                    &mut self.inputs.get_mut(&lazy_id).unwrap().input,
+                    LinkInput::GroupStartMarker,
+                ) else {
+                    unreachable!()
+                };
+                progressed = true;
+
+                for sym in file.symbols() {
+                    if let Some(binding) = symbol_binding(sym, lazy_id) {
+                        merge_into(&mut bindings, &mut errors, sym.name(), binding, &self.inputs);
+                    }
+                }
+
+                self.inputs.get_mut(&lazy_id).unwrap().input = LinkInput::Object(file);
+            }
+
+            first_pass = false;
+
+            if !progressed {
+                break;
+            }
+        }
+
+        for (name, binding) in &bindings {
+            if let Binding::Undefined(refs) = binding {
+                errors.push(LinkError::Undefined {
+                    symbol: name.clone(),
+                    referenced_by: refs.iter().map(|id| input_name(&self.inputs, *id)).collect(),
+                });
+            }
+        }
+
+        for (name, binding) in bindings {
+            let def = match binding {
+                Binding::Strong(id) | Binding::Weak(id) | Binding::Common(id, _) => {
+                    SymbolDef::Object(id)
+                }
+                Binding::Undefined(_) => SymbolDef::Undefined,
+            };
+            self.symbol_defs.insert(name, def);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Makes every `--wrap=<symbol>` name's original definition available
+    /// under `__real_<symbol>`, so `__wrap_<symbol>` can call through to
+    /// it. Must run after [`LinkState::resolve_symbols`], since it reads
+    /// `symbol_defs`; a name with no definition (an undefined symbol that
+    /// happens to be wrapped) is left alone, since there's nothing to
+    /// alias.
+    pub fn apply_wraps(&mut self, wrapped: &WrapSymbols) {
+        for name in &wrapped.0 {
+            if let Some(def) = self.symbol_defs.get(name).cloned() {
+                self.symbol_defs
+                    .entry(format!("__real_{name}"))
+                    .or_insert(def);
+            }
+        }
+    }
+}
+
+/// Symbols named by one or more `--wrap=<symbol>` options.
+///
+/// GNU ld's `--wrap` semantics: every *reference* to `sym` is redirected
+/// to `__wrap_sym`, and `__real_sym` is made available as an alias for
+/// `sym`'s original definition, so `__wrap_sym`'s implementation can call
+/// through to the one it's replacing (the usual use is a test harness or
+/// a malloc interposer). [`WrapSymbols::redirect`] handles the first half
+/// at relocation-resolution time (see [`crate::reloc::apply_relocations`]);
+/// [`LinkState::apply_wraps`] handles the second by aliasing
+/// `symbol_defs` once symbol resolution has already run.
+#[derive(Default)]
+pub struct WrapSymbols(HashSet<String>);
+
+impl WrapSymbols {
+    pub fn new(names: impl IntoIterator<Item = String>) -> Self {
+        Self(names.into_iter().collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Redirects a reference to a wrapped symbol to its `__wrap_`-prefixed
+    /// form; any other name (including `__real_<symbol>` itself) passes
+    /// through unchanged.
+    pub fn redirect<'a>(&self, symbol: &'a str) -> Cow<'a, str> {
+        if self.0.contains(symbol) {
+            Cow::Owned(format!("__wrap_{symbol}"))
+        } else {
+            Cow::Borrowed(symbol)
+        }
+    }
+}
+
+/// One resolved binding for a single symbol name, tracked while
+/// [`LinkState::resolve_symbols`] folds every input's symbol table into
+/// the global one.
+#[derive(Clone)]
+enum Binding {
+    /// A normal (non-weak, non-common) definition. Collides with another
+    /// `Strong` for the same name.
+    Strong(InputId),
+    /// A weak definition. Yields to a later `Strong`, but otherwise wins
+    /// (including over another `Weak`, where the first one seen is kept).
+    Weak(InputId),
+    /// A `COMMON` definition of the given size. Yields to either a
+    /// `Strong` or a `Weak`; merges with another `Common` by keeping
+    /// whichever has the larger size.
+    Common(InputId, u64),
+    /// Referenced, but not (yet) defined by anything processed so far.
+    /// Tracks every input that referenced it, for the eventual "undefined
+    /// symbol" diagnostic.
+    Undefined(Vec<InputId>),
+}
+
+/// Classifies `sym` as it should participate in global symbol resolution,
+/// or `None` if it shouldn't (a symbol local to `input` isn't visible to
+/// any other input, so it never enters the global table).
+fn symbol_binding(sym: &Symbol, input: InputId) -> Option<Binding> {
+    if sym.kind() == SymbolKind::Local {
+        return None;
+    }
+    if sym.value().is_none() {
+        return Some(Binding::Undefined(vec![input]));
+    }
+    if sym.symbol_type() == SymbolType::Common {
+        return Some(Binding::Common(input, sym.size().unwrap_or(0)));
+    }
+    Some(match sym.kind() {
+        SymbolKind::Weak => Binding::Weak(input),
+        _ => Binding::Strong(input),
+    })
+}
+
+/// Folds `new` into `bindings[name]` (or inserts it outright if `name`
+/// hasn't been seen before), pushing a [`LinkError::MultiplyDefined`] to
+/// `errors` rather than failing outright if two strong definitions
+/// collide -- the first one seen is kept so the rest of resolution can
+/// keep going and report every conflict it finds, not just the first.
+fn merge_into(
+    bindings: &mut HashMap<String, Binding>,
+    errors: &mut Vec<LinkError>,
+    name: &str,
+    new: Binding,
+    inputs: &IndexMap<InputId, InputFile>,
+) {
+    let merged = match bindings.remove(name) {
+        Some(existing) => merge_binding(name, existing, new, inputs, errors),
+        None => new,
+    };
+    bindings.insert(name.to_string(), merged);
+}
+
+fn merge_binding(
+    name: &str,
+    existing: Binding,
+    new: Binding,
+    inputs: &IndexMap<InputId, InputFile>,
+    errors: &mut Vec<LinkError>,
+) -> Binding {
+    use Binding::*;
+    match (existing, new) {
+        (Undefined(mut refs), Undefined(more)) => {
+            refs.extend(more);
+            Undefined(refs)
+        }
+        (Undefined(_), other) | (other, Undefined(_)) => other,
+        (Strong(a), Strong(b)) => {
+            errors.push(LinkError::MultiplyDefined {
+                symbol: name.to_string(),
+                first: input_name(inputs, a),
+                second: input_name(inputs, b),
+            });
+            Strong(a)
+        }
+        (Strong(a), Weak(_)) | (Weak(_), Strong(a)) => Strong(a),
+        (Strong(a), Common(..)) | (Common(..), Strong(a)) => Strong(a),
+        (Weak(a), Weak(_)) => Weak(a),
+        (Weak(a), Common(..)) | (Common(..), Weak(a)) => Weak(a),
+        (Common(a, sa), Common(b, sb)) => {
+            if sa >= sb {
+                Common(a, sa)
+            } else {
+                Common(b, sb)
+            }
+        }
+    }
+}
+
+fn input_name(inputs: &IndexMap<InputId, InputFile>, id: InputId) -> PathBuf {
+    inputs
+        .get(&id)
+        .map(|f| f.name.clone())
+        .unwrap_or_else(|| PathBuf::from("<unknown>"))
+}
+
+/// A symbol resolution failure from [`LinkState::resolve_symbols`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum LinkError {
+    /// Two inputs both provided a strong (non-weak, non-common) definition
+    /// of the same symbol.
+    MultiplyDefined {
+        symbol: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+    /// A symbol was referenced but never defined by any input (including
+    /// archive members lazily extracted while searching for it).
+    Undefined {
+        symbol: String,
+        referenced_by: Vec<PathBuf>,
+    },
+    /// [`crate::reloc::apply_relocations`] couldn't apply a relocation
+    /// against the final output.
+    Relocation {
+        section: String,
+        offset: u64,
+        symbol: String,
+        reason: RelocationFailure,
+    },
+}
+
+/// Why [`crate::reloc::apply_relocations`] couldn't apply a particular
+/// relocation.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum RelocationFailure {
+    /// The relocation's symbol didn't resolve to an address. Shouldn't
+    /// happen for a symbol that survived [`LinkState::resolve_symbols`]
+    /// unless the caller's `resolve` callback disagrees with it (e.g. a
+    /// dynamic symbol that's intentionally left unresolved for the
+    /// runtime loader to fill in).
+    UndefinedSymbol,
+    /// `fmt` has no [`HowTo`] for this relocation's [`RelocCode`] at all.
+    UnsupportedCode(RelocCode),
+    /// [`HowTo::apply`] rejected the resolved value -- it doesn't fit in
+    /// the relocation's representable range.
+    Overflow(binfmt::howto::HowToError),
+    /// A plain absolute relocation targets a symbol that doesn't bind
+    /// locally, in an [`crate::output::OutputType::PieExecutable`]/
+    /// [`crate::output::OutputType::Shared`] output. There's no
+    /// PIC-safe relocation code to substitute -- the object needs
+    /// recompiling with position-independent code.
+    RequiresPic(RelocCode),
+}
+
+impl fmt::Display for RelocationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelocationFailure::UndefinedSymbol => f.write_str("symbol did not resolve to an address"),
+            RelocationFailure::UnsupportedCode(code) => {
+                write!(f, "no howto for relocation code {:?}", code)
+            }
+            RelocationFailure::Overflow(err) => write!(f, "{:?}", err),
+            RelocationFailure::RequiresPic(code) => write!(
+                f,
+                "relocation {:?} against a non-local symbol can not be used when making a shared object or PIE; recompile with -fPIC",
+                code
+            ),
+        }
+    }
 }
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::MultiplyDefined {
+                symbol,
+                first,
+                second,
+            } => write!(
+                f,
+                "multiple definition of `{}`; first defined in {}, redefined in {}",
+                symbol,
+                first.display(),
+                second.display()
+            ),
+            LinkError::Undefined {
+                symbol,
+                referenced_by,
+            } => {
+                write!(f, "undefined symbol `{}`, referenced by", symbol)?;
+                let mut sep = " ";
+                for path in referenced_by {
+                    write!(f, "{}{}", sep, path.display())?;
+                    sep = ", ";
+                }
+                Ok(())
+            }
+            LinkError::Relocation {
+                section,
+                offset,
+                symbol,
+                reason,
+            } => write!(
+                f,
+                "relocation against `{}` at {}+{:#x} failed: {}",
+                symbol, section, offset, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
@@ -1,5 +1,87 @@
-pub trait LtoProvider {
+//! LTO: collecting bitcode inputs identified by [`crate::input::ident_input`]
+//! and, once every one of them has been seen, handing them to a code
+//! generator that turns the merged module into a native object the rest
+//! of the link can lay out like any other input.
+//!
+//! Like [`crate::gc`] and [`crate::icf`], nothing in the link driver
+//! calls this yet: [`LtoModule::add`] is where a caller would feed in
+//! every [`InputFileType::LtoInput`](crate::input::InputFileType::LtoInput)
+//! it sees during symbol resolution, and [`LtoProvider::codegen`] is the
+//! extension point a real code generator (`lccc`, for this format) would
+//! implement to turn that collected module into [`CodegenOutput`] bytes
+//! ready to feed back into the input list.
+
+use std::io;
+
+/// `Send + Sync` for the same reason as [`binfmt::fmt::Binfmt`]: providers
+/// are stateless, `'static` registry entries, and code that idents input
+/// files across threads needs to move a `&'static dyn LtoProvider` between
+/// them.
+pub trait LtoProvider: Send + Sync {
     fn name(&self) -> &'static str;
+
+    /// Runs this provider's code generator over `module`, returning the
+    /// bytes of a native object file in this provider's target format.
+    ///
+    /// No provider backs this with a real code generator yet -- there's
+    /// nothing in this tree that shells out to `lccc` or any other
+    /// compiler. A real implementation would serialize `module`'s
+    /// collected bitcode back out (or pipe it directly) to the
+    /// generator and read its output object back in.
+    fn codegen(&self, module: &LtoModule) -> io::Result<CodegenOutput> {
+        let _ = module;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} does not implement a code generator", self.name()),
+        ))
+    }
+}
+
+/// One bitcode input collected for LTO, as identified by
+/// [`crate::input::ident_input`]: its path and raw contents, kept around
+/// until every other input has been seen and [`LtoModule`] is handed to
+/// [`LtoProvider::codegen`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LtoInputFile {
+    pub path: std::path::PathBuf,
+    pub contents: Vec<u8>,
+}
+
+/// Every bitcode input bound for one [`LtoProvider`], collected across
+/// symbol resolution. A link may see bitcode from more than one
+/// provider (mixing `xir` and `llir` inputs, say) -- each gets its own
+/// `LtoModule`, keyed by provider in whatever structure the driver that
+/// eventually calls this builds around it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LtoModule {
+    inputs: Vec<LtoInputFile>,
+}
+
+impl LtoModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds one identified bitcode input to the module.
+    pub fn add(&mut self, path: std::path::PathBuf, contents: Vec<u8>) {
+        self.inputs.push(LtoInputFile { path, contents });
+    }
+
+    pub fn inputs(&self) -> &[LtoInputFile] {
+        &self.inputs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+}
+
+/// The result of [`LtoProvider::codegen`]: a native object, in whatever
+/// format the provider's target normally links against, ready to be fed
+/// back into the input list in place of the bitcode it replaces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodegenOutput {
+    pub object: Vec<u8>,
 }
 
 impl core::fmt::Debug for dyn LtoProvider {
@@ -24,3 +106,34 @@ impl core::hash::Hash for dyn LtoProvider {
         core::ptr::hash(self as *const _ as *const u8, state)
     }
 }
+
+/// The [`LtoProvider`] for `binfmt`'s `xir` bitcode format.
+#[cfg(feature = "xir")]
+#[derive(Debug)]
+pub struct XirProvider;
+
+#[cfg(feature = "xir")]
+impl LtoProvider for XirProvider {
+    fn name(&self) -> &'static str {
+        "xir"
+    }
+}
+
+#[cfg(feature = "xir")]
+static XIR_PROVIDER: XirProvider = XirProvider;
+
+/// If `header` (an input's first few bytes, as read by
+/// [`crate::input::ident_input`]) starts with
+/// [`binfmt::xir::raw::XIR_MAGIC`], the provider that input's bitcode
+/// belongs to.
+#[cfg(feature = "xir")]
+pub fn identify(header: &[u8]) -> Option<&'static dyn LtoProvider> {
+    header
+        .starts_with(&binfmt::xir::raw::XIR_MAGIC)
+        .then_some(&XIR_PROVIDER as &dyn LtoProvider)
+}
+
+#[cfg(not(feature = "xir"))]
+pub fn identify(_header: &[u8]) -> Option<&'static dyn LtoProvider> {
+    None
+}
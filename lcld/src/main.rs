@@ -2,13 +2,30 @@
 
 use std::io::{Error as IOError, ErrorKind};
 
+pub mod arch;
+pub mod arrays;
+pub mod diag;
 pub mod driver;
+pub mod dynamic;
+pub mod ehframe;
+pub mod gc;
+pub mod got;
+pub mod icf;
+pub mod incremental;
 pub mod input;
 pub mod link;
 pub mod lto;
+pub mod orphan;
 pub mod output;
+pub mod relax;
+pub mod reloc;
 pub mod script;
+pub mod seh;
+pub mod strmerge;
+pub mod symver;
 pub mod targ;
+pub mod tbd;
+pub mod windows;
 
 pub enum Mode {
     Unix,
@@ -0,0 +1,218 @@
+//! `--orphan-handling=place/warn/error` orphan section placement: what
+//! happens to an input section a [`crate::script::ParsedScript`]'s
+//! `SECTIONS` block never mentions.
+//!
+//! Real-world scripts only enumerate the sections they care about and
+//! rely on the linker to find a sensible home for everything else --
+//! GNU `ld` places an orphan next to the output section it most
+//! resembles (matching [`crate::output::merge_name`]'s family first,
+//! then section flags), `lld` does the same, and a script that had to
+//! spell out every section a compiler might emit would be unmaintainable.
+//! [`place_orphan`] is that placement decision in isolation: given the
+//! output sections a script's `SECTIONS` block already accounts for, in
+//! order, and one orphan's name and flags, it returns where the orphan
+//! should be inserted. Like [`crate::gc`] and [`crate::icf`], nothing in
+//! the link driver calls this yet, since there's no pass that resolves a
+//! [`crate::script::SectionPattern`] against real input sections to even
+//! know which ones are orphans -- that belongs to the layout pass
+//! [`crate::script`]'s own doc comment describes as still unwritten.
+
+use binfmt::fmt::SectionFlags;
+
+use crate::output::merge_name;
+
+/// The three `--orphan-handling=` modes GNU `ld` and `lld` both support.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OrphanHandling {
+    /// `place` (the default): silently place the orphan.
+    Place,
+    /// `warn`: place it, but the caller should emit a diagnostic --
+    /// [`place_orphan`] reports this back via [`Placement::warn`] rather
+    /// than printing anything itself, the same way this crate's other
+    /// staged passes (e.g. [`crate::gc::is_retained`]) leave diagnostics
+    /// to whatever eventually drives them.
+    Warn,
+    /// `error`: refuse to place it at all.
+    Error,
+}
+
+impl OrphanHandling {
+    /// Parses a `--orphan-handling=<mode>` value, `None` if it's not one
+    /// of GNU `ld`'s three recognized spellings.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "place" => Some(Self::Place),
+            "warn" => Some(Self::Warn),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+/// An output section a script has already placed, in the order it
+/// appears in the `SECTIONS` block -- either from an explicit
+/// [`crate::script::OutputSection`] statement, or from an earlier
+/// orphan that [`place_orphan`] has already assigned a slot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlacedSection {
+    pub name: String,
+    pub flags: Option<SectionFlags>,
+}
+
+/// Where an orphan should land among a script's already-placed output
+/// sections.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Placement {
+    /// The orphan's name already matches `placed[.0]` exactly -- it
+    /// merges into that output section rather than getting one of its
+    /// own, the same outcome a second orphan sharing an earlier orphan's
+    /// name would get from an explicit script statement.
+    MergeInto(usize),
+    /// The orphan becomes a new output section of its own, inserted
+    /// immediately after `placed[.0]`, or at the very start of the
+    /// layout if `None`.
+    NewSectionAfter(Option<usize>),
+}
+
+/// [`Placement`] plus whether `--orphan-handling=warn` asked the caller
+/// to report this placement before using it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Decision {
+    pub placement: Placement,
+    pub warn: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanError {
+    pub section: String,
+}
+
+impl std::fmt::Display for OrphanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "section `{}` is not placed by the linker script and --orphan-handling=error forbids placing it automatically",
+            self.section
+        )
+    }
+}
+
+impl std::error::Error for OrphanError {}
+
+/// Decides where an orphan input section named `name`, with flags
+/// `flags`, should go among `placed`'s already-positioned output
+/// sections, under `handling`.
+///
+/// Placement, when allowed, tries three things in order, each one GNU
+/// `ld`'s own orphan heuristic also tries before falling back to the
+/// next:
+///
+/// 1. An exact name match in `placed` -- the orphan merges into that
+///    section ([`Placement::MergeInto`]).
+/// 2. A [`merge_name`] family match (e.g. `.text.hot` lands next to
+///    `.text`) -- the orphan gets a new output section immediately after
+///    the last family match.
+/// 3. An exact [`SectionFlags`] match -- a new output section
+///    immediately after the last section sharing every flag.
+///
+/// If none of those find a placement, the orphan is appended at the
+/// very end of the layout, the same last-resort GNU `ld` uses for a
+/// section unlike anything the script already places.
+pub fn place_orphan(
+    name: &str,
+    flags: Option<SectionFlags>,
+    placed: &[PlacedSection],
+    handling: OrphanHandling,
+) -> Result<Decision, OrphanError> {
+    if handling == OrphanHandling::Error {
+        return Err(OrphanError {
+            section: name.to_string(),
+        });
+    }
+
+    let placement = if let Some(idx) = placed.iter().position(|p| p.name == name) {
+        Placement::MergeInto(idx)
+    } else if let Some(idx) = placed.iter().rposition(|p| merge_name(&p.name) == merge_name(name))
+    {
+        Placement::NewSectionAfter(Some(idx))
+    } else if let Some(idx) = flags.and_then(|flags| placed.iter().rposition(|p| p.flags == Some(flags)))
+    {
+        Placement::NewSectionAfter(Some(idx))
+    } else {
+        Placement::NewSectionAfter(placed.len().checked_sub(1))
+    };
+
+    Ok(Decision {
+        placement,
+        warn: handling == OrphanHandling::Warn,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binfmt::fmt::SectionFlag;
+
+    fn placed(sections: &[(&str, Option<SectionFlags>)]) -> Vec<PlacedSection> {
+        sections
+            .iter()
+            .map(|&(name, flags)| PlacedSection {
+                name: name.to_string(),
+                flags,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn error_mode_rejects_every_orphan() {
+        let placed = placed(&[(".text", None)]);
+        let err = place_orphan(".mysection", None, &placed, OrphanHandling::Error).unwrap_err();
+        assert_eq!(err.section, ".mysection");
+    }
+
+    #[test]
+    fn exact_name_match_merges_into_existing_section() {
+        let placed = placed(&[(".text", None), (".mysection", None)]);
+        let decision = place_orphan(".mysection", None, &placed, OrphanHandling::Place).unwrap();
+        assert_eq!(decision.placement, Placement::MergeInto(1));
+        assert!(!decision.warn);
+    }
+
+    #[test]
+    fn family_prefix_match_lands_next_to_its_family() {
+        let placed = placed(&[(".text", None), (".rodata", None)]);
+        let decision =
+            place_orphan(".text.unlikely", None, &placed, OrphanHandling::Place).unwrap();
+        assert_eq!(decision.placement, Placement::NewSectionAfter(Some(0)));
+    }
+
+    #[test]
+    fn flag_match_is_used_when_no_family_matches() {
+        let exec = Some(SectionFlags::from(SectionFlag::Executable));
+        let placed = placed(&[(".text", exec), (".rodata", None)]);
+        let decision = place_orphan(".init", exec, &placed, OrphanHandling::Place).unwrap();
+        assert_eq!(decision.placement, Placement::NewSectionAfter(Some(0)));
+    }
+
+    #[test]
+    fn unmatched_orphan_is_appended_at_the_end() {
+        let placed = placed(&[(".text", None), (".rodata", None)]);
+        let decision = place_orphan(".mystery", None, &placed, OrphanHandling::Place).unwrap();
+        assert_eq!(decision.placement, Placement::NewSectionAfter(Some(1)));
+    }
+
+    #[test]
+    fn warn_mode_still_places_but_flags_the_decision() {
+        let placed = placed(&[(".text", None)]);
+        let decision =
+            place_orphan(".mysection", None, &placed, OrphanHandling::Warn).unwrap();
+        assert!(decision.warn);
+        assert_eq!(decision.placement, Placement::NewSectionAfter(Some(0)));
+    }
+
+    #[test]
+    fn empty_script_appends_at_the_start() {
+        let decision = place_orphan(".text", None, &[], OrphanHandling::Place).unwrap();
+        assert_eq!(decision.placement, Placement::NewSectionAfter(None));
+    }
+}
@@ -1,3 +1,11 @@
+use binfmt::fmt::{Binfmt, BinaryFile, FileType, Section, SectionFlag, SectionType};
+use binfmt::howto::Reloc;
+use indexmap::IndexMap;
+
+use crate::arrays::{self, ArrayKind};
+use crate::strmerge::StringMerger;
+use crate::targ::TargetInfo;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum OutputType {
     Relocatable,      // perform partial link
@@ -6,3 +14,462 @@ pub enum OutputType {
     Shared,           // Shared object/dll
     SharedAndLink,    // dll+lib
 }
+
+/// Page size assumed when laying out loadable segments: `vaddr` and
+/// `file_offset` are kept congruent modulo this so the output can be
+/// `mmap`ed directly, matching every target this crate presently builds
+/// for. Nothing yet lets a driver override it for targets that want a
+/// different page size.
+const PAGE_SIZE: u64 = 0x1000;
+
+/// Default load address for a [`OutputType::StaticExecutable`]. PIE
+/// executables and shared objects load at 0, same as every
+/// position-independent output does.
+const DEFAULT_BASE: u64 = 0x400000;
+
+/// One input section folded into an [`OutputSection`], recording where its
+/// bytes ended up so a later relocation pass can find them.
+#[derive(Clone, Debug, Default)]
+pub struct MergedPiece {
+    pub offset: usize,
+    pub size: usize,
+    /// This piece's input section's relocations, with [`Reloc::offset`]
+    /// already rebased from "offset within the input section" to "offset
+    /// within the merged output section" -- [`crate::reloc::apply_relocations`]
+    /// consumes these directly, without needing to know which input
+    /// section any of them came from.
+    pub relocs: Vec<Reloc>,
+    /// Set only for a piece that went through [`crate::strmerge`]'s
+    /// tail-merging (an input section carrying both
+    /// `binfmt::elf::consts::SHF_MERGE` and `SHF_STRINGS`): the final
+    /// offset, within the output section, that each of this piece's
+    /// original NUL-terminated entries (in declaration order) landed at
+    /// after deduplication. Deduplicated entries aren't a constant shift
+    /// from their original position the way plain concatenation's
+    /// `offset` is, so a future pass rewriting a symbol's value based on
+    /// where its input section landed needs this map instead of
+    /// `offset` alone; nothing consumes it yet, since lcld doesn't
+    /// thread symbol values through output-section placement at all at
+    /// this layer -- see [`merge_sections`]'s own doc comment.
+    pub entry_offsets: Option<Vec<usize>>,
+}
+
+/// One section of the linked output, built by merging every input section
+/// that maps to the same output name.
+#[derive(Clone, Debug)]
+pub struct OutputSection {
+    pub name: String,
+    pub ty: SectionType,
+    pub flags: Option<binfmt::fmt::SectionFlags>,
+    pub align: usize,
+    pub content: Vec<u8>,
+    pub pieces: Vec<MergedPiece>,
+    pub vaddr: u64,
+    pub file_offset: u64,
+}
+
+/// Maps an input section name to the output section it merges into.
+///
+/// `.text.foo`/`.data.bar`-style per-function/per-object sections, as
+/// produced by `-ffunction-sections`/`-fdata-sections`, collapse into the
+/// base `.text`/`.data`/etc the same way GNU ld's default script folds
+/// them; anything else keeps its exact name, so e.g. `.comment` or a
+/// target-specific section is never merged with an unrelated one that
+/// happens to share a prefix.
+pub(crate) fn merge_name(name: &str) -> &str {
+    const MERGEABLE_PREFIXES: &[&str] = &[".text", ".data", ".rodata", ".bss", ".tdata", ".tbss"];
+
+    for prefix in MERGEABLE_PREFIXES {
+        if name == *prefix {
+            return prefix;
+        }
+        if let Some(rest) = name.strip_prefix(prefix) {
+            if rest.starts_with('.') || rest.starts_with('$') {
+                return prefix;
+            }
+        }
+    }
+    name
+}
+
+fn union_flags(
+    a: Option<binfmt::fmt::SectionFlags>,
+    b: Option<binfmt::fmt::SectionFlags>,
+) -> Option<binfmt::fmt::SectionFlags> {
+    match (a, b) {
+        (None, x) | (x, None) => x,
+        (Some(a), Some(b)) => Some(a | b),
+    }
+}
+
+fn has_flag(flags: Option<binfmt::fmt::SectionFlags>, flag: SectionFlag) -> bool {
+    flags.into_iter().flatten().any(|f| f == flag)
+}
+
+/// Whether `flags` carries both `SHF_MERGE` and `SHF_STRINGS`, the ELF
+/// bits an input section sets to ask a linker to tail-merge its
+/// NUL-terminated-string content (see [`crate::strmerge`]).
+fn is_mergeable_strings(flags: Option<binfmt::fmt::SectionFlags>) -> bool {
+    has_flag(
+        flags,
+        SectionFlag::FormatSpecific(binfmt::elf::consts::SHF_MERGE as u32),
+    ) && has_flag(
+        flags,
+        SectionFlag::FormatSpecific(binfmt::elf::consts::SHF_STRINGS as u32),
+    )
+}
+
+fn round_up(value: u64, align: u64) -> u64 {
+    if align <= 1 {
+        value
+    } else {
+        value.div_ceil(align) * align
+    }
+}
+
+/// Merges every section in `sections` by [`merge_name`] (with the
+/// `.preinit_array`/`.init_array`/`.fini_array` family handled by
+/// [`arrays::merge_array_sections`]'s priority ordering instead), in input
+/// order, concatenating their content and tracking the alignment padding
+/// between pieces.
+///
+/// This only merges -- it doesn't assign any addresses yet, since that
+/// depends on [`OutputType`] (see [`assign_addresses`]). Relocations are
+/// carried into each [`MergedPiece::relocs`] with their offsets rebased to
+/// the output section, but not resolved or applied (that is
+/// [`crate::reloc::apply_relocations`]'s job), and symbols aren't touched.
+///
+/// If `targ.convert_legacy_ctors` is set, a `.init_array`/`.fini_array`
+/// with no matching input sections falls back to converting any legacy
+/// `.ctors`/`.dtors` input instead (see [`arrays::convert_legacy_arrays`]),
+/// using `ptr_size` (the target's pointer width, in bytes) to find the
+/// function-pointer entries that need reversing.
+pub fn merge_sections(
+    sections: Vec<Section>,
+    targ: &TargetInfo,
+    ptr_size: usize,
+) -> Vec<OutputSection> {
+    let mut remaining = sections;
+    let mut order: Vec<String> = Vec::new();
+    let mut merged: IndexMap<String, OutputSection> = IndexMap::new();
+
+    for kind in [ArrayKind::Preinit, ArrayKind::Init, ArrayKind::Fini] {
+        let (matching, rest): (Vec<Section>, Vec<Section>) =
+            remaining.into_iter().partition(|s| kind.matches(&s.name));
+        remaining = rest;
+
+        let mut array_section = arrays::merge_array_sections(kind, matching);
+
+        if array_section.is_none() && targ.convert_legacy_ctors {
+            let (legacy, rest): (Vec<Section>, Vec<Section>) =
+                remaining.into_iter().partition(|s| kind.legacy_matches(&s.name));
+            remaining = rest;
+            array_section = arrays::convert_legacy_arrays(kind, legacy, ptr_size);
+        }
+
+        if let Some((array_section, _start_sym, _end_sym)) = array_section {
+            let size = array_section.content.len();
+            order.push(array_section.name.clone());
+            merged.insert(
+                array_section.name.clone(),
+                OutputSection {
+                    name: array_section.name,
+                    ty: array_section.ty,
+                    flags: array_section.flags,
+                    align: array_section.align.max(1),
+                    content: array_section.content,
+                    // `arrays::merge_array_sections` concatenates raw
+                    // content without threading the input sections'
+                    // relocations through, so there's nothing to rebase
+                    // here yet -- a pre-existing gap in that function, not
+                    // one introduced by this piece tracking.
+                    pieces: vec![MergedPiece {
+                        offset: 0,
+                        size,
+                        relocs: Vec::new(),
+                        entry_offsets: None,
+                    }],
+                    vaddr: 0,
+                    file_offset: 0,
+                },
+            );
+        }
+    }
+
+    let (mergeable, rest): (Vec<Section>, Vec<Section>) =
+        remaining.into_iter().partition(|s| is_mergeable_strings(s.flags));
+    remaining = rest;
+
+    for section in remaining {
+        let name = merge_name(&section.name).to_string();
+
+        let out = merged.entry(name.clone()).or_insert_with(|| {
+            order.push(name.clone());
+            OutputSection {
+                name,
+                ty: section.ty,
+                flags: None,
+                align: 1,
+                content: Vec::new(),
+                pieces: Vec::new(),
+                vaddr: 0,
+                file_offset: 0,
+            }
+        });
+
+        out.align = out.align.max(section.align.max(1));
+        out.flags = union_flags(out.flags, section.flags);
+
+        let align = section.align.max(1) as u64;
+        let padded = round_up(out.content.len() as u64, align) as usize;
+        out.content.resize(padded, 0);
+
+        let offset = out.content.len();
+        out.content.extend_from_slice(&section.content);
+        let relocs = section
+            .relocs
+            .iter()
+            .map(|r| Reloc {
+                code: r.code,
+                symbol: r.symbol.clone(),
+                addend: r.addend,
+                offset: r.offset + offset as u64,
+            })
+            .collect();
+        out.pieces.push(MergedPiece {
+            offset,
+            size: section.content.len(),
+            relocs,
+            entry_offsets: None,
+        });
+    }
+
+    // `SHF_MERGE | SHF_STRINGS` sections are handled last, once every
+    // plain-concatenated input section has already claimed its place:
+    // every such section feeding the same output name shares one
+    // `StringMerger`, so a string duplicated across input objects is
+    // deduplicated across them too, not just within each object's own
+    // section. The deduplicated bytes land in their own contiguous
+    // region at the end of the output section, after the
+    // plain-concatenated content above -- matching how a real linker
+    // keeps `SHF_MERGE` subsections together rather than interleaving
+    // them byte-for-byte with unrelated input.
+    let mut mergers: IndexMap<String, StringMerger> = IndexMap::new();
+    let mut mergeable_by_name: IndexMap<String, Vec<Section>> = IndexMap::new();
+    for section in mergeable {
+        let name = merge_name(&section.name).to_string();
+        mergeable_by_name.entry(name).or_default().push(section);
+    }
+
+    for (name, sections) in mergeable_by_name {
+        let out = merged.entry(name.clone()).or_insert_with(|| {
+            order.push(name.clone());
+            OutputSection {
+                name: name.clone(),
+                ty: sections[0].ty,
+                flags: None,
+                align: 1,
+                content: Vec::new(),
+                pieces: Vec::new(),
+                vaddr: 0,
+                file_offset: 0,
+            }
+        });
+
+        let merger = mergers.entry(name).or_default();
+
+        for section in sections {
+            out.align = out.align.max(section.align.max(1));
+            out.flags = union_flags(out.flags, section.flags);
+
+            let entries = crate::strmerge::split_entries(&section.content);
+            let mut entry_offsets = Vec::with_capacity(entries.len());
+            let mut entry_starts = Vec::with_capacity(entries.len());
+            let mut cursor = 0usize;
+            for entry in &entries {
+                entry_starts.push(cursor);
+                entry_offsets.push(merger.intern(entry));
+                cursor += entry.len();
+            }
+
+            // Rebase each of this section's own relocations by finding
+            // which entry its site falls in and carrying over that
+            // entry's offset *within its own bytes* -- the entry as a
+            // whole may have moved to a shared copy, but a site's
+            // position relative to the start of its own entry hasn't
+            // changed.
+            let relocs = section
+                .relocs
+                .iter()
+                .map(|r| {
+                    let site = r.offset as usize;
+                    let idx = entry_starts.partition_point(|&start| start <= site).max(1) - 1;
+                    let local = site - entry_starts[idx];
+                    Reloc {
+                        code: r.code,
+                        symbol: r.symbol.clone(),
+                        addend: r.addend,
+                        offset: (entry_offsets[idx] + local) as u64,
+                    }
+                })
+                .collect();
+
+            out.pieces.push(MergedPiece {
+                offset: entry_offsets.first().copied().unwrap_or(0),
+                size: section.content.len(),
+                relocs,
+                entry_offsets: Some(entry_offsets),
+            });
+        }
+    }
+
+    for (name, merger) in mergers {
+        let out = merged.get_mut(&name).unwrap();
+        let base = out.content.len();
+        out.content.extend_from_slice(&merger.finish());
+        for piece in &mut out.pieces {
+            if let Some(entries) = &mut piece.entry_offsets {
+                piece.offset += base;
+                for off in entries.iter_mut() {
+                    *off += base;
+                }
+                for reloc in &mut piece.relocs {
+                    reloc.offset += base as u64;
+                }
+            }
+        }
+    }
+
+    order
+        .into_iter()
+        .map(|name| merged.swap_remove(&name).unwrap())
+        .collect()
+}
+
+/// Assigns `vaddr`/`file_offset` to every section `merge_sections`
+/// produced, appropriate for `output_type`.
+///
+/// For [`OutputType::Relocatable`] output, addresses don't mean anything
+/// yet (a later link will assign them), so sections are just packed
+/// back-to-back honoring their own alignment, with `vaddr` left at 0.
+///
+/// For anything else, allocatable ([`SectionFlag::Alloc`]) sections are
+/// grouped into contiguous runs sharing the same
+/// [`SectionFlag::Writable`]/[`SectionFlag::Executable`] combination --
+/// the simplest split a linker can make into one `PT_LOAD` per
+/// permission set -- with each run starting on a fresh page so its
+/// segment can get its own protection, and [`SectionType::NoBits`]
+/// (`.bss`-like) sections sorted last within their run so they can
+/// advance `vaddr` without consuming `file_offset` space while every
+/// following section in the same run stays congruent. Non-allocatable
+/// sections (debug info and the like) are placed after everything
+/// allocatable with `vaddr` left at 0, the same way objcopy/ld leave
+/// them.
+///
+/// This doesn't attempt the segment-packing trade-offs a linker script or
+/// `-z separate-code` would let a user tune -- it's one segment per
+/// permission set, nothing more.
+pub fn assign_addresses(sections: &mut [OutputSection], output_type: OutputType) {
+    if output_type == OutputType::Relocatable {
+        let mut offset = 0u64;
+        for sect in sections.iter_mut() {
+            offset = round_up(offset, sect.align.max(1) as u64);
+            sect.vaddr = 0;
+            sect.file_offset = offset;
+            if sect.ty != SectionType::NoBits {
+                offset += sect.content.len() as u64;
+            }
+        }
+        return;
+    }
+
+    let base = match output_type {
+        OutputType::StaticExecutable => DEFAULT_BASE,
+        _ => 0,
+    };
+
+    let mut groups: Vec<((bool, bool), Vec<usize>)> = Vec::new();
+    for (i, sect) in sections.iter().enumerate() {
+        if !has_flag(sect.flags, SectionFlag::Alloc) {
+            continue;
+        }
+        let key = (
+            has_flag(sect.flags, SectionFlag::Writable),
+            has_flag(sect.flags, SectionFlag::Executable),
+        );
+        if let Some((_, idxs)) = groups.iter_mut().find(|(k, _)| *k == key) {
+            idxs.push(i);
+        } else {
+            groups.push((key, vec![i]));
+        }
+    }
+    for (_, idxs) in &mut groups {
+        idxs.sort_by_key(|&i| sections[i].ty == SectionType::NoBits);
+    }
+
+    let mut addr = base;
+    let mut file_offset = 0u64;
+    for (_, idxs) in &groups {
+        addr = round_up(addr, PAGE_SIZE);
+        file_offset = round_up(file_offset, PAGE_SIZE);
+        for &i in idxs {
+            let align = sections[i].align.max(1) as u64;
+            addr = round_up(addr, align);
+            file_offset = round_up(file_offset, align);
+
+            sections[i].vaddr = addr;
+            sections[i].file_offset = file_offset;
+
+            addr += sections[i].content.len() as u64;
+            if sections[i].ty != SectionType::NoBits {
+                file_offset += sections[i].content.len() as u64;
+            }
+        }
+    }
+
+    for sect in sections.iter_mut() {
+        if has_flag(sect.flags, SectionFlag::Alloc) {
+            continue;
+        }
+        file_offset = round_up(file_offset, sect.align.max(1) as u64);
+        sect.vaddr = 0;
+        sect.file_offset = file_offset;
+        file_offset += sect.content.len() as u64;
+    }
+}
+
+/// Builds the output [`BinaryFile`] for a laid-out set of
+/// [`OutputSection`]s, as `fmt` would write it: creates a file of the
+/// [`FileType`] matching `output_type` and adds each merged section's
+/// final content via [`BinaryFile::add_section`].
+///
+/// This only places sections -- applying relocations against the merged
+/// symbol table and writing the final symbol table out are separate
+/// passes this doesn't attempt.
+pub fn build_binary_file<'a>(
+    fmt: &'a dyn Binfmt,
+    sections: Vec<OutputSection>,
+    output_type: OutputType,
+) -> Result<BinaryFile<'a>, Box<Section>> {
+    let ty = match output_type {
+        OutputType::Relocatable => FileType::Relocatable,
+        OutputType::StaticExecutable | OutputType::PieExecutable => FileType::Exec,
+        OutputType::Shared | OutputType::SharedAndLink => FileType::SharedObject,
+    };
+
+    let mut out = fmt.create_file(ty);
+
+    for sect in sections {
+        out.add_section(Section {
+            name: sect.name,
+            align: sect.align,
+            ty: sect.ty,
+            content: sect.content,
+            flags: sect.flags,
+            ..Section::default()
+        })
+        .map_err(Box::new)?;
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,393 @@
+//! Iterative relocation relaxation: shrinking a relocation's encoding to a
+//! cheaper, shorter form once the addresses involved turn out to be close
+//! enough -- a long, fixed-width branch/call shrinking to a short relative
+//! one, the way w65's `jsl`/`jml` can shrink to `bra`, or clever's
+//! variable-width branch encodings can shrink similarly, whenever the
+//! target is near enough once addresses are assigned.
+//!
+//! This is "architecture-pluggable" the same way [`crate::reloc`] is
+//! generic over format: the decision of *whether* a relocation can shrink,
+//! and the rewrite of its bytes into the shorter encoding, are
+//! [`binfmt::howto::HowTo::relax_shrink`]'s job, overridden per target the
+//! same way [`binfmt::howto::HowTo::relax`] already is for same-size
+//! relaxation. [`relax_sections`] only owns the format-independent part:
+//! finding every relaxable relocation, deleting the bytes a shrink frees
+//! up, and shifting every later piece/relocation in the same section to
+//! match.
+//!
+//! Shrinking one relocation moves every byte after it, which can bring
+//! another relocation's target newly into a short encoding's range (or, if
+//! a section moved further from something it references, take one out of
+//! range) -- so [`relax_sections`] keeps re-checking a section's
+//! relocations, and has its caller re-run address assignment between
+//! rounds, until a full round shrinks nothing, the same
+//! iterate-to-fixpoint shape [`crate::gc::reachable_sections`] and
+//! [`crate::icf::fold_sections`] use for their own worklist passes.
+//!
+//! The only target `HowTo` that overrides [`binfmt::howto::HowTo::relax_shrink`]
+//! is `Elf32W65HowTo`, and only for `RelaxJmp` (a `JMP abs` shrinking to
+//! `BRA rel8`); on every other target this pass's first round shrinks
+//! nothing and it converges immediately. The fixpoint loop and the
+//! byte-splicing it performs once a `HowTo` reports a smaller encoding
+//! are exercised by this module's own tests against a fake always-shrinks
+//! `HowTo`. Like [`crate::got`] and
+//! [`crate::dynamic`], nothing in the link driver calls this yet.
+
+use binfmt::fmt::Binfmt;
+
+use crate::output::OutputSection;
+
+/// Runs every section in `sections` through one or more rounds of
+/// relaxation against `fmt`'s [`binfmt::howto::HowTo::relax_shrink`],
+/// re-laying out addresses (via `relayout`) and re-checking after any
+/// round that shrinks something, until a round shrinks nothing.
+///
+/// `sections`' `vaddr`/`file_offset` must already be assigned (e.g. by
+/// [`crate::output::assign_addresses`]) before the first call.
+/// `resolve`/`binds_locally` mirror [`crate::reloc::apply_relocations`]'s
+/// callbacks of the same name; `relayout` is typically just
+/// `|sections| assign_addresses(sections, output_type)`.
+///
+/// Returns whether anything shrunk at all, so a caller that only cares
+/// whether it needs to re-run [`crate::reloc::apply_relocations`] against
+/// different offsets doesn't have to compare section sizes itself.
+pub fn relax_sections(
+    fmt: &dyn Binfmt,
+    sections: &mut [OutputSection],
+    resolve: &dyn Fn(&str) -> Option<u128>,
+    binds_locally: &dyn Fn(&str) -> bool,
+    relayout: &mut dyn FnMut(&mut [OutputSection]),
+) -> bool {
+    let mut shrunk_any = false;
+    loop {
+        let mut shrunk_this_round = false;
+        for sect in sections.iter_mut() {
+            shrunk_this_round |= relax_section_once(fmt, sect, resolve, binds_locally);
+        }
+        if !shrunk_this_round {
+            break;
+        }
+        shrunk_any = true;
+        relayout(sections);
+    }
+    shrunk_any
+}
+
+/// Shrinks every relocation in `sect` that [`binfmt::howto::HowTo::relax_shrink`]
+/// reports a smaller encoding for, re-checking the same index after each
+/// shrink in case its own (now-shorter) code is itself relaxable again --
+/// a real target's short `HowTo` doesn't override `relax_shrink`, so this
+/// converges in practice, but nothing here assumes that. Returns whether
+/// anything shrunk.
+fn relax_section_once(
+    fmt: &dyn Binfmt,
+    sect: &mut OutputSection,
+    resolve: &dyn Fn(&str) -> Option<u128>,
+    binds_locally: &dyn Fn(&str) -> bool,
+) -> bool {
+    let mut shrunk = false;
+    for piece_idx in 0..sect.pieces.len() {
+        let mut reloc_idx = 0;
+        while reloc_idx < sect.pieces[piece_idx].relocs.len() {
+            if try_shrink_one(fmt, sect, piece_idx, reloc_idx, resolve, binds_locally) {
+                shrunk = true;
+            } else {
+                reloc_idx += 1;
+            }
+        }
+    }
+    shrunk
+}
+
+/// Attempts to shrink `sect.pieces[piece_idx].relocs[reloc_idx]` in place.
+/// On success, the relocation's code is updated, the unused tail bytes are
+/// removed from `sect.content`, and every later piece/relocation in `sect`
+/// is shifted to match -- leaving `reloc_idx` pointing at a still-valid
+/// (now possibly different) relocation, so the caller re-checks it rather
+/// than advancing.
+fn try_shrink_one(
+    fmt: &dyn Binfmt,
+    sect: &mut OutputSection,
+    piece_idx: usize,
+    reloc_idx: usize,
+    resolve: &dyn Fn(&str) -> Option<u128>,
+    binds_locally: &dyn Fn(&str) -> bool,
+) -> bool {
+    let reloc = sect.pieces[piece_idx].relocs[reloc_idx].clone();
+
+    let Some(howto) = fmt.code_to_howto(reloc.code) else {
+        return false;
+    };
+    let Some(sym_addr) = resolve(&reloc.symbol) else {
+        return false;
+    };
+
+    let size = howto.reloc_size();
+    let start = reloc.offset as usize;
+    let Some(region) = sect.content.get_mut(start..start + size) else {
+        return false;
+    };
+
+    let addend = reloc.addend.unwrap_or(0) as i128;
+    let addr = (sym_addr as i128 + addend) as u128;
+    let at_addr = sect.vaddr + reloc.offset;
+
+    let is_local = binds_locally(&reloc.symbol);
+    let Some((new_code, new_size)) = howto.relax_shrink(region, is_local, addr, at_addr as u128)
+    else {
+        return false;
+    };
+    if new_size >= size {
+        return false;
+    }
+
+    sect.pieces[piece_idx].relocs[reloc_idx].code = new_code;
+    delete_bytes(sect, start + new_size, size - new_size);
+    true
+}
+
+/// Removes `delta` bytes from `sect.content` starting at `at`, and shifts
+/// every piece/relocation offset that fell after the deleted range to
+/// match -- the bookkeeping a shrink needs regardless of which `HowTo`
+/// caused it.
+fn delete_bytes(sect: &mut OutputSection, at: usize, delta: usize) {
+    sect.content.drain(at..at + delta);
+
+    for piece in &mut sect.pieces {
+        if piece.offset >= at + delta {
+            piece.offset -= delta;
+        } else if piece.offset + piece.size > at {
+            piece.size -= delta;
+        }
+
+        for reloc in &mut piece.relocs {
+            if reloc.offset as usize >= at + delta {
+                reloc.offset -= delta as u64;
+            }
+        }
+
+        if let Some(entries) = &mut piece.entry_offsets {
+            for off in entries.iter_mut() {
+                if *off >= at + delta {
+                    *off -= delta;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use binfmt::fmt::{BinaryFile, FileType};
+    use binfmt::howto::{HowTo, HowToError, RelocCode, RelocOutput};
+    use binfmt::sym::Symbol;
+
+    use super::*;
+    use crate::output::MergedPiece;
+
+    /// A fake 4-byte-wide relocation that shrinks to a 1-byte
+    /// [`RelocCode::None`] form once `relax_shrink` is asked, writing a
+    /// single marker byte -- enough to exercise the fixpoint/splicing
+    /// machinery without needing a real architecture's encodings. Only the
+    /// original (long) code shrinks; [`RelocCode::None`] is its already-
+    /// short form and doesn't shrink further, the same as a real target's
+    /// short `HowTo` not overriding `relax_shrink` at all.
+    struct AlwaysShrinks;
+    struct AlreadyShort;
+
+    impl HowTo for AlwaysShrinks {
+        fn from_relnum<'a>(_: u32) -> Option<&'a Self> {
+            Some(&AlwaysShrinks)
+        }
+        fn from_reloc_code<'a>(_: RelocCode) -> Option<&'a Self> {
+            Some(&AlwaysShrinks)
+        }
+        fn reloc_num(&self) -> u32 {
+            0
+        }
+        fn name(&self) -> &'static str {
+            "always_shrinks"
+        }
+        fn reloc_size(&self) -> usize {
+            4
+        }
+        fn pcrel(&self) -> bool {
+            false
+        }
+        fn is_relax(&self) -> bool {
+            true
+        }
+        fn relax_size(&self, _addr: u128, _at_addr: u128) -> Option<usize> {
+            None
+        }
+        fn apply<'a>(
+            &self,
+            _addr: u128,
+            _at_addr: u128,
+            region: &'a mut [u8],
+        ) -> Result<&'a mut [u8], HowToError> {
+            Ok(region)
+        }
+        fn valid_in(&self, _output_ty: RelocOutput, _sym_vis: &Symbol) -> bool {
+            true
+        }
+        fn relax_shrink(
+            &self,
+            region: &mut [u8],
+            _is_local: bool,
+            _addr: u128,
+            _at_addr: u128,
+        ) -> Option<(RelocCode, usize)> {
+            region[0] = 0xAB;
+            Some((RelocCode::None, 1))
+        }
+    }
+
+    impl HowTo for AlreadyShort {
+        fn from_relnum<'a>(_: u32) -> Option<&'a Self> {
+            Some(&AlreadyShort)
+        }
+        fn from_reloc_code<'a>(_: RelocCode) -> Option<&'a Self> {
+            Some(&AlreadyShort)
+        }
+        fn reloc_num(&self) -> u32 {
+            1
+        }
+        fn name(&self) -> &'static str {
+            "already_short"
+        }
+        fn reloc_size(&self) -> usize {
+            1
+        }
+        fn pcrel(&self) -> bool {
+            false
+        }
+        fn is_relax(&self) -> bool {
+            false
+        }
+        fn relax_size(&self, _addr: u128, _at_addr: u128) -> Option<usize> {
+            None
+        }
+        fn apply<'a>(
+            &self,
+            _addr: u128,
+            _at_addr: u128,
+            region: &'a mut [u8],
+        ) -> Result<&'a mut [u8], HowToError> {
+            Ok(region)
+        }
+        fn valid_in(&self, _output_ty: RelocOutput, _sym_vis: &Symbol) -> bool {
+            true
+        }
+    }
+
+    struct TestBinfmt;
+
+    impl Binfmt for TestBinfmt {
+        fn relnum_to_howto(&self, _: u32) -> Option<&dyn HowTo> {
+            Some(&AlwaysShrinks)
+        }
+        fn code_to_howto(&self, code: RelocCode) -> Option<&dyn HowTo> {
+            match code {
+                RelocCode::None => Some(&AlreadyShort),
+                _ => Some(&AlwaysShrinks),
+            }
+        }
+        fn name(&self) -> &'static str {
+            "test"
+        }
+        fn create_file(&self, ty: FileType) -> BinaryFile {
+            BinaryFile::create(self, Box::new(()), ty)
+        }
+        fn read_file(
+            &self,
+            _: &mut (dyn binfmt::traits::ReadSeek + '_),
+        ) -> binfmt::error::Result<Option<BinaryFile>> {
+            Err(binfmt::error::BinfmtError::Unsupported("test".to_string()))
+        }
+        fn write_file(
+            &self,
+            _: &mut (dyn std::io::Write + '_),
+            _: &BinaryFile,
+        ) -> binfmt::error::Result<()> {
+            Err(binfmt::error::BinfmtError::Unsupported("test".to_string()))
+        }
+        fn has_sections(&self) -> bool {
+            true
+        }
+        fn ident_file(&self, _: &mut (dyn std::io::Read + '_)) -> std::io::Result<bool> {
+            Ok(false)
+        }
+    }
+
+    fn section(content: &[u8], relocs: &[(u64, &str)]) -> OutputSection {
+        OutputSection {
+            name: ".text".to_string(),
+            ty: Default::default(),
+            flags: None,
+            align: 1,
+            content: content.to_vec(),
+            pieces: vec![MergedPiece {
+                offset: 0,
+                size: content.len(),
+                relocs: relocs
+                    .iter()
+                    .map(|&(offset, symbol)| binfmt::howto::Reloc {
+                        code: RelocCode::Rel { addr_width: 32 },
+                        symbol: symbol.to_string(),
+                        addend: None,
+                        offset,
+                    })
+                    .collect(),
+                entry_offsets: None,
+            }],
+            vaddr: 0,
+            file_offset: 0,
+        }
+    }
+
+    #[test]
+    fn shrinks_and_shifts_later_relocations() {
+        let fmt = TestBinfmt;
+        let mut sections = vec![section(
+            &[0, 0, 0, 0, 0, 0, 0, 0],
+            &[(0, "a"), (4, "b")],
+        )];
+
+        let shrunk = relax_sections(&fmt, &mut sections, &|_| Some(0), &|_| true, &mut |_| {});
+
+        assert!(shrunk);
+        assert_eq!(sections[0].content, vec![0xAB, 0xAB]);
+        assert_eq!(sections[0].pieces[0].relocs[0].offset, 0);
+        assert_eq!(sections[0].pieces[0].relocs[1].offset, 1);
+        assert_eq!(sections[0].pieces[0].size, 2);
+    }
+
+    #[test]
+    fn relayout_runs_once_per_shrinking_round() {
+        let fmt = TestBinfmt;
+        let mut sections = vec![section(&[0, 0, 0, 0], &[(0, "a")])];
+        let mut rounds = 0;
+
+        relax_sections(
+            &fmt,
+            &mut sections,
+            &|_| Some(0),
+            &|_| true,
+            &mut |_| rounds += 1,
+        );
+
+        assert_eq!(rounds, 1);
+    }
+
+    #[test]
+    fn unresolved_symbol_is_left_alone() {
+        let fmt = TestBinfmt;
+        let mut sections = vec![section(&[0, 0, 0, 0], &[(0, "missing")])];
+
+        let shrunk = relax_sections(&fmt, &mut sections, &|_| None, &|_| true, &mut |_| {});
+
+        assert!(!shrunk);
+        assert_eq!(sections[0].content, vec![0, 0, 0, 0]);
+    }
+}
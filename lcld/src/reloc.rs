@@ -0,0 +1,159 @@
+//! Relocation application, generic over [`binfmt::howto`].
+//!
+//! [`apply_relocations`] walks every [`crate::output::MergedPiece`]'s
+//! relocations, resolves each one's symbol through a caller-supplied
+//! callback, converts it to its dynamic-linking equivalent if
+//! [`pic_reloc_code`] says `output_type` needs one, looks up the target
+//! format's [`HowTo`] for the resulting [`RelocCode`], relaxes it if the
+//! symbol binds locally, and writes the result into the section's
+//! already address-assigned content. It doesn't know where a symbol's
+//! address comes from or what "binds locally" means for a given output
+//! type -- both are left to the caller, since that depends on the rest
+//! of the link (symbol resolution, GOT/PLT allocation) that this module
+//! doesn't otherwise touch.
+
+use binfmt::fmt::Binfmt;
+use binfmt::howto::RelocCode;
+
+use crate::link::{relax_reloc, LinkError, RelocationFailure, WrapSymbols};
+use crate::output::{OutputSection, OutputType};
+
+/// Converts a position-dependent relocation code to the form it must
+/// take for `output_type`, given whether the symbol it targets binds
+/// locally within this link.
+///
+/// [`OutputType::PieExecutable`] and [`OutputType::Shared`]/[`OutputType::SharedAndLink`]
+/// load at a base address chosen at runtime, so nothing in their `.text`
+/// can embed a direct reference to a symbol that doesn't bind locally --
+/// calls route through the PLT instead ([`RelocCode::Rel`] becomes
+/// [`RelocCode::RelPlt`]; this is safe unconditionally, the same
+/// redirection `-z lazy`/`-z now` both rely on), and GOT-relative
+/// accesses the compiler already emitted ([`RelocCode::Got`]/[`RelocCode::RelGot`])
+/// are left alone since they're already position-independent.
+///
+/// A plain absolute reference ([`RelocCode::Abs`]/[`RelocCode::AbsShifted`])
+/// to a non-local symbol has no PIC-safe form to convert to -- the
+/// instruction that embeds it would need to load the address from the
+/// GOT instead, which means re-encoding the instruction, not just
+/// picking a different relocation code -- so this reports
+/// [`RelocationFailure::RequiresPic`] instead of silently emitting a
+/// text relocation GNU ld would refuse to create.
+pub fn pic_reloc_code(
+    code: RelocCode,
+    output_type: OutputType,
+    binds_locally: bool,
+) -> Result<RelocCode, RelocationFailure> {
+    if binds_locally
+        || !matches!(
+            output_type,
+            OutputType::PieExecutable | OutputType::Shared | OutputType::SharedAndLink
+        )
+    {
+        return Ok(code);
+    }
+
+    match code {
+        RelocCode::Rel { addr_width } => Ok(RelocCode::RelPlt { addr_width }),
+        RelocCode::Abs { .. } | RelocCode::AbsShifted { .. } => {
+            Err(RelocationFailure::RequiresPic(code))
+        }
+        _ => Ok(code),
+    }
+}
+
+/// Applies every relocation carried by `sections`' pieces in place.
+///
+/// `resolve(symbol)` should return the symbol's final address, or `None`
+/// if it doesn't have one (an undefined weak, or a dynamic symbol the
+/// caller intends to leave for the runtime loader -- either way, a
+/// relocation against it is reported rather than silently dropped).
+/// `binds_locally(symbol)` should report whether `symbol` resolves within
+/// this link unit, which is what [`HowTo::relax`] uses to decide whether
+/// e.g. a GOT-relative access can relax to a direct one.
+///
+/// `wrapped` redirects a reference to a `--wrap=<symbol>` name to its
+/// `__wrap_<symbol>` form (see [`WrapSymbols`]) before `resolve` and
+/// `binds_locally` ever see it, so neither callback needs to know `--wrap`
+/// exists.
+///
+/// Every failing relocation is collected and returned together, the same
+/// way [`crate::link::LinkState::resolve_symbols`] collects every
+/// undefined/multiply-defined symbol rather than stopping at the first.
+pub fn apply_relocations(
+    fmt: &dyn Binfmt,
+    sections: &mut [OutputSection],
+    output_type: OutputType,
+    wrapped: &WrapSymbols,
+    resolve: &dyn Fn(&str) -> Option<u128>,
+    binds_locally: &dyn Fn(&str) -> bool,
+) -> Result<(), Vec<LinkError>> {
+    let mut errors = Vec::new();
+
+    for sect in sections.iter_mut() {
+        for piece in &sect.pieces {
+            for reloc in &piece.relocs {
+                let symbol = wrapped.redirect(&reloc.symbol);
+                let is_local = binds_locally(&symbol);
+
+                let code = match pic_reloc_code(reloc.code, output_type, is_local) {
+                    Ok(code) => code,
+                    Err(reason) => {
+                        errors.push(LinkError::Relocation {
+                            section: sect.name.clone(),
+                            offset: reloc.offset,
+                            symbol: symbol.into_owned(),
+                            reason,
+                        });
+                        continue;
+                    }
+                };
+
+                let Some(howto) = fmt.code_to_howto(code) else {
+                    errors.push(LinkError::Relocation {
+                        section: sect.name.clone(),
+                        offset: reloc.offset,
+                        symbol: symbol.into_owned(),
+                        reason: RelocationFailure::UnsupportedCode(code),
+                    });
+                    continue;
+                };
+
+                let Some(sym_addr) = resolve(&symbol) else {
+                    errors.push(LinkError::Relocation {
+                        section: sect.name.clone(),
+                        offset: reloc.offset,
+                        symbol: symbol.into_owned(),
+                        reason: RelocationFailure::UndefinedSymbol,
+                    });
+                    continue;
+                };
+
+                let addend = reloc.addend.unwrap_or(0) as i128;
+                let addr = (sym_addr as i128 + addend) as u128;
+                let at_addr = (sect.vaddr + reloc.offset) as u128;
+
+                let start = reloc.offset as usize;
+                let size = howto.reloc_size();
+                let region = &mut sect.content[start..start + size];
+
+                let code = relax_reloc(howto, region, code, is_local);
+                let howto = fmt.code_to_howto(code).unwrap_or(howto);
+
+                if let Err(reason) = howto.apply(addr, at_addr, region) {
+                    errors.push(LinkError::Relocation {
+                        section: sect.name.clone(),
+                        offset: reloc.offset,
+                        symbol: symbol.into_owned(),
+                        reason: RelocationFailure::Overflow(reason),
+                    });
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
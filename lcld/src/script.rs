@@ -1,30 +1,1113 @@
+//! A parser and (data-only) interpreter for the core of the GNU `ld`
+//! linker script language: `ENTRY`, `INPUT`/`GROUP`/`STARTUP`/`EXTERN`,
+//! `OUTPUT`/`OUTPUT_FORMAT`/`TARGET`, `SEARCH_DIR`, `MEMORY`, `SECTIONS`
+//! (output section address expressions, `AT`/region placement, `PROVIDE`,
+//! input section wildcards, `KEEP`, and the `BYTE`/`SHORT`/`LONG`/`QUAD`
+//! data directives), and top-level/in-`SECTIONS` symbol assignment.
+//!
+//! [`parse`] turns script source into a [`ParsedScript`] -- a tree of
+//! plain data, not evaluated against any particular link's symbol table
+//! or address space. Resolving a [`FilePattern`] to the [`crate::link::InputId`]s
+//! it matches, or evaluating an [`Expr`] against a location counter while
+//! driving [`crate::output`]'s address assignment, belongs to the layout
+//! pass that consumes a [`ParsedScript`] once one exists -- the same
+//! staging [`crate::got`] and [`crate::arch`] use for algorithms nothing
+//! calls yet.
+//!
+//! Not supported: output section types/`NOLOAD`, `SUBALIGN`, `PHDRS`,
+//! `VERSION` script syntax, or any of the `DATA_SEGMENT_*`/
+//! `SEGMENT_START`-style built-in functions beyond `ALIGN`. Scripts
+//! using them fail to parse with [`ScriptError`] rather than silently
+//! ignoring the unsupported construct.
+
+use std::fmt;
 use std::path::PathBuf;
 
 use binfmt::fmt::Binfmt;
 
-use crate::link::{InputId, RegionId};
-
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct ParsedScript {
     pub command: Vec<ScriptTopCommand>,
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
-pub enum SymbolDef {
-    Extern(Vec<String>),
-}
-
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum ScriptTopCommand {
     Entry(String),
-    Input(Vec<InputId>),
-    Group(Vec<InputId>),
+    Input(Vec<PathBuf>),
+    Group(Vec<PathBuf>),
+    /// `EXTERN(sym ...)`: forces each symbol to be treated the way an
+    /// undefined reference in an input object would, so an archive
+    /// member defining it gets pulled in even though nothing else in the
+    /// link mentions it.
+    Extern(Vec<String>),
     Output(PathBuf),
     // Note: These are processed eagerly
     SearchPath(Vec<PathBuf>),
-    Startup(InputId),
+    Startup(PathBuf),
     OutputFormat(&'static dyn Binfmt),
     // Note: This isn't actually used, except to set the default `OutputFormat`
     Target(&'static dyn Binfmt),
-    RegionAlias(String, RegionId),
+    RegionAlias(String, String),
+    Memory(Vec<MemoryRegion>),
+    Sections(Vec<OutputSectionCommand>),
+    Provide(String, Expr),
+    Assign(String, Expr),
+}
+
+/// One `MEMORY { name (attrs) : ORIGIN = ..., LENGTH = ... ; }` region.
+/// `attrs` is kept as written (e.g. `"rwx"`, `"!rw"`) rather than parsed
+/// into flags -- nothing downstream reads it yet, and GNU ld's own
+/// attribute letters (`r`/`w`/`x`/`a`/`l`/`i`, negated with `!`) don't map
+/// cleanly onto this crate's [`binfmt::fmt::SectionFlag`]s on their own.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub attrs: String,
+    pub origin: Expr,
+    pub length: Expr,
+}
+
+/// One output section definition, or a bare symbol assignment, inside a
+/// `SECTIONS` block.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum OutputSectionCommand {
+    Output(OutputSection),
+    Assign(String, Expr),
+    Provide(String, Expr),
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct OutputSection {
+    pub name: String,
+    /// The address expression before the `:`, if the script gave one --
+    /// absent, the location counter's current value is used, same as
+    /// GNU ld.
+    pub address: Option<Expr>,
+    /// The `AT(...)` load-address expression, if any.
+    pub at: Option<Expr>,
+    pub commands: Vec<InputSectionCommand>,
+    /// The `> region` memory region this section is placed in, if any.
+    pub region: Option<String>,
+    /// The `AT> region` region the section's load address (as opposed to
+    /// its virtual address) is placed in, if any.
+    pub lma_region: Option<String>,
+}
+
+/// One command inside an output section's `{ ... }` body.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum InputSectionCommand {
+    /// An input section spec, e.g. `*(.text .text.*)` or
+    /// `foo.o(.data)`. `keep` is set for specs wrapped in `KEEP(...)`,
+    /// marking them as surviving garbage collection.
+    Input {
+        file: FilePattern,
+        sections: Vec<SectionPattern>,
+        keep: bool,
+    },
+    Assign(String, Expr),
+    Provide(String, Expr),
+    Byte(Expr),
+    Short(Expr),
+    Long(Expr),
+    Quad(Expr),
+}
+
+/// Which input files an [`InputSectionCommand::Input`] spec's section
+/// patterns apply to.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum FilePattern {
+    /// `*`: every input file.
+    Any,
+    /// A bare or glob-containing file name, e.g. `foo.o` or `*.a`.
+    Named(String),
+    /// `archive.a:member.o`: only `member` within `archive`, not the
+    /// whole archive -- GNU ld's way of pulling a single member's
+    /// sections out of a static library by name.
+    ArchiveMember { archive: String, member: String },
+}
+
+impl FilePattern {
+    /// Whether this file spec selects the input file named `file_name`.
+    ///
+    /// For [`FilePattern::ArchiveMember`] this only checks the archive's
+    /// own name -- telling *which* member inside it matched is the
+    /// resolution pass's job, once one exists, since that needs the
+    /// archive's member table rather than anything recorded here.
+    pub fn matches_file(&self, file_name: &str) -> bool {
+        match self {
+            FilePattern::Any => true,
+            FilePattern::Named(pattern) => glob_match(pattern, file_name),
+            FilePattern::ArchiveMember { archive, .. } => glob_match(archive, file_name),
+        }
+    }
+}
+
+/// A `SORT`/`SORT_BY_NAME`/`SORT_BY_ALIGNMENT` wrapper around a section
+/// glob, controlling the order same-spec matches are placed in.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SortKey {
+    /// No `SORT_BY_*` wrapper: matching sections keep link order.
+    None,
+    /// `SORT`/`SORT_BY_NAME`: sorted lexicographically by section name.
+    ByName,
+    /// `SORT_BY_ALIGNMENT`: sorted by descending alignment.
+    ByAlignment,
+}
+
+/// One glob inside an input spec's section list, e.g. the
+/// `SORT_BY_NAME(.text.*)` in `*(SORT_BY_NAME(.text.*) .text)`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SectionPattern {
+    pub sort: SortKey,
+    /// `EXCLUDE_FILE(a.o b.o)` globs preceding this pattern -- a
+    /// section is skipped if the file defining it matches any of these,
+    /// even if `pattern` would otherwise match.
+    pub exclude_files: Vec<String>,
+    pub pattern: String,
+}
+
+impl SectionPattern {
+    /// Whether this pattern matches a section named `section_name`,
+    /// defined in a file named `file_name`.
+    pub fn matches(&self, file_name: &str, section_name: &str) -> bool {
+        if self.exclude_files.iter().any(|p| glob_match(p, file_name)) {
+            return false;
+        }
+        glob_match(&self.pattern, section_name)
+    }
+}
+
+/// Whether `pattern` (a GNU `ld` input-section wildcard: `*` matches any
+/// run of characters including none, `?` matches exactly one character,
+/// and `[abc]`/`[!abc]` matches/excludes one character from a class)
+/// matches `name` in full.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+
+    fn go(p: &[char], pi: usize, n: &[char], ni: usize) -> bool {
+        let Some(&pc) = p.get(pi) else {
+            return ni == n.len();
+        };
+        match pc {
+            '*' => go(p, pi + 1, n, ni) || (ni < n.len() && go(p, pi, n, ni + 1)),
+            '?' => ni < n.len() && go(p, pi + 1, n, ni + 1),
+            '[' => match match_class(p, pi, n.get(ni).copied()) {
+                Some((true, next_pi)) => go(p, next_pi, n, ni + 1),
+                _ => false,
+            },
+            c => ni < n.len() && n[ni] == c && go(p, pi + 1, n, ni + 1),
+        }
+    }
+
+    go(&p, 0, &n, 0)
+}
+
+/// Parses the `[...]`/`[!...]`/`[^...]` character class starting at
+/// `p[start]` (the `[`), checking whether `c` belongs to it. Returns the
+/// match result and the index just past the class's closing `]`, or
+/// `None` if the class has no closing `]` (an invalid pattern, treated
+/// as matching nothing rather than panicking).
+fn match_class(p: &[char], start: usize, c: Option<char>) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negate = matches!(p.get(i), Some('!') | Some('^'));
+    if negate {
+        i += 1;
+    }
+    let class_start = i;
+    while p.get(i).copied() != Some(']') {
+        i += 1;
+        if i >= p.len() {
+            return None;
+        }
+    }
+    let class = &p[class_start..i];
+    let matched = matches!(c, Some(c) if class.contains(&c) != negate);
+    Some((matched, i + 1))
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// An address/size expression, as used by `ORIGIN`/`LENGTH`, an output
+/// section's address, `AT(...)`, symbol assignments, and the data
+/// directives.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Expr {
+    Int(i128),
+    Symbol(String),
+    /// `.`: the location counter.
+    Dot,
+    Align(Box<Expr>),
+    /// `ORIGIN(region)`.
+    RegionOrigin(String),
+    /// `LENGTH(region)`.
+    RegionLength(String),
+    Neg(Box<Expr>),
+    BinOp(BinOp, Box<Expr>, Box<Expr>),
+}
+
+/// Context an [`Expr`] is evaluated against: lookups for the current
+/// location counter, symbol values, and `MEMORY` regions. The layout
+/// pass implements this against the live link state; [`parse_expr_str`]
+/// callers outside `SECTIONS` (e.g. `--defsym`) that have no location
+/// counter or memory regions can stub `dot`/`region_*` to fail only if
+/// the expression actually uses them.
+pub trait EvalContext {
+    fn dot(&self) -> i128;
+    fn symbol(&self, name: &str) -> Option<i128>;
+    fn region_origin(&self, name: &str) -> Option<i128>;
+    fn region_length(&self, name: &str) -> Option<i128>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EvalError {
+    UndefinedSymbol(String),
+    UnknownRegion(String),
+    DivideByZero,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::UndefinedSymbol(s) => write!(f, "undefined symbol `{}`", s),
+            EvalError::UnknownRegion(s) => write!(f, "unknown memory region `{}`", s),
+            EvalError::DivideByZero => f.write_str("division by zero"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl Expr {
+    /// Evaluates this expression against `ctx`.
+    pub fn eval(&self, ctx: &dyn EvalContext) -> Result<i128, EvalError> {
+        match self {
+            Expr::Int(n) => Ok(*n),
+            Expr::Symbol(s) => ctx.symbol(s).ok_or_else(|| EvalError::UndefinedSymbol(s.clone())),
+            Expr::Dot => Ok(ctx.dot()),
+            Expr::Align(align) => {
+                let align = align.eval(ctx)?;
+                let dot = ctx.dot();
+                Ok(if align <= 0 {
+                    dot
+                } else {
+                    ((dot + align - 1) / align) * align
+                })
+            }
+            Expr::RegionOrigin(r) => ctx
+                .region_origin(r)
+                .ok_or_else(|| EvalError::UnknownRegion(r.clone())),
+            Expr::RegionLength(r) => ctx
+                .region_length(r)
+                .ok_or_else(|| EvalError::UnknownRegion(r.clone())),
+            Expr::Neg(e) => Ok(-e.eval(ctx)?),
+            Expr::BinOp(op, lhs, rhs) => {
+                let lhs = lhs.eval(ctx)?;
+                let rhs = rhs.eval(ctx)?;
+                Ok(match op {
+                    BinOp::Add => lhs + rhs,
+                    BinOp::Sub => lhs - rhs,
+                    BinOp::Mul => lhs * rhs,
+                    BinOp::Div => {
+                        if rhs == 0 {
+                            return Err(EvalError::DivideByZero);
+                        }
+                        lhs / rhs
+                    }
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScriptError {
+    UnexpectedEof,
+    UnexpectedToken { expected: String, found: String },
+    InvalidNumber(String),
+    UnsupportedConstruct(String),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::UnexpectedEof => f.write_str("unexpected end of script"),
+            ScriptError::UnexpectedToken { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            ScriptError::InvalidNumber(s) => write!(f, "invalid number literal `{}`", s),
+            ScriptError::UnsupportedConstruct(s) => write!(f, "unsupported linker script construct: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Tok {
+    Ident(String),
+    Str(String),
+    Int(i128),
+    Punct(char),
+}
+
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tok::Ident(s) => write!(f, "`{}`", s),
+            Tok::Str(s) => write!(f, "\"{}\"", s),
+            Tok::Int(n) => write!(f, "{}", n),
+            Tok::Punct(c) => write!(f, "`{}`", c),
+        }
+    }
+}
+
+/// Characters GNU ld allows inside a bare (unquoted) file/symbol name,
+/// beyond alphanumerics: the usual identifier characters plus the ones
+/// file names need (`.`, `/`, `-`) and the glob metacharacters section
+/// patterns use (`*`, `?`, `[`, `]`).
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric()
+        || matches!(
+            c,
+            '_' | '.' | '/' | '-' | '*' | '?' | '[' | ']' | '\\' | '$' | '~' | '+' | ':'
+        )
+}
+
+fn is_name_start(c: char) -> bool {
+    is_name_char(c) && !c.is_ascii_digit() && !matches!(c, ':' | '+' | '-')
+}
+
+fn lex(src: &str) -> Result<Vec<Tok>, ScriptError> {
+    let mut chars = src.chars().peekable();
+    let mut toks = Vec::new();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '/' => {
+                chars.next();
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    let mut prev = '\0';
+                    loop {
+                        match chars.next() {
+                            None => return Err(ScriptError::UnexpectedEof),
+                            Some('/') if prev == '*' => break,
+                            Some(c) => prev = c,
+                        }
+                    }
+                } else {
+                    toks.push(Tok::Punct('/'));
+                }
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(ScriptError::UnexpectedEof),
+                        Some('"') => break,
+                        Some(c) => s.push(c),
+                    }
+                }
+                toks.push(Tok::Str(s));
+            }
+            c if c.is_ascii_digit() => {
+                let mut s = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() {
+                        s.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                toks.push(Tok::Int(parse_int(&s)?));
+            }
+            '.' if !chars.clone().nth(1).is_some_and(is_name_char) => {
+                chars.next();
+                toks.push(Tok::Punct('.'));
+            }
+            '*' if !chars.clone().nth(1).is_some_and(is_name_char) => {
+                chars.next();
+                toks.push(Tok::Punct('*'));
+            }
+            c if is_name_start(c) => {
+                let mut s = String::new();
+                while let Some(&c) = chars.peek() {
+                    if is_name_char(c) {
+                        s.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                toks.push(Tok::Ident(s));
+            }
+            '{' | '}' | '(' | ')' | ',' | ';' | '=' | '+' | '-' | '*' | ':' | '!' | '>' => {
+                toks.push(Tok::Punct(c));
+                chars.next();
+            }
+            c => {
+                return Err(ScriptError::UnexpectedToken {
+                    expected: "a token".to_string(),
+                    found: format!("`{}`", c),
+                })
+            }
+        }
+    }
+
+    Ok(toks)
+}
+
+/// Parses a (possibly `K`/`M`-suffixed, per GNU ld's `1K == 1024`)
+/// decimal or `0x`-prefixed hexadecimal integer literal.
+pub(crate) fn parse_int(s: &str) -> Result<i128, ScriptError> {
+    let (digits, scale) = match s.strip_suffix(['K', 'k']) {
+        Some(rest) => (rest, 1024),
+        None => match s.strip_suffix(['M', 'm']) {
+            Some(rest) => (rest, 1024 * 1024),
+            None => (s, 1),
+        },
+    };
+
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i128::from_str_radix(hex, 16)
+    } else {
+        digits.parse::<i128>()
+    }
+    .map_err(|_| ScriptError::InvalidNumber(s.to_string()))?;
+
+    Ok(value * scale)
+}
+
+/// Resolves a `SEARCH_DIR`/`-L` path against `sysroot`, the way GNU `ld`
+/// does for a sysroot-relative path: a leading `=` or literal `$SYSROOT`
+/// component is replaced by `sysroot`, and anything else is left exactly
+/// as given (in particular, a plain absolute path is *not* silently
+/// redirected into the sysroot -- only these two explicit forms are).
+pub(crate) fn resolve_sysroot_path(path: &std::path::Path, sysroot: &std::path::Path) -> PathBuf {
+    let Some(s) = path.to_str() else {
+        return path.to_path_buf();
+    };
+
+    let rest = if let Some(rest) = s.strip_prefix("$SYSROOT") {
+        rest
+    } else if let Some(rest) = s.strip_prefix('=') {
+        rest
+    } else {
+        return path.to_path_buf();
+    };
+
+    sysroot.join(rest.strip_prefix('/').unwrap_or(rest))
+}
+
+struct Parser {
+    toks: Vec<Tok>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Tok, ScriptError> {
+        let tok = self.toks.get(self.pos).cloned().ok_or(ScriptError::UnexpectedEof)?;
+        self.pos += 1;
+        Ok(tok)
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<(), ScriptError> {
+        match self.next()? {
+            Tok::Punct(p) if p == c => Ok(()),
+            other => Err(ScriptError::UnexpectedToken {
+                expected: format!("`{}`", c),
+                found: other.to_string(),
+            }),
+        }
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        if matches!(self.peek(), Some(Tok::Punct(p)) if *p == c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String, ScriptError> {
+        match self.next()? {
+            Tok::Ident(s) => Ok(s),
+            other => Err(ScriptError::UnexpectedToken {
+                expected: "an identifier".to_string(),
+                found: other.to_string(),
+            }),
+        }
+    }
+
+    /// An identifier or quoted string -- GNU ld accepts either wherever a
+    /// file or symbol name is expected.
+    fn expect_name(&mut self) -> Result<String, ScriptError> {
+        match self.next()? {
+            Tok::Ident(s) | Tok::Str(s) => Ok(s),
+            other => Err(ScriptError::UnexpectedToken {
+                expected: "a name".to_string(),
+                found: other.to_string(),
+            }),
+        }
+    }
+
+    fn peek_ident_is(&self, kw: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident(s)) if s == kw)
+    }
+
+    // expr := sum
+    // sum := product (('+' | '-') product)*
+    // product := unary (('*' | '/') unary)*
+    // unary := '-' unary | atom
+    // atom := int | '.' | NAME '(' expr ')' | NAME '(' NAME ')' | NAME | '(' expr ')'
+    fn parse_expr(&mut self) -> Result<Expr, ScriptError> {
+        let mut lhs = self.parse_product()?;
+        loop {
+            let op = match self.peek() {
+                Some(Tok::Punct('+')) => BinOp::Add,
+                Some(Tok::Punct('-')) => BinOp::Sub,
+                _ => break,
+            };
+            self.pos += 1;
+            let rhs = self.parse_product()?;
+            lhs = Expr::BinOp(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, ScriptError> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Tok::Punct('*')) => BinOp::Mul,
+                Some(Tok::Punct('/')) => BinOp::Div,
+                _ => break,
+            };
+            self.pos += 1;
+            let rhs = self.parse_unary()?;
+            lhs = Expr::BinOp(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ScriptError> {
+        if self.eat_punct('-') {
+            return Ok(Expr::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, ScriptError> {
+        match self.next()? {
+            Tok::Int(n) => Ok(Expr::Int(n)),
+            Tok::Punct('.') => Ok(Expr::Dot),
+            Tok::Punct('(') => {
+                let e = self.parse_expr()?;
+                self.expect_punct(')')?;
+                Ok(e)
+            }
+            Tok::Ident(name) if name == "ALIGN" => {
+                self.expect_punct('(')?;
+                let align = self.parse_expr()?;
+                // GNU ld's two-argument `ALIGN(exp, align)` aligns `exp`
+                // to `align`; only the single-argument form (align the
+                // location counter) is represented here, so for the
+                // two-argument form the first argument is parsed (to stay
+                // in sync with the token stream) and discarded, and the
+                // location counter is aligned to the second -- exact for
+                // `ALIGN(., n)`.
+                let align = if self.eat_punct(',') {
+                    self.parse_expr()?
+                } else {
+                    align
+                };
+                self.expect_punct(')')?;
+                Ok(Expr::Align(Box::new(align)))
+            }
+            Tok::Ident(name)
+                if (name == "ORIGIN" || name == "LENGTH") && matches!(self.peek(), Some(Tok::Punct('('))) =>
+            {
+                self.expect_punct('(')?;
+                let region = self.expect_ident()?;
+                self.expect_punct(')')?;
+                if name == "ORIGIN" {
+                    Ok(Expr::RegionOrigin(region))
+                } else {
+                    Ok(Expr::RegionLength(region))
+                }
+            }
+            Tok::Ident(name) => Ok(Expr::Symbol(name)),
+            other => Err(ScriptError::UnexpectedToken {
+                expected: "an expression".to_string(),
+                found: other.to_string(),
+            }),
+        }
+    }
+
+    fn parse_paren_path_list(&mut self) -> Result<Vec<PathBuf>, ScriptError> {
+        self.expect_punct('(')?;
+        let mut paths = Vec::new();
+        loop {
+            paths.push(PathBuf::from(self.expect_name()?));
+            if !self.eat_punct(',') {
+                break;
+            }
+        }
+        self.expect_punct(')')?;
+        Ok(paths)
+    }
+
+    fn parse_paren_name_list(&mut self) -> Result<Vec<String>, ScriptError> {
+        self.expect_punct('(')?;
+        let mut names = Vec::new();
+        loop {
+            names.push(self.expect_name()?);
+            if !self.eat_punct(',') {
+                break;
+            }
+        }
+        self.expect_punct(')')?;
+        Ok(names)
+    }
+
+    fn parse_assign_rhs(&mut self) -> Result<Expr, ScriptError> {
+        self.expect_punct('=')?;
+        let e = self.parse_expr()?;
+        self.expect_punct(';')?;
+        Ok(e)
+    }
+
+    fn parse_memory(&mut self) -> Result<Vec<MemoryRegion>, ScriptError> {
+        self.expect_punct('{')?;
+        let mut regions = Vec::new();
+        while !self.eat_punct('}') {
+            let name = self.expect_name()?;
+            let mut attrs = String::new();
+            if self.eat_punct('(') {
+                loop {
+                    match self.next()? {
+                        Tok::Punct(')') => break,
+                        Tok::Ident(s) => attrs.push_str(&s),
+                        Tok::Punct(c) => attrs.push(c),
+                        other => {
+                            return Err(ScriptError::UnexpectedToken {
+                                expected: "a memory attribute".to_string(),
+                                found: other.to_string(),
+                            })
+                        }
+                    }
+                }
+            }
+            self.expect_punct(':')?;
+
+            let mut origin = None;
+            let mut length = None;
+            loop {
+                let key = self.expect_ident()?;
+                self.expect_punct('=')?;
+                let value = self.parse_expr()?;
+                match &*key {
+                    "ORIGIN" | "org" | "o" => origin = Some(value),
+                    "LENGTH" | "len" | "l" => length = Some(value),
+                    other => {
+                        return Err(ScriptError::UnsupportedConstruct(format!(
+                            "unknown MEMORY region attribute `{}`",
+                            other
+                        )))
+                    }
+                }
+                if !self.eat_punct(',') {
+                    break;
+                }
+            }
+            self.eat_punct(';');
+
+            regions.push(MemoryRegion {
+                name,
+                attrs,
+                origin: origin.ok_or(ScriptError::UnexpectedEof)?,
+                length: length.ok_or(ScriptError::UnexpectedEof)?,
+            });
+        }
+        Ok(regions)
+    }
+
+    /// `*(.text .text.*)` / `KEEP(foo.o(.data))` / bare `foo.o` -- an
+    /// input section spec, optionally wrapped in `KEEP(...)`.
+    fn parse_input_spec(&mut self) -> Result<InputSectionCommand, ScriptError> {
+        let keep = if self.peek_ident_is("KEEP") {
+            self.pos += 1;
+            self.expect_punct('(')?;
+            true
+        } else {
+            false
+        };
+
+        let file = match self.next()? {
+            Tok::Punct('*') => FilePattern::Any,
+            Tok::Ident(s) | Tok::Str(s) => match s.split_once(':') {
+                Some((archive, member)) => FilePattern::ArchiveMember {
+                    archive: archive.to_string(),
+                    member: member.to_string(),
+                },
+                None => FilePattern::Named(s),
+            },
+            other => {
+                return Err(ScriptError::UnexpectedToken {
+                    expected: "a file name or `*`".to_string(),
+                    found: other.to_string(),
+                })
+            }
+        };
+
+        let mut sections = Vec::new();
+        if self.eat_punct('(') {
+            loop {
+                match self.peek() {
+                    Some(Tok::Punct(')')) => break,
+                    _ => sections.push(self.parse_section_pattern()?),
+                }
+            }
+            self.expect_punct(')')?;
+        }
+
+        if keep {
+            self.expect_punct(')')?;
+        }
+
+        Ok(InputSectionCommand::Input { file, sections, keep })
+    }
+
+    /// One section glob inside an input spec's parentheses: an optional
+    /// `EXCLUDE_FILE(...)`, an optional `SORT`/`SORT_BY_NAME`/
+    /// `SORT_BY_ALIGNMENT(...)` wrapper, then the glob itself.
+    fn parse_section_pattern(&mut self) -> Result<SectionPattern, ScriptError> {
+        let mut exclude_files = Vec::new();
+        if self.peek_ident_is("EXCLUDE_FILE") {
+            self.pos += 1;
+            self.expect_punct('(')?;
+            loop {
+                match self.peek() {
+                    Some(Tok::Punct(')')) => break,
+                    _ => exclude_files.push(self.expect_name()?),
+                }
+            }
+            self.expect_punct(')')?;
+        }
+
+        let sort = if self.peek_ident_is("SORT") || self.peek_ident_is("SORT_BY_NAME") {
+            self.pos += 1;
+            self.expect_punct('(')?;
+            Some(SortKey::ByName)
+        } else if self.peek_ident_is("SORT_BY_ALIGNMENT") {
+            self.pos += 1;
+            self.expect_punct('(')?;
+            Some(SortKey::ByAlignment)
+        } else {
+            None
+        };
+
+        let pattern = self.expect_name()?;
+
+        if sort.is_some() {
+            self.expect_punct(')')?;
+        }
+
+        Ok(SectionPattern {
+            sort: sort.unwrap_or(SortKey::None),
+            exclude_files,
+            pattern,
+        })
+    }
+
+    fn parse_sections_body(&mut self) -> Result<Vec<InputSectionCommand>, ScriptError> {
+        self.expect_punct('{')?;
+        let mut commands = Vec::new();
+        while !self.eat_punct('}') {
+            commands.push(self.parse_input_section_command()?);
+        }
+        Ok(commands)
+    }
+
+    fn parse_input_section_command(&mut self) -> Result<InputSectionCommand, ScriptError> {
+        match self.peek() {
+            Some(Tok::Ident(kw)) if kw == "PROVIDE" || kw == "PROVIDE_HIDDEN" => {
+                self.pos += 1;
+                self.expect_punct('(')?;
+                let name = self.expect_ident()?;
+                let expr = self.parse_assign_rhs()?;
+                self.expect_punct(')')?;
+                self.eat_punct(';');
+                Ok(InputSectionCommand::Provide(name, expr))
+            }
+            Some(Tok::Ident(kw)) if matches!(&**kw, "BYTE" | "SHORT" | "LONG" | "QUAD") => {
+                let kw = kw.clone();
+                self.pos += 1;
+                self.expect_punct('(')?;
+                let expr = self.parse_expr()?;
+                self.expect_punct(')')?;
+                self.eat_punct(';');
+                Ok(match &*kw {
+                    "BYTE" => InputSectionCommand::Byte(expr),
+                    "SHORT" => InputSectionCommand::Short(expr),
+                    "LONG" => InputSectionCommand::Long(expr),
+                    _ => InputSectionCommand::Quad(expr),
+                })
+            }
+            Some(Tok::Ident(_)) if self.is_assignment_ahead() => {
+                let name = self.expect_ident()?;
+                let expr = self.parse_assign_rhs()?;
+                Ok(InputSectionCommand::Assign(name, expr))
+            }
+            Some(Tok::Punct('.')) if self.is_assignment_ahead() => {
+                self.pos += 1;
+                let expr = self.parse_assign_rhs()?;
+                Ok(InputSectionCommand::Assign(".".to_string(), expr))
+            }
+            Some(_) => {
+                let spec = self.parse_input_spec()?;
+                self.eat_punct(';');
+                Ok(spec)
+            }
+            None => Err(ScriptError::UnexpectedEof),
+        }
+    }
+
+    /// Whether the next token begins `NAME = ...` / `. = ...` rather than
+    /// an input section spec -- both start with an identifier (or `.`),
+    /// so this peeks one token further for the `=` that disambiguates
+    /// them.
+    fn is_assignment_ahead(&self) -> bool {
+        matches!(self.toks.get(self.pos + 1), Some(Tok::Punct('=')))
+    }
+
+    fn parse_output_section(&mut self, name: String) -> Result<OutputSection, ScriptError> {
+        if matches!(self.peek(), Some(Tok::Punct('(')))
+            && matches!(
+                self.toks.get(self.pos + 1),
+                Some(Tok::Ident(t)) if matches!(t.as_str(), "NOLOAD" | "DSECT" | "COPY" | "INFO" | "OVERLAY")
+            )
+        {
+            return Err(ScriptError::UnsupportedConstruct(format!(
+                "output section type on `{}`",
+                name
+            )));
+        }
+        let address = if matches!(self.peek(), Some(Tok::Punct(':'))) {
+            None
+        } else {
+            Some(self.parse_expr()?)
+        };
+        self.expect_punct(':')?;
+
+        let at = if self.peek_ident_is("AT") {
+            self.pos += 1;
+            self.expect_punct('(')?;
+            let e = self.parse_expr()?;
+            self.expect_punct(')')?;
+            Some(e)
+        } else {
+            None
+        };
+
+        let commands = self.parse_sections_body()?;
+
+        let mut region = None;
+        let mut lma_region = None;
+        loop {
+            if self.eat_punct('>') {
+                region = Some(self.expect_ident()?);
+            } else if self.peek_ident_is("AT") && matches!(self.toks.get(self.pos + 1), Some(Tok::Punct('>'))) {
+                self.pos += 2;
+                lma_region = Some(self.expect_ident()?);
+            } else {
+                break;
+            }
+        }
+        self.eat_punct(';');
+
+        Ok(OutputSection {
+            name,
+            address,
+            at,
+            commands,
+            region,
+            lma_region,
+        })
+    }
+
+    fn parse_sections(&mut self) -> Result<Vec<OutputSectionCommand>, ScriptError> {
+        self.expect_punct('{')?;
+        let mut commands = Vec::new();
+        while !self.eat_punct('}') {
+            match self.peek() {
+                Some(Tok::Ident(kw)) if kw == "PROVIDE" || kw == "PROVIDE_HIDDEN" => {
+                    self.pos += 1;
+                    self.expect_punct('(')?;
+                    let name = self.expect_ident()?;
+                    let expr = self.parse_assign_rhs()?;
+                    self.expect_punct(')')?;
+                    self.eat_punct(';');
+                    commands.push(OutputSectionCommand::Provide(name, expr));
+                }
+                Some(Tok::Ident(_)) if self.is_assignment_ahead() => {
+                    let name = self.expect_ident()?;
+                    let expr = self.parse_assign_rhs()?;
+                    commands.push(OutputSectionCommand::Assign(name, expr));
+                }
+                Some(Tok::Punct('.')) if self.is_assignment_ahead() => {
+                    self.pos += 1;
+                    let expr = self.parse_assign_rhs()?;
+                    commands.push(OutputSectionCommand::Assign(".".to_string(), expr));
+                }
+                Some(_) => {
+                    let name = self.expect_name()?;
+                    commands.push(OutputSectionCommand::Output(self.parse_output_section(name)?));
+                }
+                None => return Err(ScriptError::UnexpectedEof),
+            }
+        }
+        Ok(commands)
+    }
+
+    fn parse_top(&mut self) -> Result<Vec<ScriptTopCommand>, ScriptError> {
+        let mut commands = Vec::new();
+        while self.peek().is_some() {
+            match self.next()? {
+                Tok::Ident(kw) if kw == "ENTRY" => {
+                    self.expect_punct('(')?;
+                    let sym = self.expect_ident()?;
+                    self.expect_punct(')')?;
+                    commands.push(ScriptTopCommand::Entry(sym));
+                }
+                Tok::Ident(kw) if kw == "INPUT" => {
+                    commands.push(ScriptTopCommand::Input(self.parse_paren_path_list()?));
+                }
+                Tok::Ident(kw) if kw == "GROUP" => {
+                    commands.push(ScriptTopCommand::Group(self.parse_paren_path_list()?));
+                }
+                Tok::Ident(kw) if kw == "EXTERN" => {
+                    commands.push(ScriptTopCommand::Extern(self.parse_paren_name_list()?));
+                }
+                Tok::Ident(kw) if kw == "STARTUP" => {
+                    self.expect_punct('(')?;
+                    let path = PathBuf::from(self.expect_name()?);
+                    self.expect_punct(')')?;
+                    commands.push(ScriptTopCommand::Startup(path));
+                }
+                Tok::Ident(kw) if kw == "OUTPUT" => {
+                    self.expect_punct('(')?;
+                    let path = PathBuf::from(self.expect_name()?);
+                    self.expect_punct(')')?;
+                    commands.push(ScriptTopCommand::Output(path));
+                }
+                Tok::Ident(kw) if kw == "SEARCH_DIR" => {
+                    self.expect_punct('(')?;
+                    let path = PathBuf::from(self.expect_name()?);
+                    self.expect_punct(')')?;
+                    commands.push(ScriptTopCommand::SearchPath(vec![path]));
+                }
+                Tok::Ident(kw) if kw == "OUTPUT_FORMAT" || kw == "TARGET" => {
+                    self.expect_punct('(')?;
+                    let name = self.expect_name()?;
+                    // OUTPUT_FORMAT accepts up to three alternatives
+                    // (default/big/little); only the first is honored.
+                    while self.eat_punct(',') {
+                        self.expect_name()?;
+                    }
+                    self.expect_punct(')')?;
+                    let fmt = binfmt::format_by_name(&name).ok_or_else(|| {
+                        ScriptError::UnsupportedConstruct(format!("unknown output format `{}`", name))
+                    })?;
+                    commands.push(if kw == "TARGET" {
+                        ScriptTopCommand::Target(fmt)
+                    } else {
+                        ScriptTopCommand::OutputFormat(fmt)
+                    });
+                }
+                Tok::Ident(kw) if kw == "REGION_ALIAS" => {
+                    self.expect_punct('(')?;
+                    let alias = self.expect_name()?;
+                    self.expect_punct(',')?;
+                    let region = self.expect_ident()?;
+                    self.expect_punct(')')?;
+                    commands.push(ScriptTopCommand::RegionAlias(alias, region));
+                }
+                Tok::Ident(kw) if kw == "MEMORY" => {
+                    commands.push(ScriptTopCommand::Memory(self.parse_memory()?));
+                }
+                Tok::Ident(kw) if kw == "SECTIONS" => {
+                    commands.push(ScriptTopCommand::Sections(self.parse_sections()?));
+                }
+                Tok::Ident(kw) if kw == "PROVIDE" || kw == "PROVIDE_HIDDEN" => {
+                    self.expect_punct('(')?;
+                    let name = self.expect_ident()?;
+                    let expr = self.parse_assign_rhs()?;
+                    self.expect_punct(')')?;
+                    self.eat_punct(';');
+                    commands.push(ScriptTopCommand::Provide(name, expr));
+                }
+                Tok::Ident(name) if matches!(self.peek(), Some(Tok::Punct('='))) => {
+                    let expr = self.parse_assign_rhs()?;
+                    commands.push(ScriptTopCommand::Assign(name, expr));
+                }
+                other => {
+                    return Err(ScriptError::UnexpectedToken {
+                        expected: "a top-level linker script command".to_string(),
+                        found: other.to_string(),
+                    })
+                }
+            }
+            // Top-level commands are whitespace/newline separated in
+            // real scripts, with `;` only required inside `SECTIONS`; a
+            // stray `;` between them is harmless, so just skip it.
+            self.eat_punct(';');
+        }
+        Ok(commands)
+    }
+}
+
+/// Parses a complete linker script from source.
+pub fn parse(src: &str) -> Result<ParsedScript, ScriptError> {
+    let toks = lex(src)?;
+    let mut parser = Parser { toks, pos: 0 };
+    Ok(ParsedScript {
+        command: parser.parse_top()?,
+    })
+}
+
+/// Parses a single address/size expression, with nothing else following
+/// it -- the same [`Expr`] grammar [`parse`] uses inside `SECTIONS`, for
+/// callers that just have one expression in hand rather than a whole
+/// script. `--defsym=sym=expr`'s right-hand side is the motivating case.
+pub fn parse_expr_str(src: &str) -> Result<Expr, ScriptError> {
+    let toks = lex(src)?;
+    let mut parser = Parser { toks, pos: 0 };
+    let expr = parser.parse_expr()?;
+    if parser.pos != parser.toks.len() {
+        return Err(ScriptError::UnexpectedToken {
+            expected: "end of expression".to_string(),
+            found: parser.next()?.to_string(),
+        });
+    }
+    Ok(expr)
 }
@@ -0,0 +1,108 @@
+//! Windows x86_64 structured-exception-handling tables: the
+//! `RUNTIME_FUNCTION` array that makes up the `.pdata` section, and the
+//! `UNWIND_INFO` records its entries point at, which make up `.xdata`.
+//! Without a sorted `.pdata`, the Windows loader's stack walker can't
+//! binary-search function boundaries to find a frame's unwind info, and
+//! both structured exception handling and `RtlVirtualUnwind`-based stack
+//! walking (debuggers, crash dumps, `catch`/`finally`) break.
+//!
+//! Neither `lcld` nor `binfmt` has a PE writer yet (`binfmt::pe` is an
+//! empty stub), so nothing here is wired into an actual link; this is the
+//! self-contained piece -- the record layouts plus the sort -- a PE
+//! backend would reach for once it exists. ARM64 has its own, differently
+//! shaped compact-unwind `.pdata` format (no separate `.xdata` pointer in
+//! the common case); that's out of scope here and left for whoever adds
+//! ARM64 PE output.
+
+/// One `.pdata` entry: the address range of a function, and where to find
+/// its `UNWIND_INFO`. All three fields are image-relative virtual
+/// addresses (RVAs), as the PE format requires for `.pdata`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RuntimeFunction {
+    pub begin_addr: u32,
+    pub end_addr: u32,
+    pub unwind_info_addr: u32,
+}
+
+/// The fixed part of an `UNWIND_INFO` record (the variable-length unwind
+/// code array and optional exception handler/chained-info data that
+/// follow it aren't modeled here, since without a PE writer there's
+/// nowhere to place them yet).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UnwindInfoHeader {
+    pub version: u8,
+    pub flags: UnwindFlags,
+    pub size_of_prolog: u8,
+    pub count_of_codes: u8,
+    /// `None` if the function doesn't use a frame pointer; otherwise the
+    /// register and its scaled offset from `rsp` at the end of the
+    /// prolog.
+    pub frame_register: Option<(u8, u8)>,
+}
+
+/// The flag bits that occupy the high 5 bits of `UNWIND_INFO`'s first
+/// byte alongside the version number.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnwindFlags {
+    pub ehandler: bool,
+    pub uhandler: bool,
+    pub chaininfo: bool,
+}
+
+/// Sorts `.pdata` entries by [`RuntimeFunction::begin_addr`], as the
+/// Windows loader requires so it can binary-search the table, and
+/// verifies no two entries' address ranges overlap. Returns `Err` with
+/// the offending pair's indices (in the now-sorted order) if they do.
+pub fn sort_and_validate_pdata(
+    mut entries: Vec<RuntimeFunction>,
+) -> Result<Vec<RuntimeFunction>, (usize, usize)> {
+    entries.sort_by_key(|f| f.begin_addr);
+
+    for i in 1..entries.len() {
+        if entries[i - 1].end_addr > entries[i].begin_addr {
+            return Err((i - 1, i));
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rf(begin: u32, end: u32) -> RuntimeFunction {
+        RuntimeFunction {
+            begin_addr: begin,
+            end_addr: end,
+            unwind_info_addr: 0,
+        }
+    }
+
+    #[test]
+    fn sorts_by_begin_addr() {
+        let entries = vec![rf(0x100, 0x140), rf(0x10, 0x50), rf(0x50, 0x100)];
+        let sorted = sort_and_validate_pdata(entries).unwrap();
+        assert_eq!(
+            sorted.iter().map(|f| f.begin_addr).collect::<Vec<_>>(),
+            vec![0x10, 0x50, 0x100]
+        );
+    }
+
+    #[test]
+    fn detects_overlap() {
+        let entries = vec![rf(0x10, 0x60), rf(0x50, 0x100)];
+        assert_eq!(sort_and_validate_pdata(entries), Err((0, 1)));
+    }
+
+    #[test]
+    fn accepts_adjacent_ranges() {
+        let entries = vec![rf(0x10, 0x50), rf(0x50, 0x100)];
+        assert!(sort_and_validate_pdata(entries).is_ok());
+    }
+
+    #[test]
+    fn empty_is_ok() {
+        assert_eq!(sort_and_validate_pdata(Vec::new()), Ok(Vec::new()));
+    }
+}
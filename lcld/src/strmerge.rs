@@ -0,0 +1,191 @@
+//! Tail merging (suffix sharing) of NUL-terminated strings, for folding
+//! together the `.rodata.str*`-style sections `-fmerge-constants`-style
+//! compiler output produces.
+//!
+//! Unlike the plain concatenation [`crate::output::merge_sections`] does
+//! for same-named input sections in general, [`StringMerger`] looks
+//! *inside* a string table's content: two input strings that are
+//! identical, or where one is exactly the tail of the other (e.g.
+//! `"world\0"` is the tail of `"hello world\0"`), end up pointing at the
+//! same bytes in the merged output instead of each getting its own copy.
+//! This is the same transformation `SHF_MERGE | SHF_STRINGS` ELF sections
+//! ask a linker to perform; `merge_sections` drives one shared
+//! [`StringMerger`] per output section across every input section that
+//! carries both flags (see `binfmt::elf::consts::SHF_MERGE`/`SHF_STRINGS`),
+//! appending their deduplicated bytes after the plain-concatenated,
+//! non-mergeable content -- its own contiguous region within the output
+//! section, same as how a linker script keeps `SORT_BY_ALIGNMENT`-style
+//! subsections together rather than interleaving them with unrelated
+//! input.
+
+use std::collections::HashMap;
+
+/// Accumulates NUL-terminated strings into a single buffer, deduplicating
+/// exact repeats and sharing storage for any string that is exactly the
+/// tail of an already-interned, longer string.
+#[derive(Clone, Debug, Default)]
+pub struct StringMerger {
+    buf: Vec<u8>,
+    /// Every string interned so far (content including its NUL), keyed
+    /// by its own bytes, mapped to the offset in `buf` its copy starts
+    /// at -- scanned linearly by [`Self::find_tail`], since a single
+    /// string table has at most a few hundred entries and this isn't
+    /// hot-path code next to the input-parsing passes.
+    interned: HashMap<Vec<u8>, usize>,
+}
+
+impl StringMerger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interns `s` (a complete string, including its trailing NUL),
+    /// returning the offset in [`Self::finish`]'s output its bytes will
+    /// live at. Re-interning the same bytes, or bytes that are the tail
+    /// of a string already interned, returns an existing offset without
+    /// growing the buffer.
+    pub fn intern(&mut self, s: &[u8]) -> usize {
+        if let Some(&off) = self.interned.get(s) {
+            return off;
+        }
+
+        if let Some(off) = self.find_tail(s) {
+            self.interned.insert(s.to_vec(), off);
+            return off;
+        }
+
+        let off = self.buf.len();
+        self.buf.extend_from_slice(s);
+        self.interned.insert(s.to_vec(), off);
+        off
+    }
+
+    fn find_tail(&self, s: &[u8]) -> Option<usize> {
+        if s.is_empty() {
+            return None;
+        }
+        self.interned.iter().find_map(|(full, &off)| {
+            full.ends_with(s).then(|| off + (full.len() - s.len()))
+        })
+    }
+
+    /// The merged buffer every [`Self::intern`] call's return value is an
+    /// offset into.
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// The result of [`merge_section`]: the merged content every offset in
+/// `offsets` refers into, plus (in input order) the offset each original
+/// string landed at.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeResult {
+    pub content: Vec<u8>,
+    pub offsets: Vec<usize>,
+}
+
+impl MergeResult {
+    /// How many bytes merging saved versus keeping every string's own
+    /// copy -- the number this feeds into a `--stats`-style size report.
+    pub fn bytes_saved(&self, original_len: usize) -> usize {
+        original_len.saturating_sub(self.content.len())
+    }
+}
+
+/// Splits `content` into NUL-terminated strings, in order. A trailing run
+/// of bytes with no NUL is kept as its own entry (matching how a
+/// hand-written string table missing its final terminator would still
+/// want its bytes preserved).
+pub fn split_entries(content: &[u8]) -> Vec<&[u8]> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (i, &b) in content.iter().enumerate() {
+        if b == 0 {
+            pieces.push(&content[start..=i]);
+            start = i + 1;
+        }
+    }
+    if start < content.len() {
+        pieces.push(&content[start..]);
+    }
+    pieces
+}
+
+/// Splits `content` into NUL-terminated strings ([`split_entries`]) and
+/// tail-merges them with [`StringMerger`].
+///
+/// If `enabled` is `false` (the `--no-merge-strings` opt-out), every
+/// string is still split out and reported in `offsets`, but none are
+/// deduplicated or tail-shared -- `content` comes back byte-for-byte
+/// identical to the input, just like before merging existed.
+pub fn merge_section(content: &[u8], enabled: bool) -> MergeResult {
+    let pieces = split_entries(content);
+
+    if !enabled {
+        let mut offsets = Vec::with_capacity(pieces.len());
+        let mut offset = 0;
+        for piece in &pieces {
+            offsets.push(offset);
+            offset += piece.len();
+        }
+        return MergeResult {
+            content: content.to_vec(),
+            offsets,
+        };
+    }
+
+    let mut merger = StringMerger::new();
+    let offsets = pieces.iter().map(|piece| merger.intern(piece)).collect();
+
+    MergeResult {
+        content: merger.finish(),
+        offsets,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_strings_share_one_copy() {
+        let merged = merge_section(b"foo\0foo\0", true);
+        assert_eq!(merged.content, b"foo\0");
+        assert_eq!(merged.offsets, vec![0, 0]);
+    }
+
+    #[test]
+    fn suffix_strings_share_the_longer_strings_tail() {
+        let merged = merge_section(b"hello world\0world\0", true);
+        assert_eq!(merged.content, b"hello world\0");
+        assert_eq!(merged.offsets, vec![0, 6]);
+    }
+
+    #[test]
+    fn non_suffix_strings_each_get_their_own_copy() {
+        let merged = merge_section(b"foo\0bar\0", true);
+        assert_eq!(merged.content, b"foo\0bar\0");
+        assert_eq!(merged.offsets, vec![0, 4]);
+    }
+
+    #[test]
+    fn disabled_merging_reproduces_the_input_byte_for_byte() {
+        let merged = merge_section(b"hello world\0world\0", false);
+        assert_eq!(merged.content, b"hello world\0world\0");
+        assert_eq!(merged.offsets, vec![0, 12]);
+    }
+
+    #[test]
+    fn unterminated_trailing_bytes_are_kept_as_their_own_entry() {
+        let merged = merge_section(b"foo\0bar", true);
+        assert_eq!(merged.content, b"foo\0bar");
+        assert_eq!(merged.offsets, vec![0, 4]);
+    }
+
+    #[test]
+    fn bytes_saved_reports_the_merging_gain() {
+        let merged = merge_section(b"hello world\0world\0", true);
+        assert_eq!(merged.bytes_saved(18), 6);
+    }
+}
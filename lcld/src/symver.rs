@@ -0,0 +1,161 @@
+//! Symbol versioning: recognizing `.symver`-style versioned symbol
+//! names, collecting version definitions, and deciding which version
+//! every exported symbol belongs to.
+//!
+//! Like [`crate::gc`] and [`crate::dynamic`], nothing in the link
+//! driver builds the actual `.gnu.version*` sections yet --
+//! [`binfmt::elf::ElfVerdef`]/[`ElfVerdaux`](binfmt::elf::ElfVerdaux) are
+//! the on-disk layout those sections would use once something does.
+//! This module is the layer above that: [`parse_versioned_symbol`]
+//! recognizes the `name@version`/`name@@version` form a compiler emits
+//! for a `.symver` directive or this driver's own `--defsym-version`
+//! takes on the command line, and [`VersionTable`] collects every
+//! symbol's assignment (explicit or defaulted via `--default-symver`)
+//! into the flat list [`VersionTable::definitions`] that a verdef
+//! builder would walk.
+
+use std::collections::HashMap;
+
+/// Splits a versioned symbol name into its plain name and version, and
+/// whether `@@` (the *default* version symbols resolving to this name
+/// without a version suffix should bind to) or plain `@` (a
+/// non-default, explicitly-requested-only version) was used.
+///
+/// Returns `None` for a name with no `@`, which is just an ordinary,
+/// unversioned symbol.
+pub fn parse_versioned_symbol(raw: &str) -> Option<(&str, &str, bool)> {
+    let (name, rest) = raw.split_once('@')?;
+    if let Some(version) = rest.strip_prefix('@') {
+        Some((name, version, true))
+    } else {
+        Some((name, rest, false))
+    }
+}
+
+/// One version this object defines, in definition order -- the order
+/// [`VersionTable::add_definition`] first saw it, which becomes its
+/// `.gnu.version` index (starting at
+/// [`VER_NDX_GLOBAL`](binfmt::elf::consts::VER_NDX_GLOBAL) `+ 1`, since
+/// `0` and `1` are reserved).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionDef {
+    pub name: String,
+}
+
+/// Which version one symbol was assigned, and whether it's that
+/// symbol's default (the one an unversioned reference to it binds to).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolVersion {
+    pub version: String,
+    pub is_default: bool,
+}
+
+/// Every version definition and per-symbol assignment collected for one
+/// output, whether from `.symver`-tagged input symbols, `--defsym-version`,
+/// or (for everything left unassigned) `--default-symver`.
+#[derive(Clone, Debug, Default)]
+pub struct VersionTable {
+    definitions: Vec<VersionDef>,
+    symbols: HashMap<String, SymbolVersion>,
+}
+
+impl VersionTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `name` is a version this object defines, if it
+    /// isn't known already. Returns its `.gnu.version` index.
+    pub fn add_definition(&mut self, name: &str) -> u16 {
+        if let Some(pos) = self.definitions.iter().position(|d| d.name == name) {
+            return binfmt::elf::consts::VER_NDX_GLOBAL + 1 + pos as u16;
+        }
+        self.definitions.push(VersionDef {
+            name: name.to_string(),
+        });
+        binfmt::elf::consts::VER_NDX_GLOBAL + self.definitions.len() as u16
+    }
+
+    /// Assigns `symbol` to `version`, implicitly defining `version` if
+    /// this is the first symbol to mention it. A later call for the
+    /// same symbol overrides an earlier one -- the same last-one-wins
+    /// rule [`crate::driver::ld`] already applies to `--defsym`.
+    pub fn set_version(&mut self, symbol: &str, version: &str, is_default: bool) {
+        self.add_definition(version);
+        self.symbols.insert(
+            symbol.to_string(),
+            SymbolVersion {
+                version: version.to_string(),
+                is_default,
+            },
+        );
+    }
+
+    pub fn version_of(&self, symbol: &str) -> Option<&SymbolVersion> {
+        self.symbols.get(symbol)
+    }
+
+    /// Assigns `default_version` to every symbol in `exported_symbols`
+    /// that doesn't already have a version -- what `--default-symver`
+    /// does, normally with the output's `-soname` as `default_version`.
+    pub fn apply_default(&mut self, exported_symbols: &[String], default_version: &str) {
+        for symbol in exported_symbols {
+            if !self.symbols.contains_key(symbol) {
+                self.set_version(symbol, default_version, true);
+            }
+        }
+    }
+
+    /// Every version this object defines, in `.gnu.version` index order
+    /// starting at [`VER_NDX_GLOBAL`](binfmt::elf::consts::VER_NDX_GLOBAL) `+ 1`.
+    pub fn definitions(&self) -> &[VersionDef] {
+        &self.definitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_version_form_is_recognized() {
+        assert_eq!(
+            parse_versioned_symbol("foo@@VERS_2.0"),
+            Some(("foo", "VERS_2.0", true))
+        );
+    }
+
+    #[test]
+    fn non_default_version_form_is_recognized() {
+        assert_eq!(
+            parse_versioned_symbol("foo@VERS_1.0"),
+            Some(("foo", "VERS_1.0", false))
+        );
+    }
+
+    #[test]
+    fn unversioned_name_is_not_recognized() {
+        assert_eq!(parse_versioned_symbol("foo"), None);
+    }
+
+    #[test]
+    fn definitions_are_assigned_indices_in_first_seen_order() {
+        let mut table = VersionTable::new();
+        assert_eq!(table.add_definition("VERS_1.0"), 2);
+        assert_eq!(table.add_definition("VERS_2.0"), 3);
+        assert_eq!(table.add_definition("VERS_1.0"), 2);
+    }
+
+    #[test]
+    fn default_symver_only_covers_unversioned_symbols() {
+        let mut table = VersionTable::new();
+        table.set_version("explicit", "VERS_1.0", false);
+        table.apply_default(
+            &["explicit".to_string(), "implicit".to_string()],
+            "liba.so.1",
+        );
+
+        assert_eq!(table.version_of("explicit").unwrap().version, "VERS_1.0");
+        assert_eq!(table.version_of("implicit").unwrap().version, "liba.so.1");
+    }
+}
@@ -20,6 +20,13 @@ pub struct TargetInfo<'a> {
     pub output_dynsuffix: &'a str,
     pub default_output: OutputType,
     pub need_dylib_link: bool,
+    /// Whether [`crate::arrays::convert_legacy_arrays`] should fold a
+    /// legacy `.ctors`/`.dtors` input section into the output's
+    /// `.init_array`/`.fini_array` instead of keeping it as its own
+    /// `.ctors`/`.dtors` output section. Targets whose runtime still
+    /// expects `.ctors`/`.dtors` directly (some freestanding/embedded
+    /// setups) should set this to `false`.
+    pub convert_legacy_ctors: bool,
 }
 
 macro_rules! construct_cfg{
@@ -44,4 +51,5 @@ pub static ELF_TARG: TargetInfo = TargetInfo {
     output_dynsuffix: ".so",
     default_output: OutputType::PieExecutable,
     need_dylib_link: false,
+    convert_legacy_ctors: true,
 };
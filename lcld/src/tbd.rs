@@ -0,0 +1,132 @@
+//! Parsing for Apple's `.tbd` "text-based stub" files: a YAML document
+//! describing a dylib's linking-relevant surface (install name and
+//! exported symbols) without shipping the dylib itself, as found all
+//! over the macOS/iOS SDKs in place of the real `libSystem.B.dylib` etc.
+//!
+//! This only understands the subset of YAML `.tbd` files actually use --
+//! top-level `key: value` pairs and `key: [ a, b, c ]` inline lists,
+//! optionally wrapped over several lines -- not YAML in general.
+
+use std::fmt;
+
+/// A parsed `.tbd` stub: the dylib's install name and every symbol it
+/// exports (directly or via `reexported-symbols`), across all `exports`
+/// entries in the document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TbdFile {
+    pub install_name: Option<String>,
+    pub exported_symbols: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TbdParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TbdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TbdParseError {}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(quote).and_then(|s| s.strip_suffix(quote)) {
+            return inner.to_string();
+        }
+    }
+    s.to_string()
+}
+
+/// Collects an inline `[ a, b, c ]` list starting at `first` (the text
+/// following the list's `key:`), reading further lines from `lines` if
+/// the closing `]` hasn't appeared yet -- `.tbd` symbol lists routinely
+/// wrap across many lines.
+fn collect_bracket_list<'a>(
+    first: &str,
+    line_no: usize,
+    lines: &mut std::iter::Peekable<impl Iterator<Item = (usize, &'a str)>>,
+) -> Result<Vec<String>, TbdParseError> {
+    let mut buf = first.trim().to_string();
+    let mut last_line = line_no;
+    while !buf.contains(']') {
+        match lines.next() {
+            Some((n, l)) => {
+                last_line = n;
+                buf.push(' ');
+                buf.push_str(l.trim());
+            }
+            None => {
+                return Err(TbdParseError {
+                    line: last_line,
+                    message: "unterminated list, expected `]`".to_string(),
+                })
+            }
+        }
+    }
+
+    let start = buf.find('[').ok_or_else(|| TbdParseError {
+        line: line_no,
+        message: "expected `[` to start a list".to_string(),
+    })?;
+    let end = buf.find(']').unwrap();
+
+    Ok(buf[start + 1..end]
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(unquote)
+        .collect())
+}
+
+/// Parses a `.tbd` file's install name and exported symbols.
+///
+/// Only the document's top-level `exports`/`reexports`/`re-exports`
+/// sections are consulted for symbols -- `undefineds` (symbols the dylib
+/// itself requires, not provides) is parsed past but ignored, since it
+/// isn't relevant to resolving references *against* this dylib.
+pub fn parse_tbd(content: &str) -> Result<TbdFile, TbdParseError> {
+    let mut file = TbdFile::default();
+    let mut in_exports_section = false;
+
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l))
+        .peekable();
+
+    while let Some((line_no, raw_line)) = lines.next() {
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("---") || trimmed == "..." {
+            continue;
+        }
+        let indent = raw_line.len() - raw_line.trim_start().len();
+
+        let body = trimmed.strip_prefix("- ").unwrap_or(trimmed);
+
+        let Some((key, value)) = body.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim();
+
+        if indent == 0 {
+            in_exports_section = matches!(key, "exports" | "reexports" | "re-exports");
+            if key == "install-name" && !value.is_empty() {
+                file.install_name = Some(unquote(value));
+            }
+            continue;
+        }
+
+        if in_exports_section && key == "symbols" && !value.is_empty() {
+            let symbols = collect_bracket_list(value, line_no, &mut lines)?;
+            file.exported_symbols.extend(symbols);
+        }
+    }
+
+    Ok(file)
+}
@@ -0,0 +1,236 @@
+//! Windows-specific link inputs: module-definition (`.def`) files and the
+//! short import library format `link.exe` produces for a DLL's imports.
+//!
+//! An import library is just an ordinary [`binfmt::ar::Archive`] whose
+//! members are the "short" import format from the PE/COFF spec -- a tiny
+//! fixed header plus the exported symbol's name and the DLL it comes
+//! from, with no actual code or data, letting the linker resolve a
+//! reference to an imported symbol without ever reading the DLL itself.
+
+use std::fmt;
+use std::io::Write;
+
+use binfmt::ar::Archive;
+
+/// One `EXPORTS` entry of a parsed `.def` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExportEntry {
+    pub name: String,
+    /// The symbol actually defined in the DLL, when `EXPORTS` renames it
+    /// with `exported_name=internal_name`.
+    pub internal_name: Option<String>,
+    pub ordinal: Option<u16>,
+    /// `NONAME`: export by ordinal only, omitting the name from the
+    /// export table. Requires `ordinal`.
+    pub no_name: bool,
+    /// `DATA`: this export is a data symbol, not a function.
+    pub data: bool,
+}
+
+/// The result of parsing a `.def` file: the `LIBRARY` name (if given) and
+/// every `EXPORTS` entry, in file order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleDefinition {
+    pub library: Option<String>,
+    pub exports: Vec<ExportEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DefParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DefParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for DefParseError {}
+
+/// Parses a module-definition file's `LIBRARY` and `EXPORTS` directives.
+///
+/// Only the subset of the `.def` grammar that affects linking is
+/// recognized: `SECTIONS`, `HEAPSIZE`, `STACKSIZE`, and the like (which
+/// only matter to the final PE header) are not parsed, since nothing
+/// here writes a PE file yet. `;` starts a line comment, as in a real
+/// `.def` file.
+pub fn parse_def_file(content: &str) -> Result<ModuleDefinition, DefParseError> {
+    let mut def = ModuleDefinition::default();
+    let mut in_exports = false;
+
+    for (i, raw_line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let line = raw_line.split(';').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("LIBRARY") {
+            in_exports = false;
+            let name = rest.trim().trim_matches('"');
+            if name.is_empty() {
+                return Err(DefParseError {
+                    line: line_no,
+                    message: "LIBRARY requires a name".to_string(),
+                });
+            }
+            def.library = Some(name.to_string());
+        } else if line.eq_ignore_ascii_case("EXPORTS") {
+            in_exports = true;
+        } else if in_exports {
+            def.exports.push(parse_export_entry(line_no, line)?);
+        } else {
+            return Err(DefParseError {
+                line: line_no,
+                message: format!("unrecognized directive `{}`", line),
+            });
+        }
+    }
+
+    Ok(def)
+}
+
+fn parse_export_entry(line_no: usize, line: &str) -> Result<ExportEntry, DefParseError> {
+    let mut tokens = line.split_whitespace();
+    let first = tokens.next().ok_or_else(|| DefParseError {
+        line: line_no,
+        message: "empty EXPORTS entry".to_string(),
+    })?;
+    let (name, internal_name) = match first.split_once('=') {
+        Some((name, internal)) => (name.to_string(), Some(internal.to_string())),
+        None => (first.to_string(), None),
+    };
+
+    let mut ordinal = None;
+    let mut no_name = false;
+    let mut data = false;
+    for tok in tokens {
+        if let Some(n) = tok.strip_prefix('@') {
+            ordinal = Some(n.parse::<u16>().map_err(|_| DefParseError {
+                line: line_no,
+                message: format!("invalid ordinal `{}`", tok),
+            })?);
+        } else if tok.eq_ignore_ascii_case("NONAME") {
+            no_name = true;
+        } else if tok.eq_ignore_ascii_case("DATA") {
+            data = true;
+        } else if tok.eq_ignore_ascii_case("PRIVATE") || tok.eq_ignore_ascii_case("CONSTANT") {
+            // Accepted but not yet distinguished -- neither affects which
+            // symbols get imported, only the final export table's flags.
+        } else {
+            return Err(DefParseError {
+                line: line_no,
+                message: format!("unrecognized EXPORTS attribute `{}`", tok),
+            });
+        }
+    }
+
+    if no_name && ordinal.is_none() {
+        return Err(DefParseError {
+            line: line_no,
+            message: "NONAME requires an ordinal".to_string(),
+        });
+    }
+
+    Ok(ExportEntry {
+        name,
+        internal_name,
+        ordinal,
+        no_name,
+        data,
+    })
+}
+
+const IMPORT_OBJECT_HDR_SIG1: u16 = 0; // IMAGE_FILE_MACHINE_UNKNOWN
+const IMPORT_OBJECT_HDR_SIG2: u16 = 0xFFFF;
+
+/// `Type` field of `IMPORT_OBJECT_HEADER.TypeNameType`.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ImportType {
+    Code,
+    Data,
+    Const,
+}
+
+/// `NameType` field of `IMPORT_OBJECT_HEADER.TypeNameType`: how the
+/// import's name relates to the symbol actually defined in the DLL.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ImportNameType {
+    /// The import name is the DLL's exported name, verbatim.
+    Name,
+    /// The import name is the exported name with any `_`/`@N` decoration
+    /// stripped.
+    NoPrefix,
+    /// The import name is the exported name with any leading `_` and
+    /// trailing `@N` stdcall decoration stripped.
+    Undecorate,
+}
+
+/// Builds the bytes of one "short" import object member -- an
+/// `IMPORT_OBJECT_HEADER` plus the two null-terminated name strings that
+/// follow it (export symbol, then DLL name) -- for `entry` imported from
+/// `dll_name`, on `machine` (an `IMAGE_FILE_MACHINE_*` constant).
+pub fn build_short_import_member(
+    dll_name: &str,
+    entry: &ExportEntry,
+    machine: u16,
+    import_type: ImportType,
+    name_type: ImportNameType,
+) -> Vec<u8> {
+    let symbol_name = entry.internal_name.as_deref().unwrap_or(&entry.name);
+
+    let mut data = Vec::new();
+    data.extend_from_slice(symbol_name.as_bytes());
+    data.push(0);
+    data.extend_from_slice(dll_name.as_bytes());
+    data.push(0);
+
+    let type_name_type = (import_type as u16 & 0x3)
+        | ((name_type as u16 & 0x7) << 2);
+
+    let mut bytes = Vec::with_capacity(20 + data.len());
+    bytes.extend_from_slice(&IMPORT_OBJECT_HDR_SIG1.to_le_bytes());
+    bytes.extend_from_slice(&IMPORT_OBJECT_HDR_SIG2.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // Version
+    bytes.extend_from_slice(&machine.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // TimeDateStamp
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes()); // SizeOfData
+    bytes.extend_from_slice(&entry.ordinal.unwrap_or(0).to_le_bytes()); // OrdinalOrHint
+    bytes.extend_from_slice(&type_name_type.to_le_bytes());
+    bytes.extend_from_slice(&data);
+
+    bytes
+}
+
+/// Builds a full import library for `dll_name`'s `exports`, as `link.exe`
+/// would for a `/DEF:` or `/IMPLIB:` build: one short import object member
+/// per export.
+///
+/// Exports with `DATA` get [`ImportType::Data`]; everything else is
+/// assumed to be a function and gets [`ImportType::Code`]. Every member
+/// uses [`ImportNameType::Name`] -- decorated-name stripping is something
+/// the consuming linker would otherwise need to guess at, so this always
+/// preserves the name exactly as written in the `.def` file.
+pub fn build_import_library(dll_name: &str, exports: &[ExportEntry], machine: u16) -> Archive {
+    let mut archive = Archive::new();
+
+    for entry in exports {
+        let import_type = if entry.data {
+            ImportType::Data
+        } else {
+            ImportType::Code
+        };
+        let bytes =
+            build_short_import_member(dll_name, entry, machine, import_type, ImportNameType::Name);
+
+        let member = archive.new_member();
+        member.set_name(dll_name);
+        member
+            .write_all(&bytes)
+            .expect("writing to an in-memory ArchiveMember cannot fail");
+    }
+
+    archive
+}
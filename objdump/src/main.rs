@@ -1,7 +1,455 @@
+#[cfg(not(feature = "mmap"))]
 use std::fs::File;
 
+use std::cell::{Cell, RefCell};
+use std::fmt::Write as _;
+
+use arch_ops::disasm::OpcodePrinter;
+use binfmt::disasm::SectionReader;
+use binfmt::fmt::{Binfmt, BinaryFile, Section, SectionFlag, SectionFlags};
+use binfmt::howto::{Reloc, RelocCode};
+use binfmt::sym::Symbol;
+use dbg_info::dwarf5::{parse_all_line_number_programs, LineRow};
 use target_tuples::Target;
 
+/// The number of bytes a relocation of `code` overwrites, used to mark
+/// which hexdump bytes it covers. Codes that don't carry a width (the
+/// architecture-specific relaxable forms) default to 1 byte; that's a
+/// known underestimate for some of them, but there's no generic way to
+/// recover their true width without each arch's `HowTo::reloc_size`,
+/// which isn't implemented for every format yet.
+fn reloc_span_len(code: &RelocCode) -> usize {
+    match *code {
+        RelocCode::Abs { addr_width }
+        | RelocCode::BaseRel { addr_width }
+        | RelocCode::Rel { addr_width }
+        | RelocCode::AbsShifted { addr_width, .. }
+        | RelocCode::RelShifted { addr_width, .. }
+        | RelocCode::Got { addr_width }
+        | RelocCode::RelGot {
+            addr_wdith: addr_width,
+        }
+        | RelocCode::Plt { addr_width }
+        | RelocCode::RelPlt { addr_width }
+        | RelocCode::DtpRel { addr_width } => (addr_width / 8).max(1),
+        RelocCode::DynSymEntry { width } => (width / 8).max(1),
+        _ => 1,
+    }
+}
+
+/// `bytes` as an ASCII rendering, one character per byte: the byte itself
+/// if it's a printable ASCII character or a space, else `.` -- the
+/// right-hand column of a `-s`/`--full-contents` hexdump line.
+fn format_ascii_column(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+        .collect()
+}
+
+/// Prints `sec`'s content as a hexdump, annotating each byte covered by a
+/// relocation with `[`/`]` around the covered span and listing every
+/// relocation's target symbol below the line it starts on -- useful for
+/// checking that an encoder/`HowTo` pair left the relocated bytes exactly
+/// where and as wide as it should have. Ends each line with the same bytes'
+/// ASCII rendering (see [`format_ascii_column`]), as `-s`/`--full-contents`
+/// in a real `objdump` does.
+fn print_reloc_annotated_hexdump(sec: &Section) {
+    const BYTES_PER_LINE: usize = 16;
+
+    let mut covered = vec![false; sec.content.len()];
+    for reloc in &sec.relocs {
+        let start = reloc.offset as usize;
+        let len = reloc_span_len(&reloc.code);
+        for b in covered.iter_mut().skip(start).take(len) {
+            *b = true;
+        }
+    }
+
+    for (line_no, chunk) in sec.content.chunks(BYTES_PER_LINE).enumerate() {
+        let base = line_no * BYTES_PER_LINE;
+        print!("{:08x}  ", base);
+        for (i, byte) in chunk.iter().enumerate() {
+            let marked = covered[base + i];
+            print!("{}{:02x}{}", if marked { "[" } else { " " }, byte, if marked { "]" } else { " " });
+        }
+        for _ in chunk.len()..BYTES_PER_LINE {
+            print!("    ");
+        }
+        println!(" {}", format_ascii_column(chunk));
+
+        for reloc in &sec.relocs {
+            let offset = reloc.offset as usize;
+            if offset >= base && offset < base + BYTES_PER_LINE {
+                println!(
+                    "             ^ +{:#x}: {:?} against `{}`",
+                    offset - base,
+                    reloc.code,
+                    reloc.symbol
+                );
+            }
+        }
+    }
+}
+
+/// Groups `bytes` into `insn_width`-byte chunks (a trailing short chunk is
+/// kept as-is), formatting each chunk as one run of hex digits -- ordering
+/// the bytes within a chunk most-significant-first if `little_endian`,
+/// since that's how a little-endian target's raw instruction word reads,
+/// or left in file order otherwise. This is `--insn-width`/byte-order's
+/// display logic for [`print_insn_bytes_dump`] below, the fallback used
+/// for a format with no [`Binfmt::disassembler`]: a grouped raw-bytes
+/// view, not mnemonics.
+fn format_insn_bytes(bytes: &[u8], insn_width: usize, little_endian: bool) -> String {
+    let insn_width = insn_width.max(1);
+    let mut out = String::new();
+    let mut sep = "";
+    for chunk in bytes.chunks(insn_width) {
+        out.push_str(sep);
+        sep = " ";
+        if little_endian {
+            for b in chunk.iter().rev() {
+                out.push_str(&format!("{:02x}", b));
+            }
+        } else {
+            for b in chunk {
+                out.push_str(&format!("{:02x}", b));
+            }
+        }
+    }
+    out
+}
+
+/// Prints `sec`'s content `BYTES_PER_LINE` bytes at a time, each line's
+/// bytes grouped and ordered by [`format_insn_bytes`] -- `--insn-width`
+/// sized groups, in target byte order. If `show_raw_insn` is `false`, the
+/// bytes column is omitted entirely, as `--no-show-raw-insn` does in a
+/// real disassembly listing once mnemonics are there to show instead; here
+/// that just leaves the offset column; see [`format_insn_bytes`] for why.
+fn print_insn_bytes_dump(sec: &Section, insn_width: usize, little_endian: bool, show_raw_insn: bool) {
+    const BYTES_PER_LINE: usize = 16;
+
+    for (line_no, chunk) in sec.content.chunks(BYTES_PER_LINE).enumerate() {
+        let base = line_no * BYTES_PER_LINE;
+        if show_raw_insn {
+            println!("{:08x}  {}", base, format_insn_bytes(chunk, insn_width, little_endian));
+        } else {
+            println!("{:08x}", base);
+        }
+    }
+}
+
+/// Whether `sec` is marked executable -- what `-d` (as opposed to `-D`)
+/// restricts disassembly to, matching a real `objdump`'s distinction
+/// between "sections expected to contain instructions" and "all
+/// sections".
+fn section_is_executable(sec: &Section) -> bool {
+    sec.flags
+        .map(|flags| flags.into_iter().any(|f| f == SectionFlag::Executable))
+        .unwrap_or(false)
+}
+
+/// Displays one decoded instruction by driving `printer` over `reader`,
+/// borrowed mutably for the duration of a single [`core::fmt::Display`]
+/// call -- the only way to get at a live `&mut core::fmt::Formatter` to
+/// hand [`OpcodePrinter::print_opcode`] is from inside a `Display` (or
+/// `Debug`) impl, so this exists purely as the adapter between that and
+/// the `&mut dyn InsnRead` the printer actually wants.
+struct InsnDisplay<'p, 'r, 's> {
+    printer: &'p dyn OpcodePrinter,
+    reader: RefCell<&'r mut SectionReader<'s>>,
+}
+
+impl core::fmt::Display for InsnDisplay<'_, '_, '_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut reader = self.reader.borrow_mut();
+        self.printer
+            .print_opcode(f, &mut **reader)
+            .map_err(|_| core::fmt::Error)
+    }
+}
+
+/// Displays [`OpcodePrinter::resync`]'s `.byte` fallback for the
+/// undecodable instruction at `pos`, stashing how many bytes it said to
+/// skip in `advance` for the caller to read back afterward -- `resync`
+/// itself only has a `Formatter` to report through, same as
+/// [`InsnDisplay`] above.
+struct ResyncDisplay<'a> {
+    printer: &'a dyn OpcodePrinter,
+    bytes: &'a [u8],
+    pos: usize,
+    advance: Cell<usize>,
+}
+
+impl core::fmt::Display for ResyncDisplay<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.printer.resync(f, self.bytes, self.pos) {
+            Ok(n) => {
+                self.advance.set(n);
+                Ok(())
+            }
+            Err(_) => Err(core::fmt::Error),
+        }
+    }
+}
+
+/// The symbols defined in section `sec_idx` of `file`, keyed by their
+/// offset within it -- what [`print_disassembly`] consults to interleave
+/// `<name>:` label lines into a listing, the same way a real `objdump`
+/// marks where each function starts.
+fn section_labels<'a>(file: &'a BinaryFile<'a>, sec_idx: usize) -> Vec<(u128, &'a str)> {
+    file.symbols()
+        .filter(|s| s.section() == Some(sec_idx as u32))
+        .filter_map(|s| s.value().map(|addr| (addr, s.name())))
+        .collect()
+}
+
+/// Every [`LineRow`] from every compilation unit packed into `file`'s
+/// `.debug_line` section (if it has one), flattened and sorted by
+/// [`LineRow::address`] so [`lookup_line`] can binary-search it -- the
+/// same "address" convention [`section_labels`] already relies on
+/// (a symbol's recorded value and a disassembled instruction's offset into
+/// its section live in the same space, true for a relocatable object file
+/// where `sh_addr` is 0), so this line table lines up against disassembly
+/// offsets the same way those labels do.
+fn build_line_table(file: &BinaryFile) -> Vec<LineRow> {
+    let Some(sec) = file.sections().find(|sec| sec.name == ".debug_line") else {
+        return Vec::new();
+    };
+
+    let Ok(programs) = parse_all_line_number_programs(&sec.content) else {
+        return Vec::new();
+    };
+
+    let mut rows: Vec<LineRow> = programs.into_values().flatten().collect();
+    rows.sort_by_key(|row| row.address);
+    rows
+}
+
+/// The row covering `addr`: the last row at or before it, unless that row
+/// is an `end_sequence` marker (meaning `addr` falls after the sequence it
+/// closed, so nothing in `table` actually covers it).
+fn lookup_line(table: &[LineRow], addr: u64) -> Option<&LineRow> {
+    let idx = table.partition_point(|row| row.address <= addr);
+    if idx == 0 {
+        return None;
+    }
+    let row = &table[idx - 1];
+    (!row.end_sequence).then_some(row)
+}
+
+/// Disassembles `sec` with `printer`, the [`Binfmt::disassembler`] for
+/// `fmt` -- one line per instruction, each prefixed with its offset into
+/// `sec.content`. A `printer` that fails to decode the bytes at some
+/// offset (an invalid opcode, or data embedded in the section) falls
+/// back to [`OpcodePrinter::resync`]'s `.byte` pseudo-op and keeps going,
+/// rather than aborting the rest of the section.
+///
+/// Before the instruction at a symbol's address, prints that symbol as a
+/// `<name>:` label (per `labels`, from [`section_labels`]). After an
+/// instruction, prints every relocation covering its bytes -- branch and
+/// call operands that read through a [`binfmt::disasm::SectionReader`]
+/// already resolve to `symbol+offset` inline (see that module's doc
+/// comment), so this is mostly relevant to relocations the decoder
+/// itself doesn't read as part of the encoding it recognized.
+///
+/// `-S`'s source interleaving (`lines`, from [`build_line_table`]) is
+/// approximated as a `; file #n, line n` annotation printed whenever the
+/// covering [`LineRow`] changes, rather than actual source text: the
+/// `.debug_line` parser this draws on deliberately doesn't resolve file
+/// names (they're `DW_FORM_line_strp`/`DW_FORM_strp` offsets into string
+/// sections it isn't given -- see [`dbg_info::dwarf5`]'s module doc), so
+/// there's no path to open and no text to interleave, only the file table
+/// index the real path would have lived at.
+fn print_disassembly(
+    sec: &Section,
+    fmt: &dyn Binfmt,
+    printer: &dyn OpcodePrinter,
+    labels: &[(u128, &str)],
+    lines: &[LineRow],
+) {
+    let mut reader = SectionReader::new(sec, fmt);
+    let len = sec.content.len();
+    let mut last_line: Option<&LineRow> = None;
+
+    while reader.position() < len {
+        let pos = reader.position();
+
+        for &(addr, name) in labels {
+            if addr == pos as u128 {
+                println!("{:x} <{}>:", pos, name);
+            }
+        }
+
+        if let Some(row) = lookup_line(lines, pos as u64) {
+            if last_line.map(|l| (l.file, l.line)) != Some((row.file, row.line)) {
+                println!("; file #{}, line {}", row.file, row.line);
+                last_line = Some(row);
+            }
+        }
+
+        let mut text = String::new();
+        let decoded = {
+            let insn = InsnDisplay {
+                printer,
+                reader: RefCell::new(&mut reader),
+            };
+            write!(&mut text, "{}", insn).is_ok()
+        };
+
+        let next_pos = if decoded {
+            println!("{:8x}:\t{}", pos, text);
+            reader.position()
+        } else {
+            reader.seek_to(pos);
+
+            let resync = ResyncDisplay {
+                printer,
+                bytes: &sec.content,
+                pos,
+                advance: Cell::new(0),
+            };
+            text.clear();
+            write!(&mut text, "{}", resync).expect("OpcodePrinter::resync should not fail");
+            println!("{:8x}:\t{}", pos, text);
+
+            let next_pos = pos + resync.advance.get().max(1);
+            reader.seek_to(next_pos);
+            next_pos
+        };
+
+        for reloc in &sec.relocs {
+            let offset = reloc.offset as usize;
+            if offset >= pos && offset < next_pos {
+                println!("         \t\t{:?} against `{}`", reloc.code, reloc.symbol);
+            }
+        }
+    }
+}
+
+/// Prints one line per `reloc`, in `readelf -r`-style columns: its offset,
+/// the name of the [`binfmt::howto::HowTo`] `fmt` resolves its
+/// [`RelocCode`] to (falling back to the code's `Debug` form for a
+/// format/code pair nothing recognizes), the symbol it's against, and its
+/// addend if it has one.
+///
+/// Note this only ever has something to print for a [`BinaryFile`] whose
+/// [`Section::relocs`]/[`BinaryFile::relocs`] are already populated --
+/// true for one freshly assembled in-memory, but for ELF specifically,
+/// `read_file` doesn't yet parse `SHT_REL`/`SHT_RELA` sections back into
+/// either table, so re-reading an ELF object from disk won't show its
+/// relocations here even though they're present in the file.
+fn print_relocs<'a>(fmt: &dyn Binfmt, relocs: impl Iterator<Item = &'a Reloc>) {
+    println!("OFFSET           TYPE              VALUE");
+    for reloc in relocs {
+        let ty = fmt
+            .code_to_howto(reloc.code)
+            .map(|howto| howto.name().to_string())
+            .unwrap_or_else(|| format!("{:?}", reloc.code));
+
+        match reloc.addend {
+            Some(addend) if addend < 0 => {
+                println!("{:016x} {:17} {}-{:#x}", reloc.offset, ty, reloc.symbol, -addend)
+            }
+            Some(addend) => {
+                println!("{:016x} {:17} {}+{:#x}", reloc.offset, ty, reloc.symbol, addend)
+            }
+            None => println!("{:016x} {:17} {}", reloc.offset, ty, reloc.symbol),
+        }
+    }
+}
+
+/// `sec.flags` as a `readelf`-style letter code: `W`rite, `A`lloc,
+/// e`X`ecute, and `+<bits>` for any [`SectionFlag::FormatSpecific`] bits a
+/// particular format defines beyond those three. `None` (a section with
+/// no flags recorded at all, as opposed to one whose flags are all unset)
+/// prints as `-`.
+fn format_section_flags(flags: Option<SectionFlags>) -> String {
+    let Some(flags) = flags else {
+        return "-".to_string();
+    };
+
+    let mut out = String::new();
+    for flag in flags {
+        match flag {
+            SectionFlag::Writable => out.push('W'),
+            SectionFlag::Alloc => out.push('A'),
+            SectionFlag::Executable => out.push('X'),
+            SectionFlag::FormatSpecific(bits) => {
+                write!(&mut out, "+{:#x}", bits).unwrap();
+            }
+            _ => write!(&mut out, "?").unwrap(),
+        }
+    }
+
+    if out.is_empty() {
+        out.push('-');
+    }
+
+    out
+}
+
+/// Prints every section of `file` with its index, name, size, alignment
+/// and decoded flags -- `-h`/`--section-headers`'s listing, and also what
+/// used to be printed unconditionally before this flag existed.
+fn print_section_headers(file: &BinaryFile) {
+    println!("Sections:");
+    println!("Idx Name                 Size       Align    Flags");
+    for (idx, sec) in file.sections().enumerate() {
+        println!(
+            "{:<3} {:<20} {:<10} {:<8} {}",
+            idx,
+            sec.name,
+            sec.content.len(),
+            sec.align,
+            format_section_flags(sec.flags)
+        );
+    }
+}
+
+/// The name of the section `idx` refers to (per [`binfmt::sym::Symbol::section`]'s
+/// 0-based indexing into `file.sections()`), or `*UND*` for an undefined
+/// symbol (`idx` is `None`).
+fn symbol_section_name<'a>(file: &'a BinaryFile<'a>, idx: Option<u32>) -> &'a str {
+    idx.and_then(|idx| file.sections().nth(idx as usize))
+        .map(|sec| sec.name.as_str())
+        .unwrap_or("*UND*")
+}
+
+/// Prints `syms` in `readelf -s`-style columns: value, size, type, bind,
+/// defining section and name.
+fn print_symtab<'a>(file: &'a BinaryFile<'a>, syms: impl Iterator<Item = &'a Symbol>) {
+    println!("VALUE            SIZE     TYPE     BIND     SECTION          NAME");
+    for sym in syms {
+        let value = sym
+            .value()
+            .map(|v| format!("{:016x}", v))
+            .unwrap_or_else(|| "*UND*".to_string());
+        println!(
+            "{:<16} {:<8} {:<8?} {:<8?} {:<16} {}",
+            value,
+            sym.size().unwrap_or(0),
+            sym.symbol_type(),
+            sym.kind(),
+            symbol_section_name(file, sym.section()),
+            sym.name()
+        );
+    }
+}
+
+/// Parses `--byte-order`'s `le`/`be` argument, exiting with a diagnostic
+/// on anything else.
+fn parse_byte_order(prg_name: &str, val: &str) -> bool {
+    match val {
+        "le" => true,
+        "be" => false,
+        _ => {
+            eprintln!("{}: Invalid value for --byte-order: `{}` (expected `le` or `be`)", prg_name, val);
+            std::process::exit(1)
+        }
+    }
+}
+
 fn main() {
     let mut args = std::env::args();
 
@@ -11,9 +459,102 @@ fn main() {
 
     let mut input_file = None::<String>;
 
+    let mut reloc_hexdump = false;
+    let mut disassemble = false;
+    let mut disassemble_all = false;
+    let mut print_static_relocs = false;
+    let mut print_dynamic_relocs = false;
+    let mut print_section_hdrs = false;
+    let mut print_symbols = false;
+    let mut print_dynamic_symbols = false;
+    let mut section_filter = None::<String>;
+    let mut interleave_source = false;
+    let mut print_symbol_lines = false;
+    let mut insn_width = 4usize;
+    let mut little_endian = true;
+    let mut show_raw_insn = true;
+
     #[allow(clippy::never_loop)] // We need to handle more options than `--version` and `--help`
     while let Some(arg) = args.next() {
         match &*arg {
+            "-s" | "--full-contents" => {
+                reloc_hexdump = true;
+            }
+            "-j" | "--section" => {
+                let val = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a section name after -j", prg_name);
+                    std::process::exit(1)
+                });
+                section_filter = Some(val);
+            }
+            x if x.starts_with("-j=") => {
+                section_filter = Some(x["-j=".len()..].to_string());
+            }
+            x if x.starts_with("--section=") => {
+                section_filter = Some(x["--section=".len()..].to_string());
+            }
+            "-d" | "--disassemble" => {
+                disassemble = true;
+            }
+            "-D" | "--disassemble-all" => {
+                disassemble = true;
+                disassemble_all = true;
+            }
+            "-r" | "--reloc" => {
+                print_static_relocs = true;
+            }
+            "-R" | "--dynamic-reloc" => {
+                print_dynamic_relocs = true;
+            }
+            "-h" | "--section-headers" => {
+                print_section_hdrs = true;
+            }
+            "-t" | "--syms" => {
+                print_symbols = true;
+            }
+            "-T" | "--dynamic-syms" => {
+                print_dynamic_symbols = true;
+            }
+            "-S" | "--source" => {
+                disassemble = true;
+                interleave_source = true;
+            }
+            "-l" | "--line-numbers" => {
+                print_symbol_lines = true;
+            }
+            "--insn-width" => {
+                let val = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected a byte count after --insn-width", prg_name);
+                    std::process::exit(1)
+                });
+                insn_width = val.parse().unwrap_or_else(|_| {
+                    eprintln!("{}: Invalid value for --insn-width: `{}`", prg_name, val);
+                    std::process::exit(1)
+                });
+            }
+            x if x.starts_with("--insn-width=") => {
+                let val = &x["--insn-width=".len()..];
+                insn_width = val.parse().unwrap_or_else(|_| {
+                    eprintln!("{}: Invalid value for --insn-width: `{}`", prg_name, val);
+                    std::process::exit(1)
+                });
+            }
+            "--byte-order" => {
+                let val = args.next().unwrap_or_else(|| {
+                    eprintln!("{}: Expected `le` or `be` after --byte-order", prg_name);
+                    std::process::exit(1)
+                });
+                little_endian = parse_byte_order(&prg_name, &val);
+            }
+            x if x.starts_with("--byte-order=") => {
+                little_endian = parse_byte_order(&prg_name, &x["--byte-order=".len()..]);
+            }
+            "--show-raw-insn" => {
+                show_raw_insn = true;
+            }
+            "--no-show-raw-insn" => {
+                show_raw_insn = false;
+            }
             "--version" => {
                 eprintln!("objdump (lc-binutils {})", std::env!("CARGO_PKG_VERSION"));
                 eprintln!("Copyright (c) 2022 Lightning Creations");
@@ -40,6 +581,48 @@ fn main() {
                 eprintln!(
                     "\t--input-fmt <binfmt>: Specify the input object format (default detected)",
                 );
+                eprintln!(
+                    "\t-s, --full-contents: Dump section contents as hex and ASCII, annotated with the relocations covering each byte",
+                );
+                eprintln!(
+                    "\t-j <section>, --section <section>: Restrict -s/-d/-D to the named section",
+                );
+                eprintln!(
+                    "\t-d, --disassemble: Disassemble executable sections (or dump their contents grouped into --insn-width-sized words, if the target has no decoder yet)",
+                );
+                eprintln!(
+                    "\t-D, --disassemble-all: Like -d, but for every section, not just executable ones",
+                );
+                eprintln!(
+                    "\t-r, --reloc: Print the static relocations recorded against each section",
+                );
+                eprintln!(
+                    "\t-R, --dynamic-reloc: Print the file's dynamic relocations",
+                );
+                eprintln!(
+                    "\t-h, --section-headers: Print section headers (name, size, align, flags)",
+                );
+                eprintln!(
+                    "\t-t, --syms: Print the symbol table",
+                );
+                eprintln!(
+                    "\t-T, --dynamic-syms: Print the symbol table; dynamic entries are not separated out, so this is the same listing as -t",
+                );
+                eprintln!(
+                    "\t-S, --source: Like -d, annotated with .debug_line file/line info where it changes",
+                );
+                eprintln!(
+                    "\t-l, --line-numbers: Print the file/line each symbol's address maps to in .debug_line",
+                );
+                eprintln!(
+                    "\t--insn-width <n>: Bytes per group in -d's output (default 4)",
+                );
+                eprintln!(
+                    "\t--byte-order <le|be>: Byte order to group -d's output in (default le)",
+                );
+                eprintln!(
+                    "\t--show-raw-insn, --no-show-raw-insn: Whether -d prints the grouped bytes (default show)",
+                );
 
                 eprint!("objdump is compiled with support for the following binfmts: ");
 
@@ -66,6 +649,15 @@ fn main() {
         std::process::exit(1);
     });
 
+    #[cfg(feature = "mmap")]
+    let mapped = binfmt::mmap::MappedObject::open(&input_file).unwrap_or_else(|e| {
+        eprintln!("{}: Failed to open {}: {}", prg_name, input_file, e);
+        std::process::exit(1)
+    });
+    #[cfg(feature = "mmap")]
+    let mut file = mapped.cursor();
+
+    #[cfg(not(feature = "mmap"))]
     let mut file = File::open(&input_file).unwrap_or_else(|e| {
         eprintln!("{}: Failed to open {}: {}", prg_name, input_file, e);
         std::process::exit(1)
@@ -99,15 +691,95 @@ fn main() {
             std::process::exit(1)
         })
     };
-    println!("Sections");
-    println!();
-    println!("        Name            Size      Align");
-    for sec in file.sections() {
-        println!(
-            "{:^20} {:^10} {:^8}",
-            sec.name,
-            sec.content.len(),
-            sec.align
-        );
+    if print_section_hdrs {
+        print_section_headers(&file);
+    }
+
+    if print_symbols {
+        println!();
+        print_symtab(&file, file.symbols());
+    }
+
+    if print_dynamic_symbols {
+        // ELF's reader merges `.dynsym` and `.symtab` into the same flat
+        // `BinaryFile::symbols()` list without recording which table an
+        // entry came from (see `elf_shtype_to_file_type`'s doc comment in
+        // `binfmt::elf`), so there's currently no way to list only the
+        // dynamic symbols -- this prints the same unified table `-t` does
+        // rather than silently pretending to filter it.
+        println!();
+        print_symtab(&file, file.symbols());
+    }
+
+    if reloc_hexdump {
+        for sec in file.sections() {
+            if let Some(name) = &section_filter {
+                if sec.name != *name {
+                    continue;
+                }
+            }
+
+            println!();
+            println!("Contents of section {}:", sec.name);
+            print_reloc_annotated_hexdump(sec);
+        }
+    }
+
+    if print_static_relocs {
+        for sec in file.sections() {
+            println!();
+            println!("RELOCATION RECORDS FOR [{}]:", sec.name);
+            print_relocs(file.fmt(), sec.relocs.iter());
+        }
+    }
+
+    if print_dynamic_relocs {
+        println!();
+        println!("DYNAMIC RELOCATION RECORDS");
+        print_relocs(file.fmt(), file.relocs());
+    }
+
+    if disassemble {
+        let printer = file.fmt().disassembler();
+        let line_table = if interleave_source {
+            build_line_table(&file)
+        } else {
+            Vec::new()
+        };
+        for (sec_idx, sec) in file.sections().enumerate() {
+            if let Some(name) = &section_filter {
+                if sec.name != *name {
+                    continue;
+                }
+            } else if !disassemble_all && !section_is_executable(sec) {
+                continue;
+            }
+
+            println!();
+            println!("Disassembly of section {}:", sec.name);
+
+            if let Some(printer) = printer {
+                let labels = section_labels(&file, sec_idx);
+                print_disassembly(sec, file.fmt(), printer, &labels, &line_table);
+                continue;
+            }
+
+            print_insn_bytes_dump(sec, insn_width, little_endian, show_raw_insn);
+        }
+    }
+
+    if print_symbol_lines {
+        let line_table = build_line_table(&file);
+        println!();
+        println!("SYMBOL LINE TABLE:");
+        for sym in file.symbols() {
+            let Some(value) = sym.value() else {
+                continue;
+            };
+            match lookup_line(&line_table, value as u64) {
+                Some(row) => println!("{}: file #{}, line {}", sym.name(), row.file, row.line),
+                None => println!("{}: ??", sym.name()),
+            }
+        }
     }
 }
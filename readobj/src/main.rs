@@ -1,3 +1,113 @@
+use std::fs::File;
+
+use binfmt::stats::{hash_bucket_stats, reloc_counts_by_kind, symbol_table_stats};
+
 fn main() {
-    println!("Hello, world!");
+    let mut args = std::env::args();
+
+    let prg_name = args.next().unwrap();
+
+    let mut input_file = None::<String>;
+
+    let mut histogram = false;
+    let mut dyn_syms = false;
+
+    #[allow(clippy::never_loop, clippy::while_let_on_iterator)] // We need to handle more options than `--version` and `--help`
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--histogram" => {
+                histogram = true;
+            }
+            "--dyn-syms" => {
+                dyn_syms = true;
+            }
+            "--version" => {
+                eprintln!("readobj (lc-binutils {})", std::env!("CARGO_PKG_VERSION"));
+                eprintln!("Copyright (c) 2022 Lightning Creations");
+                eprintln!("Released under the terms of the BSD 2 Clause + Patent License");
+                std::process::exit(0);
+            }
+            "--help" => {
+                eprintln!("USAGE: {} [OPTIONS] [--] [input file]", prg_name);
+                eprintln!("Options:");
+                eprintln!(
+                    "\t--histogram: Report hash bucket, symbol table, and relocation statistics",
+                );
+                eprintln!(
+                    "\t--dyn-syms: List every symbol this file exports, regardless of its format's export mechanism",
+                );
+                std::process::exit(0);
+            }
+            _ => {
+                input_file = Some(arg);
+                break;
+            }
+        }
+    }
+
+    let input_file = input_file.unwrap_or_else(|| {
+        eprintln!("USAGE: {} [OPTIONS] [--] [input file]", prg_name);
+        std::process::exit(1);
+    });
+
+    let file = File::open(&input_file).unwrap_or_else(|e| {
+        eprintln!("{}: Failed to open {}: {}", prg_name, input_file, e);
+        std::process::exit(1)
+    });
+
+    let file = binfmt::open_file(file).unwrap_or_else(|e| {
+        eprintln!("{}: Failed to read {}: {}", prg_name, input_file, e);
+        std::process::exit(1)
+    });
+
+    println!("Sections");
+    println!();
+    println!("        Name            Size      Align");
+    for sec in file.sections() {
+        println!(
+            "{:^20} {:^10} {:^8}",
+            sec.name,
+            sec.content.len(),
+            sec.align
+        );
+    }
+
+    if dyn_syms {
+        println!();
+        println!("Dynamic/exported symbols");
+        println!();
+        println!("        Name            Value");
+        for sym in file.exports() {
+            println!("{:^20} {:^#16x}", sym.name(), sym.value().unwrap_or(0));
+        }
+    }
+
+    if histogram {
+        let names = std::iter::once("")
+            .chain(file.symbols().map(|sym| sym.name()))
+            .collect::<Vec<_>>();
+        let hash_stats = hash_bucket_stats(&names);
+        println!();
+        println!("Hash bucket statistics:");
+        println!("\tnbucket:     {}", hash_stats.nbucket);
+        println!("\tnsymbols:    {}", hash_stats.nsymbols);
+        println!("\tempty buckets: {}", hash_stats.empty_buckets);
+        println!("\tmax chain len: {}", hash_stats.max_chain_len);
+        println!("\tmean chain len: {:.2}", hash_stats.mean_chain_len);
+
+        let sym_stats = symbol_table_stats(file.symbols());
+        println!();
+        println!("Symbol table statistics:");
+        println!("\ttotal:     {}", sym_stats.total);
+        println!("\tlocal:     {}", sym_stats.local);
+        println!("\tglobal:    {}", sym_stats.global);
+        println!("\tweak:      {}", sym_stats.weak);
+        println!("\tundefined: {}", sym_stats.undefined);
+
+        println!();
+        println!("Relocation counts by kind:");
+        for (kind, count) in reloc_counts_by_kind(file.relocs()) {
+            println!("\t{:<12} {}", kind, count);
+        }
+    }
 }